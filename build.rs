@@ -0,0 +1,33 @@
+use std::{env, process::Command};
+
+/// Embeds what `gitlab-todo version` reports about the build.
+fn main() {
+  let commit = Command::new("git")
+    .args(["rev-parse", "--short=12", "HEAD"])
+    .output()
+    .ok()
+    .filter(|output| output.status.success())
+    .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    .filter(|commit| !commit.is_empty())
+    .unwrap_or_else(|| "unknown".to_string());
+  let mut features: Vec<String> = env::vars()
+    .filter_map(|(key, _)| {
+      key
+        .strip_prefix("CARGO_FEATURE_")
+        .map(|feature| feature.to_lowercase().replace('_', "-"))
+    })
+    .collect();
+  features.sort();
+
+  println!("cargo:rustc-env=GITLAB_TODO_COMMIT={}", commit);
+  println!(
+    "cargo:rustc-env=GITLAB_TODO_TARGET={}",
+    env::var("TARGET").unwrap_or_default()
+  );
+  println!(
+    "cargo:rustc-env=GITLAB_TODO_FEATURES={}",
+    features.join(",")
+  );
+  println!("cargo:rerun-if-changed=.git/HEAD");
+  println!("cargo:rerun-if-changed=.git/refs");
+}
@@ -0,0 +1,410 @@
+use crate::{
+  gitlab::{ApprovalInfo, AwardEmoji, Client, MergeRequest, MrId, Note, Pipeline, User, UserId},
+  state::State,
+  Result,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+  collections::BTreeMap,
+  fmt::Display,
+  process::{Command, Stdio},
+  thread::sleep,
+  time::Duration,
+};
+
+const REBASE_POLLS: usize = 5;
+const REBASE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Membership {
+  Assignee,
+  Reviewer,
+}
+
+impl Membership {
+  pub fn describe(&self, present: bool) -> &'static str {
+    match (self, present) {
+      (Membership::Assignee, true) => "assigned myself to",
+      (Membership::Assignee, false) => "unassigned myself from",
+      (Membership::Reviewer, true) => "added myself as reviewer to",
+      (Membership::Reviewer, false) => "removed myself as reviewer from",
+    }
+  }
+}
+
+// The update endpoint replaces the whole list, so always start from the
+// server's current members to avoid dropping people added in the meantime.
+pub fn set_membership(
+  client: &Client,
+  user: &User,
+  mr: &MergeRequest,
+  membership: Membership,
+  present: bool,
+) -> Result<MergeRequest> {
+  let current = MergeRequest::get(client, mr.project_id, mr.iid)?;
+  let (field, members) = match membership {
+    Membership::Assignee => ("assignee_ids", &current.assignees),
+    Membership::Reviewer => ("reviewer_ids", &current.reviewers),
+  };
+
+  let mut ids: Vec<UserId> = members.iter().map(|member| member.id).collect();
+  let is_member = ids.contains(&user.id);
+  if present == is_member {
+    return Ok(current);
+  }
+  if present {
+    ids.push(user.id);
+  } else {
+    ids.retain(|id| *id != user.id);
+  }
+
+  current.update(client, &json!({ field: ids }))
+}
+
+/// Takes me off the reviewers and, given a username, puts them on instead,
+/// with `note` posted after where there is one. Like `set_membership`, it
+/// starts from the server's current reviewers.
+pub fn hand_off(
+  client: &Client,
+  user: &User,
+  mr: &MergeRequest,
+  to: Option<&str>,
+  note: Option<&str>,
+) -> Result<MergeRequest> {
+  let to = to.map(|username| User::get(client, username)).transpose()?;
+  let current = MergeRequest::get(client, mr.project_id, mr.iid)?;
+  let mut ids: Vec<UserId> = current
+    .reviewers
+    .iter()
+    .map(|reviewer| reviewer.id)
+    .filter(|id| *id != user.id)
+    .collect();
+  if let Some(to) = &to {
+    if to.id == user.id {
+      return Err("Can't hand a review off to myself".into());
+    }
+    if !ids.contains(&to.id) {
+      ids.push(to.id);
+    }
+  }
+  let updated = current.update(client, &json!({ "reviewer_ids": ids }))?;
+  if let (Some(to), Some(note)) = (&to, note) {
+    ensure_unlocked(&updated)?;
+    Note::create(client, &updated, &note.replace("{user}", &to.username))?;
+  }
+  Ok(updated)
+}
+
+// Title prefixes GitLab treats as marking a merge request as draft.
+const DRAFT_PREFIXES: [&str; 5] = ["draft:", "[draft]", "(draft)", "wip:", "[wip]"];
+
+pub fn strip_draft_prefix(title: &str) -> &str {
+  let mut title = title.trim_start();
+  while let Some(prefix) = DRAFT_PREFIXES.iter().find(|prefix| {
+    title
+      .get(..prefix.len())
+      .is_some_and(|head| head.eq_ignore_ascii_case(prefix))
+  }) {
+    title = title[prefix.len()..].trim_start();
+  }
+  title
+}
+
+pub fn ensure_author(user: &User, mr: &MergeRequest, force: bool) -> Result<()> {
+  if force || mr.author.id == user.id {
+    return Ok(());
+  }
+  Err(
+    format!(
+      "{} is authored by @{}, not me (use --force to change it anyway)",
+      mr.references.full, mr.author.username
+    )
+    .into(),
+  )
+}
+
+/// Comments and reactions are refused on merge requests with a locked discussion.
+pub fn ensure_unlocked(mr: &MergeRequest) -> Result<()> {
+  if !mr.is_locked() {
+    return Ok(());
+  }
+  Err(format!("The discussion on {} is locked", mr.references.full).into())
+}
+
+// Editing the title prefix works on every GitLab version. Should an instance
+// not derive the draft flag from the title, fall back to the quick action.
+pub fn set_draft(client: &Client, mr: &MergeRequest, draft: bool) -> Result<MergeRequest> {
+  let current = MergeRequest::get(client, mr.project_id, mr.iid)?;
+  if current.draft == draft {
+    return Ok(current);
+  }
+
+  let title = strip_draft_prefix(&current.title);
+  let title = if draft {
+    format!("Draft: {}", title)
+  } else {
+    title.to_string()
+  };
+  let updated = current.update(client, &json!({ "title": title }))?;
+  if updated.draft == draft {
+    return Ok(updated);
+  }
+
+  ensure_unlocked(&updated)?;
+  Note::create(client, &updated, if draft { "/draft" } else { "/ready" })?;
+  MergeRequest::get(client, updated.project_id, updated.iid)
+}
+
+/// Adds and removes labels, leaving any others a merge request has alone.
+pub fn set_labels(
+  client: &Client,
+  mr: &MergeRequest,
+  add: &[String],
+  remove: &[String],
+) -> Result<MergeRequest> {
+  mr.update(
+    client,
+    &json!({ "add_labels": add.join(","), "remove_labels": remove.join(",") }),
+  )
+}
+
+// Waits a little for the rebase to finish, but gives up quietly when it takes
+// longer; the next refresh picks up the result either way.
+pub fn rebase(client: &Client, mr: &MergeRequest) -> Result<MergeRequest> {
+  mr.rebase(client)?;
+
+  let mut current = mr.get_rebase_status(client)?;
+  for _ in 0..REBASE_POLLS {
+    if !current.rebase_in_progress {
+      break;
+    }
+    sleep(REBASE_POLL_INTERVAL);
+    current = mr.get_rebase_status(client)?;
+  }
+
+  match &current.merge_error {
+    Some(error) if !current.rebase_in_progress => {
+      Err(format!("Rebase of {} failed: {}", current.references.full, error).into())
+    }
+    _ => Ok(current),
+  }
+}
+
+pub fn describe_rebase(mr: &MergeRequest) -> String {
+  if mr.rebase_in_progress {
+    format!("rebase of {} is still running", mr.references.full)
+  } else {
+    format!("rebased {}", mr.references.full)
+  }
+}
+
+pub fn retry_pipeline(
+  client: &Client,
+  mr: &MergeRequest,
+  pipeline: Option<&Pipeline>,
+) -> Result<Pipeline> {
+  let pipeline =
+    pipeline.ok_or_else(|| format!("{} has no pipeline to retry", mr.references.full))?;
+  pipeline.retry(client, mr)
+}
+
+const UPVOTE: &str = "thumbsup";
+
+pub fn my_upvote(client: &Client, user: &User, mr: &MergeRequest) -> Result<Option<AwardEmoji>> {
+  Ok(
+    AwardEmoji::get_all(client, mr)?
+      .into_iter()
+      .find(|award| award.name == UPVOTE && award.user.id == user.id),
+  )
+}
+
+/// Awards or withdraws my 👍 and returns whether it is now given.
+pub fn toggle_upvote(client: &Client, user: &User, mr: &MergeRequest) -> Result<bool> {
+  match my_upvote(client, user, mr)? {
+    Some(award) => {
+      award.delete(client, mr)?;
+      Ok(false)
+    }
+    None => {
+      AwardEmoji::award(client, mr, UPVOTE)?;
+      Ok(true)
+    }
+  }
+}
+
+/// Defaults for the LGTM action.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Lgtm {
+  /// Comment posted before approving, if any.
+  pub message: Option<String>,
+  /// Whether to give a 👍 as well.
+  pub award: bool,
+  #[serde(flatten, skip_serializing)]
+  pub unknown: BTreeMap<String, toml::Value>,
+}
+
+impl Default for Lgtm {
+  fn default() -> Self {
+    Lgtm {
+      message: None,
+      award: true,
+      unknown: BTreeMap::new(),
+    }
+  }
+}
+
+/// What the LGTM action got done; any step after a failed one is not tried.
+#[derive(Debug, Clone, Default)]
+pub struct LgtmSteps {
+  pub commented: bool,
+  pub upvoted: bool,
+}
+
+impl LgtmSteps {
+  fn done(&self) -> Vec<&'static str> {
+    let mut done = Vec::new();
+    if self.commented {
+      done.push("commented");
+    }
+    if self.upvoted {
+      done.push("gave my 👍");
+    }
+    done
+  }
+
+  fn failed(&self, step: &str, err: impl std::fmt::Display) -> Box<dyn std::error::Error> {
+    let done = self.done();
+    let done = if done.is_empty() {
+      "nothing done yet".to_string()
+    } else {
+      format!("already {}", done.join(" and "))
+    };
+    format!("Could not {} ({}): {}", step, done, err).into()
+  }
+
+  pub fn describe(&self, mr: &MergeRequest) -> String {
+    let mut done = self.done();
+    done.push("approved");
+    let last = done.pop().unwrap_or_default();
+    if done.is_empty() {
+      format!("{} {}", last, mr.references.full)
+    } else {
+      format!("{} and {} {}", done.join(", "), last, mr.references.full)
+    }
+  }
+}
+
+/// Comments, gives a 👍 and approves, in that order, stopping at the first
+/// step that fails. A 👍 already given counts as done.
+pub fn lgtm(
+  client: &Client,
+  user: &User,
+  mr: &MergeRequest,
+  message: Option<&str>,
+  award: bool,
+) -> Result<(ApprovalInfo, LgtmSteps)> {
+  let mut steps = LgtmSteps::default();
+  let message = message.filter(|message| !message.trim().is_empty());
+  if message.is_some() || award {
+    ensure_unlocked(mr)?;
+  }
+
+  if let Some(message) = message {
+    Note::create(client, mr, message).map_err(|err| steps.failed("comment", err))?;
+    steps.commented = true;
+  }
+  if award {
+    let given = my_upvote(client, user, mr).map_err(|err| steps.failed("give my 👍", err))?;
+    if given.is_none() {
+      AwardEmoji::award(client, mr, UPVOTE).map_err(|err| steps.failed("give my 👍", err))?;
+      steps.upvoted = true;
+    }
+  }
+  let approval_info =
+    ApprovalInfo::approve(client, mr).map_err(|err| steps.failed("approve", err))?;
+  Ok((approval_info, steps))
+}
+
+/// Hides merge requests until `until`, or for good with `None`.
+pub fn hide(ids: &[MrId], until: Option<DateTime<Utc>>) -> Result<()> {
+  State::update(|state| {
+    for id in ids {
+      match until {
+        Some(until) => {
+          state.snoozes.insert(*id, until);
+        }
+        None => {
+          state.mutes.insert(*id);
+        }
+      }
+    }
+  })
+}
+
+/// Whether a merge request is muted or snoozed at `now`.
+pub fn is_hidden(state: &State, id: MrId, now: DateTime<Utc>) -> bool {
+  state.mutes.contains(&id) || state.snoozes.get(&id).is_some_and(|until| *until > now)
+}
+
+pub fn open_in_browser(url: &str) -> Result<()> {
+  let mut command = if cfg!(target_os = "macos") {
+    Command::new("open")
+  } else if cfg!(windows) {
+    let mut command = Command::new("cmd");
+    command.args(["/C", "start", ""]);
+    command
+  } else {
+    Command::new("xdg-open")
+  };
+  command
+    .arg(url)
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .spawn()?;
+  Ok(())
+}
+
+/// The outcome of one action applied to several merge requests in turn,
+/// like `approved 4, failed 1: group/project!12 — 401 Unauthorized`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bulk {
+  verb: &'static str,
+  succeeded: usize,
+  failed: Vec<(String, String)>,
+}
+
+impl Bulk {
+  pub fn new(verb: &'static str) -> Self {
+    Bulk {
+      verb,
+      succeeded: 0,
+      failed: Vec::new(),
+    }
+  }
+
+  pub fn record<T>(&mut self, reference: &str, result: Result<T>) {
+    match result {
+      Ok(_) => self.succeeded += 1,
+      Err(err) => self.failed.push((reference.to_string(), err.to_string())),
+    }
+  }
+}
+
+impl Display for Bulk {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{} {}", self.verb, self.succeeded)?;
+    if self.failed.is_empty() {
+      return Ok(());
+    }
+    let failures: Vec<String> = self
+      .failed
+      .iter()
+      .map(|(reference, err)| format!("{} — {}", reference, err))
+      .collect();
+    write!(f, ", failed {}: {}", self.failed.len(), failures.join(", "))
+  }
+}
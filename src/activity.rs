@@ -0,0 +1,118 @@
+use crate::{
+  cache::MrCache,
+  gitlab::{Client, MergeRequest, Note},
+  Result,
+};
+use chrono::{DateTime, Utc};
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivityKind {
+  Commented,
+  Pushed,
+  Approved,
+  Unapproved,
+  RequestedReview,
+  Assigned,
+  Labeled,
+  MarkedReady,
+  MarkedDraft,
+  ResolvedThreads,
+  Retitled,
+  Edited,
+  Mentioned,
+  Updated,
+}
+
+impl ActivityKind {
+  fn from_system_note(body: &str) -> Self {
+    let body = body.to_lowercase();
+    let starts = |prefix: &str| body.starts_with(prefix);
+
+    if starts("approved this merge request") {
+      ActivityKind::Approved
+    } else if starts("unapproved this merge request") {
+      ActivityKind::Unapproved
+    } else if starts("added ") && body.contains(" commit") {
+      ActivityKind::Pushed
+    } else if starts("requested review from") {
+      ActivityKind::RequestedReview
+    } else if starts("assigned to") || starts("unassigned") {
+      ActivityKind::Assigned
+    } else if (starts("added ~") || starts("removed ~")) && body.contains("label") {
+      ActivityKind::Labeled
+    } else if starts("marked this merge request as **ready**") {
+      ActivityKind::MarkedReady
+    } else if starts("marked this merge request as **draft**") {
+      ActivityKind::MarkedDraft
+    } else if starts("resolved all threads") {
+      ActivityKind::ResolvedThreads
+    } else if starts("changed title") {
+      ActivityKind::Retitled
+    } else if starts("changed the description") {
+      ActivityKind::Edited
+    } else if starts("mentioned in") {
+      ActivityKind::Mentioned
+    } else {
+      ActivityKind::Updated
+    }
+  }
+}
+
+impl Display for ActivityKind {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      ActivityKind::Commented => "commented",
+      ActivityKind::Pushed => "pushed",
+      ActivityKind::Approved => "approved",
+      ActivityKind::Unapproved => "unapproved",
+      ActivityKind::RequestedReview => "requested review",
+      ActivityKind::Assigned => "changed assignees",
+      ActivityKind::Labeled => "changed labels",
+      ActivityKind::MarkedReady => "marked ready",
+      ActivityKind::MarkedDraft => "marked draft",
+      ActivityKind::ResolvedThreads => "resolved threads",
+      ActivityKind::Retitled => "retitled",
+      ActivityKind::Edited => "edited",
+      ActivityKind::Mentioned => "mentioned",
+      ActivityKind::Updated => "updated",
+    })
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Activity {
+  pub username: String,
+  pub kind: ActivityKind,
+}
+
+impl Activity {
+  fn from_note(note: &Note) -> Self {
+    let kind = if note.system {
+      ActivityKind::from_system_note(&note.body)
+    } else {
+      ActivityKind::Commented
+    };
+
+    Activity {
+      username: note.author.username.clone(),
+      kind,
+    }
+  }
+}
+
+impl Display for Activity {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "@{} {}", self.username, self.kind)
+  }
+}
+
+pub type ActivityCache = MrCache<DateTime<Utc>, Option<Activity>>;
+
+impl ActivityCache {
+  pub fn get(&mut self, client: &Client, mr: &MergeRequest) -> Result<Option<Activity>> {
+    self.get_or_fetch(mr.id, mr.updated_at, || {
+      Ok(Note::get_latest(client, mr)?.map(|note| Activity::from_note(&note)))
+    })
+  }
+}
@@ -0,0 +1,155 @@
+use crate::{priority::pipeline_failed, Row};
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Attention {
+  ActionRequired,
+  WaitingOnOthers,
+}
+
+/// Whether every reviewer yet to approve my merge request set a busy or
+/// out-of-office status, so that it won't move for a while.
+pub fn reviewers_unavailable(row: &Row) -> bool {
+  let mut pending = row
+    .mr
+    .reviewers
+    .iter()
+    .filter(|reviewer| {
+      !row
+        .approval_info
+        .approved_by
+        .iter()
+        .any(|approver| approver.user.id == reviewer.id)
+    })
+    .peekable();
+  pending.peek().is_some()
+    && pending.all(|reviewer| {
+      row
+        .reviewer_statuses
+        .get(&reviewer.id)
+        .is_some_and(|status| status.is_unavailable())
+    })
+}
+
+/// Whether it is up to the author to move on: it has conflicts, a failed
+/// pipeline, a reviewer requesting changes or threads someone else started
+/// that are still open.
+pub fn needs_author(row: &Row) -> bool {
+  let reviewer_threads_open = row.discussions.iter().any(|discussion| {
+    discussion.is_unresolved()
+      && discussion
+        .started_by()
+        .is_some_and(|author| author.id != row.mr.author.id)
+  });
+  row.mr.has_conflicts || pipeline_failed(row) || row.changes_requested() || reviewer_threads_open
+}
+
+pub fn attention(row: &Row) -> Option<Attention> {
+  if !row.roles.author || !row.detailed {
+    return None;
+  }
+
+  if needs_author(row) {
+    Some(Attention::ActionRequired)
+  } else {
+    Some(Attention::WaitingOnOthers)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fixtures::{mr, row, user, user_json},
+    gitlab::{Discussion, Id, Pipeline, PipelineStatus, ReviewState, Reviewer},
+  };
+  use serde_json::json;
+
+  /// My merge request with details, reviewed by bob.
+  fn mine() -> Row {
+    let mut row = row(mr(
+      1,
+      json!({ "author": user_json(1, "me"), "reviewers": [user_json(7, "bob")] }),
+    ));
+    row.detailed = true;
+    row
+  }
+
+  fn reviewed(state: ReviewState) -> Row {
+    let mut row = mine();
+    row.reviewers = vec![Reviewer {
+      user: user(7, "bob"),
+      state,
+    }];
+    row
+  }
+
+  fn thread(started_by: usize) -> Discussion {
+    serde_json::from_value(json!({
+      "id": "1",
+      "notes": [{
+        "author": user_json(started_by, "someone"),
+        "system": false,
+        "resolvable": true,
+        "resolved": false,
+      }],
+    }))
+    .unwrap()
+  }
+
+  #[test]
+  fn requested_changes_are_up_to_the_author() {
+    let row = reviewed(ReviewState::RequestedChanges);
+    assert!(needs_author(&row));
+    assert_eq!(attention(&row), Some(Attention::ActionRequired));
+  }
+
+  #[test]
+  fn other_review_states_wait_on_the_reviewers() {
+    for state in [
+      ReviewState::Unreviewed,
+      ReviewState::Reviewed,
+      ReviewState::Approved,
+      ReviewState::Unknown,
+    ] {
+      let row = reviewed(state);
+      assert!(!needs_author(&row), "{:?}", state);
+      assert_eq!(attention(&row), Some(Attention::WaitingOnOthers));
+    }
+  }
+
+  #[test]
+  fn conflicts_pipelines_and_open_threads_are_up_to_the_author() {
+    let mut conflicted = mine();
+    conflicted.mr.has_conflicts = true;
+    assert!(needs_author(&conflicted));
+
+    let mut failed = mine();
+    failed.pipeline = Some(Pipeline {
+      id: Id(1),
+      status: PipelineStatus::Failed,
+      web_url: String::new(),
+    });
+    assert!(needs_author(&failed));
+
+    let mut asked = mine();
+    asked.discussions = vec![thread(7)];
+    assert!(needs_author(&asked));
+
+    // Threads of my own are notes to self.
+    let mut noted = mine();
+    noted.discussions = vec![thread(1)];
+    assert!(!needs_author(&noted));
+  }
+
+  #[test]
+  fn only_my_detailed_merge_requests_need_attention() {
+    let mut undetailed = mine();
+    undetailed.detailed = false;
+    assert_eq!(attention(&undetailed), None);
+    let mut theirs = row(mr(1, json!({})));
+    theirs.detailed = true;
+    assert_eq!(attention(&theirs), None);
+  }
+}
@@ -0,0 +1,58 @@
+use crate::gitlab::User;
+use crossterm::style::{Color, Stylize};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// Backgrounds from the 256-color palette that black text reads well on,
+// spaced apart so that neighbouring ids don't look alike.
+const PALETTE: [u8; 12] = [167, 173, 179, 143, 107, 72, 73, 74, 104, 140, 175, 181];
+
+pub const BADGE_WIDTH: usize = 4;
+
+/// A color picked by `key` alone, so the same id gets the same color on
+/// every run and on every machine.
+pub fn stable_color(key: usize) -> Color {
+  // A multiplicative hash spreads consecutive ids over the palette.
+  let hash = (key as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32;
+  Color::AnsiValue(PALETTE[hash as usize % PALETTE.len()])
+}
+
+/// Up to two columns of initials: the first letters of the first and last
+/// word of the name, or the start of a single word. Characters as wide as
+/// two columns, as in CJK names, stand alone. Falls back to the username.
+pub fn initials(user: &User) -> String {
+  let words: Vec<&str> = user.name.split_whitespace().collect();
+  let letters: Vec<char> = match words.as_slice() {
+    [] => user.username.chars().take(2).collect(),
+    [word] => word
+      .chars()
+      .filter(|c| c.is_alphanumeric())
+      .take(2)
+      .collect(),
+    [first, .., last] => [first, last]
+      .iter()
+      .filter_map(|word| word.chars().find(|c| c.is_alphanumeric()))
+      .collect(),
+  };
+
+  let mut initials = String::new();
+  let mut width = 0;
+  for letter in letters {
+    let letter_width = letter.width().unwrap_or(0);
+    if width + letter_width > 2 {
+      break;
+    }
+    width += letter_width;
+    initials.extend(letter.to_uppercase());
+  }
+  initials
+}
+
+/// The initials on the user's color, like ` AK `, `BADGE_WIDTH` columns wide.
+pub fn badge(user: &User) -> String {
+  let initials = initials(user);
+  let padding = 2usize.saturating_sub(initials.width());
+  format!(" {}{} ", initials, " ".repeat(padding))
+    .black()
+    .on(stable_color(user.id.0))
+    .to_string()
+}
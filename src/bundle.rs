@@ -0,0 +1,286 @@
+use crate::{config::Config, gitlab::MrId, options::Options, state::State, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fs,
+  io::{stdin, stdout, IsTerminal, Write},
+  path::{Path, PathBuf},
+};
+
+const VERSION: u32 = 1;
+
+/// What moves to another machine: the settings and what I hid, but neither
+/// access tokens nor anything cached.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bundle {
+  pub version: u32,
+  /// The configuration as it takes effect, as TOML would have it.
+  pub config: toml::Table,
+  pub mutes: BTreeSet<MrId>,
+  pub snoozes: BTreeMap<MrId, DateTime<Utc>>,
+}
+
+impl Bundle {
+  pub fn of(config: &Config, options: &Options, state: &State) -> Result<Self> {
+    // The effective configuration already leaves out access tokens.
+    let mut effective = toml::Table::try_from(config.effective(options))?;
+    effective.remove("tokens");
+    let now = Utc::now();
+    Ok(Bundle {
+      version: VERSION,
+      config: effective,
+      mutes: state.mutes.clone(),
+      snoozes: state
+        .snoozes
+        .iter()
+        .filter(|(_, until)| **until > now)
+        .map(|(id, until)| (*id, *until))
+        .collect(),
+    })
+  }
+
+  /// Refuses bundles from newer versions, and configurations that would not
+  /// load, before anything is written.
+  pub fn validate(&self, options: &Options) -> Result<Config> {
+    if self.version > VERSION {
+      return Err(
+        format!(
+          "The bundle is of version {}, newer than this one",
+          self.version
+        )
+        .into(),
+      );
+    }
+    if self.config.contains_key("tokens") {
+      return Err("The bundle holds access tokens; those are not imported".into());
+    }
+    validate_config(&self.config, options)
+  }
+}
+
+fn validate_config(table: &toml::Table, options: &Options) -> Result<Config> {
+  let config: Config = toml::Value::Table(table.clone())
+    .try_into()
+    .map_err(|err| format!("Invalid configuration: {}", err))?;
+  let unknown = config.unknown_keys();
+  if !unknown.is_empty() {
+    return Err(format!("Unknown configuration keys: {}", unknown.join(", ")).into());
+  }
+  config
+    .apply(&mut options.clone())
+    .map_err(|err| format!("Invalid configuration: {}", err))?;
+  Ok(config)
+}
+
+/// Sets every value of `from` in `into`, going into tables both have.
+pub fn merge_tables(into: &mut toml::Table, from: &toml::Table) {
+  for (key, value) in from {
+    match (into.get_mut(key), value) {
+      (Some(toml::Value::Table(into)), toml::Value::Table(from)) => merge_tables(into, from),
+      _ => {
+        into.insert(key.clone(), value.clone());
+      }
+    }
+  }
+}
+
+/// Every value by its dotted path, like `weights.fork_bonus`, with arrays
+/// of tables by index.
+fn flatten(prefix: &str, table: &toml::Table, values: &mut BTreeMap<String, String>) {
+  for (key, value) in table {
+    let path = if prefix.is_empty() {
+      key.clone()
+    } else {
+      format!("{}.{}", prefix, key)
+    };
+    match value {
+      toml::Value::Table(table) => flatten(&path, table, values),
+      toml::Value::Array(items) if items.iter().all(toml::Value::is_table) && !items.is_empty() => {
+        for (index, item) in items.iter().enumerate() {
+          if let toml::Value::Table(table) = item {
+            flatten(&format!("{}.{}", path, index), table, values);
+          }
+        }
+      }
+      value => {
+        values.insert(path, value.to_string());
+      }
+    }
+  }
+}
+
+/// What changes from `before` to `after`, one line per value.
+pub fn config_diff(before: &toml::Table, after: &toml::Table) -> Vec<String> {
+  let (mut old, mut new) = (BTreeMap::new(), BTreeMap::new());
+  flatten("", before, &mut old);
+  flatten("", after, &mut new);
+  let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+  keys
+    .into_iter()
+    .filter_map(|key| match (old.get(key), new.get(key)) {
+      (None, Some(value)) => Some(format!("+ {} = {}", key, value)),
+      (Some(value), None) => Some(format!("- {} = {}", key, value)),
+      (Some(before), Some(after)) if before != after => {
+        Some(format!("~ {} = {} → {}", key, before, after))
+      }
+      _ => None,
+    })
+    .collect()
+}
+
+/// The mutes and snoozes once the bundle's are taken in: along with the
+/// present ones, keeping the later snooze, or in their place.
+pub fn merged_state(
+  state: &State,
+  bundle: &Bundle,
+  replace: bool,
+) -> (BTreeSet<MrId>, BTreeMap<MrId, DateTime<Utc>>) {
+  if replace {
+    return (bundle.mutes.clone(), bundle.snoozes.clone());
+  }
+  let mutes = state.mutes.union(&bundle.mutes).copied().collect();
+  let mut snoozes = state.snoozes.clone();
+  for (id, until) in &bundle.snoozes {
+    let entry = snoozes.entry(*id).or_insert(*until);
+    *entry = (*entry).max(*until);
+  }
+  (mutes, snoozes)
+}
+
+fn state_diff(
+  state: &State,
+  mutes: &BTreeSet<MrId>,
+  snoozes: &BTreeMap<MrId, DateTime<Utc>>,
+) -> Vec<String> {
+  let mut lines: Vec<String> = mutes
+    .difference(&state.mutes)
+    .map(|id| format!("+ mute {}", id))
+    .chain(
+      state
+        .mutes
+        .difference(mutes)
+        .map(|id| format!("- mute {}", id)),
+    )
+    .collect();
+  for (id, until) in snoozes {
+    if state.snoozes.get(id) != Some(until) {
+      lines.push(format!(
+        "+ snooze {} until {}",
+        id,
+        until.format("%Y-%m-%d %H:%M")
+      ));
+    }
+  }
+  for id in state.snoozes.keys().filter(|id| !snoozes.contains_key(id)) {
+    lines.push(format!("- snooze {}", id));
+  }
+  lines
+}
+
+fn config_path(options: &Options) -> Result<PathBuf> {
+  match &options.config {
+    Some(path) => Ok(path.clone()),
+    None => Config::default_path().ok_or("Could not determine the configuration directory".into()),
+  }
+}
+
+fn read_table(path: &Path) -> Result<toml::Table> {
+  if !path.exists() {
+    return Ok(toml::Table::new());
+  }
+  let contents = fs::read_to_string(path)?;
+  Ok(
+    contents
+      .parse()
+      .map_err(|err| format!("Invalid config file {}: {}", path.display(), err))?,
+  )
+}
+
+pub fn export(options: &Options, config: &Config) -> Result<()> {
+  let bundle = Bundle::of(config, options, &State::load())?;
+  println!("{}", serde_json::to_string_pretty(&bundle)?);
+  Ok(())
+}
+
+/// Shows what importing changes and then, once confirmed, writes it. The
+/// configuration file is rewritten without its comments, so the old one is
+/// kept next to it as `config.toml.bak`. Local access tokens stay.
+pub fn import(
+  options: &Options,
+  path: &Path,
+  replace: bool,
+  dry_run: bool,
+  yes: bool,
+) -> Result<()> {
+  let bundle: Bundle = serde_json::from_slice(&fs::read(path)?)
+    .map_err(|err| format!("Not a bundle: {}: {}", path.display(), err))?;
+  bundle.validate(options)?;
+
+  let config_path = config_path(options)?;
+  let before = read_table(&config_path)?;
+  let mut after = if replace {
+    // Our own tokens never came along, so they must not go.
+    let mut after = toml::Table::new();
+    if let Some(tokens) = before.get("tokens") {
+      after.insert("tokens".to_string(), tokens.clone());
+    }
+    after
+  } else {
+    before.clone()
+  };
+  merge_tables(&mut after, &bundle.config);
+  validate_config(&after, options)?;
+
+  let state = State::load();
+  let (mutes, snoozes) = merged_state(&state, &bundle, replace);
+
+  let config_changes = config_diff(&before, &after);
+  let state_changes = state_diff(&state, &mutes, &snoozes);
+  if config_changes.is_empty() && state_changes.is_empty() {
+    println!("Nothing to change.");
+    return Ok(());
+  }
+  if !config_changes.is_empty() {
+    println!("# {}", config_path.display());
+    config_changes.iter().for_each(|line| println!("{}", line));
+  }
+  if !state_changes.is_empty() {
+    println!("# state");
+    state_changes.iter().for_each(|line| println!("{}", line));
+  }
+  if dry_run {
+    return Ok(());
+  }
+  if !yes {
+    if !stdin().is_terminal() {
+      return Err("Not applied; confirm with --yes".into());
+    }
+    print!("\nApply? [y/N] ");
+    stdout().flush()?;
+    let mut answer = String::new();
+    stdin().read_line(&mut answer)?;
+    if !matches!(answer.trim(), "y" | "Y") {
+      println!("Not applied.");
+      return Ok(());
+    }
+  }
+
+  if !config_changes.is_empty() {
+    if let Some(dir) = config_path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+    if config_path.exists() {
+      fs::copy(&config_path, config_path.with_extension("toml.bak"))?;
+    }
+    fs::write(&config_path, toml::to_string_pretty(&after)?)?;
+  }
+  if !state_changes.is_empty() {
+    State::update(|state| {
+      state.mutes = mutes;
+      state.snoozes = snoozes;
+    })?;
+  }
+  println!("Imported {}", path.display());
+  Ok(())
+}
@@ -0,0 +1,33 @@
+use crate::{gitlab::MrId, Result};
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub struct MrCache<K, V> {
+  entries: HashMap<MrId, (K, V)>,
+}
+
+impl<K, V> Default for MrCache<K, V> {
+  fn default() -> Self {
+    MrCache {
+      entries: HashMap::new(),
+    }
+  }
+}
+
+impl<K: PartialEq, V: Clone> MrCache<K, V> {
+  pub fn get_or_fetch(&mut self, id: MrId, key: K, fetch: impl FnOnce() -> Result<V>) -> Result<V> {
+    if let Some((cached_key, value)) = self.entries.get(&id) {
+      if *cached_key == key {
+        return Ok(value.clone());
+      }
+    }
+
+    let value = fetch()?;
+    self.entries.insert(id, (key, value.clone()));
+    Ok(value)
+  }
+
+  pub fn invalidate(&mut self, id: MrId) {
+    self.entries.remove(&id);
+  }
+}
@@ -0,0 +1,255 @@
+use crate::{gitlab::MergeRequest, Result};
+use std::process::Command;
+
+/// The git invocations checking out needs, so that they can be swapped out.
+pub trait Git {
+  /// Runs git with `args` in the current directory and returns what it
+  /// printed, failing if git does.
+  fn output(&self, args: &[&str]) -> Result<String>;
+
+  /// Runs git with `args`, letting it print to the terminal.
+  fn run(&self, args: &[String]) -> Result<()>;
+}
+
+/// The `git` on the path.
+pub struct SystemGit;
+
+impl Git for SystemGit {
+  fn output(&self, args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).output()?;
+    if !output.status.success() {
+      return Err(
+        format!(
+          "git {} failed: {}",
+          args.join(" "),
+          String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into(),
+      );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+  }
+
+  fn run(&self, args: &[String]) -> Result<()> {
+    let status = Command::new("git").args(args).status()?;
+    if !status.success() {
+      return Err(format!("git {} failed with {}", args.join(" "), status).into());
+    }
+    Ok(())
+  }
+}
+
+/// The project path a remote URL points to, for the URL forms git accepts:
+/// `https://host/group/project.git`, `ssh://git@host:22/group/project.git`
+/// and `git@host:group/project.git`.
+pub fn remote_project(url: &str) -> Option<String> {
+  let url = url.trim();
+  let path = match url.split_once("://") {
+    Some((_, rest)) => rest.split_once('/')?.1,
+    None => url.split_once(':')?.1,
+  };
+  let path = path.trim_matches('/');
+  let path = path.strip_suffix(".git").unwrap_or(path);
+  (!path.is_empty()).then(|| path.to_string())
+}
+
+/// The branch a merge request is checked out to, like `mr-123`.
+pub fn branch(mr: &MergeRequest) -> String {
+  format!("mr-{}", mr.iid)
+}
+
+/// The git arguments that check out a merge request. They fetch GitLab's
+/// merge request ref rather than the source branch, which works for merge
+/// requests from forks too, and reset the branch to it, so that checking
+/// out again picks up force-pushes, even while on the branch.
+pub fn commands(mr: &MergeRequest) -> Vec<Vec<String>> {
+  vec![
+    vec![
+      "fetch".to_string(),
+      "origin".to_string(),
+      format!("merge-requests/{}/head", mr.iid),
+    ],
+    vec![
+      "checkout".to_string(),
+      "-B".to_string(),
+      branch(mr),
+      "FETCH_HEAD".to_string(),
+    ],
+  ]
+}
+
+/// Refuses unless the current repository is a clean clone of the merge
+/// request's project.
+pub fn check(git: &impl Git, mr: &MergeRequest) -> Result<()> {
+  let url = git
+    .output(&["remote", "get-url", "origin"])
+    .map_err(|_| "Not in a git repository with an `origin` remote")?;
+  let project = mr.project_path();
+  match remote_project(&url) {
+    Some(remote) if remote.eq_ignore_ascii_case(project) => {}
+    Some(remote) => {
+      return Err(
+        format!(
+          "This repository is a clone of {}, not of {}",
+          remote, project
+        )
+        .into(),
+      )
+    }
+    None => return Err(format!("Can't tell which project `{}` is", url.trim()).into()),
+  }
+  if !git.output(&["status", "--porcelain"])?.trim().is_empty() {
+    return Err("The working tree has uncommitted changes; commit or stash them first".into());
+  }
+  Ok(())
+}
+
+/// Checks out a merge request in the current repository, or only prints the
+/// git commands that would.
+pub fn run(git: &impl Git, mr: &MergeRequest, dry_run: bool) -> Result<()> {
+  check(git, mr)?;
+  for args in commands(mr) {
+    if dry_run {
+      println!("git {}", args.join(" "));
+    } else {
+      git.run(&args)?;
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::mr;
+  use serde_json::json;
+  use std::cell::RefCell;
+
+  /// A repository cloned from `remote`, if any, with `status` as what
+  /// `git status --porcelain` prints.
+  struct FakeGit {
+    remote: Option<&'static str>,
+    status: &'static str,
+    ran: RefCell<Vec<String>>,
+  }
+
+  impl FakeGit {
+    fn new(remote: Option<&'static str>, status: &'static str) -> Self {
+      FakeGit {
+        remote,
+        status,
+        ran: RefCell::new(Vec::new()),
+      }
+    }
+  }
+
+  impl Git for FakeGit {
+    fn output(&self, args: &[&str]) -> Result<String> {
+      match args {
+        ["remote", "get-url", "origin"] => self
+          .remote
+          .map(|remote| format!("{}\n", remote))
+          .ok_or_else(|| "error: No such remote 'origin'".into()),
+        ["status", "--porcelain"] => Ok(self.status.to_string()),
+        _ => Err(format!("unexpected git {}", args.join(" ")).into()),
+      }
+    }
+
+    fn run(&self, args: &[String]) -> Result<()> {
+      self.ran.borrow_mut().push(args.join(" "));
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn remotes_name_their_project() {
+    for url in [
+      "https://gitlab.example.com/group/project.git",
+      "https://gitlab.example.com/group/project",
+      "https://gitlab.example.com:8443/group/project/",
+      "ssh://git@gitlab.example.com:22/group/project.git",
+      "git@gitlab.example.com:group/project.git",
+      "  git@gitlab.example.com:/group/project\n",
+    ] {
+      assert_eq!(
+        remote_project(url).as_deref(),
+        Some("group/project"),
+        "{}",
+        url
+      );
+    }
+    assert_eq!(
+      remote_project("https://gitlab.example.com/a/b/c.git").as_deref(),
+      Some("a/b/c")
+    );
+    for url in ["", "origin", "https://gitlab.example.com", "git@host:.git"] {
+      assert_eq!(remote_project(url), None, "{}", url);
+    }
+  }
+
+  #[test]
+  fn checking_out_fetches_the_merge_request_ref() {
+    let commands: Vec<String> = commands(&mr(42, json!({})))
+      .iter()
+      .map(|args| args.join(" "))
+      .collect();
+    assert_eq!(
+      commands,
+      [
+        "fetch origin merge-requests/42/head",
+        "checkout -B mr-42 FETCH_HEAD",
+      ]
+    );
+  }
+
+  #[test]
+  fn clean_clones_of_the_project_check_out() {
+    let git = FakeGit::new(Some("git@gitlab.example.com:Group/Project.git"), "");
+    run(&git, &mr(42, json!({})), false).unwrap();
+    assert_eq!(
+      *git.ran.borrow(),
+      [
+        "fetch origin merge-requests/42/head",
+        "checkout -B mr-42 FETCH_HEAD",
+      ]
+    );
+
+    let git = FakeGit::new(Some("https://gitlab.example.com/group/project.git"), "");
+    run(&git, &mr(42, json!({})), true).unwrap();
+    assert!(git.ran.borrow().is_empty());
+  }
+
+  fn refusal(remote: Option<&'static str>, status: &'static str) -> String {
+    let git = FakeGit::new(remote, status);
+    let err = run(&git, &mr(42, json!({})), false).unwrap_err();
+    assert!(git.ran.borrow().is_empty());
+    err.to_string()
+  }
+
+  #[test]
+  fn other_repositories_are_refused() {
+    assert_eq!(
+      refusal(Some("git@gitlab.example.com:group/other.git"), ""),
+      "This repository is a clone of group/other, not of group/project"
+    );
+    assert_eq!(
+      refusal(None, ""),
+      "Not in a git repository with an `origin` remote"
+    );
+    assert_eq!(
+      refusal(Some("/srv/mirror"), ""),
+      "Can't tell which project `/srv/mirror` is"
+    );
+  }
+
+  #[test]
+  fn dirty_trees_are_refused() {
+    assert_eq!(
+      refusal(
+        Some("git@gitlab.example.com:group/project.git"),
+        " M src/main.rs\n?? notes.txt\n"
+      ),
+      "The working tree has uncommitted changes; commit or stash them first"
+    );
+  }
+}
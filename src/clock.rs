@@ -0,0 +1,161 @@
+use chrono::{DateTime, Duration, Utc};
+use std::{
+  sync::atomic::{AtomicBool, AtomicI64, Ordering},
+  time::{Duration as StdDuration, Instant},
+};
+
+/// Beyond this, the local clock is considered off.
+pub const MAX_SKEW: Duration = Duration::minutes(5);
+
+// How far the server's clock is ahead of ours, from the latest response.
+static SKEW_SECONDS: AtomicI64 = AtomicI64::new(0);
+static MEASURED: AtomicBool = AtomicBool::new(false);
+
+/// Takes note of the time a response says the server had.
+pub fn record(server_time: DateTime<Utc>) {
+  SKEW_SECONDS.store((server_time - Utc::now()).num_seconds(), Ordering::Relaxed);
+  MEASURED.store(true, Ordering::Relaxed);
+}
+
+/// How far the server's clock is ahead of ours, once a response told.
+pub fn skew() -> Option<Duration> {
+  MEASURED
+    .load(Ordering::Relaxed)
+    .then(|| Duration::seconds(SKEW_SECONDS.load(Ordering::Relaxed)))
+}
+
+/// The server's time, to compare GitLab's timestamps against, so that a
+/// local clock running fast or slow doesn't shift ages and time windows.
+pub fn server_now() -> DateTime<Utc> {
+  Utc::now() + skew().unwrap_or_else(Duration::zero)
+}
+
+pub fn is_skewed(skew: Duration) -> bool {
+  skew.abs() > MAX_SKEW
+}
+
+pub fn warn_if_skewed() {
+  if let Some(skew) = skew().filter(|skew| is_skewed(*skew)) {
+    eprintln!(
+      "warning: the local clock is {}s {} GitLab's; ages are measured by the server's clock",
+      skew.num_seconds().abs(),
+      if skew > Duration::zero() {
+        "behind"
+      } else {
+        "ahead of"
+      }
+    );
+  }
+}
+
+/// How far the wall clock may get ahead of the monotonic clock during a
+/// wait before that counts as the system having slept.
+const SLEEP_GAP: StdDuration = StdDuration::from_secs(10);
+
+/// How often a wait for the next refresh looks out for the system having
+/// slept, which is how soon after resuming it refreshes.
+pub const WAKE_CHECK: StdDuration = StdDuration::from_secs(2);
+
+/// Whether the system slept during a wait meant to take `expected`, which
+/// took `monotonic` by the monotonic clock and `wall` by the wall clock.
+/// The monotonic clock stands still while the system is suspended on Linux
+/// and macOS, while the wall clock goes on; where both go on, the wait
+/// itself overruns. A wall clock set forward looks the same, which only
+/// costs a refresh.
+pub fn slept(expected: StdDuration, monotonic: StdDuration, wall: Duration) -> bool {
+  let wall = wall.to_std().unwrap_or_default();
+  wall > monotonic + SLEEP_GAP || monotonic > expected * 2 + SLEEP_GAP
+}
+
+/// Both clocks at the start of a wait, to tell afterwards whether the
+/// system slept through it.
+pub struct Watch {
+  monotonic: Instant,
+  wall: DateTime<Utc>,
+}
+
+impl Watch {
+  pub fn start() -> Self {
+    Watch::at(Instant::now(), Utc::now())
+  }
+
+  fn at(monotonic: Instant, wall: DateTime<Utc>) -> Self {
+    Watch { monotonic, wall }
+  }
+
+  pub fn slept(&self, expected: StdDuration) -> bool {
+    self.slept_by(expected, Instant::now(), Utc::now())
+  }
+
+  /// Whether the system slept, with both clocks now at `monotonic` and `wall`.
+  fn slept_by(&self, expected: StdDuration, monotonic: Instant, wall: DateTime<Utc>) -> bool {
+    slept(
+      expected,
+      monotonic.saturating_duration_since(self.monotonic),
+      wall - self.wall,
+    )
+  }
+}
+
+/// Waits `interval` for the next refresh, cut short when the system
+/// resumes from sleep, so that what was shown before doesn't linger on.
+pub fn wait(interval: StdDuration) {
+  let start = Instant::now();
+  loop {
+    let left = interval.saturating_sub(start.elapsed());
+    if left.is_zero() {
+      return;
+    }
+    let slice = left.min(WAKE_CHECK);
+    let watch = Watch::start();
+    std::thread::sleep(slice);
+    if watch.slept(slice) {
+      return;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const WAIT: StdDuration = StdDuration::from_secs(2);
+
+  /// Whether a wait for `WAIT` counts as slept through, when it took
+  /// `monotonic` seconds on one clock and `wall` seconds on the other.
+  fn slept_through(monotonic: u64, wall: i64) -> bool {
+    let (instant, time) = (Instant::now(), "2026-10-14T09:00:00Z".parse().unwrap());
+    let watch = Watch::at(instant, time);
+    watch.slept_by(
+      WAIT,
+      instant + StdDuration::from_secs(monotonic),
+      time + Duration::seconds(wall),
+    )
+  }
+
+  #[test]
+  fn waits_that_take_their_time_are_no_sleep() {
+    assert!(!slept_through(2, 2));
+    // Scheduling and clock adjustments move either a little.
+    assert!(!slept_through(3, 12));
+    assert!(!slept_through(2, 0));
+  }
+
+  #[test]
+  fn a_suspended_system_stops_the_monotonic_clock() {
+    assert!(slept_through(2, 3600));
+    assert!(slept_through(2, 13));
+  }
+
+  #[test]
+  fn waits_overrunning_on_both_clocks_count_as_sleep() {
+    assert!(slept_through(3600, 3600));
+    assert!(!slept_through(14, 14));
+    assert!(slept_through(15, 15));
+  }
+
+  #[test]
+  fn a_wall_clock_set_back_is_no_sleep() {
+    assert!(!slept_through(2, -3600));
+  }
+}
@@ -0,0 +1,997 @@
+use crate::{
+  actions::Lgtm,
+  digest::Since,
+  dirs::config_dir,
+  events::Alerts,
+  gitlab::User,
+  glob::glob_match,
+  layout::WidthBounds,
+  options::{AuthMode, GroupBy, Options, Theme},
+  rules::Rule,
+  secrets::IN_KEYRING,
+  terminal::{ColorDepth, Hyperlinks},
+  timezone::TimeZone,
+  views::{presets, View},
+  Result,
+};
+use chrono::Weekday;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+  borrow::Cow,
+  collections::BTreeMap,
+  fs,
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+const TEMPLATE: &str = r#"# gitlab-todo configuration
+#
+# Every setting is optional. Command-line flags take precedence over
+# GITLAB_TODO_* environment variables, which take precedence over this file.
+
+# GitLab instance, as a host name or base URL.
+# host = "gitlab.com"
+
+# GitLab user whose to-do list to show.
+# user = "me"
+
+# How to send the access token: "auto" picks the header from the token's
+# format, "bearer" or "private-token" force one.
+# auth_style = "auto"
+
+# Seconds between refreshes.
+# interval = 30
+
+# Seconds to wait for GitLab to answer a request before giving up on it; 0
+# waits as long as it takes. Hosts can have their own under
+# [request_timeouts].
+# request_timeout = 30
+
+# Fetch approvals, pipelines and threads only for this many of the
+# highest-ranked merge requests; defaults to what fits the terminal.
+# detail_limit = 30
+
+# Re-download the complete merge request lists every N refreshes.
+# full_resync_every = 10
+
+# Also list merge requests from branches I pushed to within this many days.
+# push_lookback_days = 14
+
+# In the interactive mode, record the time spent reviewing with a `/spend`
+# comment when I stop focusing on a merge request.
+# focus_spend = false
+
+# While another instance refreshes, refuse to start instead of refreshing
+# half as often.
+# single_instance = false
+
+# Comment posted when handing a review off to someone else in the
+# interactive mode, with {user} for their name; none by default.
+# handoff_note = "@{user} could you take this one?"
+
+# Show the list from the last run, marked stale, while the first refresh runs.
+# stale_start = true
+
+# Clear the whole screen on every refresh instead of redrawing the last list
+# in place, which keeps what scrolled by before it.
+# clear_screen = false
+
+# Write per-phase refresh timings here in the Prometheus text format, e.g.
+# for node exporter's textfile collector.
+# metrics_file = "/var/lib/node_exporter/gitlab-todo.prom"
+
+# Users whose assignment means a bot is taking care of the merge request.
+# bots = ["nomadic-margebot"]
+
+# Target branches that count as main branches where a project's default
+# branch can't be looked up.
+# main_branches = ["master", "main"]
+
+# Color theme matching the terminal background: "dark" or "light".
+# theme = "dark"
+
+# Clickable references: "auto" detects whether the terminal supports them,
+# "always" or "never" decide regardless. `gitlab-todo debug term` shows what
+# was detected.
+# hyperlinks = "auto"
+
+# Colors the terminal shows: "auto" tells from COLORTERM and TERM, or one of
+# "none", "8", "16", "256" and "truecolor".
+# color_depth = "auto"
+
+# View preset to start with; see [views] below.
+# view = "all"
+
+# Time zone for displayed times: "local", "utc" or an offset like "+02:00".
+# timezone = "local"
+
+# Glob patterns of CI job names known to fail spuriously; shown dimmed.
+# flaky_jobs = ["integration-*"]
+
+# Access tokens by host, used when GITLAB_TOKEN is not set. "keyring" reads
+# the host's token from the system keyring.
+[tokens]
+# "gitlab.example.com" = "glpat-..."
+
+# Request timeouts in seconds by host, over request_timeout.
+[request_timeouts]
+# "gitlab.example.com" = 60
+
+# Groups of usernames, to filter by as `authors = ["@backend"]`.
+[teams]
+# backend = ["alice", "bob", "carol"]
+
+# Short names for projects with long paths, shown in references like
+# `backend!412`. JSON output and links keep the full path.
+[aliases]
+# "company/sub/team-service-backend" = "backend"
+
+[columns]
+# author_width = "4..20"
+# assignee_width = "4..30"
+# reviewer_width = "4..30"
+# branch_width = "6..20"
+# milestone_width = "4..16"
+# activity_width = "8..30"
+# min_title_width = 20
+# column_gap = 1
+# ellipsis = "..."
+# two_line = false
+# badges = false
+# Description lines passed over for the subtitle in the two-line layout.
+# description_skip = ['## What does this MR do', '## Description']
+# last_activity = false
+# ticket_pattern = "[A-Z]+-[0-9]+"
+# ticket_url = "https://tracker.example.com/browse/{}"
+# failed_jobs = false
+# tasks = false
+# notes_count = false
+# Review effort left as S, M or L, from the files changed less those my
+# notes suggest I looked at. Up to effort_small files is S, up to
+# effort_medium is M.
+# effort = false
+# effort_small = 5
+# effort_medium = 20
+# tiers = false
+# Headings per milestone instead, soonest due first.
+# group_by = "milestone"
+# explain = false
+
+[weights]
+# failed_pipeline_bonus = 4
+# waiting_penalty = 2
+# eligible_approver_bonus = 1
+# blocked_penalty = 3
+# blocking_bonus = 1
+# fork_bonus = 0
+# upvote_bonus = 0
+# upvote_bonus_cap = 3
+# thread_reply_bonus = 2
+# changes_requested_bonus = 3
+# open_tasks_bonus = 2
+# decay_after = 7
+# decay_per_week = 1
+# decay_cap = 5
+# tier_high = 5
+# tier_low = 1
+
+[filters]
+# show_merged = 24
+# unassigned_in_group = ["platform/backend"]
+# triage_limit = 10
+# review_window = 14
+# hide_drafts = false
+# hide_inaccessible = false
+# target_branch = "release/*"
+# authors = ["alice", "@backend"]
+# milestone = "Sprint 12"
+# current_iteration = false
+# include_archived = false
+# include_issues = false
+# stale_report = false
+
+# How to alert on changes between refreshes: "none", "bell", "notify" (a
+# desktop notification) or "notify+bell". A table can add a command to run,
+# which gets GT_EVENT, GT_MR_REFERENCE, GT_MR_TITLE and GT_MR_URL in its
+# environment and is killed after 30 seconds.
+[alerts]
+# on_new_review_request = "none"
+# on_my_mr_approved = "none"
+# on_my_mr_conflicts = "none"
+# on_pipeline_failed = "none"
+# on_new_review_request = { alert = "notify", exec = ["mpv", "/usr/share/sounds/ding.ogg"] }
+
+# How long merge requests may wait for my review: amber after `warn`, red
+# after `breach`, as hours, days or weeks like "24h", "2d" or "1w". With
+# `business_days`, the `weekend` days don't count.
+[sla]
+# warn = "1d"
+# breach = "2d"
+# business_days = false
+# weekend = ["sat", "sun"]
+
+# The LGTM action: an optional comment, a 👍 unless `award` is false, and
+# the approval.
+[lgtm]
+# message = "LGTM"
+# award = true
+
+# Priority adjustments for projects, by path glob.
+[project_priority]
+# "infra/**" = 2
+
+# View presets, cycled with Tab in the interactive mode. Besides the built-in
+# "all", "actionable", "authored" and "reviewing", which can be redefined,
+# any number can be added. Filters are "actionable", "authored",
+# "reviewing" and "assigned"; sort is "priority", "updated", "created", "agenda" or
+# "effort", which --sort overrides.
+# [views.release]
+# filters = ["reviewing"]
+# sort = "updated"
+
+# Priority adjustments for merge requests matching an expression.
+# [[rules]]
+# name = "hotfix"
+# when = 'target_branch starts_with "release/"'
+# delta = 3
+
+# Overrides for the merge requests of projects matching a path glob, applied
+# after the global settings. Sections accept `main_branches`, `extra_bots`,
+# `[project.weights]` and `[project.filters]`.
+# [[project]]
+# path = "infra/**"
+# extra_bots = ["renovate-bot"]
+#
+# [project.filters]
+# hide_drafts = true
+"#;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Columns {
+  pub author_width: Option<WidthBounds>,
+  pub assignee_width: Option<WidthBounds>,
+  pub reviewer_width: Option<WidthBounds>,
+  pub two_line: Option<bool>,
+  pub badges: Option<bool>,
+  pub description_skip: Option<Vec<String>>,
+  pub branch_width: Option<WidthBounds>,
+  pub milestone_width: Option<WidthBounds>,
+  pub activity_width: Option<WidthBounds>,
+  pub min_title_width: Option<usize>,
+  pub column_gap: Option<usize>,
+  pub ellipsis: Option<String>,
+  pub last_activity: Option<bool>,
+  pub ticket_pattern: Option<String>,
+  pub ticket_url: Option<String>,
+  pub failed_jobs: Option<bool>,
+  pub tasks: Option<bool>,
+  pub notes_count: Option<bool>,
+  pub effort: Option<bool>,
+  pub effort_small: Option<usize>,
+  pub effort_medium: Option<usize>,
+  pub tiers: Option<bool>,
+  pub group_by: Option<GroupBy>,
+  pub explain: Option<bool>,
+  #[serde(flatten, skip_serializing)]
+  unknown: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Weights {
+  pub failed_pipeline_bonus: Option<isize>,
+  pub waiting_penalty: Option<isize>,
+  pub eligible_approver_bonus: Option<isize>,
+  pub blocked_penalty: Option<isize>,
+  pub blocking_bonus: Option<isize>,
+  pub fork_bonus: Option<isize>,
+  pub upvote_bonus: Option<isize>,
+  pub upvote_bonus_cap: Option<isize>,
+  pub thread_reply_bonus: Option<isize>,
+  pub changes_requested_bonus: Option<isize>,
+  pub open_tasks_bonus: Option<isize>,
+  pub decay_after: Option<u32>,
+  pub decay_per_week: Option<isize>,
+  pub decay_cap: Option<isize>,
+  pub tier_high: Option<isize>,
+  pub tier_low: Option<isize>,
+  #[serde(flatten, skip_serializing)]
+  unknown: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Filters {
+  pub show_merged: Option<u32>,
+  pub unassigned_in_group: Option<Vec<String>>,
+  pub triage_limit: Option<usize>,
+  pub review_window: Option<i64>,
+  pub hide_drafts: Option<bool>,
+  pub hide_inaccessible: Option<bool>,
+  pub target_branch: Option<String>,
+  pub authors: Option<Vec<String>>,
+  pub milestone: Option<String>,
+  pub current_iteration: Option<bool>,
+  pub include_archived: Option<bool>,
+  pub include_issues: Option<bool>,
+  pub stale_report: Option<bool>,
+  #[serde(flatten, skip_serializing)]
+  unknown: BTreeMap<String, toml::Value>,
+}
+
+// Plain values come before sections so that the effective configuration
+// serializes back into valid TOML.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+  pub host: Option<String>,
+  pub user: Option<String>,
+  pub auth_style: Option<AuthMode>,
+  pub interval: Option<u64>,
+  pub request_timeout: Option<u64>,
+  pub detail_limit: Option<usize>,
+  pub full_resync_every: Option<u64>,
+  pub push_lookback_days: Option<i64>,
+  pub focus_spend: Option<bool>,
+  pub single_instance: Option<bool>,
+  pub handoff_note: Option<String>,
+  pub stale_start: Option<bool>,
+  pub clear_screen: Option<bool>,
+  pub metrics_file: Option<PathBuf>,
+  pub bots: Option<Vec<String>>,
+  pub main_branches: Option<Vec<String>>,
+  pub theme: Option<Theme>,
+  pub hyperlinks: Option<Hyperlinks>,
+  pub color_depth: Option<ColorDepth>,
+  pub view: Option<String>,
+  pub timezone: Option<TimeZone>,
+  /// Glob patterns of CI job names that are known to fail spuriously.
+  pub flaky_jobs: Vec<String>,
+  /// Access tokens by host, for when `GITLAB_TOKEN` is not set.
+  pub tokens: BTreeMap<String, String>,
+  /// Request timeouts in seconds by host, over `request_timeout`.
+  pub request_timeouts: BTreeMap<String, u64>,
+  /// Usernames by team name, for author filters.
+  pub teams: BTreeMap<String, Vec<String>>,
+  /// Short names by project path, for references.
+  pub aliases: BTreeMap<String, String>,
+  pub columns: Columns,
+  pub weights: Weights,
+  pub filters: Filters,
+  pub alerts: Alerts,
+  pub lgtm: Lgtm,
+  pub sla: Sla,
+  pub project_priority: BTreeMap<String, isize>,
+  pub views: BTreeMap<String, View>,
+  pub rules: Vec<Rule>,
+  pub project: Vec<ProjectConfig>,
+  #[serde(flatten, skip_serializing)]
+  unknown: BTreeMap<String, toml::Value>,
+}
+
+// Overrides an option with its configured value unless the option was given
+// on the command line or through the environment.
+macro_rules! merge {
+  ($options:ident . $field:ident, $value:expr) => {
+    if let Some(value) = $value {
+      if !$options.explicit.contains(stringify!($field)) {
+        $options.$field = value;
+      }
+    }
+  };
+}
+
+impl Weights {
+  fn apply(&self, options: &mut Options) {
+    merge!(options.failed_pipeline_bonus, self.failed_pipeline_bonus);
+    merge!(options.waiting_penalty, self.waiting_penalty);
+    merge!(
+      options.eligible_approver_bonus,
+      self.eligible_approver_bonus
+    );
+    merge!(options.blocked_penalty, self.blocked_penalty);
+    merge!(options.blocking_bonus, self.blocking_bonus);
+    merge!(options.fork_bonus, self.fork_bonus);
+    merge!(options.upvote_bonus, self.upvote_bonus);
+    merge!(options.upvote_bonus_cap, self.upvote_bonus_cap);
+    merge!(options.thread_reply_bonus, self.thread_reply_bonus);
+    merge!(
+      options.changes_requested_bonus,
+      self.changes_requested_bonus
+    );
+    merge!(options.open_tasks_bonus, self.open_tasks_bonus);
+    merge!(options.decay_after, self.decay_after.map(Some));
+    merge!(options.decay_per_week, self.decay_per_week);
+    merge!(options.decay_cap, self.decay_cap);
+    merge!(options.tier_high, self.tier_high);
+    merge!(options.tier_low, self.tier_low);
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Sla {
+  pub warn: Option<Since>,
+  pub breach: Option<Since>,
+  pub business_days: Option<bool>,
+  pub weekend: Option<Vec<Weekday>>,
+  #[serde(flatten, skip_serializing)]
+  unknown: BTreeMap<String, toml::Value>,
+}
+
+impl Sla {
+  fn apply(&self, options: &mut Options) {
+    merge!(options.sla_warn, self.warn);
+    merge!(options.sla_breach, self.breach);
+    merge!(options.business_days, self.business_days);
+    merge!(options.weekend, self.weekend.clone());
+  }
+}
+
+impl Filters {
+  fn apply(&self, options: &mut Options) {
+    merge!(options.show_merged, self.show_merged.map(Some));
+    merge!(
+      options.unassigned_in_group,
+      self.unassigned_in_group.clone()
+    );
+    merge!(options.triage_limit, self.triage_limit);
+    merge!(options.review_window, self.review_window);
+    merge!(options.hide_drafts, self.hide_drafts);
+    merge!(options.hide_inaccessible, self.hide_inaccessible);
+    merge!(options.target_branch, self.target_branch.clone().map(Some));
+    merge!(options.authors, self.authors.clone());
+    merge!(options.milestone, self.milestone.clone().map(Some));
+    merge!(options.current_iteration, self.current_iteration);
+    merge!(options.include_archived, self.include_archived);
+    merge!(options.include_issues, self.include_issues);
+    merge!(options.stale_report, self.stale_report);
+  }
+}
+
+/// Settings for the merge requests of projects matching a path glob.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ProjectConfig {
+  pub path: String,
+  pub main_branches: Option<Vec<String>>,
+  /// Bots in addition to the global list.
+  pub extra_bots: Vec<String>,
+  pub weights: Weights,
+  pub filters: Filters,
+  #[serde(flatten, skip_serializing)]
+  unknown: BTreeMap<String, toml::Value>,
+}
+
+impl Config {
+  pub fn default_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+  }
+
+  pub fn load(path: Option<&Path>) -> Result<Self> {
+    let (path, required) = match path {
+      Some(path) => (path.to_path_buf(), true),
+      None => match Self::default_path() {
+        Some(path) => (path, false),
+        None => return Ok(Config::default()),
+      },
+    };
+
+    if !required && !path.exists() {
+      return Ok(Config::default());
+    }
+
+    let contents = fs::read_to_string(&path)
+      .map_err(|err| format!("Could not read config file {}: {}", path.display(), err))?;
+    let config = toml::from_str(&contents)
+      .map_err(|err| format!("Invalid config file {}: {}", path.display(), err))?;
+    Ok(config)
+  }
+
+  /// Writes the commented template, refusing to replace an existing file unless forced.
+  pub fn init(path: Option<&Path>, force: bool) -> Result<PathBuf> {
+    let path = match path {
+      Some(path) => path.to_path_buf(),
+      None => Self::default_path().ok_or("Could not determine the configuration directory")?,
+    };
+    if path.exists() && !force {
+      return Err(
+        format!(
+          "{} already exists (use --force to overwrite it)",
+          path.display()
+        )
+        .into(),
+      );
+    }
+
+    if let Some(dir) = path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, TEMPLATE)?;
+    Ok(path)
+  }
+
+  /// Writes the commented template to the default path with the given host
+  /// and user set, and the host's access token referred to the keyring where
+  /// it is kept there. Only the owner may read it all the same.
+  pub fn init_with(host: &str, user: Option<&str>, token_in_keyring: bool) -> Result<PathBuf> {
+    let path = Self::default_path().ok_or("Could not determine the configuration directory")?;
+    let quoted = |value: &str| toml::Value::String(value.to_string()).to_string();
+    let mut contents = format!("host = {}\n", quoted(host));
+    if let Some(user) = user {
+      contents.push_str(&format!("user = {}\n", quoted(user)));
+    }
+    contents.push('\n');
+    contents.push_str(&match token_in_keyring {
+      true => TEMPLATE.replacen(
+        "\n[tokens]\n",
+        &format!("\n[tokens]\n{} = {}\n", quoted(host), quoted(IN_KEYRING)),
+        1,
+      ),
+      false => TEMPLATE.to_string(),
+    });
+
+    if let Some(dir) = path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+    let mut file = fs::OpenOptions::new();
+    file.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut file, 0o600);
+    std::io::Write::write_all(&mut file.open(&path)?, contents.as_bytes())?;
+    Ok(path)
+  }
+
+  /// Keys in the file that no setting reads, with their section as prefix.
+  pub fn unknown_keys(&self) -> Vec<String> {
+    let sections = [
+      ("", &self.unknown),
+      ("columns.", &self.columns.unknown),
+      ("weights.", &self.weights.unknown),
+      ("filters.", &self.filters.unknown),
+      ("alerts.", &self.alerts.unknown),
+      ("lgtm.", &self.lgtm.unknown),
+      ("sla.", &self.sla.unknown),
+    ];
+    let mut keys: Vec<String> = sections
+      .into_iter()
+      .flat_map(|(prefix, unknown)| unknown.keys().map(move |key| format!("{}{}", prefix, key)))
+      .collect();
+    for project in &self.project {
+      let sections = [
+        ("", &project.unknown),
+        ("weights.", &project.weights.unknown),
+        ("filters.", &project.filters.unknown),
+      ];
+      keys.extend(sections.into_iter().flat_map(|(prefix, unknown)| {
+        unknown
+          .keys()
+          .map(move |key| format!("project.{}.{}{}", project.path, prefix, key))
+      }));
+    }
+    for (name, view) in &self.views {
+      keys.extend(
+        view
+          .unknown
+          .keys()
+          .map(|key| format!("views.{}.{}", name, key)),
+      );
+    }
+    for rule in &self.rules {
+      keys.extend(
+        rule
+          .unknown_keys
+          .iter()
+          .map(|key| format!("rules.{}.{}", rule.name, key)),
+      );
+    }
+    keys
+  }
+
+  pub fn apply(&self, options: &mut Options) -> Result<()> {
+    let columns = &self.columns;
+
+    merge!(options.host, self.host.clone());
+    merge!(options.user, self.user.clone().map(Some));
+    merge!(options.auth_style, self.auth_style);
+    merge!(options.interval, self.interval);
+    merge!(options.request_timeout, self.request_timeout);
+    merge!(options.detail_limit, self.detail_limit.map(Some));
+    merge!(options.full_resync_every, self.full_resync_every);
+    merge!(options.push_lookback_days, self.push_lookback_days);
+    merge!(options.focus_spend, self.focus_spend);
+    merge!(options.single_instance, self.single_instance);
+    merge!(options.handoff_note, self.handoff_note.clone().map(Some));
+    merge!(options.no_stale_start, self.stale_start.map(|start| !start));
+    merge!(options.clear_screen, self.clear_screen);
+    merge!(options.metrics_file, self.metrics_file.clone().map(Some));
+    merge!(options.bots, self.bots.clone());
+    merge!(options.main_branches, self.main_branches.clone());
+    merge!(options.theme, self.theme);
+    merge!(options.hyperlinks, self.hyperlinks);
+    merge!(options.color_depth, self.color_depth);
+    merge!(options.view, self.view.clone());
+    merge!(options.timezone, self.timezone);
+
+    merge!(options.author_width, columns.author_width);
+    merge!(options.assignee_width, columns.assignee_width);
+    merge!(options.reviewer_width, columns.reviewer_width);
+    merge!(options.two_line, columns.two_line);
+    merge!(options.badges, columns.badges);
+    merge!(options.description_skip, columns.description_skip.clone());
+    merge!(options.branch_width, columns.branch_width);
+    merge!(options.milestone_width, columns.milestone_width);
+    merge!(options.activity_width, columns.activity_width);
+    merge!(options.min_title_width, columns.min_title_width);
+    merge!(options.column_gap, columns.column_gap);
+    merge!(options.ellipsis, columns.ellipsis.clone());
+    merge!(options.last_activity, columns.last_activity);
+    merge!(
+      options.ticket_pattern,
+      columns
+        .ticket_pattern
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .map_err(|err| format!("Invalid ticket_pattern in config file: {}", err))?
+        .map(Some)
+    );
+    merge!(options.ticket_url, columns.ticket_url.clone().map(Some));
+    merge!(options.failed_jobs, columns.failed_jobs);
+    merge!(options.tasks, columns.tasks);
+    merge!(options.notes_count, columns.notes_count);
+    merge!(options.effort, columns.effort);
+    merge!(options.effort_small, columns.effort_small);
+    merge!(options.effort_medium, columns.effort_medium);
+    merge!(options.tiers, columns.tiers);
+    merge!(options.group_by, columns.group_by.map(Some));
+    merge!(options.explain, columns.explain);
+
+    self.weights.apply(options);
+    self.filters.apply(options);
+    self.sla.apply(options);
+    options.aliases = self.checked_aliases()?;
+    Ok(())
+  }
+
+  /// The project aliases, unless two projects share one, which would make
+  /// their references look alike.
+  fn checked_aliases(&self) -> Result<BTreeMap<String, String>> {
+    let mut projects: BTreeMap<&str, &str> = BTreeMap::new();
+    for (path, alias) in &self.aliases {
+      if let Some(other) = projects.insert(alias, path) {
+        return Err(
+          format!(
+            "Alias '{}' is given to both {} and {}; aliases must be unique",
+            alias, other, path
+          )
+          .into(),
+        );
+      }
+    }
+    Ok(self.aliases.clone())
+  }
+
+  /// Options for merge requests of the given project: the global options with every matching
+  /// `[[project]]` section applied in file order, while flags and environment still win.
+  pub fn project_options<'a>(&self, options: &'a Options, project_path: &str) -> Cow<'a, Options> {
+    let mut sections = self
+      .project
+      .iter()
+      .filter(|project| glob_match(&project.path, project_path))
+      .peekable();
+    if sections.peek().is_none() {
+      return Cow::Borrowed(options);
+    }
+
+    let mut options = options.clone();
+    for project in sections {
+      merge!(options.main_branches, project.main_branches.clone());
+      options.bots.extend(project.extra_bots.iter().cloned());
+      project.weights.apply(&mut options);
+      project.filters.apply(&mut options);
+    }
+    Cow::Owned(options)
+  }
+
+  /// Whether `user` is one of the authors `options` filter on, or in one of
+  /// the teams among them; everyone is while there are none.
+  pub fn is_author(&self, options: &Options, user: &User) -> bool {
+    let ids = &options.user_ids;
+    options.authors.is_empty()
+      || options
+        .authors
+        .iter()
+        .any(|author| match author.strip_prefix('@') {
+          Some(team) => self
+            .teams
+            .get(team)
+            .is_some_and(|members| members.iter().any(|member| ids.is(member, user))),
+          None => ids.is(author, user),
+        })
+  }
+
+  /// Fails on author filters naming teams that aren't configured.
+  pub fn check_teams(&self, options: &Options) -> Result<()> {
+    let authors = options.authors.iter().chain(
+      self
+        .project
+        .iter()
+        .filter_map(|project| project.filters.authors.as_ref())
+        .flatten(),
+    );
+    for author in authors {
+      let Some(team) = author.strip_prefix('@') else {
+        continue;
+      };
+      if !self.teams.contains_key(team) {
+        let known: Vec<&str> = self.teams.keys().map(String::as_str).collect();
+        return Err(if known.is_empty() {
+          format!("Unknown team '{}'; no [teams] are configured", team).into()
+        } else {
+          format!(
+            "Unknown team '{}'; configured teams: {}",
+            team,
+            known.join(", ")
+          )
+          .into()
+        });
+      }
+    }
+    Ok(())
+  }
+
+  /// The longest any project looks back for merge requests to review.
+  pub fn widest_review_window(&self, options: &Options) -> i64 {
+    self
+      .project
+      .iter()
+      .filter_map(|project| project.filters.review_window)
+      .fold(options.review_window, i64::max)
+  }
+
+  /// The configuration as it takes effect, with every setting filled in from the merged options.
+  pub fn effective(&self, options: &Options) -> Self {
+    Config {
+      host: Some(options.host.clone()),
+      user: options.user.clone(),
+      auth_style: Some(options.auth_style),
+      interval: Some(options.interval),
+      request_timeout: Some(options.request_timeout),
+      detail_limit: options.detail_limit,
+      full_resync_every: Some(options.full_resync_every),
+      push_lookback_days: Some(options.push_lookback_days),
+      focus_spend: Some(options.focus_spend),
+      single_instance: Some(options.single_instance),
+      handoff_note: options.handoff_note.clone(),
+      stale_start: Some(!options.no_stale_start),
+      clear_screen: Some(options.clear_screen),
+      metrics_file: options.metrics_file.clone(),
+      bots: Some(options.bots.clone()),
+      main_branches: Some(options.main_branches.clone()),
+      theme: Some(options.theme),
+      hyperlinks: Some(options.hyperlinks),
+      color_depth: Some(options.color_depth),
+      view: Some(options.view.clone()),
+      timezone: Some(options.timezone),
+      flaky_jobs: self.flaky_jobs.clone(),
+      // Secrets stay out of the printed configuration.
+      tokens: BTreeMap::new(),
+      request_timeouts: self.request_timeouts.clone(),
+      teams: self.teams.clone(),
+      aliases: self.aliases.clone(),
+      columns: Columns {
+        author_width: Some(options.author_width),
+        assignee_width: Some(options.assignee_width),
+        reviewer_width: Some(options.reviewer_width),
+        two_line: Some(options.two_line),
+        badges: Some(options.badges),
+        description_skip: Some(options.description_skip.clone()),
+        branch_width: Some(options.branch_width),
+        milestone_width: Some(options.milestone_width),
+        activity_width: Some(options.activity_width),
+        min_title_width: Some(options.min_title_width),
+        column_gap: Some(options.column_gap),
+        ellipsis: Some(options.ellipsis.clone()),
+        last_activity: Some(options.last_activity),
+        ticket_pattern: options
+          .ticket_pattern
+          .as_ref()
+          .map(|pattern| pattern.as_str().to_string()),
+        ticket_url: options.ticket_url.clone(),
+        failed_jobs: Some(options.failed_jobs),
+        tasks: Some(options.tasks),
+        notes_count: Some(options.notes_count),
+        effort: Some(options.effort),
+        effort_small: Some(options.effort_small),
+        effort_medium: Some(options.effort_medium),
+        tiers: Some(options.tiers),
+        group_by: options.group_by,
+        explain: Some(options.explain),
+        unknown: BTreeMap::new(),
+      },
+      weights: Weights {
+        failed_pipeline_bonus: Some(options.failed_pipeline_bonus),
+        waiting_penalty: Some(options.waiting_penalty),
+        eligible_approver_bonus: Some(options.eligible_approver_bonus),
+        blocked_penalty: Some(options.blocked_penalty),
+        blocking_bonus: Some(options.blocking_bonus),
+        fork_bonus: Some(options.fork_bonus),
+        upvote_bonus: Some(options.upvote_bonus),
+        upvote_bonus_cap: Some(options.upvote_bonus_cap),
+        thread_reply_bonus: Some(options.thread_reply_bonus),
+        changes_requested_bonus: Some(options.changes_requested_bonus),
+        open_tasks_bonus: Some(options.open_tasks_bonus),
+        decay_after: options.decay_after,
+        decay_per_week: Some(options.decay_per_week),
+        decay_cap: Some(options.decay_cap),
+        tier_high: Some(options.tier_high),
+        tier_low: Some(options.tier_low),
+        unknown: BTreeMap::new(),
+      },
+      filters: Filters {
+        show_merged: options.show_merged,
+        unassigned_in_group: Some(options.unassigned_in_group.clone()),
+        triage_limit: Some(options.triage_limit),
+        review_window: Some(options.review_window),
+        hide_drafts: Some(options.hide_drafts),
+        hide_inaccessible: Some(options.hide_inaccessible),
+        target_branch: options.target_branch.clone(),
+        authors: Some(options.authors.clone()),
+        milestone: options.milestone.clone(),
+        current_iteration: Some(options.current_iteration),
+        include_archived: Some(options.include_archived),
+        include_issues: Some(options.include_issues),
+        stale_report: Some(options.stale_report),
+        unknown: BTreeMap::new(),
+      },
+      alerts: self.alerts.clone(),
+      lgtm: self.lgtm.clone(),
+      sla: Sla {
+        warn: Some(options.sla_warn),
+        breach: Some(options.sla_breach),
+        business_days: Some(options.business_days),
+        weekend: Some(options.weekend.clone()),
+        unknown: BTreeMap::new(),
+      },
+      project_priority: self.project_priority.clone(),
+      views: self.views.clone(),
+      rules: self.rules.clone(),
+      project: self.project.clone(),
+      unknown: BTreeMap::new(),
+    }
+  }
+
+  pub fn views(&self) -> Vec<(String, View)> {
+    presets(&self.views)
+  }
+
+  pub fn view(&self, name: &str) -> Result<View> {
+    let views = self.views();
+    match views.iter().find(|(preset, _)| preset == name) {
+      Some((_, view)) => Ok(view.clone()),
+      None => Err(
+        format!(
+          "Unknown view `{}`; expected one of {}",
+          name,
+          views
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+        )
+        .into(),
+      ),
+    }
+  }
+
+  pub fn project_priority(&self, project_path: &str) -> Option<(&str, isize)> {
+    self
+      .project_priority
+      .iter()
+      .filter(|(pattern, _)| glob_match(pattern, project_path))
+      .max_by_key(|(pattern, _)| pattern.len())
+      .map(|(pattern, weight)| (pattern.as_str(), *weight))
+  }
+
+  pub fn is_flaky(&self, job_name: &str) -> bool {
+    self
+      .flaky_jobs
+      .iter()
+      .any(|pattern| glob_match(pattern, job_name))
+  }
+
+  /// How long to wait on requests to the host, by its own timeout if it has
+  /// one; None to wait as long as they take.
+  pub fn request_timeout(&self, options: &Options) -> Option<Duration> {
+    let seconds = self
+      .request_timeouts
+      .get(&options.host)
+      .copied()
+      .unwrap_or(options.request_timeout);
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::{isolate, options};
+
+  const LAYERED: &str = r#"
+[filters]
+hide_drafts = false
+review_window = 20
+
+[[project]]
+path = "infra/*"
+extra_bots = ["terraform-bot"]
+
+[project.filters]
+hide_drafts = true
+review_window = 30
+"#;
+
+  /// The options for a merge request in `project`, as given by `args` and
+  /// then the layered configuration.
+  fn layered(args: &[&str], project: &str) -> Options {
+    let config: Config = toml::from_str(LAYERED).unwrap();
+    let mut options = options(args);
+    config.apply(&mut options).unwrap();
+    config.project_options(&options, project).into_owned()
+  }
+
+  #[test]
+  fn global_settings_apply_outside_matching_projects() {
+    let options = layered(&[], "app/frontend");
+    assert!(!options.hide_drafts);
+    assert_eq!(options.review_window, 20);
+    assert!(!options.bots.contains(&"terraform-bot".to_string()));
+  }
+
+  #[test]
+  fn project_settings_override_global_ones() {
+    let options = layered(&[], "infra/terraform");
+    assert!(options.hide_drafts);
+    assert_eq!(options.review_window, 30);
+    assert!(options.bots.contains(&"terraform-bot".to_string()));
+  }
+
+  #[test]
+  fn command_line_wins_over_both() {
+    let options = layered(&["--review-window", "7"], "infra/terraform");
+    assert_eq!(options.review_window, 7);
+    assert!(options.hide_drafts);
+
+    let options = layered(&["--hide-drafts"], "app/frontend");
+    assert!(options.hide_drafts);
+  }
+
+  /// The configuration `init_with` writes.
+  fn initialized(token_in_keyring: bool) -> Config {
+    isolate();
+    let path = Config::init_with("gitlab.example.com", Some("me"), token_in_keyring).unwrap();
+    let contents = fs::read_to_string(&path).unwrap();
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      let mode = fs::metadata(&path).unwrap().permissions().mode();
+      assert_eq!(mode & 0o777, 0o600);
+    }
+    fs::remove_file(&path).unwrap();
+    toml::from_str(&contents).unwrap()
+  }
+
+  #[test]
+  fn set_up_configurations_only_refer_to_the_keyring() {
+    let config = initialized(true);
+    assert_eq!(config.host.as_deref(), Some("gitlab.example.com"));
+    assert_eq!(config.user.as_deref(), Some("me"));
+    assert_eq!(config.tokens["gitlab.example.com"], IN_KEYRING);
+
+    let config = initialized(false);
+    assert!(config.tokens.is_empty());
+  }
+}
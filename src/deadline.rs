@@ -0,0 +1,72 @@
+use crate::gitlab::Milestone;
+use chrono::NaiveDate;
+use crossterm::style::Color;
+
+/// An item's own due date wins over its milestone's.
+pub fn deadline(due_date: Option<NaiveDate>, milestone: Option<&Milestone>) -> Option<NaiveDate> {
+  due_date.or_else(|| milestone.and_then(|milestone| milestone.due_date))
+}
+
+/// Red once overdue, yellow within the next 48 hours.
+pub fn deadline_color(deadline: NaiveDate, today: NaiveDate) -> Option<Color> {
+  match (deadline - today).num_days() {
+    days if days < 0 => Some(Color::Red),
+    0 | 1 => Some(Color::DarkYellow),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn day(date: &str) -> NaiveDate {
+    date.parse().unwrap()
+  }
+
+  fn milestone(due_date: Option<&str>) -> Milestone {
+    serde_json::from_value(serde_json::json!({ "title": "1.0", "due_date": due_date })).unwrap()
+  }
+
+  #[test]
+  fn due_dates_override_milestones() {
+    let release = milestone(Some("2026-11-01"));
+    assert_eq!(
+      deadline(Some(day("2026-10-20")), Some(&release)),
+      Some(day("2026-10-20"))
+    );
+    assert_eq!(
+      deadline(Some(day("2026-12-01")), Some(&release)),
+      Some(day("2026-12-01"))
+    );
+  }
+
+  #[test]
+  fn either_may_be_missing() {
+    assert_eq!(
+      deadline(None, Some(&milestone(Some("2026-11-01")))),
+      Some(day("2026-11-01"))
+    );
+    assert_eq!(
+      deadline(Some(day("2026-10-20")), None),
+      Some(day("2026-10-20"))
+    );
+    assert_eq!(deadline(None, Some(&milestone(None))), None);
+    assert_eq!(deadline(None, None), None);
+  }
+
+  #[test]
+  fn deadlines_turn_yellow_two_days_ahead_and_red_once_past() {
+    let today = day("2026-10-14");
+    assert_eq!(deadline_color(day("2026-10-13"), today), Some(Color::Red));
+    assert_eq!(
+      deadline_color(day("2026-10-14"), today),
+      Some(Color::DarkYellow)
+    );
+    assert_eq!(
+      deadline_color(day("2026-10-15"), today),
+      Some(Color::DarkYellow)
+    );
+    assert_eq!(deadline_color(day("2026-10-16"), today), None);
+  }
+}
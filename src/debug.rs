@@ -0,0 +1,263 @@
+use crate::{
+  gitlab::{Client, Issue, MergeRequest, Role, User, ASSIGNED_ISSUES},
+  options::Options,
+  terminal::{Depth, DETECTION_VARS},
+  Result,
+};
+use clap::ValueEnum;
+use regex::Regex;
+use serde::{
+  de::{self, Visitor},
+  Deserialize,
+};
+use serde_json::Value;
+
+const REDACTED: &str = "[redacted]";
+
+/// Whether an item of a payload can be read into our type.
+type Parse = fn(Value) -> serde_json::Result<()>;
+
+/// A request the tool makes, to be repeated for a look at the raw reply.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+  User,
+  ReviewerMrs,
+  AssigneeMrs,
+  AuthorMrs,
+  Issues,
+}
+
+impl Endpoint {
+  fn fetch(self, client: &Client, user: &User) -> Result<Value> {
+    match self {
+      Endpoint::User => client.get_raw("/user", &[]),
+      Endpoint::ReviewerMrs => user.get_raw_role_mrs(client, Role::Reviewer),
+      Endpoint::AssigneeMrs => user.get_raw_role_mrs(client, Role::Assignee),
+      Endpoint::AuthorMrs => user.get_raw_role_mrs(client, Role::Author),
+      Endpoint::Issues => client.get_raw("/issues", &ASSIGNED_ISSUES),
+    }
+  }
+
+  /// The fields read from each item, and whether an item can be read.
+  fn expected(self) -> (&'static [&'static str], Parse) {
+    match self {
+      Endpoint::User => (field_names(User::deserialize), |item| {
+        serde_json::from_value::<User>(item).map(drop)
+      }),
+      Endpoint::ReviewerMrs | Endpoint::AssigneeMrs | Endpoint::AuthorMrs => {
+        (field_names(MergeRequest::deserialize), |item| {
+          serde_json::from_value::<MergeRequest>(item).map(drop)
+        })
+      }
+      Endpoint::Issues => (field_names(Issue::deserialize), |item| {
+        serde_json::from_value::<Issue>(item).map(drop)
+      }),
+    }
+  }
+}
+
+/// A deserializer that serde's derived code hands the names of a struct's
+/// fields to, and that fails with them right after.
+struct FieldNames;
+
+/// How `FieldNames` fails: with the names, or with none for anything other
+/// than a struct.
+#[derive(Debug)]
+struct Fields(&'static [&'static str]);
+
+impl std::fmt::Display for Fields {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "fields {}", self.0.join(", "))
+  }
+}
+
+impl std::error::Error for Fields {}
+
+impl de::Error for Fields {
+  fn custom<T: std::fmt::Display>(_: T) -> Self {
+    Fields(&[])
+  }
+}
+
+impl<'de> de::Deserializer<'de> for FieldNames {
+  type Error = Fields;
+
+  fn deserialize_any<V: Visitor<'de>>(self, _: V) -> std::result::Result<V::Value, Fields> {
+    Err(Fields(&[]))
+  }
+
+  fn deserialize_struct<V: Visitor<'de>>(
+    self,
+    _: &'static str,
+    fields: &'static [&'static str],
+    _: V,
+  ) -> std::result::Result<V::Value, Fields> {
+    Err(Fields(fields))
+  }
+
+  serde::forward_to_deserialize_any! {
+    bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+    option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+    ignored_any
+  }
+}
+
+/// The top-level field names a derived `Deserialize` reads.
+fn field_names<T>(
+  deserialize: fn(FieldNames) -> std::result::Result<T, Fields>,
+) -> &'static [&'static str] {
+  match deserialize(FieldNames) {
+    Err(Fields(fields)) => fields,
+    Ok(_) => &[],
+  }
+}
+
+fn is_secret(key: &str) -> bool {
+  key == "name" || key == "username" || key.contains("email") || key.contains("token")
+}
+
+fn gather_secrets(payload: &Value, secrets: &mut Vec<String>) {
+  match payload {
+    Value::Object(fields) => {
+      for (key, value) in fields {
+        match value {
+          Value::String(text) if is_secret(key) && !text.is_empty() => secrets.push(text.clone()),
+          _ => gather_secrets(value, secrets),
+        }
+      }
+    }
+    Value::Array(items) => items.iter().for_each(|item| gather_secrets(item, secrets)),
+    _ => {}
+  }
+}
+
+/// Blanks out tokens, email addresses and names: where they are the value of
+/// a field like `username`, wherever else they turn up, as in a profile URL,
+/// and wherever one of `patterns` matches.
+pub fn redact(payload: &mut Value, patterns: &[Regex]) {
+  let mut secrets = Vec::new();
+  gather_secrets(payload, &mut secrets);
+  // Longer first, so that a name containing another is blanked out whole.
+  secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+  secrets.dedup();
+  let email = Regex::new(r"[^\s@/]+@[^\s@/]+\.[a-zA-Z]+").unwrap();
+  redact_strings(payload, &secrets, &[&[email], patterns].concat());
+}
+
+fn redact_strings(payload: &mut Value, secrets: &[String], patterns: &[Regex]) {
+  match payload {
+    Value::Object(fields) => fields
+      .values_mut()
+      .for_each(|value| redact_strings(value, secrets, patterns)),
+    Value::Array(items) => items
+      .iter_mut()
+      .for_each(|item| redact_strings(item, secrets, patterns)),
+    Value::String(text) => {
+      for secret in secrets {
+        *text = text.replace(secret.as_str(), REDACTED);
+      }
+      for pattern in patterns {
+        *text = pattern.replace_all(text, REDACTED).into_owned();
+      }
+    }
+    _ => {}
+  }
+}
+
+/// What the payload lacks compared to what we read, like
+/// `source_project_id: missing from 40 of 40`, and why items can't be read.
+pub fn findings(payload: &Value, expected: &[&str], parse: Parse) -> Vec<String> {
+  let items: Vec<&Value> = match payload {
+    Value::Array(items) => items.iter().collect(),
+    item => vec![item],
+  };
+  let mut findings = Vec::new();
+  for field in expected {
+    let missing = items
+      .iter()
+      .filter(|item| item.get(field).is_none())
+      .count();
+    if missing > 0 {
+      findings.push(format!(
+        "{}: missing from {} of {}",
+        field,
+        missing,
+        items.len()
+      ));
+    }
+  }
+  let errors: Vec<serde_json::Error> = items
+    .iter()
+    .filter_map(|item| parse((*item).clone()).err())
+    .collect();
+  if let Some(error) = errors.first() {
+    findings.push(format!(
+      "{} of {} can't be read, the first because: {}",
+      errors.len(),
+      items.len(),
+      error
+    ));
+  }
+  findings
+}
+
+/// Prints what an endpoint returns as pretty JSON, and on stderr which of
+/// the fields we read it lacks.
+pub fn dump(
+  client: &Client,
+  user: &User,
+  endpoint: Endpoint,
+  redacting: bool,
+  patterns: &[Regex],
+) -> Result<()> {
+  let mut payload = endpoint.fetch(client, user)?;
+  let (expected, parse) = endpoint.expected();
+  let findings = findings(&payload, expected, parse);
+  if redacting {
+    redact(&mut payload, patterns);
+  }
+  println!("{}", serde_json::to_string_pretty(&payload)?);
+  if findings.is_empty() {
+    eprintln!("Every field we read is there.");
+  }
+  for finding in findings {
+    eprintln!("{}", finding);
+  }
+  Ok(())
+}
+
+/// Prints what was concluded about the terminal and the variables it was
+/// concluded from, to paste into a report about garbled output.
+pub fn print_terminal(options: &Options) {
+  let setting = |value: Option<clap::builder::PossibleValue>| {
+    value.map_or(String::new(), |value| value.get_name().to_string())
+  };
+  let colors = match options.depth {
+    Depth::Mono => "none",
+    Depth::Ansi8 => "8",
+    Depth::Ansi16 => "16",
+    Depth::Ansi256 => "256",
+    Depth::TrueColor => "truecolor",
+  };
+  println!(
+    "Colors:      {} (--color-depth {})",
+    colors,
+    setting(options.color_depth.to_possible_value())
+  );
+  println!(
+    "Hyperlinks:  {} (--hyperlinks {})",
+    if options.links { "on" } else { "off" },
+    setting(options.hyperlinks.to_possible_value())
+  );
+  match crossterm::terminal::size() {
+    Ok((width, height)) => println!("Size:        {}x{}", width, height),
+    Err(_) => println!("Size:        unknown, not a terminal"),
+  }
+  println!("Environment:");
+  for name in DETECTION_VARS {
+    match std::env::var(name) {
+      Ok(value) => println!("  {}={}", name, value),
+      Err(_) => println!("  {} unset", name),
+    }
+  }
+}
@@ -0,0 +1,234 @@
+use crate::{
+  attention::reviewers_unavailable,
+  clock,
+  config::Config,
+  gitlab::{LinkedMergeRequest, User},
+  options::Options,
+  priority::Score,
+  sla, sparkline,
+  table::failed_jobs_label,
+  terminal::printable,
+  threads::ThreadCounts,
+  Row,
+};
+use crossterm::style::Stylize;
+
+fn usernames(users: &[User]) -> String {
+  if users.is_empty() {
+    return "nobody".to_string();
+  }
+  users
+    .iter()
+    .map(|user| format!("@{}", printable(&user.username)))
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+/// Reviewers with what their status says, like `@alice ⛔busy`.
+fn reviewer_names(row: &Row) -> String {
+  if row.mr.reviewers.is_empty() {
+    return "nobody".to_string();
+  }
+  row
+    .mr
+    .reviewers
+    .iter()
+    .map(|user| {
+      let name = format!("@{}", printable(&user.username));
+      match row
+        .reviewer_statuses
+        .get(&user.id)
+        .and_then(|status| status.marker())
+      {
+        Some(marker) => format!("{} {}", name, marker),
+        None => name,
+      }
+    })
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+fn references(mrs: &[LinkedMergeRequest]) -> String {
+  mrs
+    .iter()
+    .map(|mr| printable(&mr.references.full))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// Everything known about a single merge request, for the interactive detail pane.
+/// `my_upvote` is whether I gave a 👍, when that has been looked up.
+pub fn detail_lines(
+  row: &Row,
+  score: &Score,
+  options: &Options,
+  user: &User,
+  config: &Config,
+  my_upvote: Option<bool>,
+) -> Vec<String> {
+  let Row {
+    mr,
+    approval_info,
+    approval_rules,
+    pipeline,
+    dependencies,
+    activity,
+    ..
+  } = row;
+  let mut lines = vec![
+    format!(
+      "{} {}",
+      printable(&mr.references.full).blue(),
+      printable(&mr.title)
+    )
+    .bold()
+    .to_string(),
+    format!(
+      "{} → {}, by @{}, assigned to {}, reviewed by {}",
+      printable(&mr.source_branch),
+      printable(&mr.target_branch),
+      printable(&mr.author.username),
+      usernames(&mr.assignees),
+      reviewer_names(row)
+    ),
+    format!(
+      "Created {}, updated {}",
+      options.timezone.datetime(mr.created_at),
+      options.timezone.datetime(mr.updated_at)
+    ),
+  ];
+  if let Some(requested_at) = row.review_requested_at {
+    lines.push(format!(
+      "Review requested {}",
+      options.timezone.datetime(requested_at)
+    ));
+  }
+  if let Some(waited) = sla::waited(row, user, options, clock::server_now()) {
+    let mut line = format!("Waiting on my review for {}", sla::label(waited));
+    if sla::Level::of(waited, options) == sla::Level::Breach {
+      line = format!("{}, over the {} SLA", line, options.sla_breach);
+    }
+    lines.push(line);
+  }
+  if let Some(milestone) = &mr.milestone {
+    lines.push(format!("Milestone: {}", printable(&milestone.describe())));
+  }
+  if mr.is_from_fork() {
+    lines.push(match &row.fork_path {
+      Some(path) => format!("⑂ From the fork {}", printable(path)),
+      None => "⑂ From a fork".to_string(),
+    });
+  }
+  if reviewers_unavailable(row) {
+    lines.push("⛔ Everyone it waits on is busy or away".to_string());
+  }
+  if mr.is_locked() {
+    lines.push("🔐 Discussion locked".to_string());
+  }
+  if row.inaccessible {
+    lines.push("🔒 No longer accessible; the project may be private or archived".to_string());
+    return lines;
+  }
+
+  if row.approvals {
+    let pending_rules: Vec<_> = approval_rules
+      .iter()
+      .filter(|rule| !rule.approved)
+      .map(|rule| printable(&rule.name))
+      .collect();
+    let mut approvals = format!("Approvals left: {}", approval_info.approvals_left);
+    if !pending_rules.is_empty() {
+      approvals.push_str(&format!(" ({})", pending_rules.join(", ")));
+    }
+    lines.push(approvals);
+  }
+
+  if let Some(tasks) = mr.tasks() {
+    lines.push(format!("Tasks: {}", tasks));
+  }
+
+  if mr.user_notes_count > 0 {
+    let mut line = format!("Notes: {}", mr.user_notes_count);
+    // Threads are only there for merge requests looked at in detail.
+    if !row.discussions.is_empty() {
+      let participants = sparkline::participants(&row.discussions);
+      line.push_str(&format!(
+        " by {} {}, last {} days {}",
+        participants,
+        if participants == 1 {
+          "person"
+        } else {
+          "people"
+        },
+        sparkline::DAYS,
+        sparkline::sparkline(
+          &sparkline::note_times(&row.discussions),
+          clock::server_now(),
+          sparkline::DAYS
+        ),
+      ));
+    }
+    lines.push(line);
+  }
+
+  if let Some(pipeline) = pipeline {
+    let mut line = format!("Pipeline: {}", pipeline.status);
+    if !row.failed_jobs.is_empty() {
+      line.push_str(&format!(" in {}", failed_jobs_label(row, options, config)));
+    }
+    lines.push(line);
+  }
+
+  if let Some(train) = &row.merge_train {
+    let mut line = format!("🚂 Merge train: {} of {}", train.position, train.length);
+    if let Some(pipeline) = &train.pipeline {
+      line.push_str(&format!(
+        ", waiting on pipeline #{} ({})",
+        pipeline.id, pipeline.status
+      ));
+    }
+    lines.push(line);
+  } else if mr.merge_when_pipeline_succeeds {
+    lines.push("Auto-merge: once the pipeline passes".to_string());
+  }
+
+  if !dependencies.blocked_by.is_empty() || !dependencies.blocking.is_empty() {
+    let mut line = Vec::new();
+    if !dependencies.blocked_by.is_empty() {
+      line.push(format!(
+        "Blocked by {}",
+        references(&dependencies.blocked_by)
+      ));
+    }
+    if !dependencies.blocking.is_empty() {
+      line.push(format!("Blocks {}", references(&dependencies.blocking)));
+    }
+    lines.push(line.join("; "));
+  }
+
+  let threads = ThreadCounts::of(row, user);
+  if !threads.is_empty() {
+    lines.push(format!(
+      "Threads: {} awaiting you / {} awaiting author",
+      threads.awaiting_me, threads.awaiting_author
+    ));
+  }
+
+  let mut awards = format!("Awards: ▲{} ▼{}", mr.upvotes, mr.downvotes);
+  match my_upvote {
+    Some(true) => awards.push_str(", including my 👍"),
+    Some(false) => awards.push_str(", none from me"),
+    None => {}
+  }
+  lines.push(awards);
+
+  if let Some(activity) = activity {
+    lines.push(format!(
+      "Last activity: {}",
+      printable(&activity.to_string())
+    ));
+  }
+
+  lines.push(format!("Priority {}", score).dark_grey().to_string());
+  lines
+}
@@ -0,0 +1,81 @@
+use crate::{gitlab::FileDiff, layout::cell, Result};
+use crossterm::style::Stylize;
+
+// Past this, reading the diff in a terminal pane is no longer practical.
+const MAX_FILES: usize = 10;
+const MAX_CHANGED_LINES: usize = 200;
+
+fn changed_lines(file: &FileDiff) -> usize {
+  file
+    .diff
+    .lines()
+    .filter(|line| line.starts_with('+') || line.starts_with('-'))
+    .count()
+}
+
+/// File contents are shown verbatim: control characters are escaped so that
+/// nothing in a diff can drive the terminal.
+pub fn sanitize(line: &str) -> String {
+  line
+    .chars()
+    .flat_map(|c| match c {
+      '\t' => "    ".chars().collect::<Vec<_>>(),
+      c if c.is_control() => c.escape_unicode().collect(),
+      c => vec![c],
+    })
+    .collect()
+}
+
+fn file_header(file: &FileDiff) -> String {
+  if file.new_file {
+    format!("{} (new)", file.new_path)
+  } else if file.deleted_file {
+    format!("{} (deleted)", file.old_path)
+  } else if file.old_path != file.new_path {
+    format!("{} → {}", file.old_path, file.new_path)
+  } else {
+    file.new_path.clone()
+  }
+}
+
+/// Refuses merge requests too large to preview, pointing to the browser instead.
+pub fn check_size(files: &[FileDiff], web_url: &str) -> Result<()> {
+  let changed: usize = files.iter().map(changed_lines).sum();
+  if files.len() > MAX_FILES || changed > MAX_CHANGED_LINES {
+    return Err(
+      format!(
+        "Too large to preview ({} files, {} changed lines); open {} instead",
+        files.len(),
+        changed,
+        web_url
+      )
+      .into(),
+    );
+  }
+  Ok(())
+}
+
+/// A colored unified diff with a header per file, cut to `width`.
+pub fn diff_lines(files: &[FileDiff], width: usize) -> Vec<String> {
+  let mut lines = Vec::new();
+  for file in files {
+    lines.push(
+      cell(width, &sanitize(&file_header(file)))
+        .bold()
+        .to_string(),
+    );
+    for line in file.diff.lines() {
+      let text = cell(width, &sanitize(line));
+      lines.push(if line.starts_with("@@") {
+        text.cyan().to_string()
+      } else if line.starts_with('+') {
+        text.green().to_string()
+      } else if line.starts_with('-') {
+        text.red().to_string()
+      } else {
+        text
+      });
+    }
+  }
+  lines
+}
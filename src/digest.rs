@@ -0,0 +1,433 @@
+use crate::{
+  clock,
+  gitlab::{ApprovalInfo, Client, MergeRequest, MrIid, ProjectId, Role, User},
+  list::ago,
+  options::{Format, Options},
+  sync::Source,
+  timezone::TimeZone,
+  Result,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+/// A period such as how far back a digest reaches, as `N` followed by `h`,
+/// `d` or `w`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Since(Duration);
+
+impl Since {
+  pub fn duration(&self) -> Duration {
+    self.0
+  }
+}
+
+impl Display for Since {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let hours = self.0.num_hours();
+    if hours != 0 && hours % (24 * 7) == 0 {
+      write!(f, "{}w", hours / (24 * 7))
+    } else if hours != 0 && hours % 24 == 0 {
+      write!(f, "{}d", hours / 24)
+    } else {
+      write!(f, "{}h", hours)
+    }
+  }
+}
+
+impl TryFrom<String> for Since {
+  type Error = String;
+
+  fn try_from(input: String) -> std::result::Result<Self, Self::Error> {
+    input.parse()
+  }
+}
+
+impl From<Since> for String {
+  fn from(since: Since) -> Self {
+    since.to_string()
+  }
+}
+
+impl FromStr for Since {
+  type Err = String;
+
+  fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+    let invalid = || {
+      format!(
+        "Invalid period '{}': expected a number of hours, days or weeks like `7d`",
+        input
+      )
+    };
+    let split = input.len().saturating_sub(1);
+    let (count, unit) = (input.get(..split), input.get(split..));
+    let count: i64 = count
+      .and_then(|count| count.parse().ok())
+      .ok_or_else(invalid)?;
+    match unit {
+      Some("h") => Ok(Since(Duration::hours(count))),
+      Some("d") => Ok(Since(Duration::days(count))),
+      Some("w") => Ok(Since(Duration::weeks(count))),
+      _ => Err(invalid()),
+    }
+  }
+}
+
+/// How an entry is dated: when it happened, or how long it has been waiting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stamp {
+  Date(&'static str),
+  Age,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+  pub reference: String,
+  pub title: String,
+  pub web_url: String,
+  /// When it was merged or approved, or opened for the review sections.
+  pub at: DateTime<Utc>,
+}
+
+impl Entry {
+  fn new(mr: &MergeRequest, at: DateTime<Utc>) -> Self {
+    Entry {
+      reference: mr.references.full.clone(),
+      title: mr.title.clone(),
+      web_url: mr.web_url.clone(),
+      at,
+    }
+  }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Digest {
+  pub user: String,
+  pub from: DateTime<Utc>,
+  pub until: DateTime<Utc>,
+  /// My merge requests merged within the period.
+  pub merged: Vec<Entry>,
+  /// Merge requests I approved within the period.
+  pub approved: Vec<Entry>,
+  /// Open merge requests I review and haven't approved, oldest first.
+  pub awaiting_review: Vec<Entry>,
+  /// Merge requests I review that were opened within the period.
+  pub review_requests: Vec<Entry>,
+}
+
+impl Digest {
+  pub fn fetch(client: &Client, user: &User, since: Since, until: DateTime<Utc>) -> Result<Self> {
+    let from = until - since.0;
+
+    let (merged, failed) = user.get_merged_mrs(client, from)?;
+    for role in failed {
+      eprintln!(
+        "warning: could not list {} merge requests, continuing without them",
+        Source::Merged(role).describe()
+      );
+    }
+    let merged = merged
+      .into_values()
+      .filter(|mr| mr.author.id == user.id)
+      .filter_map(|mr| {
+        let merged_at = mr.merged_at.filter(|at| within(*at, from, until))?;
+        Some(Entry::new(&mr, merged_at))
+      })
+      .collect();
+
+    // The events API only takes whole days and treats `after` as exclusive,
+    // in whatever time zone the server runs; ask for a day more than needed
+    // and cut the period precisely afterwards.
+    let after = (from - Duration::days(1)).date_naive();
+    let mut approvals: HashMap<(ProjectId, MrIid), DateTime<Utc>> = HashMap::new();
+    for event in user.get_mr_events(client, "approved", after)? {
+      let Some(iid) = event.target_iid else {
+        continue;
+      };
+      if within(event.created_at, from, until) {
+        let at = approvals
+          .entry((event.project_id, iid))
+          .or_insert(event.created_at);
+        *at = (*at).max(event.created_at);
+      }
+    }
+    let approved = approvals
+      .into_iter()
+      .map(|((project_id, iid), at)| {
+        let mr = MergeRequest::get(client, project_id, iid)?;
+        Ok(Entry::new(&mr, at))
+      })
+      .collect::<Result<_>>()?;
+
+    let mut awaiting_review = Vec::new();
+    for mr in user.get_role_mrs(client, Role::Reviewer, None, None)? {
+      if mr.author.id == user.id {
+        continue;
+      }
+      let approval_info = ApprovalInfo::get(client, &mr)?.unwrap_or_default();
+      if !approval_info
+        .approved_by
+        .iter()
+        .any(|approver| approver.user.id == user.id)
+      {
+        awaiting_review.push(Entry::new(&mr, mr.created_at));
+      }
+    }
+
+    // GitLab doesn't say when a reviewer was added, so a merge request
+    // opened within the period stands in for a review requested within it.
+    let review_requests = user
+      .get_role_mrs(client, Role::Reviewer, Some(from), None)?
+      .into_iter()
+      .filter(|mr| mr.author.id != user.id && within(mr.created_at, from, until))
+      .map(|mr| Entry::new(&mr, mr.created_at))
+      .collect();
+
+    Ok(
+      Digest {
+        user: user.username.clone(),
+        from,
+        until,
+        merged,
+        approved,
+        awaiting_review,
+        review_requests,
+      }
+      .sorted(),
+    )
+  }
+
+  /// Orders every section by time and then reference, so that the same
+  /// responses always render the same digest.
+  fn sorted(mut self) -> Self {
+    for entries in [
+      &mut self.merged,
+      &mut self.approved,
+      &mut self.awaiting_review,
+      &mut self.review_requests,
+    ] {
+      entries.sort_by(|a, b| (a.at, &a.reference).cmp(&(b.at, &b.reference)));
+    }
+    self
+  }
+
+  fn sections(&self) -> [(&'static str, Stamp, &[Entry]); 4] {
+    [
+      ("Merged", Stamp::Date("merged"), &self.merged),
+      ("Approved", Stamp::Date("approved"), &self.approved),
+      ("Awaiting my review", Stamp::Age, &self.awaiting_review),
+      ("New review requests", Stamp::Age, &self.review_requests),
+    ]
+  }
+
+  fn describe(&self, stamp: Stamp, entry: &Entry, timezone: &TimeZone) -> String {
+    match stamp {
+      Stamp::Date(verb) => format!("{} {}", verb, timezone.format(entry.at, "%a %Y-%m-%d")),
+      Stamp::Age => format!("opened {}", ago(entry.at, self.until)),
+    }
+  }
+
+  fn period(&self, timezone: &TimeZone) -> String {
+    format!(
+      "{} to {}",
+      timezone.datetime(self.from),
+      timezone.datetime(self.until)
+    )
+  }
+
+  pub fn markdown(&self, timezone: &TimeZone) -> String {
+    let mut text = format!("# Digest for @{}\n\n{}\n", self.user, self.period(timezone));
+    for (section, stamp, entries) in self.sections() {
+      text.push_str(&format!("\n## {} ({})\n\n", section, entries.len()));
+      if entries.is_empty() {
+        text.push_str("None.\n");
+      }
+      for entry in entries {
+        text.push_str(&format!(
+          "- [{}]({}) {} ({})\n",
+          entry.reference,
+          entry.web_url,
+          entry.title,
+          self.describe(stamp, entry, timezone)
+        ));
+      }
+    }
+    text
+  }
+
+  pub fn list(&self, timezone: &TimeZone) -> String {
+    let mut text = format!("Digest for {}, {}.\n", self.user, self.period(timezone));
+    for (section, stamp, entries) in self.sections() {
+      text.push_str(&format!("\n{}, {}:\n", section, entries.len()));
+      for (number, entry) in entries.iter().enumerate() {
+        text.push_str(&format!(
+          "{}. '{}', {}, {}, link: {}\n",
+          number + 1,
+          entry.title,
+          entry.reference,
+          self.describe(stamp, entry, timezone),
+          entry.web_url
+        ));
+      }
+    }
+    text
+  }
+}
+
+fn within(at: DateTime<Utc>, from: DateTime<Utc>, until: DateTime<Utc>) -> bool {
+  from <= at && at <= until
+}
+
+pub fn run(client: &Client, user: &User, options: &Options, since: Since) -> Result<()> {
+  let digest = Digest::fetch(client, user, since, clock::server_now())?;
+  match options.format {
+    Format::Json => println!("{}", serde_json::to_string_pretty(&digest)?),
+    Format::List => print!("{}", digest.list(&options.timezone)),
+    Format::Table | Format::Prompt => print!("{}", digest.markdown(&options.timezone)),
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fixtures::{me, mr_json, user_json},
+    mock::{get, MockServer},
+  };
+  use serde_json::{json, Value};
+
+  fn at(time: &str) -> DateTime<Utc> {
+    time.parse().unwrap()
+  }
+
+  fn approvals(iid: usize, approved_by: Value) -> crate::tape::Exchange {
+    get(
+      &format!("/projects/5/merge_requests/{}/approvals", iid),
+      None,
+      json!({ "approvals_left": 1, "approved_by": approved_by }),
+    )
+  }
+
+  /// A week of mine, with every list in the order given or reversed.
+  fn server(reversed: bool) -> MockServer {
+    let list = |mut mrs: Vec<Value>| {
+      if reversed {
+        mrs.reverse();
+      }
+      Value::Array(mrs)
+    };
+    let me_json = user_json(1, "me");
+    MockServer::start(vec![
+      get(
+        "/merge_requests",
+        Some("state=merged"),
+        list(vec![
+          mr_json(
+            10,
+            json!({ "author": me_json, "merged_at": "2026-10-12T15:00:00Z" }),
+          ),
+          mr_json(
+            11,
+            json!({ "author": me_json, "merged_at": "2026-10-01T15:00:00Z" }),
+          ),
+          mr_json(12, json!({ "merged_at": "2026-10-12T16:00:00Z" })),
+          mr_json(
+            13,
+            json!({ "author": me_json, "merged_at": "2026-10-09T08:00:00Z" }),
+          ),
+        ]),
+      ),
+      get(
+        "/users/1/events",
+        Some("action=approved"),
+        list(vec![
+          json!({ "project_id": 5, "target_iid": 20, "created_at": "2026-10-09T10:00:00Z" }),
+          json!({ "project_id": 5, "target_iid": 20, "created_at": "2026-10-11T10:00:00Z" }),
+          json!({ "project_id": 5, "target_iid": 21, "created_at": "2026-10-01T10:00:00Z" }),
+          json!({ "project_id": 5, "target_iid": 22, "created_at": "2026-10-08T10:00:00Z" }),
+          json!({ "project_id": 5, "target_iid": null, "created_at": "2026-10-10T10:00:00Z" }),
+        ]),
+      ),
+      get(
+        "/projects/5/merge_requests/20",
+        None,
+        mr_json(20, json!({})),
+      ),
+      get(
+        "/projects/5/merge_requests/22",
+        None,
+        mr_json(22, json!({})),
+      ),
+      get(
+        "/merge_requests",
+        Some("reviewer_username=me"),
+        list(vec![
+          mr_json(30, json!({ "created_at": "2026-10-02T09:00:00Z" })),
+          mr_json(31, json!({ "created_at": "2026-10-13T08:00:00Z" })),
+          mr_json(32, json!({ "created_at": "2026-10-12T08:00:00Z" })),
+          mr_json(
+            33,
+            json!({ "author": me_json, "created_at": "2026-10-12T08:00:00Z" }),
+          ),
+        ]),
+      ),
+      get("/merge_requests", None, json!([])),
+      approvals(30, json!([])),
+      approvals(31, json!([])),
+      approvals(32, json!([{ "user": me_json }])),
+    ])
+  }
+
+  fn digest(reversed: bool) -> Digest {
+    let server = server(reversed);
+    let week = "7d".parse().unwrap();
+    Digest::fetch(&server.client(), &me(), week, at("2026-10-14T12:00:00Z")).unwrap()
+  }
+
+  #[test]
+  fn digests_render_the_same_from_the_same_responses() {
+    let utc = "utc".parse().unwrap();
+    let markdown = digest(false).markdown(&utc);
+    assert_eq!(markdown, digest(true).markdown(&utc));
+    assert_eq!(
+      markdown,
+      "# Digest for @me\n\
+       \n\
+       2026-10-07 12:00 to 2026-10-14 12:00\n\
+       \n\
+       ## Merged (2)\n\
+       \n\
+       - [group/project!13](https://gitlab.example.com/group/project/-/merge_requests/13) MR 13 (merged Fri 2026-10-09)\n\
+       - [group/project!10](https://gitlab.example.com/group/project/-/merge_requests/10) MR 10 (merged Mon 2026-10-12)\n\
+       \n\
+       ## Approved (2)\n\
+       \n\
+       - [group/project!22](https://gitlab.example.com/group/project/-/merge_requests/22) MR 22 (approved Thu 2026-10-08)\n\
+       - [group/project!20](https://gitlab.example.com/group/project/-/merge_requests/20) MR 20 (approved Sun 2026-10-11)\n\
+       \n\
+       ## Awaiting my review (2)\n\
+       \n\
+       - [group/project!30](https://gitlab.example.com/group/project/-/merge_requests/30) MR 30 (opened 12 days ago)\n\
+       - [group/project!31](https://gitlab.example.com/group/project/-/merge_requests/31) MR 31 (opened 1 day ago)\n\
+       \n\
+       ## New review requests (2)\n\
+       \n\
+       - [group/project!32](https://gitlab.example.com/group/project/-/merge_requests/32) MR 32 (opened 2 days ago)\n\
+       - [group/project!31](https://gitlab.example.com/group/project/-/merge_requests/31) MR 31 (opened 1 day ago)\n"
+    );
+  }
+
+  #[test]
+  fn periods_parse_and_print_alike() {
+    for input in ["36h", "3d", "2w"] {
+      assert_eq!(input.parse::<Since>().unwrap().to_string(), input);
+    }
+    assert_eq!("14d".parse::<Since>().unwrap().to_string(), "2w");
+    assert_eq!("48h".parse::<Since>().unwrap().to_string(), "2d");
+    assert!("7".parse::<Since>().is_err());
+    assert!("d".parse::<Since>().is_err());
+    assert!("7m".parse::<Since>().is_err());
+  }
+}
@@ -0,0 +1,27 @@
+use std::{
+  env,
+  path::{Path, PathBuf},
+};
+
+// The XDG variables win on every platform; without them each platform's
+// conventional location is used.
+fn platform_dir(variable: &str, fallback: &str) -> Option<PathBuf> {
+  let base = env::var_os(variable).map(PathBuf::from).or_else(|| {
+    if cfg!(windows) {
+      env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+      env::var_os("HOME").map(|home| Path::new(&home).join("Library/Application Support"))
+    } else {
+      env::var_os("HOME").map(|home| Path::new(&home).join(fallback))
+    }
+  })?;
+  Some(base.join("gitlab-todo"))
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+  platform_dir("XDG_CONFIG_HOME", ".config")
+}
+
+pub fn state_dir() -> Option<PathBuf> {
+  platform_dir("XDG_STATE_HOME", ".local/state")
+}
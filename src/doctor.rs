@@ -0,0 +1,432 @@
+use crate::{
+  access_token, clock,
+  config::Config,
+  gitlab::{ApprovalInfo, Client, Group, Role, TokenInfo, User, Version},
+  options::Options,
+  Result,
+};
+use chrono::{DateTime, Duration, Utc};
+use crossterm::style::Stylize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+  Pass,
+  Warn,
+  Fail,
+}
+
+/// The outcome of one check, with a hint on how to fix it unless it passed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+  pub name: &'static str,
+  pub status: Status,
+  pub detail: String,
+  pub hint: Option<String>,
+}
+
+impl Check {
+  fn pass(name: &'static str, detail: impl ToString) -> Self {
+    Check {
+      name,
+      status: Status::Pass,
+      detail: detail.to_string(),
+      hint: None,
+    }
+  }
+
+  fn warn(name: &'static str, detail: impl ToString, hint: impl ToString) -> Self {
+    Check {
+      name,
+      status: Status::Warn,
+      detail: detail.to_string(),
+      hint: Some(hint.to_string()),
+    }
+  }
+
+  fn fail(name: &'static str, detail: impl ToString, hint: impl ToString) -> Self {
+    Check {
+      name,
+      status: Status::Fail,
+      detail: detail.to_string(),
+      hint: Some(hint.to_string()),
+    }
+  }
+}
+
+pub fn check_token_present(token: &Result<String>) -> Check {
+  match token {
+    Ok(_) => Check::pass("Access token", "found"),
+    Err(err) => Check::fail(
+      "Access token",
+      err,
+      "Create a personal access token with the `read_api` scope in your GitLab profile",
+    ),
+  }
+}
+
+pub fn check_token_valid(client: &Client) -> Check {
+  match User::current(client) {
+    Ok(user) => Check::pass(
+      "Token validity",
+      format!("authenticated as @{}", user.username),
+    ),
+    Err(err) => Check::fail(
+      "Token validity",
+      err,
+      "The token may have expired or been revoked, or belongs to a different instance",
+    ),
+  }
+}
+
+pub fn check_scopes(client: &Client) -> Check {
+  match TokenInfo::get(client) {
+    Ok(info)
+      if info
+        .scopes
+        .iter()
+        .any(|scope| scope == "api" || scope == "read_api") =>
+    {
+      Check::pass("Token scopes", info.scopes.join(", "))
+    }
+    Ok(info) => Check::fail(
+      "Token scopes",
+      format!("only {}", info.scopes.join(", ")),
+      "Create a token with the `read_api` scope, or `api` to use the actions",
+    ),
+    Err(err) => Check::warn(
+      "Token scopes",
+      format!("could not be determined: {}", err),
+      "Tokens can only be inspected on GitLab 15.5 and later; make sure yours has `read_api`",
+    ),
+  }
+}
+
+pub fn check_version(client: &Client, options: &Options) -> Check {
+  match Version::get(client) {
+    Ok(version) => Check::pass(
+      "API",
+      format!("GitLab {} at {}", version.version, options.host),
+    ),
+    Err(err) => Check::fail(
+      "API",
+      err,
+      "Check `host`: it should be the instance's host name or URL, without `/api/v4`",
+    ),
+  }
+}
+
+pub fn check_clock(server_time: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Check {
+  let Some(server_time) = server_time else {
+    return Check::warn(
+      "Clock",
+      "the server did not send its time",
+      "Make sure the system clock is synchronized",
+    );
+  };
+  let skew = server_time - now;
+  if clock::is_skewed(skew) {
+    Check::warn(
+      "Clock",
+      format!(
+        "{}s off from the server; ages and windows are measured by the server's clock",
+        skew.num_seconds().abs()
+      ),
+      "Synchronize the system clock, e.g. with NTP, for times shown in local time",
+    )
+  } else {
+    Check::pass(
+      "Clock",
+      format!("{}s off from the server", skew.num_seconds().abs()),
+    )
+  }
+}
+
+pub fn check_user(client: &Client, options: &Options) -> (Check, Option<User>) {
+  let name = match options.user() {
+    Ok(name) => name,
+    Err(err) => {
+      let hint = "Pass the user name as an argument or set `user` in the config file";
+      return (Check::fail("User", err, hint), None);
+    }
+  };
+  match User::get(client, name) {
+    Ok(user) => (
+      Check::pass("User", format!("@{} found", user.username)),
+      Some(user),
+    ),
+    Err(err) => (
+      Check::fail(
+        "User",
+        err,
+        "Check the spelling; it is the @handle, not the display name",
+      ),
+      None,
+    ),
+  }
+}
+
+/// The dashboard's group in place of a user, whom it has none of.
+pub fn check_group(client: &Client, path: &str) -> Check {
+  match Group::get(client, path).and_then(|group| group.get_open_mrs(client)) {
+    Ok(mrs) => Check::pass(
+      "Group",
+      format!("{} with {} open merge requests", path, mrs.len()),
+    ),
+    Err(err) => Check::fail(
+      "Group",
+      err,
+      "Check --group: it is the full path, like `platform/backend`",
+    ),
+  }
+}
+
+pub fn check_list_query(client: &Client, user: &User) -> Check {
+  let since = clock::server_now() - Duration::days(1);
+  match user.get_role_mrs(client, Role::Reviewer, Some(since), None) {
+    Ok(mrs) => Check::pass(
+      "Merge request list",
+      format!("{} updated for review today", mrs.len()),
+    ),
+    Err(err) => Check::fail(
+      "Merge request list",
+      err,
+      "The token may lack access to merge requests, or the instance may be too old",
+    ),
+  }
+}
+
+/// Asks for the approvals of one of my merge requests, which instances with
+/// approvals turned off don't know.
+pub fn check_approvals(client: &Client, user: &User) -> Check {
+  let mrs = match user.get_role_mrs(client, Role::Author, None, None) {
+    Ok(mrs) => mrs,
+    Err(err) => return Check::warn("Approvals", err, "The approvals column may stay empty"),
+  };
+  let Some(mr) = mrs.first() else {
+    return Check::pass("Approvals", "not checked, no open merge request of mine");
+  };
+  match ApprovalInfo::get(client, mr) {
+    Ok(Some(_)) => Check::pass("Approvals", "available"),
+    Ok(None) => Check::warn(
+      "Approvals",
+      "disabled on this instance",
+      "Approvals are left out of the table and of priorities",
+    ),
+    Err(err) => Check::warn("Approvals", err, "The approvals column may stay empty"),
+  }
+}
+
+pub fn check_terminal(options: &Options) -> Check {
+  let colors = crossterm::style::available_color_count();
+  let links = if options.links { "with" } else { "without" };
+  if std::env::var_os("NO_COLOR").is_some() || colors < 8 {
+    Check::warn(
+      "Terminal",
+      format!("{} colors, {} hyperlinks", colors, links),
+      "Colors carry meaning in the table; try `--format list` for plain text",
+    )
+  } else {
+    Check::pass(
+      "Terminal",
+      format!("{} colors, {} hyperlinks", colors, links),
+    )
+  }
+}
+
+pub fn checks(options: &Options, config: &Config) -> Vec<Check> {
+  let token = access_token(options, config);
+  let mut checks = vec![check_token_present(&token)];
+  let client = token.and_then(|token| {
+    Client::new(&options.host, &token, options.auth_style.style())?
+      .with_timeout(config.request_timeout(options))
+  });
+  match client {
+    Ok(client) => {
+      let version = check_version(&client, options);
+      let reachable = version.status == Status::Pass;
+      checks.push(version);
+      // Everything else would only fail the same way.
+      if !reachable {
+        checks.push(check_terminal(options));
+        return checks;
+      }
+      checks.push(check_clock(
+        Version::get(&client)
+          .ok()
+          .and_then(|version| version.server_time),
+        Utc::now(),
+      ));
+      checks.push(check_token_valid(&client));
+      checks.push(check_scopes(&client));
+      if let Some(group) = options.group.as_ref().filter(|_| options.dashboard) {
+        checks.push(check_group(&client, group));
+      } else {
+        let (check, user) = check_user(&client, options);
+        checks.push(check);
+        if let Some(user) = user {
+          checks.push(check_list_query(&client, &user));
+          checks.push(check_approvals(&client, &user));
+        }
+      }
+    }
+    Err(err) if checks[0].status == Status::Pass => checks.push(Check::fail(
+      "API",
+      err,
+      "Check `host`: it should be the instance's host name or URL",
+    )),
+    Err(_) => {}
+  }
+  checks.push(check_terminal(options));
+  checks
+}
+
+pub fn run(options: &Options, config: &Config) -> Result<()> {
+  let checks = checks(options, config);
+  for check in &checks {
+    let mark = match check.status {
+      Status::Pass => "✔".green(),
+      Status::Warn => "!".yellow(),
+      Status::Fail => "✘".red(),
+    };
+    println!("{} {}: {}", mark, check.name.bold(), check.detail);
+    if let Some(hint) = &check.hint {
+      println!("  {}", hint.as_str().dark_grey());
+    }
+  }
+
+  let failed = checks
+    .iter()
+    .filter(|check| check.status == Status::Fail)
+    .count();
+  if failed > 0 {
+    return Err(format!("{} of {} checks failed", failed, checks.len()).into());
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fixtures::{me, mr_json, options, user_json},
+    mock::{get, reply, MockServer},
+  };
+  use serde_json::json;
+
+  fn at(time: &str) -> DateTime<Utc> {
+    time.parse().unwrap()
+  }
+
+  fn statuses(checks: &[Check]) -> Vec<Status> {
+    checks.iter().map(|check| check.status).collect()
+  }
+
+  #[test]
+  fn tokens_must_be_there() {
+    assert_eq!(
+      check_token_present(&Ok("token".into())).status,
+      Status::Pass
+    );
+    let missing = check_token_present(&Err("No access token".into()));
+    assert_eq!(missing.status, Status::Fail);
+    assert_eq!(missing.detail, "No access token");
+    assert!(missing.hint.unwrap().contains("read_api"));
+  }
+
+  #[test]
+  fn clocks_may_be_off_by_a_few_minutes() {
+    let now = at("2026-10-14T12:00:00Z");
+    let check = |server| check_clock(Some(at(server)), now);
+    assert_eq!(check("2026-10-14T12:04:00Z").status, Status::Pass);
+    assert_eq!(
+      check("2026-10-14T11:56:00Z").detail,
+      "240s off from the server"
+    );
+    assert_eq!(check("2026-10-14T12:06:00Z").status, Status::Warn);
+    assert_eq!(check_clock(None, now).status, Status::Warn);
+  }
+
+  #[test]
+  fn checks_against_a_working_instance_pass() {
+    let server = MockServer::start(vec![
+      get("/version", None, json!({ "version": "16.5.1" })),
+      get("/user", None, user_json(1, "me")),
+      get(
+        "/personal_access_tokens/self",
+        None,
+        json!({ "scopes": ["read_api"] }),
+      ),
+      get("/users", Some("username=me"), json!([user_json(1, "me")])),
+      get(
+        "/merge_requests",
+        Some("author_username=me"),
+        json!([mr_json(1, json!({}))]),
+      ),
+      get("/merge_requests", None, json!([])),
+      get(
+        "/projects/5/merge_requests/1/approvals",
+        None,
+        json!({ "approvals_left": 1, "approved_by": [] }),
+      ),
+    ]);
+    let client = server.client();
+    let options = options(&["--host", &server.url, "me"]);
+    let (user_check, user) = check_user(&client, &options);
+    let user = user.unwrap();
+    let checks = [
+      check_version(&client, &options),
+      check_token_valid(&client),
+      check_scopes(&client),
+      user_check,
+      check_list_query(&client, &user),
+      check_approvals(&client, &user),
+    ];
+    assert_eq!(statuses(&checks), [Status::Pass; 6], "{:#?}", checks);
+    assert_eq!(checks[1].detail, "authenticated as @me");
+    assert_eq!(checks[5].detail, "available");
+  }
+
+  #[test]
+  fn checks_against_a_misconfigured_instance_say_what_is_wrong() {
+    let server = MockServer::start(vec![
+      reply(
+        "GET",
+        "/user",
+        None,
+        401,
+        json!({ "message": "401 Unauthorized" }),
+      ),
+      get(
+        "/personal_access_tokens/self",
+        None,
+        json!({ "scopes": ["read_user"] }),
+      ),
+      get("/users", None, json!([])),
+    ]);
+    let client = server.client();
+    let options = options(&["--host", &server.url, "nobody"]);
+    let (user_check, user) = check_user(&client, &options);
+    assert!(user.is_none());
+    let checks = [
+      check_version(&client, &options),
+      check_token_valid(&client),
+      check_scopes(&client),
+      user_check,
+    ];
+    assert_eq!(statuses(&checks), [Status::Fail; 4], "{:#?}", checks);
+    assert_eq!(checks[2].detail, "only read_user");
+    assert!(checks.iter().all(|check| check.hint.is_some()));
+  }
+
+  #[test]
+  fn instances_without_approvals_only_warn() {
+    let server = MockServer::start(vec![get(
+      "/merge_requests",
+      Some("author_username=me"),
+      json!([mr_json(1, json!({}))]),
+    )]);
+    let check = check_approvals(&server.client(), &me());
+    assert_eq!(check.status, Status::Warn);
+    assert_eq!(check.detail, "disabled on this instance");
+  }
+}
@@ -0,0 +1,216 @@
+use crate::{
+  actions, clock,
+  gitlab::{Client, Discussion, PipelineStatus, Role, User},
+  list::ago,
+  options::Options,
+  stats::RefreshStats,
+  terminal::printable,
+  Caches, Result, Row,
+};
+use crossterm::style::Stylize;
+use std::io::{stdin, stdout, IsTerminal, Write};
+
+/// Whether a draft looks finished: its pipeline passed, it merges cleanly and
+/// no thread on it is left unresolved.
+pub fn probably_ready(row: &Row) -> bool {
+  row.detailed
+    && !row.mr.has_conflicts
+    && row
+      .pipeline
+      .as_ref()
+      .is_some_and(|pipeline| pipeline.status == PipelineStatus::Success)
+    && !row.discussions.iter().any(Discussion::is_unresolved)
+}
+
+/// My draft merge requests with their details, the probably ready ones
+/// first and the oldest first among them.
+fn fetch(client: &Client, user: &User, options: &Options, caches: &mut Caches) -> Result<Vec<Row>> {
+  let mut stats = RefreshStats::default();
+  let mut rows = Vec::new();
+  for mr in user.get_role_mrs(client, Role::Author, None, None)? {
+    if !mr.draft {
+      continue;
+    }
+    let mut row = Row::new(user, options, mr);
+    row.fetch_details(client, user, options, caches, &mut stats)?;
+    rows.push(row);
+  }
+  rows.sort_by_key(|row| (!probably_ready(row), row.mr.created_at));
+  Ok(rows)
+}
+
+fn line(number: usize, row: &Row) -> String {
+  let mr = &row.mr;
+  let mut parts = vec![format!(
+    "opened {}",
+    ago(mr.created_at, clock::server_now())
+  )];
+  match &row.pipeline {
+    Some(pipeline) => parts.push(format!("pipeline {}", pipeline.status)),
+    None => parts.push("no pipeline".to_string()),
+  }
+  if row.approvals_known() {
+    parts.push(format!(
+      "{} approved, {} left",
+      row.approval_info.approved_by.len(),
+      row.approval_info.approvals_left
+    ));
+  }
+  let mut line = format!(
+    "{}. {} {} ({})",
+    number,
+    printable(&mr.references.full).magenta(),
+    printable(actions::strip_draft_prefix(&mr.title)),
+    parts.join(", ")
+  );
+  if probably_ready(row) {
+    line.push_str(&format!(" {}", "✓ probably ready".green()));
+  }
+  line
+}
+
+/// Which drafts to mark ready, from numbers as listed or `ready` for all
+/// probably ready ones; None for anything else.
+fn selection(answer: &str, rows: &[Row]) -> Option<Vec<usize>> {
+  if answer.trim() == "ready" {
+    return Some(
+      rows
+        .iter()
+        .enumerate()
+        .filter(|(_, row)| probably_ready(row))
+        .map(|(index, _)| index)
+        .collect(),
+    );
+  }
+  answer
+    .split([' ', ','])
+    .filter(|word| !word.is_empty())
+    .map(|word| {
+      word
+        .parse::<usize>()
+        .ok()
+        .filter(|number| (1..=rows.len()).contains(number))
+        .map(|number| number - 1)
+    })
+    .collect()
+}
+
+/// Lists my drafts and, on a terminal, offers to mark some of them ready.
+pub fn run(client: &Client, user: &User, options: &Options, caches: &mut Caches) -> Result<()> {
+  let rows = fetch(client, user, options, caches)?;
+  if rows.is_empty() {
+    println!("No drafts.");
+    return Ok(());
+  }
+  for (index, row) in rows.iter().enumerate() {
+    println!("{}", line(index + 1, row));
+  }
+  if !stdin().is_terminal() {
+    return Ok(());
+  }
+
+  print!("\nMark ready: numbers like `1 3`, `ready` for all probably ready, or nothing: ");
+  stdout().flush()?;
+  let mut answer = String::new();
+  stdin().read_line(&mut answer)?;
+  let Some(selected) = selection(&answer, &rows) else {
+    return Err(format!("Not a selection: '{}'", answer.trim()).into());
+  };
+  for index in selected {
+    let mr = &rows[index].mr;
+    match actions::set_draft(client, mr, false) {
+      Ok(mr) => println!("{} is ready", mr.references.full),
+      Err(err) => eprintln!("Could not mark {} ready: {}", mr.references.full, err),
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fixtures::{mr, row, user_json},
+    gitlab::{Id, Pipeline},
+  };
+  use serde_json::json;
+
+  /// A detailed draft of mine with a passed pipeline, a resolved thread and
+  /// one just commented on.
+  fn ready() -> Row {
+    let mut row = row(mr(
+      1,
+      json!({ "draft": true, "author": user_json(1, "me") }),
+    ));
+    row.detailed = true;
+    row.pipeline = Some(pipeline(PipelineStatus::Success));
+    row.discussions = serde_json::from_value(json!([
+      thread("resolved", true, true),
+      thread("comment", false, false),
+    ]))
+    .unwrap();
+    row
+  }
+
+  fn pipeline(status: PipelineStatus) -> Pipeline {
+    Pipeline {
+      id: Id(7),
+      status,
+      web_url: String::new(),
+    }
+  }
+
+  fn thread(id: &str, resolvable: bool, resolved: bool) -> serde_json::Value {
+    json!({
+      "id": id,
+      "notes": [{
+        "author": user_json(2, "reviewer"),
+        "system": false,
+        "resolvable": resolvable,
+        "resolved": resolved,
+        "created_at": "2026-10-10T09:00:00Z",
+      }],
+    })
+  }
+
+  #[test]
+  fn passed_clean_and_resolved_is_ready() {
+    assert!(probably_ready(&ready()));
+  }
+
+  #[test]
+  fn any_one_thing_missing_holds_a_draft_back() {
+    let mut undetailed = ready();
+    undetailed.detailed = false;
+    assert!(!probably_ready(&undetailed));
+
+    let mut conflicted = ready();
+    conflicted.mr.has_conflicts = true;
+    assert!(!probably_ready(&conflicted));
+
+    let mut unresolved = ready();
+    unresolved.discussions = serde_json::from_value(json!([
+      thread("resolved", true, true),
+      thread("open", true, false),
+    ]))
+    .unwrap();
+    assert!(!probably_ready(&unresolved));
+  }
+
+  #[test]
+  fn only_a_passed_pipeline_counts() {
+    let mut without = ready();
+    without.pipeline = None;
+    assert!(!probably_ready(&without));
+    for status in [
+      PipelineStatus::Running,
+      PipelineStatus::Failed,
+      PipelineStatus::Manual,
+      PipelineStatus::Canceled,
+    ] {
+      let mut row = ready();
+      row.pipeline = Some(pipeline(status));
+      assert!(!probably_ready(&row), "{:?}", status);
+    }
+  }
+}
@@ -0,0 +1,127 @@
+use crate::{gitlab::User, options::Options, Row};
+use serde::Serialize;
+
+/// How much reviewing a merge request still takes me, in rough sizes to fit
+/// reviews into the gaps of a day.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Effort {
+  S,
+  M,
+  L,
+}
+
+impl Effort {
+  pub fn label(&self) -> &'static str {
+    match self {
+      Effort::S => "S",
+      Effort::M => "M",
+      Effort::L => "L",
+    }
+  }
+}
+
+/// Estimates the effort left from the files changed and how far I got:
+/// each note of mine counts as a fifth of the files looked at, up to three
+/// fifths, and while threads I started wait on the author only the
+/// follow-up is left, at most `M`. What remains is `S` up to `small` files,
+/// `M` up to `medium` and `L` beyond.
+pub fn estimate(
+  changed_files: usize,
+  my_notes: usize,
+  waiting_on_author: bool,
+  small: usize,
+  medium: usize,
+) -> Effort {
+  let reviewed = my_notes.min(3);
+  let remaining = changed_files * (5 - reviewed) / 5;
+  let effort = if remaining <= small {
+    Effort::S
+  } else if remaining <= medium {
+    Effort::M
+  } else {
+    Effort::L
+  };
+  if waiting_on_author {
+    effort.min(Effort::M)
+  } else {
+    effort
+  }
+}
+
+/// The effort left on a merge request I review, once its size is known.
+pub fn of(row: &Row, user: &User, options: &Options) -> Option<Effort> {
+  if !row.roles.reviewing() {
+    return None;
+  }
+  let changed_files = row.changed_files?;
+  let my_notes = row
+    .discussions
+    .iter()
+    .flat_map(|discussion| &discussion.notes)
+    .filter(|note| !note.system && note.author.id == user.id)
+    .count();
+  let waiting_on_author = row.discussions.iter().any(|discussion| {
+    discussion.is_unresolved()
+      && discussion
+        .started_by()
+        .is_some_and(|author| author.id == user.id)
+  });
+  Some(estimate(
+    changed_files,
+    my_notes,
+    waiting_on_author,
+    options.effort_small,
+    options.effort_medium,
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Sizes from untouched merge requests, up to 5 files small and 20 medium.
+  fn fresh(changed_files: usize) -> Effort {
+    estimate(changed_files, 0, false, 5, 20)
+  }
+
+  #[test]
+  fn sizes_break_after_the_thresholds() {
+    assert_eq!(fresh(0), Effort::S);
+    assert_eq!(fresh(5), Effort::S);
+    assert_eq!(fresh(6), Effort::M);
+    assert_eq!(fresh(20), Effort::M);
+    assert_eq!(fresh(21), Effort::L);
+    assert_eq!(fresh(500), Effort::L);
+  }
+
+  #[test]
+  fn each_note_of_mine_takes_off_a_fifth() {
+    // 25 files: 25, 20, 15 and then 10 left.
+    assert_eq!(estimate(25, 0, false, 5, 20), Effort::L);
+    assert_eq!(estimate(25, 1, false, 5, 20), Effort::M);
+    assert_eq!(estimate(25, 3, false, 5, 20), Effort::M);
+    // Rounded down: 8 files at two fifths reviewed leave 4.
+    assert_eq!(estimate(8, 2, false, 5, 20), Effort::S);
+  }
+
+  #[test]
+  fn notes_count_for_three_fifths_at_most() {
+    // With three fifths or more, 55 files leave 22.
+    assert_eq!(estimate(55, 3, false, 5, 20), Effort::L);
+    assert_eq!(estimate(55, 10, false, 5, 20), Effort::L);
+    assert_eq!(estimate(50, 10, false, 5, 20), Effort::M);
+  }
+
+  #[test]
+  fn waiting_on_the_author_leaves_only_the_follow_up() {
+    assert_eq!(estimate(500, 0, true, 5, 20), Effort::M);
+    assert_eq!(estimate(3, 0, true, 5, 20), Effort::S);
+  }
+
+  #[test]
+  fn thresholds_are_inclusive_upper_bounds() {
+    assert_eq!(estimate(10, 0, false, 10, 10), Effort::S);
+    assert_eq!(estimate(11, 0, false, 10, 10), Effort::L);
+    assert_eq!(estimate(1, 0, false, 0, 0), Effort::L);
+  }
+}
@@ -0,0 +1,643 @@
+use crate::{
+  gitlab::{Id, MergeRequest, MrId, PipelineStatus, User},
+  options::Options,
+  prompt::Summary,
+  sync::Source,
+  terminal::printable,
+  Row, Snapshot,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::{BTreeMap, HashMap},
+  io::{stdout, Write},
+  process::{Command, Stdio},
+  thread,
+  time::{Duration, Instant},
+};
+
+const HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+const HOOK_POLL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+  NewReviewRequest,
+  MyMrApproved,
+  MyMrConflicts,
+  PipelineFailed,
+  /// No longer listed, most likely because it was merged or closed.
+  Gone,
+}
+
+impl EventKind {
+  fn describe(&self) -> &'static str {
+    match self {
+      EventKind::NewReviewRequest => "Review requested",
+      EventKind::MyMrApproved => "Approved",
+      EventKind::MyMrConflicts => "Has conflicts",
+      EventKind::PipelineFailed => "Pipeline failed",
+      EventKind::Gone => "Gone",
+    }
+  }
+
+  /// As hooks get it in `GT_EVENT`.
+  fn name(&self) -> &'static str {
+    match self {
+      EventKind::NewReviewRequest => "new_review_request",
+      EventKind::MyMrApproved => "my_mr_approved",
+      EventKind::MyMrConflicts => "my_mr_conflicts",
+      EventKind::PipelineFailed => "pipeline_failed",
+      EventKind::Gone => "gone",
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+  pub kind: EventKind,
+  pub mr: MrId,
+  pub reference: String,
+  pub title: String,
+  pub web_url: String,
+  pub author: String,
+  /// Approvals given and required, as of the event.
+  pub approvals: (usize, usize),
+}
+
+impl Event {
+  fn of(kind: EventKind, row: &Row) -> Self {
+    let given = row.approval_info.approved_by.len();
+    Event {
+      approvals: (given, given + row.approval_info.approvals_left),
+      ..Event::about(kind, &row.mr)
+    }
+  }
+
+  /// Without the approvals, which only rows know.
+  fn about(kind: EventKind, mr: &MergeRequest) -> Self {
+    Event {
+      kind,
+      mr: mr.id,
+      reference: mr.references.full.clone(),
+      title: mr.title.clone(),
+      web_url: mr.web_url.clone(),
+      author: mr.author.username.clone(),
+      approvals: (0, 0),
+    }
+  }
+
+  /// One line of the change log, like `NEW review request: group/project!42 'Fix X' by alice`.
+  pub fn log_line(&self) -> String {
+    let Event {
+      reference, title, ..
+    } = self;
+    match self.kind {
+      EventKind::NewReviewRequest => format!(
+        "NEW review request: {} '{}' by {}",
+        reference, title, self.author
+      ),
+      EventKind::MyMrApproved => format!(
+        "APPROVED: my MR {} now has {}/{}",
+        reference, self.approvals.0, self.approvals.1
+      ),
+      EventKind::MyMrConflicts => format!("CONFLICTS: my MR {} '{}'", reference, title),
+      EventKind::PipelineFailed => format!("PIPELINE FAILED: {} '{}'", reference, title),
+      EventKind::Gone => format!("GONE (merged?): {} '{}'", reference, title),
+    }
+  }
+}
+
+/// The parts of a merge request whose transitions raise events, also kept
+/// between runs; fields missing from older state files read as unset.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Tracked {
+  pub review_requested: bool,
+  pub assigned: bool,
+  pub approved: bool,
+  pub has_conflicts: bool,
+  pub failed_pipeline: Option<Id>,
+}
+
+impl Tracked {
+  pub fn of(row: &Row) -> Self {
+    Tracked {
+      review_requested: row.roles.reviewing(),
+      assigned: row.roles.assigned_by_others(),
+      approved: row.roles.author && row.approvals && row.approval_info.approvals_left == 0,
+      has_conflicts: row.roles.author && row.mr.has_conflicts,
+      failed_pipeline: row
+        .pipeline
+        .as_ref()
+        .filter(|pipeline| row.roles.author && pipeline.status == PipelineStatus::Failed)
+        .map(|pipeline| pipeline.id),
+    }
+  }
+}
+
+/// Compares each refresh with the last state seen of every merge request.
+///
+/// Merge requests missing from a snapshot keep their last state rather than
+/// being forgotten, so one failed or partial refresh can neither swallow a
+/// transition nor report merge requests as new once they are back.
+///
+/// Leaving the list is only reported from snapshots where every source
+/// could be queried, and only for merge requests no longer listed at all:
+/// snoozing, muting or filtering one out of the rows is no reason to report it.
+#[derive(Debug, Default)]
+pub struct EventTracker {
+  seen: HashMap<MrId, Tracked>,
+  listed: HashMap<MrId, Event>,
+  primed: bool,
+}
+
+impl EventTracker {
+  pub fn update(&mut self, snapshot: &Snapshot) -> Vec<Event> {
+    let rows = snapshot.rows.iter().map(|(row, _)| row);
+    let mut events = Vec::new();
+    // The merged lists only add to the footer.
+    let complete = snapshot
+      .unavailable
+      .iter()
+      .all(|source| matches!(source, Source::Merged(_)));
+    if complete {
+      let set_aside = snapshot.set_aside.iter().map(|mr| {
+        // What a row last said about it beats what the list says.
+        let event = self
+          .listed
+          .get(&mr.id)
+          .cloned()
+          .unwrap_or_else(|| Event::about(EventKind::Gone, mr));
+        (mr.id, event)
+      });
+      let listed: HashMap<MrId, Event> = rows
+        .clone()
+        .map(|row| (row.mr.id, Event::of(EventKind::Gone, row)))
+        .chain(set_aside)
+        .collect();
+      let previous = std::mem::replace(&mut self.listed, listed);
+      if self.primed {
+        let mut gone: Vec<Event> = previous
+          .into_iter()
+          .filter(|(id, _)| !self.listed.contains_key(id))
+          .map(|(_, event)| event)
+          .collect();
+        gone.sort_by(|a, b| a.reference.cmp(&b.reference));
+        events.extend(gone);
+      }
+    }
+
+    // Rows without details would look like everything was reset.
+    for row in rows.filter(|row| row.detailed) {
+      let state = Tracked::of(row);
+      let previous = self.seen.insert(row.mr.id, state);
+      if !self.primed {
+        continue;
+      }
+
+      let kinds = match previous {
+        Some(previous) => transitions(&previous, &state),
+        None if state.review_requested => vec![EventKind::NewReviewRequest],
+        None => Vec::new(),
+      };
+      events.extend(kinds.into_iter().map(|kind| Event::of(kind, row)));
+    }
+
+    // The first snapshot only establishes what is already known.
+    self.primed = true;
+    events
+  }
+}
+
+pub fn transitions(previous: &Tracked, current: &Tracked) -> Vec<EventKind> {
+  let mut kinds = Vec::new();
+  if current.review_requested && !previous.review_requested {
+    kinds.push(EventKind::NewReviewRequest);
+  }
+  if current.approved && !previous.approved {
+    kinds.push(EventKind::MyMrApproved);
+  }
+  if current.has_conflicts && !previous.has_conflicts {
+    kinds.push(EventKind::MyMrConflicts);
+  }
+  // A different pipeline failing counts even if the previous one failed too.
+  if current.failed_pipeline.is_some() && current.failed_pipeline != previous.failed_pipeline {
+    kinds.push(EventKind::PipelineFailed);
+  }
+  kinds
+}
+
+/// Appends what changed since the last refresh to stdout, timestamped, for
+/// `--diff-refresh`. Like alerts, the first refresh reports no changes; it
+/// gets a summary of what is listed instead.
+pub fn print_log(
+  snapshot: &Snapshot,
+  events: &[Event],
+  first: bool,
+  user: &User,
+  options: &Options,
+) {
+  let now = options.timezone.format(Utc::now(), "%Y-%m-%d %H:%M:%S");
+  if first {
+    let summary = Summary::of(&snapshot.rows, user, options);
+    println!(
+      "{} Watching {} merge requests, {} actionable, {} urgent",
+      now,
+      snapshot.rows.len(),
+      summary.actionable,
+      summary.urgent
+    );
+  }
+  for event in events {
+    println!("{} {}", now, printable(&event.log_line()));
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Alert {
+  #[default]
+  #[serde(rename = "none")]
+  None,
+  #[serde(rename = "bell")]
+  Bell,
+  #[serde(rename = "notify")]
+  Notify,
+  #[serde(rename = "notify+bell")]
+  NotifyAndBell,
+}
+
+impl Alert {
+  fn bell(&self) -> bool {
+    matches!(self, Alert::Bell | Alert::NotifyAndBell)
+  }
+
+  fn notify(&self) -> bool {
+    matches!(self, Alert::Notify | Alert::NotifyAndBell)
+  }
+}
+
+/// An alert and a command to run for an event, written either as just the
+/// alert, like `"bell"`, or as a table like `{ alert = "bell", exec = ["mpv", "ding.ogg"] }`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(from = "AlertEntry", into = "AlertEntry")]
+pub struct AlertSetting {
+  pub alert: Alert,
+  /// Nothing runs unless this is set.
+  pub exec: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum AlertEntry {
+  Alert(Alert),
+  Table {
+    #[serde(default)]
+    alert: Alert,
+    #[serde(default)]
+    exec: Vec<String>,
+  },
+}
+
+impl From<AlertEntry> for AlertSetting {
+  fn from(entry: AlertEntry) -> Self {
+    match entry {
+      AlertEntry::Alert(alert) => AlertSetting {
+        alert,
+        exec: Vec::new(),
+      },
+      AlertEntry::Table { alert, exec } => AlertSetting { alert, exec },
+    }
+  }
+}
+
+impl From<AlertSetting> for AlertEntry {
+  fn from(setting: AlertSetting) -> Self {
+    if setting.exec.is_empty() {
+      AlertEntry::Alert(setting.alert)
+    } else {
+      AlertEntry::Table {
+        alert: setting.alert,
+        exec: setting.exec,
+      }
+    }
+  }
+}
+
+/// How to alert on each kind of event.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Alerts {
+  pub on_new_review_request: AlertSetting,
+  pub on_my_mr_approved: AlertSetting,
+  pub on_my_mr_conflicts: AlertSetting,
+  pub on_pipeline_failed: AlertSetting,
+  #[serde(flatten, skip_serializing)]
+  pub unknown: BTreeMap<String, toml::Value>,
+}
+
+impl Alerts {
+  fn for_kind(&self, kind: EventKind) -> Option<&AlertSetting> {
+    match kind {
+      EventKind::NewReviewRequest => Some(&self.on_new_review_request),
+      EventKind::MyMrApproved => Some(&self.on_my_mr_approved),
+      EventKind::MyMrConflicts => Some(&self.on_my_mr_conflicts),
+      EventKind::PipelineFailed => Some(&self.on_pipeline_failed),
+      EventKind::Gone => None,
+    }
+  }
+
+  pub fn deliver(&self, events: &[Event]) {
+    let mut bell = false;
+    for event in events {
+      let Some(setting) = self.for_kind(event.kind) else {
+        continue;
+      };
+      if !setting.exec.is_empty() {
+        run_hook(&setting.exec, event);
+      }
+      let alert = setting.alert;
+      bell |= alert.bell();
+      if alert.notify() {
+        notify(
+          &format!("{}: {}", event.kind.describe(), event.reference),
+          &event.title,
+        );
+      }
+    }
+
+    if bell {
+      let mut target = stdout();
+      let _ = target.write_all(b"\x07").and_then(|_| target.flush());
+    }
+  }
+}
+
+/// Runs a hook in the background, with the event in its environment. A hook
+/// that hangs is killed after a while, and failures are only reported.
+fn run_hook(exec: &[String], event: &Event) {
+  let Some((program, args)) = exec.split_first() else {
+    return;
+  };
+  let spawned = Command::new(program)
+    .args(args)
+    .env("GT_EVENT", event.kind.name())
+    .env("GT_MR_REFERENCE", &event.reference)
+    .env("GT_MR_TITLE", &event.title)
+    .env("GT_MR_URL", &event.web_url)
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .spawn();
+  let mut child = match spawned {
+    Ok(child) => child,
+    Err(err) => {
+      eprintln!(
+        "warning: could not run the {} hook: {}",
+        event.kind.name(),
+        err
+      );
+      return;
+    }
+  };
+
+  let name = event.kind.name();
+  thread::spawn(move || {
+    let started = Instant::now();
+    loop {
+      match child.try_wait() {
+        Ok(Some(status)) if status.success() => return,
+        Ok(Some(status)) => {
+          eprintln!("warning: the {} hook failed with {}", name, status);
+          return;
+        }
+        Ok(None) if started.elapsed() < HOOK_TIMEOUT => thread::sleep(HOOK_POLL),
+        Ok(None) => {
+          let _ = child.kill();
+          let _ = child.wait();
+          eprintln!(
+            "warning: killed the {} hook after {}s",
+            name,
+            HOOK_TIMEOUT.as_secs()
+          );
+          return;
+        }
+        Err(err) => {
+          eprintln!("warning: lost track of the {} hook: {}", name, err);
+          return;
+        }
+      }
+    }
+  });
+}
+
+// Desktop notifications are best effort; a missing notifier must not get in
+// the way of the list itself.
+fn notify(summary: &str, body: &str) {
+  let mut command = if cfg!(target_os = "macos") {
+    let mut command = Command::new("osascript");
+    command.arg("-e").arg(format!(
+      "display notification {:?} with title {:?}",
+      body, summary
+    ));
+    command
+  } else {
+    let mut command = Command::new("notify-send");
+    command.arg("--app-name=gitlab-todo").arg(summary).arg(body);
+    command
+  };
+  let _ = command
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fixtures::{mr, row, snapshot, user_json},
+    gitlab::{Pipeline, Role},
+  };
+  use serde_json::json;
+
+  fn detailed(mr: MergeRequest) -> Row {
+    let mut row = row(mr);
+    row.detailed = true;
+    row.approvals = true;
+    row
+  }
+
+  /// Someone else's merge request I was asked to review, with details.
+  fn review(iid: usize) -> Row {
+    detailed(mr(iid, json!({ "reviewers": [user_json(1, "me")] })))
+  }
+
+  /// My own merge request, with details, still needing an approval.
+  fn mine(iid: usize) -> Row {
+    let mut row = detailed(mr(iid, json!({ "author": user_json(1, "me") })));
+    row.approval_info.approvals_left = 1;
+    row
+  }
+
+  fn approved(iid: usize) -> Row {
+    let mut row = mine(iid);
+    row.approval_info.approvals_left = 0;
+    row
+  }
+
+  fn conflicted(iid: usize) -> Row {
+    let mut row = mine(iid);
+    row.mr.has_conflicts = true;
+    row
+  }
+
+  fn failing(iid: usize, pipeline: usize) -> Row {
+    let mut row = mine(iid);
+    row.pipeline = Some(Pipeline {
+      id: Id(pipeline),
+      status: PipelineStatus::Failed,
+      web_url: String::new(),
+    });
+    row
+  }
+
+  fn kinds(events: &[Event]) -> Vec<(EventKind, usize)> {
+    events
+      .iter()
+      .map(|event| (event.kind, event.mr.0 - 1000))
+      .collect()
+  }
+
+  /// The events from `after`, once `before` was seen.
+  fn diff(before: Vec<Row>, after: Vec<Row>) -> Vec<(EventKind, usize)> {
+    let mut tracker = EventTracker::default();
+    tracker.update(&snapshot(before));
+    kinds(&tracker.update(&snapshot(after)))
+  }
+
+  #[test]
+  fn the_first_snapshot_raises_nothing() {
+    let mut tracker = EventTracker::default();
+    assert!(tracker
+      .update(&snapshot(vec![review(1), approved(2)]))
+      .is_empty());
+  }
+
+  #[test]
+  fn new_review_requests_are_reported() {
+    assert_eq!(
+      diff(vec![mine(1)], vec![mine(1), review(2)]),
+      [(EventKind::NewReviewRequest, 2)]
+    );
+    // Merge requests of mine that show up are nothing to review.
+    assert!(diff(vec![review(1)], vec![review(1), mine(2)]).is_empty());
+  }
+
+  #[test]
+  fn approvals_reaching_zero_are_reported_once() {
+    assert_eq!(
+      diff(vec![mine(1)], vec![approved(1)]),
+      [(EventKind::MyMrApproved, 1)]
+    );
+    assert!(diff(vec![approved(1)], vec![approved(1)]).is_empty());
+  }
+
+  #[test]
+  fn conflicts_are_reported_when_they_appear() {
+    assert_eq!(
+      diff(vec![mine(1)], vec![conflicted(1)]),
+      [(EventKind::MyMrConflicts, 1)]
+    );
+    assert!(diff(vec![conflicted(1)], vec![mine(1)]).is_empty());
+    assert!(diff(vec![conflicted(1)], vec![conflicted(1)]).is_empty());
+  }
+
+  #[test]
+  fn each_failed_pipeline_is_reported() {
+    assert_eq!(
+      diff(vec![mine(1)], vec![failing(1, 10)]),
+      [(EventKind::PipelineFailed, 1)]
+    );
+    assert_eq!(
+      diff(vec![failing(1, 10)], vec![failing(1, 11)]),
+      [(EventKind::PipelineFailed, 1)]
+    );
+    assert!(diff(vec![failing(1, 10)], vec![failing(1, 10)]).is_empty());
+  }
+
+  #[test]
+  fn transitions_survive_missed_cycles() {
+    let mut tracker = EventTracker::default();
+    tracker.update(&snapshot(vec![mine(1), review(2)]));
+
+    // A partial refresh leaves both out, which nobody should hear about.
+    let mut partial = snapshot(vec![]);
+    partial.unavailable = vec![Source::Role(Role::Author), Source::Role(Role::Reviewer)];
+    assert!(tracker.update(&partial).is_empty());
+
+    // Rows without details say nothing either.
+    let mut undetailed = approved(1);
+    undetailed.detailed = false;
+    assert!(tracker
+      .update(&snapshot(vec![undetailed, review(2)]))
+      .is_empty());
+
+    // What changed meanwhile is reported once they are back, and nothing
+    // is new just for having been missed.
+    assert_eq!(
+      kinds(&tracker.update(&snapshot(vec![approved(1), review(2)]))),
+      [(EventKind::MyMrApproved, 1)]
+    );
+  }
+
+  #[test]
+  fn leaving_the_list_is_reported_only_from_complete_refreshes() {
+    assert_eq!(
+      diff(vec![mine(1), review(2)], vec![review(2)]),
+      [(EventKind::Gone, 1)]
+    );
+
+    let mut tracker = EventTracker::default();
+    tracker.update(&snapshot(vec![mine(1), review(2)]));
+    let mut partial = snapshot(vec![review(2)]);
+    partial.unavailable = vec![Source::Role(Role::Author)];
+    assert!(tracker.update(&partial).is_empty());
+  }
+
+  /// A refresh listing `rows`, and `set_aside` without showing them.
+  fn hiding(rows: Vec<Row>, set_aside: Vec<Row>) -> Snapshot {
+    let mut snapshot = snapshot(rows);
+    snapshot.set_aside = set_aside.into_iter().map(|row| row.mr).collect();
+    snapshot
+  }
+
+  #[test]
+  fn hiding_a_merge_request_is_not_leaving_the_list() {
+    let mut tracker = EventTracker::default();
+    tracker.update(&snapshot(vec![mine(1), review(2)]));
+    assert!(tracker
+      .update(&hiding(vec![review(2)], vec![mine(1)]))
+      .is_empty());
+    assert!(tracker
+      .update(&hiding(vec![review(2)], vec![mine(1)]))
+      .is_empty());
+
+    // Once it is no longer listed at all, it is gone, as its row last had it.
+    let gone = tracker.update(&snapshot(vec![review(2)]));
+    assert_eq!(kinds(&gone), [(EventKind::Gone, 1)]);
+    assert_eq!(gone[0].approvals, (0, 1));
+  }
+
+  #[test]
+  fn failing_merged_lists_dont_hold_back_leaving() {
+    let mut tracker = EventTracker::default();
+    tracker.update(&snapshot(vec![mine(1), review(2)]));
+    let mut without_merged = snapshot(vec![review(2)]);
+    without_merged.unavailable = vec![Source::Merged(Role::Author)];
+    assert_eq!(
+      kinds(&tracker.update(&without_merged)),
+      [(EventKind::Gone, 1)]
+    );
+  }
+}
@@ -0,0 +1,106 @@
+use crate::{
+  gitlab::{MergeRequest, User, UserId},
+  options::Options,
+  priority::Score,
+  Row, Snapshot,
+};
+use chrono::Utc;
+use clap::CommandFactory;
+use serde_json::{json, Value};
+use std::{env, sync::Once};
+
+pub fn user(id: usize, username: &str) -> User {
+  User {
+    id: UserId(id),
+    name: username.to_string(),
+    username: username.to_string(),
+  }
+}
+
+/// A user as GitLab lists them in merge requests.
+pub fn user_json(id: usize, username: &str) -> Value {
+  json!({ "id": id, "name": username, "username": username })
+}
+
+/// Whose to-do list the tests look at.
+pub fn me() -> User {
+  user(1, "me")
+}
+
+/// A merge request in `group/project` as a current GitLab lists it, by
+/// someone else and with nobody assigned, with `fields` set over that.
+pub fn mr_json(iid: usize, fields: Value) -> Value {
+  let mut payload = json!({
+    "id": 1000 + iid,
+    "iid": iid,
+    "project_id": 5,
+    "title": format!("MR {}", iid),
+    "state": "opened",
+    "milestone": null,
+    "labels": [],
+    "draft": false,
+    "has_conflicts": false,
+    "references": { "full": format!("group/project!{}", iid) },
+    "source_branch": format!("feature-{}", iid),
+    "target_branch": "main",
+    "sha": format!("{:040}", iid),
+    "web_url": format!("https://gitlab.example.com/group/project/-/merge_requests/{}", iid),
+    "created_at": "2026-10-01T09:00:00Z",
+    "updated_at": "2026-10-10T09:00:00Z",
+    "author": { "id": 2, "name": "Author", "username": "author" },
+    "assignees": [],
+    "reviewers": [],
+  });
+  if let (Some(payload), Value::Object(fields)) = (payload.as_object_mut(), fields) {
+    payload.extend(fields);
+  }
+  payload
+}
+
+pub fn mr(iid: usize, fields: Value) -> MergeRequest {
+  serde_json::from_value(mr_json(iid, fields)).unwrap()
+}
+
+/// The options as parsed from `args`, without any configuration file.
+pub fn options(args: &[&str]) -> Options {
+  let matches = Options::command()
+    .try_get_matches_from(std::iter::once("gitlab-todo").chain(args.iter().copied()))
+    .unwrap();
+  Options::from_matches(&matches).unwrap()
+}
+
+/// A row as listed for `me`, before any details are fetched.
+pub fn row(mr: MergeRequest) -> Row {
+  Row::new(&me(), &options(&[]), mr)
+}
+
+/// A refresh that listed `rows` from every source.
+pub fn snapshot(rows: Vec<Row>) -> Snapshot {
+  Snapshot {
+    rows: rows
+      .into_iter()
+      .map(|row| (row, Score::default()))
+      .collect(),
+    merged: Vec::new(),
+    issues: Vec::new(),
+    fetched_at: Utc::now(),
+    stats: Default::default(),
+    focus: None,
+    unavailable: Vec::new(),
+    filtered: 0,
+    set_aside: Vec::new(),
+    unparsed: Vec::new(),
+    no_approvals: false,
+  }
+}
+
+/// Points the state and configuration directories somewhere of the tests'
+/// own, so that nothing a test saves ends up in the real ones.
+pub fn isolate() {
+  static ONCE: Once = Once::new();
+  ONCE.call_once(|| {
+    let dir = env::temp_dir().join(format!("gitlab-todo-tests-{}", std::process::id()));
+    env::set_var("XDG_STATE_HOME", dir.join("state"));
+    env::set_var("XDG_CONFIG_HOME", dir.join("config"));
+  });
+}
@@ -0,0 +1,40 @@
+use crate::{gitlab::MrId, state::State, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The one merge request I'm reviewing right now, kept across restarts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Focus {
+  pub mr: MrId,
+  pub started_at: DateTime<Utc>,
+}
+
+impl Focus {
+  pub fn start(mr: MrId) -> Self {
+    Focus {
+      mr,
+      started_at: Utc::now(),
+    }
+  }
+
+  pub fn load() -> Option<Self> {
+    State::load().focus
+  }
+
+  pub fn save(focus: Option<&Self>) -> Result<()> {
+    State::update(|state| state.focus = focus.cloned())
+  }
+
+  pub fn elapsed(&self, now: DateTime<Utc>) -> Duration {
+    (now - self.started_at).max(Duration::zero())
+  }
+}
+
+/// Hours and minutes as GitLab's `/spend` reads them, like `1h5m`.
+pub fn spent(elapsed: Duration) -> String {
+  match (elapsed.num_hours(), elapsed.num_minutes() % 60) {
+    (0, minutes) => format!("{}m", minutes),
+    (hours, 0) => format!("{}h", hours),
+    (hours, minutes) => format!("{}h{}m", hours, minutes),
+  }
+}
@@ -0,0 +1,2486 @@
+use crate::{
+  clock,
+  deadline::deadline,
+  reference::MrRef,
+  tape::{Exchange, Tape},
+  Result,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use reqwest::{
+  blocking::{RequestBuilder, Response},
+  header::{HeaderMap, HeaderName, HeaderValue, DATE, LINK, SET_COOKIE},
+  Method, StatusCode, Url,
+};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+use std::{
+  collections::{HashMap, HashSet},
+  fmt::Display,
+  sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Mutex,
+  },
+  time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthStyle {
+  Bearer,
+  PrivateToken,
+}
+
+impl AuthStyle {
+  /// Personal, project and group access tokens carry a `gl…-` prefix and
+  /// work everywhere as `PRIVATE-TOKEN`; anything else is likely OAuth,
+  /// which only works as a Bearer token.
+  pub fn detect(token: &str) -> Self {
+    if token.starts_with("gl") && token.contains('-') {
+      AuthStyle::PrivateToken
+    } else {
+      AuthStyle::Bearer
+    }
+  }
+
+  fn other(&self) -> Self {
+    match self {
+      AuthStyle::Bearer => AuthStyle::PrivateToken,
+      AuthStyle::PrivateToken => AuthStyle::Bearer,
+    }
+  }
+
+  fn apply(&self, request: RequestBuilder, token: &str) -> RequestBuilder {
+    match self {
+      AuthStyle::Bearer => request.bearer_auth(token),
+      AuthStyle::PrivateToken => request.header("PRIVATE-TOKEN", token),
+    }
+  }
+}
+
+/// HTTP client bound to the API of one GitLab instance.
+#[derive(Debug, Clone)]
+pub struct Client {
+  http: reqwest::blocking::Client,
+  api_url: String,
+  token: String,
+  auth_style: Arc<Mutex<AuthStyle>>,
+  /// Whether to retry with the other header style on 401, for detected styles.
+  auth_fallback: bool,
+  /// Replies to GET requests by URL, while coalescing.
+  coalesced: Arc<Mutex<Option<HashMap<String, Reply>>>>,
+  /// Cleared once the instance rejected keyset pagination.
+  keyset: Arc<AtomicBool>,
+  /// Merge requests left out of lists because they couldn't be read.
+  unparsed: Arc<Mutex<Vec<Unparsed>>>,
+  /// Bytes of response bodies received since the last `take_received`.
+  received: Arc<AtomicU64>,
+  /// Where requests are recorded to, or answered from instead of the network.
+  tape: Option<Arc<Tape>>,
+  /// Set to answer every further request as cancelled, without sending it.
+  cancelled: Arc<AtomicBool>,
+}
+
+impl Client {
+  /// `host` is either a bare host name like `gitlab.com` or a base URL.
+  /// Without an explicit `auth_style`, it is detected from the token.
+  pub fn new(host: &str, token: &str, auth_style: Option<AuthStyle>) -> Result<Self> {
+    let base = if host.contains("://") {
+      host.trim_end_matches('/').to_string()
+    } else {
+      format!("https://{}", host.trim_end_matches('/'))
+    };
+    Ok(Client {
+      http: reqwest::blocking::Client::builder().build()?,
+      api_url: format!("{}/api/v4", base),
+      token: token.to_string(),
+      auth_style: Arc::new(Mutex::new(
+        auth_style.unwrap_or_else(|| AuthStyle::detect(token)),
+      )),
+      auth_fallback: auth_style.is_none(),
+      coalesced: Arc::new(Mutex::new(None)),
+      keyset: Arc::new(AtomicBool::new(true)),
+      unparsed: Arc::new(Mutex::new(Vec::new())),
+      received: Arc::new(AtomicU64::new(0)),
+      tape: None,
+      cancelled: Arc::new(AtomicBool::new(false)),
+    })
+  }
+
+  /// Gives up on requests that take longer than `timeout`, or waits as long
+  /// as they take without one.
+  pub fn with_timeout(self, timeout: Option<Duration>) -> Result<Self> {
+    Ok(Client {
+      http: reqwest::blocking::Client::builder()
+        .timeout(timeout)
+        .build()?,
+      ..self
+    })
+  }
+
+  /// Fails every request from now on, in every clone of this client, so
+  /// that whatever is fetching stops after the one in flight.
+  pub fn cancel(&self) {
+    self.cancelled.store(true, Ordering::Relaxed);
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    self.cancelled.load(Ordering::Relaxed)
+  }
+
+  /// Sends requests again after `cancel`.
+  pub fn resume(&self) {
+    self.cancelled.store(false, Ordering::Relaxed);
+  }
+
+  pub fn with_tape(self, tape: Tape) -> Self {
+    Client {
+      tape: Some(Arc::new(tape)),
+      ..self
+    }
+  }
+
+  /// Writes out what was recorded so far, if recording.
+  pub fn save_tape(&self) -> Result<()> {
+    match &self.tape {
+      Some(tape) => tape.save(),
+      None => Ok(()),
+    }
+  }
+
+  /// Until the guard is dropped, identical GET requests are only sent once
+  /// and answered from the first reply afterwards, e.g. a merge request
+  /// listed by several queries within one refresh. Any other request
+  /// forgets the replies collected so far, as it may have changed them.
+  pub fn coalesce(&self) -> Coalescing<'_> {
+    *self.coalesced.lock().unwrap() = Some(HashMap::new());
+    Coalescing(self)
+  }
+
+  fn request(&self, method: Method, path: impl Display) -> Request<'_> {
+    Request {
+      client: self,
+      builder: self
+        .http
+        .request(method, format!("{}{}", self.api_url, path)),
+    }
+  }
+
+  pub fn get(&self, path: impl Display) -> Request<'_> {
+    self.request(Method::GET, path)
+  }
+
+  /// Follows GitLab's offset pagination until the last page.
+  pub fn get_all<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<Vec<T>> {
+    self.get_pages(path, query, |_| true)
+  }
+
+  /// Merge requests from `path`, most recently updated first, down to
+  /// `stop_before`: their order means that every page after the first to
+  /// reach back that far holds nothing newer. Pages by keyset, which stays
+  /// fast however deep the list, unless the instance rejects that.
+  pub fn get_recent_mrs(
+    &self,
+    path: &str,
+    query: &[(&str, &str)],
+    stop_before: Option<DateTime<Utc>>,
+  ) -> Result<Vec<MergeRequest>> {
+    let query = recent_mrs_query(query);
+    let more = |page: &[Listed]| !reaches_back(page, stop_before);
+    let mut mrs = None;
+    if self.keyset.load(Ordering::Relaxed) {
+      mrs = self.get_keyset_pages(path, &query, more)?;
+      if mrs.is_none() {
+        self.keyset.store(false, Ordering::Relaxed);
+      }
+    }
+    let listed = match mrs {
+      Some(listed) => listed,
+      None => self.get_pages(path, &query, more)?,
+    };
+    let mut mrs = self.readable(path, listed);
+    if let Some(stop_before) = stop_before {
+      mrs.retain(|mr| mr.updated_at >= stop_before);
+    }
+    Ok(mrs)
+  }
+
+  /// The first page of a merge request list as GitLab sends it, asked for
+  /// as `get_recent_mrs` asks.
+  pub fn get_raw_recent_mrs(&self, path: &str, query: &[(&str, &str)]) -> Result<Value> {
+    self.get_raw(path, &recent_mrs_query(query))
+  }
+
+  /// The first page of `path` as GitLab sends it, without reading it into
+  /// any of our types.
+  pub fn get_raw(&self, path: &str, query: &[(&str, &str)]) -> Result<Value> {
+    let response = self
+      .get(path)
+      .query(query)
+      .query(&[("per_page", "100")])
+      .send()?;
+    checked(response)?.decode()
+  }
+
+  /// `None` when the instance doesn't do keyset pagination for `path`.
+  fn get_keyset_pages<T: DeserializeOwned>(
+    &self,
+    path: &str,
+    query: &[(&str, &str)],
+    mut more: impl FnMut(&[T]) -> bool,
+  ) -> Result<Option<Vec<T>>> {
+    let response = self
+      .get(path)
+      .query(query)
+      .query(&[("pagination", "keyset"), ("per_page", "100")])
+      .send()?;
+    let status = response.status();
+    if status == StatusCode::BAD_REQUEST || status == StatusCode::METHOD_NOT_ALLOWED {
+      return Ok(None);
+    }
+
+    let mut response = checked(response)?;
+    let mut items = Vec::new();
+    loop {
+      // The next page's link carries the query, cursor included. Behind a
+      // proxy, its host may not be the one we talk to.
+      let next = next_link(response.headers())
+        .and_then(|next| next.split_once("/api/v4"))
+        .map(|(_, next)| next.to_string());
+      let page = response.decode::<Vec<T>>()?;
+      let done = !more(&page);
+      items.extend(page);
+      match next.filter(|_| !done) {
+        Some(next) => response = checked(self.get(next).send()?)?,
+        None => return Ok(Some(items)),
+      }
+    }
+  }
+
+  /// Offset pagination, until the last page or until `more` says that was enough.
+  fn get_pages<T: DeserializeOwned>(
+    &self,
+    path: &str,
+    query: &[(&str, &str)],
+    mut more: impl FnMut(&[T]) -> bool,
+  ) -> Result<Vec<T>> {
+    let mut items = Vec::new();
+    let mut page = "1".to_string();
+    loop {
+      let response = self
+        .get(path)
+        .query(query)
+        .query(&[("per_page", "100"), ("page", page.as_str())])
+        .send()?;
+      let response = checked(response)?;
+      let next_page = response
+        .headers()
+        .get("x-next-page")
+        .and_then(|next| next.to_str().ok())
+        .filter(|next| !next.is_empty())
+        .map(str::to_string);
+      let page_items = response.decode::<Vec<T>>()?;
+      let done = !more(&page_items);
+      items.extend(page_items);
+      match next_page.filter(|_| !done) {
+        Some(next) => page = next,
+        None => return Ok(items),
+      }
+    }
+  }
+
+  /// The merge requests of a list that could be read. The rest are kept
+  /// to report, rather than failing the whole list over one of them.
+  pub fn readable(&self, source: &str, listed: Vec<Listed>) -> Vec<MergeRequest> {
+    let mut unparsed = self.unparsed.lock().unwrap();
+    listed
+      .into_iter()
+      .enumerate()
+      .filter_map(|(index, listed)| match listed {
+        Listed::Read(mr) => Some(*mr),
+        Listed::Unreadable { hint, error } => {
+          unparsed.push(Unparsed {
+            source: format!("{}, element {}", source, index + 1),
+            hint,
+            error,
+          });
+          None
+        }
+      })
+      .collect()
+  }
+
+  /// Bytes received since the last call.
+  pub fn take_received(&self) -> u64 {
+    self.received.swap(0, Ordering::Relaxed)
+  }
+
+  /// The merge requests that couldn't be read since the last call, each once.
+  pub fn take_unparsed(&self) -> Vec<Unparsed> {
+    let mut unparsed = std::mem::take(&mut *self.unparsed.lock().unwrap());
+    unparsed.sort_by(|a, b| a.hint.cmp(&b.hint));
+    unparsed.dedup_by(|a, b| a.hint == b.hint);
+    unparsed
+  }
+
+  pub fn post(&self, path: impl Display) -> Request<'_> {
+    self.request(Method::POST, path)
+  }
+
+  pub fn put(&self, path: impl Display) -> Request<'_> {
+    self.request(Method::PUT, path)
+  }
+
+  pub fn delete(&self, path: impl Display) -> Request<'_> {
+    self.request(Method::DELETE, path)
+  }
+}
+
+pub struct Coalescing<'a>(&'a Client);
+
+impl Drop for Coalescing<'_> {
+  fn drop(&mut self) {
+    *self.0.coalesced.lock().unwrap() = None;
+  }
+}
+
+// Client Closed Request, as nginx calls it.
+const CANCELLED: u16 = 499;
+
+/// A response read in full, so that it can answer an identical request again.
+#[derive(Debug, Clone)]
+pub struct Reply {
+  status: StatusCode,
+  headers: HeaderMap,
+  url: Url,
+  body: Arc<str>,
+}
+
+impl Reply {
+  fn read(response: Response) -> reqwest::Result<Self> {
+    Ok(Reply {
+      status: response.status(),
+      headers: response.headers().clone(),
+      url: response.url().clone(),
+      body: response.text()?.into(),
+    })
+  }
+
+  /// What a request gets once the client was cancelled: an error status of
+  /// its own, so that it is never taken for a missing endpoint or a
+  /// merge request that is gone.
+  fn cancelled(request: &reqwest::blocking::Request) -> Self {
+    Reply {
+      status: StatusCode::from_u16(CANCELLED).unwrap(),
+      headers: HeaderMap::new(),
+      url: request.url().clone(),
+      body: json!({ "message": "Cancelled" }).to_string().into(),
+    }
+  }
+
+  /// The recorded reply to `request`, or a 502 naming it where there is none.
+  fn replayed(tape: &Tape, request: &reqwest::blocking::Request) -> Self {
+    let url = request.url();
+    let Some(exchange) = tape.reply(request.method().as_str(), url.path(), url.query()) else {
+      let message = format!("Not in the recording: {} {}", request.method(), url.path());
+      return Reply {
+        status: StatusCode::BAD_GATEWAY,
+        headers: HeaderMap::new(),
+        url: url.clone(),
+        body: json!({ "message": message }).to_string().into(),
+      };
+    };
+    let headers = exchange
+      .headers
+      .iter()
+      .filter_map(|(name, value)| {
+        Some((
+          HeaderName::try_from(name.as_str()).ok()?,
+          HeaderValue::try_from(value.as_str()).ok()?,
+        ))
+      })
+      .collect();
+    Reply {
+      status: StatusCode::from_u16(exchange.status).unwrap_or(StatusCode::BAD_GATEWAY),
+      headers,
+      url: url.clone(),
+      body: match exchange.body {
+        Value::String(text) => text.into(),
+        body => body.to_string().into(),
+      },
+    }
+  }
+
+  fn exchange(&self, request: &reqwest::blocking::Request) -> Exchange {
+    Exchange {
+      method: request.method().to_string(),
+      path: request.url().path().to_string(),
+      query: request.url().query().map(str::to_string),
+      status: self.status.as_u16(),
+      headers: self
+        .headers
+        .iter()
+        .filter(|(name, _)| *name != SET_COOKIE)
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect(),
+      body: serde_json::from_str(&self.body)
+        .unwrap_or_else(|_| Value::String(self.body.to_string())),
+    }
+  }
+
+  pub fn status(&self) -> StatusCode {
+    self.status
+  }
+
+  pub fn headers(&self) -> &HeaderMap {
+    &self.headers
+  }
+
+  /// The time the server had when it replied, from its `Date` header.
+  pub fn server_time(&self) -> Option<DateTime<Utc>> {
+    self
+      .headers
+      .get(DATE)
+      .and_then(|date| date.to_str().ok())
+      .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+      .map(|date| date.with_timezone(&Utc))
+  }
+
+  /// Worth repeating only when asked again: the server may recover, and a
+  /// rejected token is retried with the other header style.
+  fn is_final(&self) -> bool {
+    !self.status.is_server_error()
+      && self.status != StatusCode::UNAUTHORIZED
+      && self.status != StatusCode::TOO_MANY_REQUESTS
+  }
+
+  /// Like `json`, but names the endpoint and the offending field when the
+  /// payload doesn't have the expected shape.
+  fn decode<T: DeserializeOwned>(self) -> Result<T> {
+    serde_json::from_str(&self.body)
+      .map_err(|err| format!("Unexpected response from {}: {}", self.url.path(), err).into())
+  }
+}
+
+/// A request that adds authentication when sent.
+pub struct Request<'a> {
+  client: &'a Client,
+  builder: RequestBuilder,
+}
+
+impl Request<'_> {
+  pub fn query<T: Serialize + ?Sized>(self, query: &T) -> Self {
+    Request {
+      builder: self.builder.query(query),
+      ..self
+    }
+  }
+
+  pub fn json<T: Serialize + ?Sized>(self, json: &T) -> Self {
+    Request {
+      builder: self.builder.json(json),
+      ..self
+    }
+  }
+
+  // On a 401 with a detected header style, tries the other style once and
+  // keeps whichever works for every later request.
+  pub fn send(self) -> reqwest::Result<Reply> {
+    let Request { client, builder } = self;
+    let request = builder.try_clone().and_then(|builder| builder.build().ok());
+    if let Some(request) = request.as_ref().filter(|_| client.is_cancelled()) {
+      return Ok(Reply::cancelled(request));
+    }
+    let key = request
+      .as_ref()
+      .filter(|request| request.method() == Method::GET)
+      .map(|request| request.url().to_string());
+
+    {
+      let mut coalesced = client.coalesced.lock().unwrap();
+      if let Some(replies) = coalesced.as_mut() {
+        match &key {
+          Some(key) => {
+            if let Some(reply) = replies.get(key) {
+              return Ok(reply.clone());
+            }
+          }
+          None => replies.clear(),
+        }
+      }
+    }
+
+    let reply = match (client.tape.as_deref(), &request) {
+      (Some(tape), Some(request)) if tape.is_replaying() => Reply::replayed(tape, request),
+      (tape, request) => {
+        let reply = Reply::read(Self::authenticate(client, builder)?)?;
+        if let (Some(tape), Some(request)) = (tape, request) {
+          tape.add(reply.exchange(request));
+        }
+        reply
+      }
+    };
+    client
+      .received
+      .fetch_add(reply.body.len() as u64, Ordering::Relaxed);
+    if let Some(server_time) = reply.server_time() {
+      clock::record(server_time);
+    }
+    if let (Some(key), true) = (key, reply.is_final()) {
+      if let Some(replies) = client.coalesced.lock().unwrap().as_mut() {
+        replies.insert(key, reply.clone());
+      }
+    }
+    Ok(reply)
+  }
+
+  fn authenticate(client: &Client, builder: RequestBuilder) -> reqwest::Result<Response> {
+    let style = *client.auth_style.lock().unwrap();
+    let retry = builder.try_clone().filter(|_| client.auth_fallback);
+
+    let response = style.apply(builder, &client.token).send()?;
+    let Some(retry) = retry.filter(|_| response.status() == StatusCode::UNAUTHORIZED) else {
+      return Ok(response);
+    };
+
+    let retried = style.other().apply(retry, &client.token).send()?;
+    if retried.status() != StatusCode::UNAUTHORIZED {
+      *client.auth_style.lock().unwrap() = style.other();
+    }
+    Ok(retried)
+  }
+}
+
+fn recent_mrs_query<'a>(query: &[(&'a str, &'a str)]) -> Vec<(&'a str, &'a str)> {
+  [
+    query,
+    &[("order_by", "updated_at"), ("sort", "desc")],
+    &LEAN_MR_LISTS,
+  ]
+  .concat()
+}
+
+/// The `rel="next"` URL of a `Link` header, as keyset pagination sends it.
+fn next_link(headers: &HeaderMap) -> Option<&str> {
+  headers
+    .get(LINK)?
+    .to_str()
+    .ok()?
+    .split(',')
+    .find(|link| link.contains(r#"rel="next""#))?
+    .split_once('<')?
+    .1
+    .split_once('>')
+    .map(|(url, _)| url)
+}
+
+/// Whether a page sorted newest first got to merge requests last updated
+/// before `stop_before`, so that later pages would only have older ones.
+fn reaches_back(page: &[Listed], stop_before: Option<DateTime<Utc>>) -> bool {
+  let oldest = page.iter().rev().find_map(|listed| match listed {
+    Listed::Read(mr) => Some(mr),
+    Listed::Unreadable { .. } => None,
+  });
+  match (oldest, stop_before) {
+    (Some(oldest), Some(stop_before)) => oldest.updated_at < stop_before,
+    _ => false,
+  }
+}
+
+/// An unsuccessful response from the API.
+#[derive(Debug)]
+pub struct ApiError {
+  pub status: StatusCode,
+  message: Option<String>,
+}
+
+impl ApiError {
+  /// Whether the resource is gone for us, e.g. because its project was made
+  /// private or archived, rather than the request having failed.
+  pub fn is_inaccessible(error: &(dyn std::error::Error + 'static)) -> bool {
+    error.downcast_ref::<ApiError>().is_some_and(|error| {
+      error.status == StatusCode::FORBIDDEN || error.status == StatusCode::NOT_FOUND
+    })
+  }
+}
+
+impl Display for ApiError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match &self.message {
+      Some(message) => write!(f, "{}: {}", self.status, message),
+      None => self.status.fmt(f),
+    }
+  }
+}
+
+impl std::error::Error for ApiError {}
+
+fn checked(response: Reply) -> Result<Reply> {
+  let status = response.status();
+  if status.is_success() {
+    return Ok(response);
+  }
+
+  let message = serde_json::from_str::<serde_json::Value>(&response.body)
+    .ok()
+    .and_then(|body| body.get("message").or_else(|| body.get("error")).cloned())
+    .map(|message| match message {
+      serde_json::Value::String(message) => message,
+      other => other.to_string(),
+    });
+  Err(ApiError { status, message }.into())
+}
+
+/// Whether a 404 is for an endpoint the instance doesn't have, rather than
+/// for a merge request or project that is gone, which GitLab names, as in
+/// `404 Merge Request Not Found`.
+fn is_missing_endpoint(response: &Reply) -> bool {
+  response.status() == StatusCode::NOT_FOUND
+    && serde_json::from_str::<Value>(&response.body)
+      .ok()
+      .and_then(|body| body.get("message")?.as_str().map(str::to_string))
+      .is_none_or(|message| message == "404 Not Found")
+}
+
+macro_rules! ids {
+  ($($(#[$doc:meta])* $name:ident;)*) => {
+    $(
+      $(#[$doc])*
+      #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Copy)]
+      pub struct $name(pub usize);
+
+      impl Display for $name {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+          self.0.fmt(f)
+        }
+      }
+    )*
+  };
+}
+
+// GitLab numbers everything with plain integers; distinct types keep a
+// project ID from ending up where an iid belongs.
+ids! {
+  /// For anything without an ID type of its own, like notes and pipelines.
+  Id;
+  UserId;
+  ProjectId;
+  GroupId;
+  /// Unique across the instance.
+  MrId;
+  /// Unique within the project, as in `group/project!42`.
+  MrIid;
+  IssueId;
+  IssueIid;
+}
+
+/// The API path of a merge request.
+fn mr_url(project: ProjectId, iid: MrIid) -> String {
+  format!("/projects/{}/merge_requests/{}", project, iid)
+}
+
+/// The API path of something belonging to a merge request, like its `approvals`.
+fn mr_endpoint_url(project: ProjectId, iid: MrIid, endpoint: &str) -> String {
+  format!("{}/{}", mr_url(project, iid), endpoint)
+}
+
+pub const ASSIGNED_ISSUES: [(&str, &str); 2] = [("scope", "assigned_to_me"), ("state", "opened")];
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct User {
+  pub id: UserId,
+  pub name: String,
+  pub username: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserStatus {
+  /// An emoji name like `palm_tree`.
+  #[serde(default)]
+  pub emoji: Option<String>,
+  #[serde(default)]
+  pub message: Option<String>,
+  /// `busy` or `not_set`.
+  #[serde(default)]
+  pub availability: Option<String>,
+}
+
+impl User {
+  /// Stands in for the user on the dashboard: GitLab numbers users from 1,
+  /// so nothing ever is this one's, and every role and "me" is empty.
+  pub fn nobody() -> Self {
+    User {
+      id: UserId(0),
+      name: "nobody".to_string(),
+      username: String::new(),
+    }
+  }
+
+  /// The user the access token belongs to.
+  pub fn current(client: &Client) -> Result<Self> {
+    let response = client.get("/user").send()?;
+    checked(response)?.decode()
+  }
+
+  /// The status the user set: an emoji, a message and whether they are busy.
+  pub fn get_status(client: &Client, user: UserId) -> Result<UserStatus> {
+    let response = client.get(format!("/users/{}/status", user)).send()?;
+    checked(response)?.decode()
+  }
+
+  pub fn get<UserName: AsRef<str>>(client: &Client, user: UserName) -> Result<Self> {
+    User::find(client, user)?.ok_or("No user found with that name".into())
+  }
+
+  /// The user by that name, or None where there is nobody by it.
+  pub fn find<UserName: AsRef<str>>(client: &Client, user: UserName) -> Result<Option<Self>> {
+    let response = client
+      .get("/users")
+      .query(&[("username", user.as_ref())])
+      .send()?;
+    let users: Vec<User> = checked(response)?.decode()?;
+    Ok(users.into_iter().next())
+  }
+
+  /// Push events after the given day, newest first.
+  pub fn get_recent_pushes(&self, client: &Client, after: NaiveDate) -> Result<Vec<RecentPush>> {
+    let after = after.format("%Y-%m-%d").to_string();
+    client.get_all(
+      &format!("/users/{}/events", self.id),
+      &[("action", "pushed"), ("after", after.as_str())],
+    )
+  }
+
+  /// Merge request events of one kind, such as `approved`, after the given day.
+  pub fn get_mr_events(
+    &self,
+    client: &Client,
+    action: &str,
+    after: NaiveDate,
+  ) -> Result<Vec<UserEvent>> {
+    let after = after.format("%Y-%m-%d").to_string();
+    client.get_all(
+      &format!("/users/{}/events", self.id),
+      &[
+        ("target_type", "merge_request"),
+        ("action", action),
+        ("after", after.as_str()),
+      ],
+    )
+  }
+
+  /// Without `updated_after`, the open ones; with it, all updated since.
+  /// Either way, none last updated before `stop_before`.
+  pub fn get_role_mrs(
+    &self,
+    client: &Client,
+    role: Role,
+    updated_after: Option<DateTime<Utc>>,
+    stop_before: Option<DateTime<Utc>>,
+  ) -> Result<Vec<MergeRequest>> {
+    let updated_after = updated_after.map(|at| at.to_rfc3339());
+    let query = self.role_query(role, updated_after.as_deref());
+    client.get_recent_mrs("/merge_requests", &query, stop_before)
+  }
+
+  /// The first page of my open merge requests in a role, as GitLab sends it.
+  pub fn get_raw_role_mrs(&self, client: &Client, role: Role) -> Result<Value> {
+    client.get_raw_recent_mrs("/merge_requests", &self.role_query(role, None))
+  }
+
+  fn role_query<'a>(
+    &'a self,
+    role: Role,
+    updated_after: Option<&'a str>,
+  ) -> Vec<(&'a str, &'a str)> {
+    let mut query = vec![("scope", "all"), (role.query_key(), self.username.as_str())];
+    match updated_after {
+      Some(updated_after) => {
+        query.push(("state", "all"));
+        query.push(("updated_after", updated_after));
+      }
+      None => query.push(("state", "opened")),
+    }
+    query
+  }
+
+  /// Merge requests merged since `since` in any of my roles, along with the
+  /// roles whose list failed, which the others do without. Fails only when
+  /// every one of them does, or once cancelled.
+  pub fn get_merged_mrs(
+    &self,
+    client: &Client,
+    since: DateTime<Utc>,
+  ) -> Result<(HashMap<MrId, MergeRequest>, Vec<Role>)> {
+    let updated_after = since.to_rfc3339();
+    let mut all_mrs = HashMap::new();
+    let mut failed = Vec::new();
+    let mut first_error = None;
+
+    for role in Role::ALL {
+      let query = [
+        ("scope", "all"),
+        (role.query_key(), self.username.as_str()),
+        ("state", "merged"),
+        ("updated_after", updated_after.as_str()),
+      ];
+      match client.get_recent_mrs("/merge_requests", &query, Some(since)) {
+        Ok(mrs) => all_mrs.extend(mrs.into_iter().map(|mr| (mr.id, mr))),
+        Err(err) if client.is_cancelled() => return Err(err),
+        Err(err) => {
+          failed.push(role);
+          first_error.get_or_insert(err);
+        }
+      }
+    }
+
+    match first_error {
+      Some(err) if failed.len() == Role::ALL.len() => Err(err),
+      _ => Ok((all_mrs, failed)),
+    }
+  }
+
+  /// Open merge requests from branches I pushed to within the last `lookback_days`.
+  pub fn get_push_mrs(
+    &self,
+    client: &Client,
+    lookback_days: i64,
+  ) -> Result<HashMap<MrId, MergeRequest>> {
+    let after = (clock::server_now() - chrono::Duration::days(lookback_days)).date_naive();
+    let pushes = self.get_recent_pushes(client, after)?;
+    Ok(
+      pushed_branches(&pushes)
+        .into_iter()
+        .map(|(project_id, branch)| MergeRequest::get_by_branch(client, project_id, branch))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flat_map(|mrs| mrs.into_iter())
+        .collect(),
+    )
+  }
+}
+
+/// Each branch pushed to once, from events ordered newest first, leaving out
+/// tags and branches whose latest push deleted them.
+fn pushed_branches(pushes: &[RecentPush]) -> Vec<(ProjectId, &str)> {
+  let mut seen = HashSet::new();
+  let mut branches = Vec::new();
+  for push in pushes {
+    let data = &push.push_data;
+    let Some(branch) = data.ref_.as_deref() else {
+      continue;
+    };
+    if data
+      .ref_type
+      .as_deref()
+      .is_some_and(|ref_type| ref_type != "branch")
+    {
+      continue;
+    }
+    if seen.insert((push.project_id, branch)) && data.action != "removed" {
+      branches.push((push.project_id, branch));
+    }
+  }
+  branches
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+  Reviewer,
+  Assignee,
+  Author,
+}
+
+impl Role {
+  pub const ALL: [Role; 3] = [Role::Reviewer, Role::Assignee, Role::Author];
+
+  fn query_key(&self) -> &'static str {
+    match self {
+      Role::Reviewer => "reviewer_username",
+      Role::Assignee => "assignee_username",
+      Role::Author => "author_username",
+    }
+  }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PushData {
+  #[serde(rename = "ref")]
+  pub ref_: Option<String>,
+  #[serde(default)]
+  pub ref_type: Option<String>,
+  /// `pushed new`, `pushed to` or `removed`.
+  #[serde(default)]
+  pub action: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RecentPush {
+  pub project_id: ProjectId,
+  pub push_data: PushData,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UserEvent {
+  pub project_id: ProjectId,
+  pub target_iid: Option<MrIid>,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct References {
+  pub full: String,
+}
+
+/// A project or a group milestone, which only differ in whose ID they carry.
+/// Sprints are commonly group milestones spanning a start and a due date.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Milestone {
+  pub title: String,
+  #[serde(default)]
+  pub due_date: Option<NaiveDate>,
+  #[serde(default)]
+  pub start_date: Option<NaiveDate>,
+  #[serde(default)]
+  pub project_id: Option<ProjectId>,
+  #[serde(default)]
+  pub group_id: Option<GroupId>,
+}
+
+impl Milestone {
+  /// Whether `today` lies within its dates, where a missing date leaves that
+  /// end open; a milestone without any dates is never current.
+  pub fn is_current(&self, today: NaiveDate) -> bool {
+    (self.start_date.is_some() || self.due_date.is_some())
+      && self.start_date.is_none_or(|start| start <= today)
+      && self.due_date.is_none_or(|due| today <= due)
+  }
+
+  /// The title with whatever dates it has, like `Sprint 12 (group, 2024-05-06 to 2024-05-17)`.
+  pub fn describe(&self) -> String {
+    let scope = if self.group_id.is_some() {
+      "group"
+    } else {
+      "project"
+    };
+    match (self.start_date, self.due_date) {
+      (Some(start), Some(due)) => format!("{} ({}, {} to {})", self.title, scope, start, due),
+      (Some(start), None) => format!("{} ({}, from {})", self.title, scope, start),
+      (None, Some(due)) => format!("{} ({}, due {})", self.title, scope, due),
+      (None, None) => format!("{} ({})", self.title, scope),
+    }
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Issue {
+  pub id: IssueId,
+  pub iid: IssueIid,
+  pub project_id: ProjectId,
+  pub title: String,
+  pub references: References,
+  #[serde(default)]
+  pub labels: Vec<String>,
+  pub due_date: Option<NaiveDate>,
+  pub milestone: Option<Milestone>,
+  pub web_url: String,
+  pub updated_at: DateTime<Utc>,
+}
+
+impl Issue {
+  pub fn deadline(&self) -> Option<NaiveDate> {
+    deadline(self.due_date, self.milestone.as_ref())
+  }
+
+  pub fn get_assigned(client: &Client) -> Result<Vec<Self>> {
+    client.get_all("/issues", &ASSIGNED_ISSUES)
+  }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Version {
+  pub version: String,
+  /// The server's clock, from the response's `Date` header.
+  #[serde(skip)]
+  pub server_time: Option<DateTime<Utc>>,
+}
+
+impl Version {
+  pub fn get(client: &Client) -> Result<Self> {
+    let response = checked(client.get("/version").send()?)?;
+    let server_time = response.server_time();
+    let mut version: Version = response.decode()?;
+    version.server_time = server_time;
+    Ok(version)
+  }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TokenInfo {
+  pub scopes: Vec<String>,
+}
+
+impl TokenInfo {
+  /// Only personal, project and group access tokens can be inspected, and
+  /// only from GitLab 15.5 on.
+  pub fn get(client: &Client) -> Result<Self> {
+    let response = client.get("/personal_access_tokens/self").send()?;
+    checked(response)?.decode()
+  }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Project {
+  pub id: ProjectId,
+  pub path_with_namespace: String,
+  #[serde(default)]
+  pub name_with_namespace: String,
+  /// None for projects without a repository.
+  #[serde(default)]
+  pub default_branch: Option<String>,
+  #[serde(default)]
+  pub archived: bool,
+  #[serde(default)]
+  pub avatar_url: Option<String>,
+}
+
+impl Project {
+  pub fn get(client: &Client, id: ProjectId) -> Result<Self> {
+    let response = client.get(format!("/projects/{}", id)).send()?;
+    checked(response)?.decode()
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+  pub name: String,
+}
+
+impl Label {
+  /// The labels merge requests of a project can have, its group's included.
+  pub fn get_all(client: &Client, project: ProjectId) -> Result<Vec<Self>> {
+    client.get_all(
+      &format!("/projects/{}/labels", project),
+      &[("include_ancestor_groups", "true")],
+    )
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+  pub id: GroupId,
+  pub full_path: String,
+}
+
+impl Group {
+  /// Looks a group up by its full path, like `platform/backend`.
+  pub fn get(client: &Client, path: &str) -> Result<Self> {
+    let response = client
+      .get(format!(
+        "/groups/{}",
+        path.trim_matches('/').replace('/', "%2F")
+      ))
+      .send()?;
+    match checked(response) {
+      Ok(response) => response.decode(),
+      Err(err) if ApiError::is_inaccessible(&*err) => {
+        Err(format!("No group '{}', or it isn't visible to this token", path).into())
+      }
+      Err(err) => Err(err),
+    }
+  }
+
+  /// Every open merge request in the group and its subgroups.
+  pub fn get_open_mrs(&self, client: &Client) -> Result<Vec<MergeRequest>> {
+    client.get_recent_mrs(
+      &format!("/groups/{}/merge_requests", self.id),
+      &[("state", "opened")],
+      None,
+    )
+  }
+
+  /// Open merge requests in the group and its subgroups that nobody was asked
+  /// to review yet. Drafts aren't ready for a reviewer, so they are left out.
+  pub fn get_unreviewed_mrs(&self, client: &Client) -> Result<Vec<MergeRequest>> {
+    client.get_recent_mrs(
+      &format!("/groups/{}/merge_requests", self.id),
+      &[("state", "opened"), ("reviewer_id", "None"), ("wip", "no")],
+      None,
+    )
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(remote = "Self")]
+pub struct MergeRequest {
+  pub id: MrId,
+  pub iid: MrIid,
+  pub project_id: ProjectId,
+  /// A different project for merge requests from a fork.
+  #[serde(default)]
+  pub source_project_id: Option<ProjectId>,
+  pub title: String,
+  pub state: String,
+  pub milestone: Option<Milestone>,
+  #[serde(default)]
+  pub labels: Vec<String>,
+  pub draft: bool,
+  #[serde(default)]
+  pub has_conflicts: bool,
+  pub references: References,
+  pub source_branch: String,
+  pub target_branch: String,
+  pub sha: String,
+  pub web_url: String,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+  #[serde(default)]
+  pub merged_at: Option<DateTime<Utc>>,
+  pub author: User,
+  #[serde(default)]
+  pub assignees: Vec<User>,
+  #[serde(default)]
+  pub reviewers: Vec<User>,
+  #[serde(default)]
+  pub upvotes: usize,
+  #[serde(default)]
+  pub downvotes: usize,
+  /// Notes by people, not counting GitLab's own.
+  #[serde(default)]
+  pub user_notes_count: usize,
+  #[serde(default)]
+  pub rebase_in_progress: bool,
+  /// Missing on instances that don't count tasks.
+  #[serde(default)]
+  pub task_completion_status: Option<TaskCompletion>,
+  /// Set to merge, or to join the merge train, once the pipeline passes.
+  #[serde(default)]
+  pub merge_when_pipeline_succeeds: bool,
+  #[serde(default)]
+  pub merge_error: Option<String>,
+  /// Null rather than false on merge requests that were never locked.
+  #[serde(default)]
+  pub discussion_locked: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TaskCompletion {
+  pub count: usize,
+  pub completed_count: usize,
+}
+
+impl TaskCompletion {
+  pub fn is_complete(&self) -> bool {
+    self.completed_count >= self.count
+  }
+}
+
+impl Display for TaskCompletion {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "☑ {}/{}", self.completed_count, self.count)
+  }
+}
+
+/// Spares list endpoints work and payload we have no use for: label details
+/// where names do, and a mergeability recheck for every merge request listed.
+/// The REST API has no way to pick fields, so `view=simple` would be the only
+/// leaner reply, and that one lacks authors, references and more.
+const LEAN_MR_LISTS: [(&str, &str); 2] = [
+  ("with_labels_details", "false"),
+  ("with_merge_status_recheck", "false"),
+];
+
+/// An element of a list of merge requests, which one malformed merge request
+/// shouldn't be able to fail as a whole.
+#[derive(Debug)]
+pub enum Listed {
+  Read(Box<MergeRequest>),
+  Unreadable { hint: String, error: String },
+}
+
+impl<'de> Deserialize<'de> for Listed {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+    let payload = Value::deserialize(deserializer)?;
+    match MergeRequest::deserialize(&payload) {
+      Ok(mr) => Ok(Listed::Read(Box::new(mr))),
+      Err(err) => Ok(Listed::Unreadable {
+        hint: unparsed_hint(&payload),
+        error: err.to_string(),
+      }),
+    }
+  }
+}
+
+/// Whatever tells which merge request failed to be read.
+fn unparsed_hint(payload: &Value) -> String {
+  let field = |name: &str| payload.get(name).filter(|value| !value.is_null());
+  if let Some(url) = field("web_url").and_then(Value::as_str) {
+    return url.to_string();
+  }
+  match (field("project_id"), field("iid"), field("id")) {
+    (Some(project), Some(iid), _) => format!("project {} !{}", project, iid),
+    (_, _, Some(id)) => format!("merge request {}", id),
+    _ => "a merge request without an id".to_string(),
+  }
+}
+
+/// A merge request left out of a list because it couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unparsed {
+  /// The list it was in and where.
+  pub source: String,
+  pub hint: String,
+  pub error: String,
+}
+
+impl Display for Unparsed {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{} ({}): {}", self.hint, self.source, self.error)
+  }
+}
+
+/// A note such as `2 MRs could not be parsed (--verbose for details)`, if
+/// any couldn't be; verbose, the note names them instead.
+pub fn unparsed_note(unparsed: &[Unparsed], verbose: bool) -> Option<String> {
+  let count = match unparsed.len() {
+    0 => return None,
+    1 => "1 MR could not be parsed".to_string(),
+    count => format!("{} MRs could not be parsed", count),
+  };
+  Some(if verbose {
+    let hints: Vec<&str> = unparsed
+      .iter()
+      .map(|unparsed| unparsed.hint.as_str())
+      .collect();
+    format!("{}: {}", count, hints.join(", "))
+  } else {
+    format!("{} (--verbose for details)", count)
+  })
+}
+
+// Instances before 12.6 have no `references`, and `draft` only replaced
+// `work_in_progress` in 13.x; fill both in before the derived deserializer
+// sees the payload.
+impl<'de> Deserialize<'de> for MergeRequest {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+    let mut payload = Value::deserialize(deserializer)?;
+    if let Some(fields) = payload.as_object_mut() {
+      if !fields.contains_key("draft") {
+        let draft = fields.get("work_in_progress").cloned();
+        fields.insert("draft".to_string(), draft.unwrap_or(Value::Bool(false)));
+      }
+      if fields.get("references").is_none_or(Value::is_null) {
+        let full = fields
+          .get("web_url")
+          .and_then(Value::as_str)
+          .and_then(|url| url.parse::<MrRef>().ok())
+          .map(|reference| reference.to_string())
+          .or_else(|| {
+            Some(format!(
+              "{}!{}",
+              fields.get("project_id")?,
+              fields.get("iid")?
+            ))
+          });
+        if let Some(full) = full {
+          fields.insert("references".to_string(), json!({ "full": full }));
+        }
+      }
+    }
+    MergeRequest::deserialize(payload)
+      .map(MergeRequest::normalized)
+      .map_err(serde::de::Error::custom)
+  }
+}
+
+impl MergeRequest {
+  /// Irons out what the API occasionally gets wrong, like the same user
+  /// listed twice as assignee after a group sync hiccup, so that nothing
+  /// downstream counts or shows it twice.
+  fn normalized(mut self) -> Self {
+    for users in [&mut self.assignees, &mut self.reviewers] {
+      let mut seen = HashSet::new();
+      users.retain(|user| seen.insert(user.id));
+    }
+    self
+  }
+
+  pub fn deadline(&self) -> Option<NaiveDate> {
+    deadline(None, self.milestone.as_ref())
+  }
+
+  /// The description's task list, if it has one.
+  pub fn tasks(&self) -> Option<TaskCompletion> {
+    self.task_completion_status.filter(|tasks| tasks.count > 0)
+  }
+
+  pub fn is_from_fork(&self) -> bool {
+    self
+      .source_project_id
+      .is_some_and(|source| source != self.project_id)
+  }
+
+  pub fn is_locked(&self) -> bool {
+    self.discussion_locked == Some(true)
+  }
+
+  pub fn project_path(&self) -> &str {
+    self
+      .references
+      .full
+      .rsplit_once('!')
+      .map_or(self.references.full.as_str(), |(path, _)| path)
+  }
+
+  pub fn assignee_names(&self) -> String {
+    self
+      .assignees
+      .iter()
+      .map(|a| a.username.as_str())
+      .collect::<Vec<_>>()
+      .join(" ")
+  }
+
+  pub fn get(client: &Client, project_id: ProjectId, iid: MrIid) -> Result<Self> {
+    let response = client.get(mr_url(project_id, iid)).send()?;
+    checked(response)?.decode()
+  }
+
+  pub fn get_by_reference(client: &Client, reference: &MrRef) -> Result<Self> {
+    let response = client
+      .get(format!(
+        "/projects/{}/merge_requests/{}",
+        reference.encoded_project(),
+        reference.iid
+      ))
+      .send()?;
+    checked(response)?.decode()
+  }
+
+  pub fn get_rebase_status(&self, client: &Client) -> Result<Self> {
+    let response = client
+      .get(mr_url(self.project_id, self.iid))
+      .query(&[("include_rebase_in_progress", "true")])
+      .send()?;
+    checked(response)?.decode()
+  }
+
+  // The rebase runs asynchronously; GitLab refuses a second request while
+  // one is still running, which is as good as having started it.
+  pub fn rebase(&self, client: &Client) -> Result<()> {
+    let response = client
+      .put(mr_endpoint_url(self.project_id, self.iid, "rebase"))
+      .send()?;
+
+    let status = response.status();
+    if (status == StatusCode::FORBIDDEN || status == StatusCode::CONFLICT)
+      && self.get_rebase_status(client)?.rebase_in_progress
+    {
+      return Ok(());
+    }
+    checked(response)?;
+    Ok(())
+  }
+
+  pub fn update(&self, client: &Client, changes: &serde_json::Value) -> Result<Self> {
+    let response = client
+      .put(mr_url(self.project_id, self.iid))
+      .json(changes)
+      .send()?;
+    checked(response)?.decode()
+  }
+
+  pub fn get_by_branch<BranchName: AsRef<str>>(
+    client: &Client,
+    project_id: ProjectId,
+    branch: BranchName,
+  ) -> Result<HashMap<MrId, MergeRequest>> {
+    let response = client
+      .get(format!("/projects/{}/merge_requests", project_id))
+      .query(&[
+        ("state", "opened"),
+        ("scope", "all"),
+        ("source_branch", branch.as_ref()),
+      ])
+      .query(&LEAN_MR_LISTS)
+      .send()?;
+
+    if !response.status().is_success() {
+      return Ok(HashMap::new());
+    }
+
+    let listed = response.decode()?;
+    let mrs = client.readable(&format!("/projects/{}/merge_requests", project_id), listed);
+    let mrs: HashMap<MrId, MergeRequest> = mrs.into_iter().map(|mr| (mr.id, mr)).collect();
+    Ok(mrs)
+  }
+}
+
+/// Where a reviewer stands, on instances that track it (GitLab 16.x and later).
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewState {
+  Unreviewed,
+  Reviewed,
+  RequestedChanges,
+  Approved,
+  #[default]
+  #[serde(other)]
+  Unknown,
+}
+
+impl ReviewState {
+  pub fn glyph(&self) -> &'static str {
+    match self {
+      ReviewState::Unreviewed => "○",
+      ReviewState::Reviewed | ReviewState::Approved => "✓",
+      ReviewState::RequestedChanges => "✗",
+      ReviewState::Unknown => "",
+    }
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Reviewer {
+  pub user: User,
+  #[serde(default)]
+  pub state: ReviewState,
+}
+
+impl Reviewer {
+  /// `None` on instances without the reviewers endpoint.
+  pub fn get_all(client: &Client, mr: &MergeRequest) -> Result<Option<Vec<Self>>> {
+    let response = client
+      .get(mr_endpoint_url(mr.project_id, mr.iid, "reviewers"))
+      .send()?;
+    if is_missing_endpoint(&response) {
+      return Ok(None);
+    }
+    checked(response)?.decode().map(Some)
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FileDiff {
+  pub old_path: String,
+  pub new_path: String,
+  pub diff: String,
+  #[serde(default)]
+  pub new_file: bool,
+  #[serde(default)]
+  pub deleted_file: bool,
+}
+
+impl FileDiff {
+  pub fn get_all(client: &Client, mr: &MergeRequest) -> Result<Vec<Self>> {
+    client.get_all(&mr_endpoint_url(mr.project_id, mr.iid, "diffs"), &[])
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Approver {
+  pub user: User,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ApprovalInfo {
+  pub approvals_left: usize,
+  pub approved_by: Vec<Approver>,
+}
+
+impl ApprovalInfo {
+  /// `None` on instances without merge request approvals.
+  pub fn get(client: &Client, mr: &MergeRequest) -> Result<Option<Self>> {
+    let response = client
+      .get(mr_endpoint_url(mr.project_id, mr.iid, "approvals"))
+      .send()?;
+    if is_missing_endpoint(&response) {
+      return Ok(None);
+    }
+    checked(response)?.decode().map(Some)
+  }
+
+  /// Approves as the current user and returns the approvals that follow.
+  pub fn approve(client: &Client, mr: &MergeRequest) -> Result<Self> {
+    let response = client
+      .post(mr_endpoint_url(mr.project_id, mr.iid, "approve"))
+      .send()?;
+    checked(response)?.decode()
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ApprovalRule {
+  pub name: String,
+  #[serde(default)]
+  pub eligible_approvers: Vec<User>,
+  pub approvals_required: usize,
+  #[serde(default)]
+  pub approved: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct ApprovalState {
+  rules: Vec<ApprovalRule>,
+}
+
+impl ApprovalRule {
+  /// Empty on tiers without approval rules.
+  pub fn get_all(client: &Client, mr: &MergeRequest) -> Result<Vec<Self>> {
+    let response = client
+      .get(mr_endpoint_url(mr.project_id, mr.iid, "approval_state"))
+      .send()?;
+    if is_missing_endpoint(&response) {
+      return Ok(Vec::new());
+    }
+    let state: ApprovalState = checked(response)?.decode()?;
+    Ok(state.rules)
+  }
+
+  pub fn is_eligible(&self, user: &User) -> bool {
+    self
+      .eligible_approvers
+      .iter()
+      .any(|approver| approver.id == user.id)
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Note {
+  pub id: Id,
+  pub body: String,
+  pub author: User,
+  pub system: bool,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+impl Note {
+  /// Every system note, oldest first.
+  pub fn get_system(client: &Client, mr: &MergeRequest) -> Result<Vec<Self>> {
+    let notes: Vec<Note> = client.get_all(
+      &mr_endpoint_url(mr.project_id, mr.iid, "notes"),
+      &[("order_by", "created_at"), ("sort", "asc")],
+    )?;
+    Ok(notes.into_iter().filter(|note| note.system).collect())
+  }
+
+  pub fn get_latest(client: &Client, mr: &MergeRequest) -> Result<Option<Self>> {
+    let notes = client
+      .get(mr_endpoint_url(mr.project_id, mr.iid, "notes"))
+      .query(&[
+        ("order_by", "updated_at"),
+        ("sort", "desc"),
+        ("per_page", "1"),
+      ])
+      .send()?;
+    let notes: Vec<Note> = checked(notes)?.decode()?;
+    Ok(notes.into_iter().next())
+  }
+
+  pub fn create(client: &Client, mr: &MergeRequest, body: &str) -> Result<Self> {
+    let response = client
+      .post(mr_endpoint_url(mr.project_id, mr.iid, "notes"))
+      .json(&serde_json::json!({ "body": body }))
+      .send()?;
+    checked(response)?.decode()
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AwardEmoji {
+  pub id: Id,
+  pub name: String,
+  pub user: User,
+}
+
+impl AwardEmoji {
+  pub fn get_all(client: &Client, mr: &MergeRequest) -> Result<Vec<Self>> {
+    let response = client
+      .get(mr_endpoint_url(mr.project_id, mr.iid, "award_emoji"))
+      .query(&[("per_page", "100")])
+      .send()?;
+    checked(response)?.decode()
+  }
+
+  pub fn award(client: &Client, mr: &MergeRequest, name: &str) -> Result<Self> {
+    let response = client
+      .post(mr_endpoint_url(mr.project_id, mr.iid, "award_emoji"))
+      .json(&serde_json::json!({ "name": name }))
+      .send()?;
+    checked(response)?.decode()
+  }
+
+  pub fn delete(&self, client: &Client, mr: &MergeRequest) -> Result<()> {
+    let response = client
+      .delete(mr_endpoint_url(
+        mr.project_id,
+        mr.iid,
+        &format!("award_emoji/{}", self.id),
+      ))
+      .send()?;
+    checked(response)?;
+    Ok(())
+  }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStatus {
+  Created,
+  WaitingForResource,
+  Preparing,
+  Pending,
+  Running,
+  Success,
+  Failed,
+  Canceled,
+  Skipped,
+  Manual,
+  Scheduled,
+  #[serde(other)]
+  Unknown,
+}
+
+impl Display for PipelineStatus {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let name = match self {
+      PipelineStatus::Created => "created",
+      PipelineStatus::WaitingForResource => "waiting for resource",
+      PipelineStatus::Preparing => "preparing",
+      PipelineStatus::Pending => "pending",
+      PipelineStatus::Running => "running",
+      PipelineStatus::Success => "passed",
+      PipelineStatus::Failed => "failed",
+      PipelineStatus::Canceled => "canceled",
+      PipelineStatus::Skipped => "skipped",
+      PipelineStatus::Manual => "manual",
+      PipelineStatus::Scheduled => "scheduled",
+      PipelineStatus::Unknown => "unknown",
+    };
+    name.fmt(f)
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pipeline {
+  pub id: Id,
+  pub status: PipelineStatus,
+  pub web_url: String,
+}
+
+/// What only the single merge request endpoint tells, not the lists.
+#[derive(Deserialize, Debug, Clone)]
+pub struct MergeRequestDetails {
+  pub head_pipeline: Option<Pipeline>,
+  /// Changed files, as a string that reads like `1000+` for huge diffs.
+  #[serde(default)]
+  changes_count: Option<String>,
+  #[serde(default)]
+  pub description: Option<String>,
+}
+
+impl MergeRequestDetails {
+  pub fn get(client: &Client, mr: &MergeRequest) -> Result<Self> {
+    checked(client.get(mr_url(mr.project_id, mr.iid)).send()?)?.decode()
+  }
+
+  /// How many files the merge request changes, at least.
+  pub fn changed_files(&self) -> Option<usize> {
+    let count = self.changes_count.as_deref()?;
+    count.trim_end_matches('+').parse().ok()
+  }
+}
+
+impl Pipeline {
+  pub fn get_head(client: &Client, mr: &MergeRequest) -> Result<Option<Self>> {
+    Ok(MergeRequestDetails::get(client, mr)?.head_pipeline)
+  }
+
+  pub fn retry(&self, client: &Client, mr: &MergeRequest) -> Result<Self> {
+    let response = client
+      .post(format!(
+        "/projects/{}/pipelines/{}/retry",
+        mr.project_id, self.id
+      ))
+      .send()?;
+    checked(response)?.decode()
+  }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct MergeTrainCar {
+  merge_request: MergeTrainMr,
+  target_branch: String,
+  pipeline: Option<Pipeline>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct MergeTrainMr {
+  iid: MrIid,
+}
+
+/// Where a merge request waits in its target branch's merge train.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MergeTrain {
+  /// Counted from 1, the car merging next.
+  pub position: usize,
+  pub length: usize,
+  /// The train's pipeline for this car, which has to pass before it merges.
+  pub pipeline: Option<Pipeline>,
+}
+
+impl MergeTrain {
+  /// `None` on projects without merge trains, which are an optional feature;
+  /// `Some(None)` when the merge request isn't in the train.
+  pub fn get(client: &Client, mr: &MergeRequest) -> Result<Option<Option<Self>>> {
+    let path = format!("/projects/{}/merge_trains", mr.project_id);
+    let cars: Vec<MergeTrainCar> =
+      match client.get_all(&path, &[("scope", "active"), ("sort", "asc")]) {
+        Ok(cars) => cars,
+        Err(err) if ApiError::is_inaccessible(&*err) => return Ok(None),
+        Err(err) => return Err(err),
+      };
+    let train: Vec<&MergeTrainCar> = cars
+      .iter()
+      .filter(|car| car.target_branch == mr.target_branch)
+      .collect();
+    Ok(Some(
+      train
+        .iter()
+        .position(|car| car.merge_request.iid == mr.iid)
+        .map(|index| MergeTrain {
+          position: index + 1,
+          length: train.len(),
+          pipeline: train[index].pipeline.clone(),
+        }),
+    ))
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Job {
+  pub id: Id,
+  pub name: String,
+  pub web_url: String,
+}
+
+impl Job {
+  pub fn get_failed(client: &Client, mr: &MergeRequest, pipeline: &Pipeline) -> Result<Vec<Self>> {
+    let response = client
+      .get(format!(
+        "/projects/{}/pipelines/{}/jobs",
+        mr.project_id, pipeline.id
+      ))
+      .query(&[("scope", "failed")])
+      .send()?;
+    checked(response)?.decode()
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DiscussionNote {
+  pub author: User,
+  pub system: bool,
+  #[serde(default)]
+  pub resolvable: bool,
+  #[serde(default)]
+  pub resolved: bool,
+  #[serde(default)]
+  pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Discussion {
+  pub id: String,
+  pub notes: Vec<DiscussionNote>,
+}
+
+impl Discussion {
+  pub fn get_all(client: &Client, mr: &MergeRequest) -> Result<Vec<Self>> {
+    client.get_all(&mr_endpoint_url(mr.project_id, mr.iid, "discussions"), &[])
+  }
+
+  pub fn is_unresolved(&self) -> bool {
+    self
+      .notes
+      .iter()
+      .any(|note| note.resolvable && !note.resolved)
+  }
+
+  pub fn started_by(&self) -> Option<&User> {
+    self.notes.first().map(|note| &note.author)
+  }
+
+  /// Who wrote the last reply, not counting system notes.
+  pub fn last_replied_by(&self) -> Option<&User> {
+    self
+      .notes
+      .iter()
+      .rev()
+      .find(|note| !note.system)
+      .map(|note| &note.author)
+  }
+
+  pub fn has_note_by(&self, user: &User) -> bool {
+    self.notes.iter().any(|note| note.author.id == user.id)
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LinkedMergeRequest {
+  pub id: MrId,
+  pub iid: MrIid,
+  pub references: References,
+  pub web_url: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Block {
+  blocking_merge_request: LinkedMergeRequest,
+  blocked_merge_request: LinkedMergeRequest,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dependencies {
+  pub blocked_by: Vec<LinkedMergeRequest>,
+  pub blocking: Vec<LinkedMergeRequest>,
+}
+
+impl Dependencies {
+  fn get_blocks(client: &Client, mr: &MergeRequest, endpoint: &str) -> Result<Vec<Block>> {
+    let response = client
+      .get(mr_endpoint_url(mr.project_id, mr.iid, endpoint))
+      .send()?;
+
+    if !response.status().is_success() {
+      return Ok(Vec::new());
+    }
+
+    response.decode()
+  }
+
+  pub fn get(client: &Client, mr: &MergeRequest) -> Result<Self> {
+    let blocked_by = Self::get_blocks(client, mr, "blocks")?
+      .into_iter()
+      .map(|block| block.blocking_merge_request)
+      .collect();
+    let blocking = Self::get_blocks(client, mr, "blockees")?
+      .into_iter()
+      .map(|block| block.blocked_merge_request)
+      .collect();
+
+    Ok(Dependencies {
+      blocked_by,
+      blocking,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    config::Config,
+    fixtures::{me, mr, mr_json, options, row, user_json},
+    mock::{get, reply, MockServer},
+    priority::priority,
+    Row,
+  };
+  use serde_json::json;
+
+  const GITLAB_13: &str = include_str!("../tests/fixtures/gitlab-13-merge-requests.json");
+  const GITLAB_16: &str = include_str!("../tests/fixtures/gitlab-16-merge-requests.json");
+  const DUPLICATE_USERS: &str = include_str!("../tests/fixtures/duplicate-users.json");
+  const POISONED: &str = include_str!("../tests/fixtures/poisoned-merge-requests.json");
+
+  /// The first merge request of a list fixture, as it came.
+  fn payload(fixture: &str) -> Value {
+    let list: Vec<Value> = serde_json::from_str(fixture).unwrap();
+    list.into_iter().next().unwrap()
+  }
+
+  fn read(payload: Value) -> std::result::Result<MergeRequest, serde_json::Error> {
+    serde_json::from_value(payload)
+  }
+
+  #[test]
+  fn gitlab_13_merge_requests_read_as_drafts_without_reviewers() {
+    let mr = read(payload(GITLAB_13)).unwrap();
+    assert_eq!(mr.iid, MrIid(3));
+    assert!(mr.draft);
+    assert!(mr.reviewers.is_empty());
+    assert_eq!(mr.assignee_names(), "bob");
+    assert_eq!(mr.project_path(), "tools/importer");
+    assert_eq!(mr.milestone, None);
+    assert_eq!(mr.tasks(), None);
+    assert!(!mr.is_from_fork());
+  }
+
+  #[test]
+  fn gitlab_16_merge_requests_read_whole() {
+    let mr = read(payload(GITLAB_16)).unwrap();
+    assert!(mr.draft);
+    assert_eq!(mr.author.username, "alice");
+    assert_eq!(
+      mr.reviewers
+        .iter()
+        .map(|user| user.username.as_str())
+        .collect::<Vec<_>>(),
+      ["bob", "carol"]
+    );
+    assert_eq!(mr.labels, ["backend", "reliability"]);
+    let milestone = mr.milestone.as_ref().unwrap();
+    assert_eq!(milestone.title, "16.5");
+    assert_eq!(milestone.due_date, "2026-10-22".parse().ok());
+    assert_eq!(milestone.group_id, Some(GroupId(40)));
+    assert_eq!(
+      mr.tasks(),
+      Some(TaskCompletion {
+        count: 3,
+        completed_count: 1,
+      })
+    );
+    assert!(mr.is_from_fork());
+  }
+
+  #[test]
+  fn missing_fields_are_filled_in_where_they_can_be() {
+    let mut old = payload(GITLAB_13);
+    let fields = old.as_object_mut().unwrap();
+    fields.remove("references");
+    fields.remove("work_in_progress");
+    fields.remove("milestone");
+    fields.remove("labels");
+    let mr = read(old.clone()).unwrap();
+    assert_eq!(mr.references.full, "tools/importer!3");
+    assert!(!mr.draft);
+    assert_eq!(mr.milestone, None);
+
+    // Without a web URL to go by, the reference uses the project's ID.
+    old["web_url"] = json!("not a url");
+    assert_eq!(read(old).unwrap().references.full, "12!3");
+  }
+
+  #[test]
+  fn missing_required_fields_are_named() {
+    let mut payload = payload(GITLAB_16);
+    payload.as_object_mut().unwrap().remove("author");
+    assert_eq!(
+      read(payload).unwrap_err().to_string(),
+      "missing field `author`"
+    );
+  }
+
+  fn since() -> DateTime<Utc> {
+    "2026-10-09T00:00:00Z".parse().unwrap()
+  }
+
+  #[test]
+  fn merged_lists_page_and_skip_failed_roles() {
+    let mut next_page = get(
+      "/merge_requests",
+      Some("assignee_username=me&pagination=keyset"),
+      json!([mr_json(1, json!({ "state": "merged" }))]),
+    );
+    next_page.headers.insert(
+      "link".into(),
+      "<http://gitlab.example.com/api/v4/merge_requests?assignee_username=me&cursor=2>; rel=\"next\"".into(),
+    );
+    let server = MockServer::start(vec![
+      reply(
+        "GET",
+        "/merge_requests",
+        Some("reviewer_username=me"),
+        500,
+        json!({ "message": "500 Internal Server Error" }),
+      ),
+      next_page,
+      get(
+        "/merge_requests",
+        Some("assignee_username=me&cursor=2"),
+        json!([mr_json(2, json!({ "state": "merged" }))]),
+      ),
+      get(
+        "/merge_requests",
+        Some("author_username=me"),
+        json!([mr_json(3, json!({ "state": "merged" }))]),
+      ),
+    ]);
+
+    let (mrs, failed) = me().get_merged_mrs(&server.client(), since()).unwrap();
+    let mut iids: Vec<usize> = mrs.values().map(|mr| mr.iid.0).collect();
+    iids.sort();
+    assert_eq!(iids, [1, 2, 3]);
+    assert_eq!(failed, [Role::Reviewer]);
+    assert!(server
+      .requests()
+      .iter()
+      .all(|request| !request.contains("reviewer_username") || request.contains("state=merged")));
+  }
+
+  #[test]
+  fn merged_lists_fail_when_every_role_does() {
+    let server = MockServer::start(vec![reply(
+      "GET",
+      "/merge_requests",
+      None,
+      500,
+      json!({ "message": "500 Internal Server Error" }),
+    )]);
+    assert!(me().get_merged_mrs(&server.client(), since()).is_err());
+  }
+
+  fn push(project_id: usize, branch: &str, action: &str) -> Value {
+    json!({
+      "project_id": project_id,
+      "push_data": { "ref": branch, "ref_type": "branch", "action": action },
+    })
+  }
+
+  #[test]
+  fn push_lookback_pages_through_the_window() {
+    let after = (clock::server_now() - chrono::Duration::days(14))
+      .date_naive()
+      .format("%Y-%m-%d")
+      .to_string();
+    let mut first_page = get(
+      "/users/1/events",
+      Some(&format!("action=pushed&after={}&page=1", after)),
+      json!([
+        push(5, "feature-1", "pushed to"),
+        push(5, "gone", "removed"),
+        {
+          "project_id": 5,
+          "push_data": { "ref": "v1.0", "ref_type": "tag", "action": "pushed new" },
+        },
+      ]),
+    );
+    first_page.headers.insert("x-next-page".into(), "2".into());
+    let server = MockServer::start(vec![
+      first_page,
+      get(
+        "/users/1/events",
+        Some(&format!("action=pushed&after={}&page=2", after)),
+        json!([
+          push(5, "gone", "pushed new"),
+          push(5, "feature-1", "pushed new"),
+          push(6, "feature-6", "pushed new"),
+        ]),
+      ),
+      get(
+        "/projects/5/merge_requests",
+        Some("source_branch=feature-1"),
+        json!([mr_json(1, json!({}))]),
+      ),
+      get(
+        "/projects/6/merge_requests",
+        Some("source_branch=feature-6"),
+        json!([mr_json(6, json!({ "project_id": 6 }))]),
+      ),
+    ]);
+
+    let mrs = me().get_push_mrs(&server.client(), 14).unwrap();
+    let mut iids: Vec<usize> = mrs.values().map(|mr| mr.iid.0).collect();
+    iids.sort();
+    assert_eq!(iids, [1, 6]);
+
+    let requests = server.requests();
+    let events: Vec<&String> = requests
+      .iter()
+      .filter(|request| request.contains("/events"))
+      .collect();
+    assert_eq!(events.len(), 2);
+    assert!(events
+      .iter()
+      .all(|request| request.contains(&format!("after={}", after))));
+    let lookups: Vec<&String> = requests
+      .iter()
+      .filter(|request| request.contains("source_branch="))
+      .collect();
+    assert_eq!(lookups.len(), 2, "{:?}", lookups);
+    assert!(lookups
+      .iter()
+      .all(|request| !request.contains("gone") && !request.contains("v1.0")));
+  }
+
+  #[test]
+  fn coalescing_asks_for_each_url_once() {
+    let server = MockServer::start(vec![
+      get("/users/2/status", None, json!({ "emoji": "palm_tree" })),
+      reply("PUT", "/user/status", None, 200, json!({})),
+    ]);
+    let client = server.client();
+    let status = |client: &Client| User::get_status(client, UserId(2)).unwrap();
+    let asked = |server: &MockServer| {
+      server
+        .requests()
+        .iter()
+        .filter(|request| request.ends_with("/users/2/status"))
+        .count()
+    };
+
+    status(&client);
+    status(&client);
+    assert_eq!(asked(&server), 2);
+
+    {
+      let _coalescing = client.coalesce();
+      assert_eq!(status(&client).emoji.as_deref(), Some("palm_tree"));
+      assert_eq!(status(&client).emoji.as_deref(), Some("palm_tree"));
+      assert_eq!(asked(&server), 3);
+
+      // Anything but a GET may change what the next one reads.
+      client.put("/user/status").send().unwrap();
+      status(&client);
+      assert_eq!(asked(&server), 4);
+    }
+
+    status(&client);
+    assert_eq!(asked(&server), 5);
+  }
+
+  #[test]
+  fn ids_read_and_print_as_plain_numbers() {
+    let id: MrIid = serde_json::from_value(json!(42)).unwrap();
+    assert_eq!(id, MrIid(42));
+    assert_eq!(id.to_string(), "42");
+    assert_eq!(serde_json::to_value(ProjectId(5)).unwrap(), json!(5));
+  }
+
+  #[test]
+  fn merge_request_urls_put_the_project_before_the_iid() {
+    assert_eq!(
+      mr_url(ProjectId(5), MrIid(42)),
+      "/projects/5/merge_requests/42"
+    );
+    assert_eq!(
+      mr_endpoint_url(ProjectId(5), MrIid(42), "approvals"),
+      "/projects/5/merge_requests/42/approvals"
+    );
+    assert_eq!(
+      mr_endpoint_url(ProjectId(300), MrIid(7), "resource_state_events"),
+      "/projects/300/merge_requests/7/resource_state_events"
+    );
+  }
+
+  #[test]
+  fn approvals_are_asked_for_by_project_and_iid() {
+    let server = MockServer::start(vec![get(
+      "/projects/5/merge_requests/42/approvals",
+      None,
+      json!({ "approvals_left": 1, "approved_by": [] }),
+    )]);
+    let mr: MergeRequest = read(mr_json(42, json!({}))).unwrap();
+    let info = ApprovalInfo::get(&server.client(), &mr).unwrap().unwrap();
+    assert_eq!(info.approvals_left, 1);
+    assert_eq!(
+      server.requests(),
+      ["GET /api/v4/projects/5/merge_requests/42/approvals"]
+    );
+  }
+
+  #[test]
+  fn users_listed_twice_count_and_show_once() {
+    let duplicated = payload(DUPLICATE_USERS);
+    let mut clean = duplicated.clone();
+    for (field, keep) in [("assignees", [0, 1]), ("reviewers", [0, 2])] {
+      let users = clean[field].as_array().unwrap();
+      clean[field] = json!([users[keep[0]], users[keep[1]]]);
+    }
+    let duplicated = read(duplicated).unwrap();
+    let clean = read(clean).unwrap();
+    assert_eq!(duplicated.assignees, clean.assignees);
+    assert_eq!(duplicated.reviewers, clean.reviewers);
+    assert_eq!(duplicated.assignee_names(), "me bob");
+
+    let (duplicated, clean) = (row(duplicated), row(clean));
+    assert_eq!(duplicated.reviewer_names(), clean.reviewer_names());
+    assert_eq!(duplicated.reviewer_names().matches("carol").count(), 1);
+    let score = |row: &Row| priority(row, &me(), &options(&[]), &Config::default());
+    assert_eq!(score(&duplicated), score(&clean));
+    assert_eq!(
+      score(&duplicated)
+        .terms
+        .iter()
+        .filter(|(reason, _)| reason == "assigned to me")
+        .count(),
+      1
+    );
+  }
+
+  #[test]
+  fn one_unreadable_merge_request_spares_the_rest_of_its_list() {
+    let listed: Vec<Listed> = serde_json::from_str(POISONED).unwrap();
+    assert!(matches!(listed[1], Listed::Unreadable { .. }));
+
+    let client = Client::new("https://gitlab.example.com", "token", None).unwrap();
+    let mrs = client.readable("/merge_requests", listed);
+    let iids: Vec<usize> = mrs.iter().map(|mr| mr.iid.0).collect();
+    assert_eq!(iids, [11, 13]);
+
+    let [unparsed] = &client.take_unparsed()[..] else {
+      panic!("expected one merge request left out");
+    };
+    assert_eq!(unparsed.source, "/merge_requests, element 2");
+    assert_eq!(
+      unparsed.hint,
+      "https://gitlab.example.com/group/project/-/merge_requests/12"
+    );
+    assert!(unparsed.error.contains("author"), "{}", unparsed.error);
+    assert!(client.take_unparsed().is_empty());
+  }
+
+  #[test]
+  fn lists_from_the_api_leave_unreadable_merge_requests_out() {
+    let list: Value = serde_json::from_str(POISONED).unwrap();
+    let server = MockServer::start(vec![get(
+      "/projects/5/merge_requests",
+      Some("source_branch=storage-migration"),
+      list,
+    )]);
+    let client = server.client();
+    let mrs = MergeRequest::get_by_branch(&client, ProjectId(5), "storage-migration").unwrap();
+    let mut iids: Vec<usize> = mrs.values().map(|mr| mr.iid.0).collect();
+    iids.sort();
+    assert_eq!(iids, [11, 13]);
+    assert_eq!(client.take_unparsed().len(), 1);
+  }
+
+  /// How each request authenticated, like `PRIVATE-TOKEN: glpat-a`.
+  fn authentication(server: &MockServer) -> Vec<String> {
+    let private = server.headers("private-token");
+    let authorization = server.headers("authorization");
+    private
+      .into_iter()
+      .zip(authorization)
+      .map(|headers| match headers {
+        (Some(token), None) => format!("PRIVATE-TOKEN: {}", token),
+        (None, Some(value)) => format!("Authorization: {}", value),
+        headers => panic!("not one authentication header: {:?}", headers),
+      })
+      .collect()
+  }
+
+  #[test]
+  fn tokens_go_in_the_header_for_their_kind() {
+    let cases = [
+      ("glpat-a1b2", None, "PRIVATE-TOKEN: glpat-a1b2"),
+      ("glptt-a1b2", None, "PRIVATE-TOKEN: glptt-a1b2"),
+      ("9f8e7d6c5b4a", None, "Authorization: Bearer 9f8e7d6c5b4a"),
+      (
+        "glpat-a1b2",
+        Some(AuthStyle::Bearer),
+        "Authorization: Bearer glpat-a1b2",
+      ),
+      (
+        "9f8e7d6c5b4a",
+        Some(AuthStyle::PrivateToken),
+        "PRIVATE-TOKEN: 9f8e7d6c5b4a",
+      ),
+    ];
+    for (token, style, header) in cases {
+      let server = MockServer::start(vec![get("/user", None, user_json(1, "me"))]);
+      let client = Client::new(&server.url, token, style).unwrap();
+      User::current(&client).unwrap();
+      assert_eq!(authentication(&server), [header], "{} {:?}", token, style);
+    }
+  }
+
+  fn rejecting_once() -> MockServer {
+    MockServer::start(vec![
+      reply(
+        "GET",
+        "/user",
+        None,
+        401,
+        json!({ "message": "401 Unauthorized" }),
+      ),
+      get("/user", None, user_json(1, "me")),
+    ])
+  }
+
+  #[test]
+  fn rejected_tokens_try_the_other_header_once_and_keep_it() {
+    let server = rejecting_once();
+    let client = Client::new(&server.url, "glpat-a1b2", None).unwrap();
+    User::current(&client).unwrap();
+    User::current(&client).unwrap();
+    assert_eq!(
+      authentication(&server),
+      [
+        "PRIVATE-TOKEN: glpat-a1b2",
+        "Authorization: Bearer glpat-a1b2",
+        "Authorization: Bearer glpat-a1b2",
+      ]
+    );
+  }
+
+  #[test]
+  fn chosen_headers_are_not_second_guessed() {
+    let server = rejecting_once();
+    let client = Client::new(&server.url, "glpat-a1b2", Some(AuthStyle::PrivateToken)).unwrap();
+    let err = User::current(&client).unwrap_err();
+    assert_eq!(err.to_string(), "401 Unauthorized: 401 Unauthorized");
+    assert_eq!(authentication(&server), ["PRIVATE-TOKEN: glpat-a1b2"]);
+  }
+
+  fn updated(iid: usize, updated_at: &str) -> Value {
+    mr_json(iid, json!({ "updated_at": updated_at }))
+  }
+
+  fn at(time: &str) -> Option<DateTime<Utc>> {
+    Some(time.parse().unwrap())
+  }
+
+  fn iids(mrs: &[MergeRequest]) -> Vec<usize> {
+    mrs.iter().map(|mr| mr.iid.0).collect()
+  }
+
+  fn asked(server: &MockServer, part: &str) -> usize {
+    server
+      .requests()
+      .iter()
+      .filter(|request| request.contains(part))
+      .count()
+  }
+
+  /// A list on two keyset pages, the second one starting before October.
+  fn keyset_pages() -> MockServer {
+    // Only the first page is asked for with the whole query; the next link
+    // has what the cursor needs.
+    let mut first = get(
+      "/merge_requests",
+      Some("pagination=keyset&order_by=updated_at"),
+      json!([
+        updated(1, "2026-10-10T09:00:00Z"),
+        updated(2, "2026-10-02T09:00:00Z"),
+      ]),
+    );
+    first.headers.insert(
+      "link".into(),
+      r#"<https://proxy.example.com/api/v4/merge_requests?pagination=keyset&cursor=two>; rel="next""#
+        .into(),
+    );
+    MockServer::start(vec![
+      first,
+      get(
+        "/merge_requests",
+        Some("cursor=two"),
+        json!([updated(3, "2026-09-20T09:00:00Z")]),
+      ),
+    ])
+  }
+
+  #[test]
+  fn keyset_pages_stop_where_they_reach_back_far_enough() {
+    let server = keyset_pages();
+    let client = server.client();
+    let mrs = client
+      .get_recent_mrs("/merge_requests", &[], at("2026-10-05T00:00:00Z"))
+      .unwrap();
+    // The page reaching back is read, but what is older is left out.
+    assert_eq!(iids(&mrs), [1]);
+    assert_eq!(asked(&server, "cursor=two"), 0);
+
+    let mrs = client
+      .get_recent_mrs("/merge_requests", &[], at("2026-10-01T00:00:00Z"))
+      .unwrap();
+    // Nothing on the first page is that old, so the next may hold more.
+    assert_eq!(iids(&mrs), [1, 2]);
+    assert_eq!(asked(&server, "cursor=two"), 1);
+
+    let mrs = client
+      .get_recent_mrs("/merge_requests", &[], at("2026-09-01T00:00:00Z"))
+      .unwrap();
+    assert_eq!(iids(&mrs), [1, 2, 3]);
+    assert_eq!(asked(&server, "cursor=two"), 2);
+  }
+
+  #[test]
+  fn rejected_keyset_pagination_falls_back_to_offsets_for_good() {
+    for status in [400, 405] {
+      let mut first = get(
+        "/merge_requests",
+        Some("page=1"),
+        json!([
+          updated(1, "2026-10-10T09:00:00Z"),
+          updated(2, "2026-10-02T09:00:00Z"),
+        ]),
+      );
+      first.headers.insert("x-next-page".into(), "2".into());
+      let server = MockServer::start(vec![
+        reply(
+          "GET",
+          "/merge_requests",
+          Some("pagination=keyset"),
+          status,
+          json!({ "message": "keyset pagination is not supported" }),
+        ),
+        first,
+        get(
+          "/merge_requests",
+          Some("page=2"),
+          json!([updated(3, "2026-09-20T09:00:00Z")]),
+        ),
+      ]);
+      let client = server.client();
+      let mrs = client.get_recent_mrs("/merge_requests", &[], None).unwrap();
+      assert_eq!(iids(&mrs), [1, 2, 3], "{}", status);
+      assert_eq!(asked(&server, "pagination=keyset"), 1);
+
+      // Later lists go straight to offsets, and stop early there too.
+      let mrs = client
+        .get_recent_mrs("/merge_requests", &[], at("2026-10-05T00:00:00Z"))
+        .unwrap();
+      assert_eq!(iids(&mrs), [1]);
+      assert_eq!(asked(&server, "pagination=keyset"), 1);
+      assert_eq!(asked(&server, "page=2"), 1);
+    }
+  }
+
+  fn discussion(id: &str) -> Value {
+    json!({ "id": id, "notes": [{ "author": user_json(2, "alice"), "system": false }] })
+  }
+
+  #[test]
+  fn discussions_are_read_past_the_first_page() {
+    let path = "/projects/5/merge_requests/1/discussions";
+    let mut first = get(path, Some("page=1"), json!([discussion("a")]));
+    first.headers.insert("x-next-page".into(), "2".into());
+    let server = MockServer::start(vec![
+      first,
+      get(path, Some("page=2"), json!([discussion("b")])),
+    ]);
+    let discussions = Discussion::get_all(&server.client(), &mr(1, json!({}))).unwrap();
+    let ids: Vec<&str> = discussions
+      .iter()
+      .map(|discussion| discussion.id.as_str())
+      .collect();
+    assert_eq!(ids, ["a", "b"]);
+  }
+
+  #[test]
+  fn unreadable_discussions_are_errors() {
+    let server = MockServer::start(vec![reply(
+      "GET",
+      "/projects/5/merge_requests/1/discussions",
+      None,
+      403,
+      json!({ "message": "403 Forbidden" }),
+    )]);
+    assert!(Discussion::get_all(&server.client(), &mr(1, json!({}))).is_err());
+  }
+
+  #[test]
+  fn rejected_user_lookups_say_why() {
+    let server = MockServer::start(vec![reply(
+      "GET",
+      "/users",
+      None,
+      401,
+      json!({ "message": "401 Unauthorized" }),
+    )]);
+    let err = User::find(&server.client(), "alice").unwrap_err();
+    let err = err.downcast_ref::<ApiError>().unwrap();
+    assert_eq!(err.status, StatusCode::UNAUTHORIZED);
+  }
+}
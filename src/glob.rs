@@ -0,0 +1,20 @@
+// Minimal glob matching: `*` matches within one path segment, `**` matches
+// across segments and `?` matches a single character.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+  let pattern: Vec<char> = pattern.chars().collect();
+  let text: Vec<char> = text.chars().collect();
+  matches(&pattern, &text)
+}
+
+fn matches(pattern: &[char], text: &[char]) -> bool {
+  match pattern {
+    [] => text.is_empty(),
+    ['*', '*', rest @ ..] => (0..=text.len()).any(|skip| matches(rest, &text[skip..])),
+    ['*', rest @ ..] => {
+      let segment = text.iter().take_while(|c| **c != '/').count();
+      (0..=segment).any(|skip| matches(rest, &text[skip..]))
+    }
+    ['?', rest @ ..] => !text.is_empty() && text[0] != '/' && matches(rest, &text[1..]),
+    [c, rest @ ..] => text.first() == Some(c) && matches(rest, &text[1..]),
+  }
+}
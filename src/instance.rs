@@ -0,0 +1,233 @@
+use crate::{dirs::state_dir, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+  fs,
+  io::ErrorKind,
+  path::{Path, PathBuf},
+  process,
+};
+
+/// Who holds the instance lock, as written into it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Holder {
+  pub pid: u32,
+  pub started_at: DateTime<Utc>,
+}
+
+/// The lock a refreshing instance holds so that others can tell it runs,
+/// removed again when dropped.
+#[derive(Debug)]
+pub enum Instance {
+  /// This instance holds the lock.
+  Only(PathBuf),
+  /// Another live instance holds it.
+  Another(Holder),
+  /// There is nowhere to keep a lock, so nothing can be told.
+  Unknown,
+}
+
+impl Instance {
+  pub fn acquire() -> Self {
+    match state_dir() {
+      Some(dir) => Self::acquire_at(&dir.join("instance.lock"), process::id(), is_alive),
+      None => Instance::Unknown,
+    }
+  }
+
+  /// Takes the lock at `path` for `pid`, unless a process that `alive`
+  /// confirms still runs holds it. Locks left by processes that are gone,
+  /// or that can't be read, are taken over.
+  pub fn acquire_at(path: &Path, pid: u32, alive: impl Fn(u32) -> bool) -> Self {
+    if let Some(dir) = path.parent() {
+      let _ = fs::create_dir_all(dir);
+    }
+    let holder = Holder {
+      pid,
+      started_at: Utc::now(),
+    };
+    // The lock only ever appears whole: it is written beside it first and
+    // then linked into place, which fails where one exists. Another
+    // instance starting at the same time can't find it empty and take it.
+    let written = path.with_extension(format!("{}.tmp", pid));
+    let wrote = serde_json::to_vec(&holder)
+      .map_err(Into::into)
+      .and_then(|contents| fs::write(&written, contents));
+    if wrote.is_err() {
+      return Instance::Unknown;
+    }
+    let mut acquired = Instance::Unknown;
+    // Once for a free lock, and once more after clearing a stale one.
+    for _ in 0..2 {
+      match fs::hard_link(&written, path) {
+        Ok(()) => {
+          acquired = Instance::Only(path.to_path_buf());
+          break;
+        }
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+          let contents = fs::read(path).ok();
+          let other = contents
+            .as_deref()
+            .and_then(|contents| serde_json::from_slice::<Holder>(contents).ok());
+          match other {
+            Some(other) if other.pid != pid && alive(other.pid) => {
+              acquired = Instance::Another(other);
+              break;
+            }
+            // Unless someone else took it over in the meantime.
+            _ if fs::read(path).ok() == contents => {
+              let _ = fs::remove_file(path);
+            }
+            _ => {}
+          }
+        }
+        Err(_) => break,
+      }
+    }
+    let _ = fs::remove_file(&written);
+    acquired
+  }
+
+  /// Refuses to go on while another instance runs.
+  pub fn require_only(&self) -> Result<()> {
+    match self {
+      Instance::Another(other) => Err(
+        format!(
+          "Another instance is running: pid {}, started {}",
+          other.pid,
+          other
+            .started_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+        )
+        .into(),
+      ),
+      _ => Ok(()),
+    }
+  }
+}
+
+impl Drop for Instance {
+  fn drop(&mut self) {
+    if let Instance::Only(path) = self {
+      let _ = fs::remove_file(path);
+    }
+  }
+}
+
+/// Whether a process with this pid runs. Where that can't be told, it is
+/// assumed to, which at worst costs a slower refresh.
+fn is_alive(pid: u32) -> bool {
+  if cfg!(target_os = "linux") {
+    Path::new("/proc").join(pid.to_string()).exists()
+  } else if cfg!(unix) {
+    process::Command::new("kill")
+      .args(["-0", &pid.to_string()])
+      .stderr(process::Stdio::null())
+      .status()
+      .map_or(true, |status| status.success())
+  } else {
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::env;
+
+  /// A lock of the test's own, holding `contents` where given.
+  fn lock(name: &str, contents: Option<&[u8]>) -> PathBuf {
+    let dir = env::temp_dir().join(format!("gitlab-todo-lock-{}-{}", process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("instance.lock");
+    if let Some(contents) = contents {
+      fs::write(&path, contents).unwrap();
+    }
+    path
+  }
+
+  fn held_by(pid: u32) -> Vec<u8> {
+    let holder = Holder {
+      pid,
+      started_at: "2026-10-14T08:00:00Z".parse().unwrap(),
+    };
+    serde_json::to_vec(&holder).unwrap()
+  }
+
+  fn holder(path: &Path) -> Holder {
+    serde_json::from_slice(&fs::read(path).unwrap()).unwrap()
+  }
+
+  /// Only the lock itself is left beside it, if anything.
+  fn files(path: &Path) -> Vec<String> {
+    let mut files: Vec<String> = fs::read_dir(path.parent().unwrap())
+      .unwrap()
+      .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+      .collect();
+    files.sort();
+    files
+  }
+
+  #[test]
+  fn free_locks_are_taken_and_given_back() {
+    let path = lock("free", None);
+    let instance = Instance::acquire_at(&path, 100, |_| true);
+    assert!(matches!(&instance, Instance::Only(held) if *held == path));
+    assert_eq!(holder(&path).pid, 100);
+    assert_eq!(files(&path), ["instance.lock"]);
+
+    // Whoever comes next finds it held.
+    let next = Instance::acquire_at(&path, 200, |pid| pid == 100);
+    assert!(matches!(&next, Instance::Another(other) if other.pid == 100));
+    assert!(next.require_only().is_err());
+    drop(next);
+    assert_eq!(holder(&path).pid, 100);
+
+    drop(instance);
+    assert!(files(&path).is_empty());
+  }
+
+  #[test]
+  fn live_holders_keep_the_lock() {
+    let path = lock("live", Some(&held_by(42)));
+    let instance = Instance::acquire_at(&path, 100, |pid| pid == 42);
+    match &instance {
+      Instance::Another(other) => assert_eq!(*other, holder(&path)),
+      other => panic!("{:?}", other),
+    }
+    let err = instance.require_only().unwrap_err().to_string();
+    assert!(err.starts_with("Another instance is running: pid 42, started "));
+    drop(instance);
+    assert_eq!(files(&path), ["instance.lock"]);
+  }
+
+  #[test]
+  fn locks_of_gone_processes_are_taken_over() {
+    let path = lock("dead", Some(&held_by(42)));
+    let instance = Instance::acquire_at(&path, 100, |_| false);
+    assert!(matches!(instance, Instance::Only(_)));
+    assert_eq!(holder(&path).pid, 100);
+  }
+
+  #[test]
+  fn our_own_pid_is_no_other_instance() {
+    // As after a restart in a container, where pids repeat.
+    let path = lock("own", Some(&held_by(100)));
+    let instance = Instance::acquire_at(&path, 100, |_| true);
+    assert!(matches!(instance, Instance::Only(_)));
+    assert_ne!(fs::read(&path).unwrap(), held_by(100));
+  }
+
+  #[test]
+  fn unreadable_locks_are_taken_over() {
+    for (name, contents) in [("empty", &b""[..]), ("garbage", &b"{\"pid\": \"x"[..])] {
+      let path = lock(name, Some(contents));
+      let instance = Instance::acquire_at(&path, 100, |_| true);
+      assert!(matches!(instance, Instance::Only(_)), "{}", name);
+      assert_eq!(holder(&path).pid, 100, "{}", name);
+      assert_eq!(files(&path), ["instance.lock"], "{}", name);
+    }
+  }
+}
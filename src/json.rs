@@ -0,0 +1,522 @@
+use crate::{
+  attention::{attention, reviewers_unavailable, Attention},
+  badges::initials,
+  clock,
+  effort::{self, Effort},
+  gitlab::{PipelineStatus, ReviewState, User},
+  options::Options,
+  priority::Score,
+  sla::{self, Level},
+  threads::ThreadCounts,
+  tier::Tier,
+  Result, Row,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::stdout;
+
+#[derive(Serialize, Debug)]
+struct PriorityTerm<'a> {
+  reason: &'a str,
+  delta: isize,
+}
+
+#[derive(Serialize, Debug)]
+struct PendingApprovalRule<'a> {
+  name: &'a str,
+  approvals_required: usize,
+  eligible_approvers: Vec<&'a str>,
+  includes_me: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct JsonTasks {
+  completed: usize,
+  total: usize,
+}
+
+#[derive(Serialize, Debug)]
+struct JsonMergeRequest<'a> {
+  reference: &'a str,
+  title: &'a str,
+  web_url: &'a str,
+  author: &'a str,
+  author_initials: String,
+  assignees: Vec<&'a str>,
+  reviewers: Vec<&'a str>,
+  my_review_state: ReviewState,
+  source_branch: &'a str,
+  target_branch: &'a str,
+  draft: bool,
+  has_conflicts: bool,
+  inaccessible: bool,
+  discussion_locked: bool,
+  from_fork: bool,
+  /// Review effort left, for merge requests I review once their size is known.
+  effort: Option<Effort>,
+  auto_merge: bool,
+  merge_train_position: Option<usize>,
+  tasks: Option<JsonTasks>,
+  /// From a group's triage queue rather than any role of mine.
+  needs_triage: bool,
+  updated_at: DateTime<Utc>,
+  review_requested_at: Option<DateTime<Utc>>,
+  /// Set while the merge request waits on my review.
+  waiting_on_me_since: Option<DateTime<Utc>>,
+  sla: Option<Level>,
+  /// None until details are fetched, and on instances without merge
+  /// request approvals.
+  approvals_left: Option<usize>,
+  notes: usize,
+  upvotes: usize,
+  downvotes: usize,
+  pending_approval_rules: Vec<PendingApprovalRule<'a>>,
+  pipeline_status: Option<PipelineStatus>,
+  attention: Option<Attention>,
+  /// Set when every reviewer yet to approve is busy or away.
+  reviewers_unavailable: bool,
+  threads_awaiting_me: usize,
+  threads_awaiting_author: usize,
+  blocked_by: Vec<&'a str>,
+  blocking: Vec<&'a str>,
+  tickets: &'a [String],
+  last_activity: Option<String>,
+  priority: isize,
+  tier: Tier,
+  priority_terms: Vec<PriorityTerm<'a>>,
+}
+
+/// Goes up with every change that could break a consumer: a field removed,
+/// renamed or given another type. New fields don't count.
+///
+/// 2: `approvals_left` is null where unknown, rather than 0.
+const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Serialize, Debug)]
+struct JsonOutput<'a> {
+  schema: u32,
+  generated_at: DateTime<Utc>,
+  user: &'a str,
+  mrs: Vec<JsonMergeRequest<'a>>,
+}
+
+pub fn print_json(rows: &[(Row, Score)], user: &User, options: &Options) -> Result<()> {
+  serde_json::to_writer_pretty(stdout(), &output(rows, user, options))?;
+  println!();
+  Ok(())
+}
+
+fn output<'a>(rows: &'a [(Row, Score)], user: &'a User, options: &Options) -> JsonOutput<'a> {
+  let now = clock::server_now();
+  let mrs: Vec<JsonMergeRequest> = rows
+    .iter()
+    .map(|(row, score)| {
+      let mr = &row.mr;
+      let threads = ThreadCounts::of(row, user);
+      JsonMergeRequest {
+        reference: &mr.references.full,
+        title: &mr.title,
+        web_url: &mr.web_url,
+        author: &mr.author.username,
+        author_initials: initials(&mr.author),
+        assignees: mr.assignees.iter().map(|u| u.username.as_str()).collect(),
+        reviewers: mr.reviewers.iter().map(|u| u.username.as_str()).collect(),
+        my_review_state: row.review_state(user),
+        source_branch: &mr.source_branch,
+        target_branch: &mr.target_branch,
+        draft: mr.draft,
+        has_conflicts: mr.has_conflicts,
+        inaccessible: row.inaccessible,
+        discussion_locked: mr.is_locked(),
+        from_fork: mr.is_from_fork(),
+        effort: effort::of(row, user, options),
+        auto_merge: mr.merge_when_pipeline_succeeds,
+        merge_train_position: row.merge_train.as_ref().map(|train| train.position),
+        tasks: mr.tasks().map(|tasks| JsonTasks {
+          completed: tasks.completed_count,
+          total: tasks.count,
+        }),
+        needs_triage: row.triage,
+        updated_at: mr.updated_at,
+        review_requested_at: row.review_requested_at,
+        waiting_on_me_since: sla::waiting_since(row, user),
+        sla: sla::waited(row, user, options, now).map(|waited| Level::of(waited, options)),
+        approvals_left: row
+          .approvals_known()
+          .then_some(row.approval_info.approvals_left),
+        notes: mr.user_notes_count,
+        upvotes: mr.upvotes,
+        downvotes: mr.downvotes,
+        pending_approval_rules: row
+          .approval_rules
+          .iter()
+          .filter(|rule| !rule.approved)
+          .map(|rule| PendingApprovalRule {
+            name: &rule.name,
+            approvals_required: rule.approvals_required,
+            eligible_approvers: rule
+              .eligible_approvers
+              .iter()
+              .map(|u| u.username.as_str())
+              .collect(),
+            includes_me: rule.is_eligible(user),
+          })
+          .collect(),
+        pipeline_status: row.pipeline.as_ref().map(|pipeline| pipeline.status),
+        attention: attention(row),
+        reviewers_unavailable: reviewers_unavailable(row),
+        threads_awaiting_me: threads.awaiting_me,
+        threads_awaiting_author: threads.awaiting_author,
+        blocked_by: row
+          .dependencies
+          .blocked_by
+          .iter()
+          .map(|mr| mr.references.full.as_str())
+          .collect(),
+        blocking: row
+          .dependencies
+          .blocking
+          .iter()
+          .map(|mr| mr.references.full.as_str())
+          .collect(),
+        tickets: &row.tickets,
+        last_activity: row.activity.as_ref().map(|activity| activity.to_string()),
+        priority: score.total(),
+        tier: Tier::of(score, options),
+        priority_terms: score
+          .terms
+          .iter()
+          .map(|(reason, delta)| PriorityTerm {
+            reason,
+            delta: *delta,
+          })
+          .collect(),
+      }
+    })
+    .collect();
+
+  JsonOutput {
+    schema: SCHEMA_VERSION,
+    generated_at: Utc::now(),
+    user: &user.username,
+    mrs,
+  }
+}
+
+/// A JSON Schema of the output above, for consumers to validate against.
+/// Written out by hand, so it has to change along with the structs; the
+/// tests check real output against it.
+pub fn schema() -> Value {
+  let string = || json!({ "type": "string" });
+  let strings = || json!({ "type": "array", "items": { "type": "string" } });
+  let count = || json!({ "type": "integer", "minimum": 0 });
+  let boolean = || json!({ "type": "boolean" });
+  let time = || json!({ "type": "string", "format": "date-time" });
+  let nullable = |schema: Value| json!({ "anyOf": [schema, { "type": "null" }] });
+  let described = |mut schema: Value, description: &str| {
+    schema["description"] = json!(description);
+    schema
+  };
+  let one_of = |values: &[&str]| json!({ "type": "string", "enum": values });
+  let object = |properties: Value| {
+    let required: Vec<&String> = properties.as_object().unwrap().keys().collect();
+    json!({
+      "type": "object",
+      "properties": properties,
+      "required": required,
+    })
+  };
+
+  // Nested parts are built on their own, as one macro call can only nest so deep.
+  let pending_approval_rules = json!({
+    "type": "array",
+    "items": object(json!({
+      "name": string(),
+      "approvals_required": count(),
+      "eligible_approvers": strings(),
+      "includes_me": boolean(),
+    })),
+  });
+  let pipeline_status = one_of(&[
+    "created",
+    "waiting_for_resource",
+    "preparing",
+    "pending",
+    "running",
+    "success",
+    "failed",
+    "canceled",
+    "skipped",
+    "manual",
+    "scheduled",
+    "unknown",
+  ]);
+  let priority_terms = json!({
+    "type": "array",
+    "items": object(json!({ "reason": string(), "delta": { "type": "integer" } })),
+  });
+
+  let merge_request = object(json!({
+    "reference": string(),
+    "title": string(),
+    "web_url": string(),
+    "author": string(),
+    "author_initials": string(),
+    "assignees": strings(),
+    "reviewers": strings(),
+    "my_review_state": one_of(&["unreviewed", "reviewed", "requested_changes", "approved", "unknown"]),
+    "source_branch": string(),
+    "target_branch": string(),
+    "draft": boolean(),
+    "has_conflicts": boolean(),
+    "inaccessible": boolean(),
+    "discussion_locked": boolean(),
+    "from_fork": boolean(),
+    "effort": nullable(one_of(&["S", "M", "L"])),
+    "auto_merge": boolean(),
+    "merge_train_position": nullable(count()),
+    "tasks": nullable(object(json!({ "completed": count(), "total": count() }))),
+    "needs_triage": boolean(),
+    "updated_at": time(),
+    "review_requested_at": nullable(time()),
+    "waiting_on_me_since": nullable(time()),
+    "sla": nullable(one_of(&["ok", "warn", "breach"])),
+    "approvals_left": described(
+      nullable(count()),
+      "Null until details are fetched, and on instances without merge request approvals",
+    ),
+    "notes": count(),
+    "upvotes": count(),
+    "downvotes": count(),
+    "pending_approval_rules": pending_approval_rules,
+    "pipeline_status": nullable(pipeline_status),
+    "attention": nullable(one_of(&["action_required", "waiting_on_others"])),
+    "reviewers_unavailable": boolean(),
+    "threads_awaiting_me": count(),
+    "threads_awaiting_author": count(),
+    "blocked_by": strings(),
+    "blocking": strings(),
+    "tickets": strings(),
+    "last_activity": nullable(string()),
+    "priority": { "type": "integer" },
+    "tier": one_of(&["action", "look", "low"]),
+    "priority_terms": priority_terms,
+  }));
+
+  let mut schema = object(json!({
+    "schema": { "const": SCHEMA_VERSION },
+    "generated_at": time(),
+    "user": string(),
+    "mrs": { "type": "array", "items": merge_request },
+  }));
+  let fields = schema.as_object_mut().unwrap();
+  fields.insert(
+    "$schema".to_string(),
+    json!("https://json-schema.org/draft/2020-12/schema"),
+  );
+  fields.insert("title".to_string(), json!("gitlab-todo --format json"));
+  schema
+}
+
+pub fn print_schema() -> Result<()> {
+  serde_json::to_writer_pretty(stdout(), &schema())?;
+  println!();
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    activity::{Activity, ActivityKind},
+    config::Config,
+    fixtures::{me, mr, options, row, user, user_json},
+    gitlab::{Id, Pipeline, Reviewer},
+    priority::priority,
+  };
+
+  /// Where `value` breaks `schema`, for the parts of JSON Schema that
+  /// `schema()` uses. Fields the schema doesn't name count, too, so that
+  /// the two can't drift apart.
+  fn violations(schema: &Value, value: &Value, at: &str) -> Vec<String> {
+    if let Some(alternatives) = schema["anyOf"].as_array() {
+      return match alternatives
+        .iter()
+        .any(|alternative| violations(alternative, value, at).is_empty())
+      {
+        true => Vec::new(),
+        false => vec![format!("{}: {} matches no alternative", at, value)],
+      };
+    }
+    let mut found = Vec::new();
+    if schema
+      .get("const")
+      .is_some_and(|expected| expected != value)
+    {
+      found.push(format!("{}: {} is not {}", at, value, schema["const"]));
+    }
+    if let Some(values) = schema["enum"].as_array() {
+      if !values.contains(value) {
+        found.push(format!("{}: {} is not one of {:?}", at, value, values));
+      }
+    }
+    let fits = match schema["type"].as_str() {
+      Some("string") => value.is_string(),
+      Some("integer") => value.is_i64() || value.is_u64(),
+      Some("boolean") => value.is_boolean(),
+      Some("null") => value.is_null(),
+      Some("array") => value.is_array(),
+      Some("object") => value.is_object(),
+      Some(other) => panic!("no check for type {}", other),
+      None => true,
+    };
+    if !fits {
+      found.push(format!("{}: {} is no {}", at, value, schema["type"]));
+      return found;
+    }
+    if let (Some(minimum), Some(number)) = (schema["minimum"].as_i64(), value.as_i64()) {
+      if number < minimum {
+        found.push(format!("{}: {} is below {}", at, number, minimum));
+      }
+    }
+    if let (Some(items), Some(values)) = (schema.get("items"), value.as_array()) {
+      for (index, item) in values.iter().enumerate() {
+        found.extend(violations(items, item, &format!("{}[{}]", at, index)));
+      }
+    }
+    if let (Some(properties), Some(fields)) = (schema["properties"].as_object(), value.as_object())
+    {
+      for required in schema["required"].as_array().into_iter().flatten() {
+        let required = required.as_str().unwrap();
+        if !fields.contains_key(required) {
+          found.push(format!("{}: {} is missing", at, required));
+        }
+      }
+      for (name, field) in fields {
+        let at = format!("{}.{}", at, name);
+        match properties.get(name) {
+          Some(property) => found.extend(violations(property, field, &at)),
+          None => found.push(format!("{}: not in the schema", at)),
+        }
+      }
+    }
+    found
+  }
+
+  /// A merge request of mine with everything filled in that can be.
+  fn detailed() -> Row {
+    let mut row = row(mr(
+      1,
+      json!({
+        "author": user_json(1, "me"),
+        "assignees": [user_json(3, "bob")],
+        "reviewers": [user_json(4, "carol")],
+        "task_completion_status": { "count": 3, "completed_count": 1 },
+        "milestone": { "title": "16.5", "due_date": "2026-10-22" },
+      }),
+    ));
+    row.detailed = true;
+    row.approvals = true;
+    row.approval_info.approvals_left = 1;
+    row.changed_files = Some(12);
+    row.pipeline = Some(Pipeline {
+      id: Id(7),
+      status: PipelineStatus::Failed,
+      web_url: String::new(),
+    });
+    row.reviewers = vec![Reviewer {
+      user: user(4, "carol"),
+      state: ReviewState::RequestedChanges,
+    }];
+    row.tickets = vec!["PROJ-12".to_string()];
+    row.activity = Some(Activity {
+      username: "carol".to_string(),
+      kind: ActivityKind::Commented,
+    });
+    row
+  }
+
+  /// Someone else's merge request I review, as listed before any details.
+  fn listed() -> Row {
+    let mut row = row(mr(2, json!({ "reviewers": [user_json(1, "me")] })));
+    row.review_requested_at = "2026-10-09T08:00:00Z".parse().ok();
+    row
+  }
+
+  fn document(rows: Vec<Row>) -> Value {
+    let (user, options) = (me(), options(&[]));
+    let rows: Vec<(Row, Score)> = rows
+      .into_iter()
+      .map(|row| {
+        let score = priority(&row, &user, &options, &Config::default());
+        (row, score)
+      })
+      .collect();
+    serde_json::to_value(output(&rows, &user, &options)).unwrap()
+  }
+
+  #[test]
+  fn output_matches_the_schema() {
+    let document = document(vec![detailed(), listed()]);
+    assert_eq!(violations(&schema(), &document, "$"), Vec::<String>::new());
+    assert_eq!(document["schema"], json!(2));
+    assert_eq!(document["mrs"][0]["approvals_left"], json!(1));
+    assert_eq!(document["mrs"][1]["approvals_left"], Value::Null);
+  }
+
+  #[test]
+  fn titles_are_kept_as_they_came() {
+    // JSON escapes control characters itself, and whoever reads it may want them.
+    let title = "Fix \x1b[31mred\x1b[0m \x1b]8;;https://evil.example\x07here\u{9b}2J";
+    let document = document(vec![row(mr(1, json!({ "title": title })))]);
+    assert_eq!(document["mrs"][0]["title"], json!(title));
+    let text = serde_json::to_string(&document).unwrap();
+    assert!(!text.contains('\x1b'));
+  }
+
+  #[test]
+  fn approvals_are_null_without_approvals() {
+    let mut row = detailed();
+    row.approvals = false;
+    let document = document(vec![row]);
+    assert_eq!(violations(&schema(), &document, "$"), Vec::<String>::new());
+    assert_eq!(document["mrs"][0]["approvals_left"], Value::Null);
+    assert_eq!(document["mrs"][0]["pending_approval_rules"], json!([]));
+  }
+
+  #[test]
+  fn every_status_is_in_the_schema() {
+    let statuses: Vec<PipelineStatus> = serde_json::from_value(json!([
+      "created",
+      "waiting_for_resource",
+      "preparing",
+      "pending",
+      "running",
+      "success",
+      "failed",
+      "canceled",
+      "skipped",
+      "manual",
+      "scheduled",
+      "something new",
+    ]))
+    .unwrap();
+    let merge_request = &schema()["properties"]["mrs"]["items"];
+    for status in statuses {
+      let status = serde_json::to_value(status).unwrap();
+      let property = &merge_request["properties"]["pipeline_status"];
+      assert!(violations(property, &status, "$").is_empty(), "{}", status);
+    }
+  }
+
+  #[test]
+  fn the_schema_catches_drift() {
+    let mut document = document(vec![listed()]);
+    document["mrs"][0]["approvals_left"] = json!("two");
+    document["mrs"][0]["renamed"] = json!(true);
+    document["mrs"][0].as_object_mut().unwrap().remove("tier");
+    let found = violations(&schema(), &document, "$");
+    assert_eq!(found.len(), 3, "{:#?}", found);
+  }
+}
@@ -0,0 +1,145 @@
+use crate::terminal::printable;
+use crossterm::style::Stylize;
+use std::collections::BTreeSet;
+
+/// Picks the labels of a merge request from those of its project.
+#[derive(Debug, Clone)]
+pub struct Picker {
+  available: Vec<String>,
+  current: BTreeSet<String>,
+  checked: BTreeSet<String>,
+  pub filter: String,
+  cursor: usize,
+}
+
+/// The scope of a scoped label like `priority::high`, which only one label
+/// of a merge request can have.
+pub fn scope(label: &str) -> Option<&str> {
+  label.rsplit_once("::").map(|(scope, _)| scope)
+}
+
+/// Whether all of `filter` appears in `label` in order, ignoring case, so
+/// that `prh` finds `priority::high`.
+pub fn fuzzy_match(filter: &str, label: &str) -> bool {
+  let mut label = label.chars().flat_map(char::to_lowercase);
+  filter
+    .chars()
+    .flat_map(char::to_lowercase)
+    .all(|wanted| label.any(|c| c == wanted))
+}
+
+impl Picker {
+  /// Labels the merge request has but the project no longer lists stay
+  /// pickable, so that they can be removed.
+  pub fn new(mut available: Vec<String>, current: &[String]) -> Self {
+    for label in current {
+      if !available.contains(label) {
+        available.push(label.clone());
+      }
+    }
+    available.sort_by_key(|label| label.to_lowercase());
+    let current: BTreeSet<String> = current.iter().cloned().collect();
+    Picker {
+      available,
+      checked: current.clone(),
+      current,
+      filter: String::new(),
+      cursor: 0,
+    }
+  }
+
+  /// The labels the filter lets through, in order.
+  pub fn matches(&self) -> Vec<&str> {
+    self
+      .available
+      .iter()
+      .filter(|label| fuzzy_match(&self.filter, label))
+      .map(String::as_str)
+      .collect()
+  }
+
+  pub fn set_filter(&mut self, filter: String) {
+    self.filter = filter;
+    self.cursor = 0;
+  }
+
+  pub fn move_by(&mut self, down: bool) {
+    let last = self.matches().len().saturating_sub(1);
+    self.cursor = if down {
+      (self.cursor + 1).min(last)
+    } else {
+      self.cursor.saturating_sub(1)
+    };
+  }
+
+  /// Checks or unchecks the label under the cursor. Checking a scoped label
+  /// unchecks the others of its scope.
+  pub fn toggle(&mut self) {
+    let Some(label) = self
+      .matches()
+      .get(self.cursor)
+      .map(|label| label.to_string())
+    else {
+      return;
+    };
+    if self.checked.remove(&label) {
+      return;
+    }
+    if let Some(label_scope) = scope(&label) {
+      self
+        .checked
+        .retain(|other| scope(other) != Some(label_scope));
+    }
+    self.checked.insert(label);
+  }
+
+  /// The labels to add and to remove.
+  pub fn changes(&self) -> (Vec<String>, Vec<String>) {
+    (
+      self.checked.difference(&self.current).cloned().collect(),
+      self.current.difference(&self.checked).cloned().collect(),
+    )
+  }
+
+  /// The labels the merge request has once the changes are made, in the
+  /// order it had them, followed by those added.
+  pub fn applied(&self, labels: &[String]) -> Vec<String> {
+    let (added, _) = self.changes();
+    labels
+      .iter()
+      .filter(|label| self.checked.contains(*label))
+      .cloned()
+      .chain(added)
+      .collect()
+  }
+
+  /// The filter and up to `height - 1` of the matching labels, scrolled to
+  /// keep the cursor in view.
+  pub fn lines(&self, height: usize) -> Vec<String> {
+    let mut lines = vec![format!(
+      "Labels: {}_  {}",
+      printable(&self.filter),
+      "(type to filter, ↑/↓ move, Tab check, Enter apply, Esc cancel)".dark_grey()
+    )];
+    let matches = self.matches();
+    let room = height.saturating_sub(1).max(1);
+    let skip = (self.cursor + 1).saturating_sub(room);
+    for (index, label) in matches.iter().enumerate().skip(skip).take(room) {
+      let checkbox = if self.checked.contains(*label) {
+        "[x]"
+      } else {
+        "[ ]"
+      };
+      let line = format!("{} {}", checkbox, printable(label));
+      lines.push(if index == self.cursor {
+        format!("▶ {}", line.bold())
+      } else {
+        format!("  {}", line)
+      });
+    }
+    if matches.is_empty() {
+      lines.push("  no label matches".dark_grey().to_string());
+    }
+    lines
+  }
+}
@@ -0,0 +1,526 @@
+use crate::{
+  options::Options, sparkline::notes_label, terminal::printable, tickets::ticket_label, Row,
+};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, num::ParseIntError, str::FromStr};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct WidthBounds {
+  pub min: usize,
+  pub max: usize,
+}
+
+impl WidthBounds {
+  fn fit(&self, width: usize) -> usize {
+    width.clamp(self.min, self.max)
+  }
+}
+
+impl FromStr for WidthBounds {
+  type Err = String;
+
+  fn from_str(input: &str) -> Result<Self, Self::Err> {
+    let parse = |s: &str| {
+      s.trim()
+        .parse::<usize>()
+        .map_err(|err: ParseIntError| format!("Invalid width '{}': {}", s, err))
+    };
+
+    let bounds = match input.split_once("..") {
+      Some((min, max)) => WidthBounds {
+        min: parse(min)?,
+        max: parse(max)?,
+      },
+      None => {
+        let width = parse(input)?;
+        WidthBounds {
+          min: width,
+          max: width,
+        }
+      }
+    };
+
+    if bounds.min > bounds.max {
+      return Err(format!(
+        "Minimum width {} exceeds maximum width {}",
+        bounds.min, bounds.max
+      ));
+    }
+
+    Ok(bounds)
+  }
+}
+
+impl TryFrom<String> for WidthBounds {
+  type Error = String;
+
+  fn try_from(input: String) -> Result<Self, Self::Error> {
+    input.parse()
+  }
+}
+
+impl From<WidthBounds> for String {
+  fn from(bounds: WidthBounds) -> Self {
+    bounds.to_string()
+  }
+}
+
+impl Display for WidthBounds {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    if self.min == self.max {
+      write!(f, "{}", self.min)
+    } else {
+      write!(f, "{}..{}", self.min, self.max)
+    }
+  }
+}
+
+/// The knobs of the table layout, all of them configurable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout<'a> {
+  pub ellipsis: &'a str,
+  /// Spaces between two columns.
+  pub gap: usize,
+  pub min_title_width: usize,
+  pub author: WidthBounds,
+  pub assignees: WidthBounds,
+  pub reviewers: WidthBounds,
+  pub branch: WidthBounds,
+  pub milestone: WidthBounds,
+  pub activity: WidthBounds,
+}
+
+impl<'a> Layout<'a> {
+  pub fn of(options: &'a Options) -> Self {
+    Layout {
+      ellipsis: &options.ellipsis,
+      gap: options.column_gap,
+      min_title_width: options.min_title_width,
+      author: options.author_width,
+      assignees: options.assignee_width,
+      reviewers: options.reviewer_width,
+      branch: options.branch_width,
+      milestone: options.milestone_width,
+      activity: options.activity_width,
+    }
+  }
+
+  pub fn cell(&self, width: usize, body: &str) -> String {
+    truncate(width, body, self.ellipsis)
+  }
+
+  /// The columns side by side, with the gap between them.
+  pub fn join(&self, cells: &[String]) -> String {
+    cells.join(&" ".repeat(self.gap))
+  }
+
+  fn bounds(&self, column: Column) -> Option<WidthBounds> {
+    match column {
+      Column::Author => Some(self.author),
+      Column::Assignees => Some(self.assignees),
+      Column::Reviewers => Some(self.reviewers),
+      Column::Branch => Some(self.branch),
+      Column::Milestone => Some(self.milestone),
+      Column::Activity => Some(self.activity),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+  Reference,
+  Ticket,
+  Title,
+  Author,
+  Assignees,
+  Reviewers,
+  Branch,
+  Tasks,
+  Notes,
+  Milestone,
+  Deadline,
+  Activity,
+}
+
+// Shrunk in this order when the terminal is too narrow, and then left out in
+// the next, before the title goes below its minimum.
+const SHRINKABLE: [Column; 6] = [
+  Column::Activity,
+  Column::Milestone,
+  Column::Branch,
+  Column::Reviewers,
+  Column::Assignees,
+  Column::Author,
+];
+const OPTIONAL: [Column; 6] = [
+  Column::Activity,
+  Column::Notes,
+  Column::Tasks,
+  Column::Milestone,
+  Column::Ticket,
+  Column::Deadline,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnPlan {
+  pub column: Column,
+  pub width: usize,
+}
+
+fn widest(widths: impl Iterator<Item = usize>) -> usize {
+  widths.max().unwrap_or(0)
+}
+
+/// How wide each column's content gets, or None for columns not shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DataWidths {
+  pub reference: usize,
+  pub ticket: Option<usize>,
+  pub author: usize,
+  pub assignees: usize,
+  pub reviewers: usize,
+  pub branch: usize,
+  pub tasks: Option<usize>,
+  pub notes: Option<usize>,
+  pub milestone: Option<usize>,
+  pub deadline: Option<usize>,
+  pub activity: Option<usize>,
+}
+
+impl DataWidths {
+  pub fn of<'a>(rows: impl Iterator<Item = &'a Row> + Clone, options: &Options) -> Self {
+    let mrs = rows.clone().map(|row| &row.mr);
+    DataWidths {
+      reference: mrs
+        .clone()
+        .map(|mr| options.short_reference(&mr.references.full).width())
+        .max()
+        .unwrap_or(25),
+      ticket: options
+        .ticket_pattern
+        .as_ref()
+        .map(|_| widest(rows.clone().map(|row| ticket_label(&row.tickets).width()))),
+      author: widest(mrs.clone().map(|mr| mr.author.username.width())),
+      assignees: widest(mrs.clone().map(|mr| mr.assignee_names().width())),
+      reviewers: widest(rows.clone().map(|row| row.reviewer_names().width())),
+      branch: widest(mrs.clone().map(|mr| mr.target_branch.width())),
+      tasks: mrs
+        .clone()
+        .filter(|_| options.tasks)
+        .filter_map(|mr| mr.tasks())
+        .map(|tasks| tasks.to_string().width())
+        .max(),
+      notes: options
+        .notes_count
+        .then(|| widest(mrs.clone().map(|mr| notes_label(mr).width()))),
+      milestone: mrs
+        .clone()
+        .filter_map(|mr| mr.milestone.as_ref())
+        .map(|milestone| milestone.title.width())
+        .max(),
+      deadline: mrs
+        .clone()
+        .any(|mr| mr.deadline().is_some())
+        .then_some(DEADLINE_WIDTH),
+      activity: options.last_activity.then(|| {
+        widest(
+          rows
+            .filter_map(|row| row.activity.as_ref())
+            .map(|activity| activity.to_string().width()),
+        )
+      }),
+    }
+  }
+}
+
+/// Decides every column's width, in display order, so that they add up to
+/// the terminal's width. The title takes what the other columns leave,
+/// shrunk to their minimums and without the optional ones if need be, and
+/// then without the name and branch columns rather than any of them going
+/// below its minimum. Only a terminal too narrow for the reference and the
+/// title at its minimum gets a shorter title, and one too narrow for the
+/// reference alone a cut reference.
+pub fn plan_columns(term_width: usize, data: &DataWidths, layout: &Layout) -> Vec<ColumnPlan> {
+  let mut plan: Vec<ColumnPlan> = [
+    (Column::Reference, Some(data.reference)),
+    (Column::Ticket, data.ticket),
+    (Column::Title, Some(0)),
+    (Column::Author, Some(data.author)),
+    (Column::Assignees, Some(data.assignees)),
+    (Column::Reviewers, Some(data.reviewers)),
+    (Column::Branch, Some(data.branch)),
+    (Column::Tasks, data.tasks),
+    (Column::Notes, data.notes),
+    (Column::Milestone, data.milestone),
+    (Column::Deadline, data.deadline),
+    (Column::Activity, data.activity),
+  ]
+  .into_iter()
+  .filter_map(|(column, width)| {
+    let width = width?;
+    let width = layout
+      .bounds(column)
+      .map_or(width, |bounds| bounds.fit(width));
+    Some(ColumnPlan { column, width })
+  })
+  .collect();
+
+  // What the title would leave over at its minimum; negative where too wide.
+  let excess = |plan: &[ColumnPlan]| {
+    let used: usize = plan.iter().map(|column| column.width).sum();
+    let gaps = plan.len().saturating_sub(1) * layout.gap;
+    (used + gaps + layout.min_title_width) as isize - term_width as isize
+  };
+
+  for column in SHRINKABLE {
+    let over = excess(&plan);
+    if over <= 0 {
+      break;
+    }
+    let min = layout.bounds(column).map_or(0, |bounds| bounds.min);
+    if let Some(planned) = plan.iter_mut().find(|planned| planned.column == column) {
+      let slack = planned.width.saturating_sub(min);
+      planned.width -= slack.min(over as usize);
+    }
+  }
+  for column in OPTIONAL.iter().chain(&SHRINKABLE) {
+    if excess(&plan) <= 0 {
+      break;
+    }
+    plan.retain(|planned| planned.column != *column);
+  }
+
+  let room = term_width.saturating_sub(plan.len().saturating_sub(1) * layout.gap);
+  let others: usize = plan
+    .iter()
+    .filter(|planned| planned.column != Column::Title)
+    .map(|planned| planned.width)
+    .sum();
+  for planned in &mut plan {
+    match planned.column {
+      Column::Title => planned.width = room.saturating_sub(others),
+      Column::Reference => planned.width = planned.width.min(room),
+      _ => {}
+    }
+  }
+  plan
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnWidths {
+  pub reference: usize,
+  pub ticket: Option<usize>,
+  pub title: usize,
+  pub author: Option<usize>,
+  pub assignees: Option<usize>,
+  pub reviewers: Option<usize>,
+  pub branch: Option<usize>,
+  pub tasks: Option<usize>,
+  pub notes: Option<usize>,
+  pub milestone: Option<usize>,
+  pub deadline: Option<usize>,
+  pub activity: Option<usize>,
+  /// All columns together, as wide as a row gets.
+  pub total: usize,
+}
+
+impl ColumnWidths {
+  pub fn of(plan: &[ColumnPlan], layout: &Layout) -> Self {
+    let width = |column| {
+      plan
+        .iter()
+        .find(|planned| planned.column == column)
+        .map(|planned| planned.width)
+    };
+    ColumnWidths {
+      reference: width(Column::Reference).unwrap_or(0),
+      ticket: width(Column::Ticket),
+      title: width(Column::Title).unwrap_or(0),
+      author: width(Column::Author),
+      assignees: width(Column::Assignees),
+      reviewers: width(Column::Reviewers),
+      branch: width(Column::Branch),
+      tasks: width(Column::Tasks),
+      notes: width(Column::Notes),
+      milestone: width(Column::Milestone),
+      deadline: width(Column::Deadline),
+      activity: width(Column::Activity),
+      total: plan.iter().map(|planned| planned.width).sum::<usize>()
+        + plan.len().saturating_sub(1) * layout.gap,
+    }
+  }
+}
+
+pub const DEADLINE_WIDTH: usize = 10;
+
+pub fn column_widths<'a>(
+  term_width: usize,
+  rows: impl Iterator<Item = &'a Row> + Clone,
+  options: &Options,
+) -> ColumnWidths {
+  let layout = Layout::of(options);
+  let plan = plan_columns(term_width, &DataWidths::of(rows, options), &layout);
+  ColumnWidths::of(&plan, &layout)
+}
+
+pub fn cell(width: usize, body: &str) -> String {
+  truncate(width, body, "...")
+}
+
+/// Pads or cuts `body` to exactly `width`, marking a cut with `ellipsis`
+/// where that fits.
+fn truncate(width: usize, body: &str, ellipsis: &str) -> String {
+  let body = printable(body);
+  let len = body.width();
+
+  if len > width {
+    let ellipsis = if ellipsis.width() <= width {
+      ellipsis
+    } else {
+      ""
+    };
+    let limit = width - ellipsis.width();
+    let mut used = 0;
+    let mut truncated = String::new();
+    for c in body.chars() {
+      let char_width = c.width().unwrap_or(0);
+      if used + char_width > limit {
+        break;
+      }
+      used += char_width;
+      truncated.push(c);
+    }
+    truncated.push_str(ellipsis);
+    truncated.push_str(&" ".repeat(limit - used));
+    truncated
+  } else {
+    let suffix = " ".repeat(width - len);
+    let mut body = body.to_string();
+    body.push_str(&suffix);
+    body
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::{mr, options, row};
+  use serde_json::json;
+
+  fn rows_by(usernames: &[&str]) -> Vec<Row> {
+    usernames
+      .iter()
+      .enumerate()
+      .map(|(index, username)| {
+        let someone = json!({ "id": 10 + index, "name": username, "username": username });
+        row(mr(
+          index + 1,
+          json!({ "author": someone, "assignees": [someone] }),
+        ))
+      })
+      .collect()
+  }
+
+  #[test]
+  fn widths_add_up_to_the_terminal_width() {
+    let options = options(&[]);
+    let short = rows_by(&["ann", "bob"]);
+    let long = rows_by(&["a-rather-long-username", "bob"]);
+    for rows in [&short, &long] {
+      for term_width in [40, 80, 120, 200] {
+        let widths = column_widths(term_width, rows.iter(), &options);
+        assert_eq!(widths.total, term_width, "at {} columns", term_width);
+      }
+    }
+  }
+
+  #[test]
+  fn short_usernames_leave_their_room_to_the_title() {
+    let options = options(&[]);
+    let short = column_widths(120, rows_by(&["ann", "bob"]).iter(), &options);
+    let long = column_widths(120, rows_by(&["a-rather-long-username"]).iter(), &options);
+    // Author names are cut at 20 and assignees at 30 by default.
+    assert_eq!((short.author, short.assignees), (Some(4), Some(4)));
+    assert_eq!((long.author, long.assignees), (Some(20), Some(22)));
+    assert_eq!(short.title - long.title, (20 - 4) + (22 - 4));
+  }
+
+  /// A xorshift generator, so that every run checks the same cases.
+  struct Cases(u64);
+
+  impl Cases {
+    fn below(&mut self, bound: usize) -> usize {
+      self.0 ^= self.0 << 13;
+      self.0 ^= self.0 >> 7;
+      self.0 ^= self.0 << 17;
+      (self.0 % bound as u64) as usize
+    }
+
+    fn maybe(&mut self, bound: usize) -> Option<usize> {
+      (self.below(2) == 0).then(|| self.below(bound))
+    }
+
+    fn bounds(&mut self) -> WidthBounds {
+      let min = self.below(12);
+      WidthBounds {
+        min,
+        max: min + self.below(30),
+      }
+    }
+  }
+
+  #[test]
+  fn planned_columns_fill_the_terminal_and_keep_their_minimums() {
+    let mut cases = Cases(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..5000 {
+      let data = DataWidths {
+        reference: cases.below(40),
+        ticket: cases.maybe(20),
+        author: cases.below(40),
+        assignees: cases.below(60),
+        reviewers: cases.below(60),
+        branch: cases.below(40),
+        tasks: cases.maybe(8),
+        notes: cases.maybe(8),
+        milestone: cases.maybe(30),
+        deadline: cases.maybe(2).map(|_| DEADLINE_WIDTH),
+        activity: cases.maybe(20),
+      };
+      let layout = Layout {
+        ellipsis: "…",
+        gap: cases.below(4),
+        min_title_width: cases.below(40),
+        author: cases.bounds(),
+        assignees: cases.bounds(),
+        reviewers: cases.bounds(),
+        branch: cases.bounds(),
+        milestone: cases.bounds(),
+        activity: cases.bounds(),
+      };
+      // Reference and title are always there, with a gap between them.
+      let term_width = layout.gap + cases.below(300);
+
+      let plan = plan_columns(term_width, &data, &layout);
+      let widths = ColumnWidths::of(&plan, &layout);
+      let case = format!("{:?} {:?} at {}: {:?}", data, layout, term_width, plan);
+      assert_eq!(widths.total, term_width, "{}", case);
+      for planned in &plan {
+        if let Some(bounds) = layout.bounds(planned.column) {
+          assert!(planned.width >= bounds.min, "below minimum: {}", case);
+        }
+      }
+      let reference = data.reference + layout.gap;
+      if term_width >= reference + layout.min_title_width {
+        assert!(
+          widths.title >= layout.min_title_width,
+          "title below minimum: {}",
+          case
+        );
+        assert_eq!(widths.reference, data.reference, "cut reference: {}", case);
+      }
+    }
+  }
+}
@@ -0,0 +1,312 @@
+use crate::{
+  attention::{attention, reviewers_unavailable, Attention},
+  clock,
+  config::Config,
+  gitlab::User,
+  options::Options,
+  priority::pipeline_failed,
+  sla,
+  terminal::printable,
+  view::sections,
+  views::View,
+  Row, Snapshot,
+};
+use chrono::{DateTime, Utc};
+
+// The state a merge request is in, in words rather than colors.
+fn state(row: &Row, options: &Options) -> &'static str {
+  let roles = &row.roles;
+  if row.inaccessible {
+    "INACCESSIBLE"
+  } else if row.mr.draft {
+    "DRAFT"
+  } else if options.dashboard {
+    "OPEN"
+  } else if roles.author {
+    match attention(row) {
+      Some(Attention::ActionRequired) => "NEEDS YOUR CHANGES",
+      Some(Attention::WaitingOnOthers) if reviewers_unavailable(row) => "WAITING ON BUSY REVIEWERS",
+      Some(Attention::WaitingOnOthers) => "WAITING ON OTHERS",
+      None => "YOURS",
+    }
+  } else if roles.reviewing() {
+    "NEEDS REVIEW"
+  } else if roles.assigned_by_others() {
+    "ASSIGNED TO YOU"
+  } else {
+    "FOR YOUR INFORMATION"
+  }
+}
+
+pub fn ago(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+  let age = now - time;
+  let (count, unit) = if age.num_days() > 0 {
+    (age.num_days(), "day")
+  } else if age.num_hours() > 0 {
+    (age.num_hours(), "hour")
+  } else {
+    (age.num_minutes(), "minute")
+  };
+  format!(
+    "{} {}{} ago",
+    count,
+    unit,
+    if count == 1 { "" } else { "s" }
+  )
+}
+
+fn sentence(row: &Row, user: &User, options: &Options, now: DateTime<Utc>) -> String {
+  let mr = &row.mr;
+  let mut parts = vec![
+    format!(
+      "{}: '{}' in {}",
+      state(row, options),
+      mr.title,
+      mr.project_path()
+    ),
+    format!("by {}", mr.author.username),
+    match row.review_requested_at {
+      Some(requested_at) => format!("review requested {}", ago(requested_at, now)),
+      None => format!("updated {}", ago(mr.updated_at, now)),
+    },
+  ];
+  if mr.has_conflicts {
+    parts.push("has conflicts".to_string());
+  }
+  if let Some(waited) = sla::waited(row, user, options, now) {
+    match sla::Level::of(waited, options) {
+      sla::Level::Ok => {}
+      sla::Level::Warn => parts.push(format!("waiting {} on my review", sla::label(waited))),
+      sla::Level::Breach => parts.push(format!(
+        "waiting {} on my review, over the SLA",
+        sla::label(waited)
+      )),
+    }
+  }
+  if let Some(blocker) = row.dependencies.blocked_by.first() {
+    parts.push(format!("blocked by !{}", blocker.iid));
+  }
+  if row.detailed {
+    if pipeline_failed(row) {
+      parts.push("pipeline failed".to_string());
+    }
+    if row.approvals {
+      parts.push(match row.approval_info.approvals_left {
+        0 => "fully approved".to_string(),
+        1 => "1 approval missing".to_string(),
+        left => format!("{} approvals missing", left),
+      });
+    }
+  }
+  if let Some(tasks) = mr.tasks() {
+    parts.push(format!(
+      "{} of {} tasks done",
+      tasks.completed_count, tasks.count
+    ));
+  }
+  if let Some(train) = &row.merge_train {
+    parts.push(format!("merge train position {}", train.position));
+  } else if mr.merge_when_pipeline_succeeds {
+    parts.push("set to auto-merge".to_string());
+  }
+  if let Some(deadline) = mr.deadline() {
+    parts.push(format!("due {}", deadline.format("%Y-%m-%d")));
+  }
+  if mr.is_locked() {
+    parts.push("discussion locked".to_string());
+  }
+  parts.push(format!("link: {}", mr.web_url));
+  parts.join(", ")
+}
+
+/// Every merge request as a plain numbered sentence, for screen readers.
+pub fn list_lines(
+  snapshot: &Snapshot,
+  view: &View,
+  user: &User,
+  options: &Options,
+  config: &Config,
+) -> Vec<String> {
+  let now = clock::server_now();
+  let mut lines = Vec::new();
+  let mut number = 0;
+  let focus = snapshot.focus.as_ref().map(|focus| focus.mr);
+  for section in sections(&snapshot.rows, view, user, options, config, focus) {
+    if let Some(heading) = section.heading {
+      if !lines.is_empty() {
+        lines.push(String::new());
+      }
+      lines.push(format!(
+        "{}, {}:",
+        heading.title(),
+        count(section.rows.len() + section.more)
+      ));
+      if let Some(progress) = section.progress {
+        lines.push(format!("{}.", progress));
+      }
+    }
+    for (row, _) in section.rows {
+      number += 1;
+      lines.push(format!("{}. {}", number, sentence(row, user, options, now)));
+    }
+    if section.more > 0 {
+      lines.push(format!("And {} more.", section.more));
+    }
+  }
+
+  if !snapshot.issues.is_empty() {
+    if !lines.is_empty() {
+      lines.push(String::new());
+    }
+    lines.push(format!("Issues, {}:", count_issues(snapshot.issues.len())));
+    let mut issues: Vec<_> = snapshot.issues.iter().collect();
+    view.sort_issues(&mut issues);
+    for (issue, _) in issues {
+      number += 1;
+      let mut sentence = format!(
+        "{}. ISSUE: '{}' in {}",
+        number,
+        issue.title,
+        issue
+          .references
+          .full
+          .rsplit_once('#')
+          .map_or(issue.references.full.as_str(), |(project, _)| project)
+      );
+      if let Some(deadline) = issue.deadline() {
+        sentence.push_str(&format!(", due {}", deadline.format("%Y-%m-%d")));
+      }
+      sentence.push_str(&format!(", link: {}", issue.web_url));
+      lines.push(sentence);
+    }
+  }
+
+  if let Some(hours) = options.show_merged.filter(|_| !snapshot.merged.is_empty()) {
+    lines.push(String::new());
+    lines.push(format!(
+      "Merged in the last {} hours, {}:",
+      hours,
+      count(snapshot.merged.len())
+    ));
+    for mr in &snapshot.merged {
+      lines.push(format!(
+        "MERGED: '{}' in {}, by {}, link: {}",
+        mr.title,
+        mr.project_path(),
+        mr.author.username,
+        mr.web_url
+      ));
+    }
+  }
+
+  if lines.is_empty() {
+    lines.push("Nothing to do.".to_string());
+  }
+  // These lines carry no styling of our own, so they are cleaned whole.
+  lines
+    .iter()
+    .map(|line| printable(line).into_owned())
+    .collect()
+}
+
+fn count(merge_requests: usize) -> String {
+  match merge_requests {
+    1 => "1 merge request".to_string(),
+    n => format!("{} merge requests", n),
+  }
+}
+
+fn count_issues(issues: usize) -> String {
+  match issues {
+    1 => "1 issue".to_string(),
+    n => format!("{} issues", n),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fixtures::{me, mr, options, row, snapshot, user_json},
+    priority::priority,
+    sort_rows,
+    table::table_lines,
+  };
+  use serde_json::json;
+
+  /// A few merge requests in different roles, states and milestones,
+  /// ranked as a refresh ranks them.
+  fn ranked(options: &Options) -> Snapshot {
+    let me_json = user_json(1, "me");
+    let release = json!({ "title": "1.0", "due_date": "2026-11-01" });
+    let mut snapshot = snapshot(vec![
+      row(mr(1, json!({ "reviewers": [me_json] }))),
+      row(mr(2, json!({ "author": me_json, "milestone": release }))),
+      row(mr(
+        3,
+        json!({ "assignees": [me_json], "target_branch": "old" }),
+      )),
+      row(mr(4, json!({ "draft": true, "milestone": release }))),
+      row(mr(5, json!({ "updated_at": "2026-01-01T00:00:00Z" }))),
+    ]);
+    for (row, score) in &mut snapshot.rows {
+      *score = priority(row, &me(), options, &Config::default());
+    }
+    sort_rows(&mut snapshot.rows);
+    snapshot
+  }
+
+  #[test]
+  fn lists_show_the_rows_the_table_does_in_its_order() {
+    let config = Config::default();
+    for (args, view) in [
+      (&[][..], "all"),
+      (&["--tiers"][..], "all"),
+      (&["--group-by", "milestone"][..], "all"),
+      (&[][..], "reviewing"),
+      (&[][..], "agenda"),
+      (&["--decay-after", "14", "--stale-report"][..], "all"),
+    ] {
+      let options = options(args);
+      let view = config.view(view).unwrap();
+      let snapshot = ranked(&options);
+      let listed: Vec<String> = list_lines(&snapshot, &view, &me(), &options, &config)
+        .iter()
+        .filter_map(|line| Some(line.rsplit_once("link: ")?.1.to_string()))
+        .collect();
+      let tabled: Vec<String> = table_lines(&snapshot, &view, &me(), &options, &config, 120)
+        .iter()
+        .filter_map(|line| line.mr)
+        .map(|id| {
+          let (row, _) = snapshot
+            .rows
+            .iter()
+            .find(|(row, _)| row.mr.id == id)
+            .unwrap();
+          row.mr.web_url.clone()
+        })
+        .collect();
+      assert!(!listed.is_empty(), "{:?} {:?}", args, view);
+      assert_eq!(listed, tabled, "{:?} {:?}", args, view);
+    }
+  }
+
+  #[test]
+  fn sentences_put_state_into_words() {
+    let options = options(&[]);
+    let snapshot = ranked(&options);
+    let config = Config::default();
+    let lines = list_lines(
+      &snapshot,
+      &config.view("all").unwrap(),
+      &me(),
+      &options,
+      &config,
+    );
+    assert!(lines
+      .iter()
+      .any(|line| line.contains("NEEDS REVIEW: 'MR 1' in group/project, by author")));
+    assert!(lines.iter().any(|line| line.contains("DRAFT: 'MR 4'")));
+    assert!(lines.iter().all(|line| !line.contains('\x1B')));
+  }
+}
@@ -1,413 +1,1524 @@
-use chrono::{DateTime, Utc};
-use crossterm::{
-  style::{Color, Print, Stylize},
-  terminal::{Clear, ClearType},
+mod actions;
+mod activity;
+mod attention;
+mod badges;
+mod bundle;
+mod cache;
+mod checkout;
+mod clock;
+mod config;
+mod deadline;
+mod debug;
+mod detail;
+mod diff;
+mod digest;
+mod dirs;
+mod doctor;
+mod drafts;
+mod effort;
+mod events;
+#[cfg(test)]
+mod fixtures;
+mod focus;
+mod gitlab;
+mod glob;
+mod instance;
+mod json;
+mod labels;
+mod layout;
+mod list;
+#[cfg(test)]
+mod mock;
+mod options;
+mod policy;
+mod priority;
+mod progress;
+mod projects;
+mod prompt;
+mod recap;
+mod reference;
+mod review_request;
+mod roles;
+mod rules;
+mod secrets;
+mod sla;
+mod sparkline;
+mod stale;
+mod state;
+mod stats;
+mod status;
+mod subtitle;
+mod sync;
+mod table;
+mod tape;
+mod terminal;
+mod threads;
+mod tickets;
+mod tier;
+mod timezone;
+mod tui;
+mod users;
+mod version;
+mod view;
+mod views;
+mod wizard;
+
+use actions::is_hidden;
+use activity::{Activity, ActivityCache};
+use cache::MrCache;
+use chrono::{DateTime, Local, Utc};
+use clap::ValueEnum;
+use config::Config;
+use events::EventTracker;
+use focus::Focus;
+use gitlab::{
+  unparsed_note, ApiError, ApprovalInfo, ApprovalRule, Client, Dependencies, Discussion, FileDiff,
+  Group, Id, Issue, Job, Label, MergeRequest, MergeRequestDetails, MergeTrain, MrId, Pipeline,
+  PipelineStatus, ProjectId, ReviewState, Reviewer, Unparsed, User, UserId, UserStatus,
 };
-use reqwest::{blocking::Client, header::HeaderMap};
-use serde::Deserialize;
+use glob::glob_match;
+use instance::Instance;
+use json::print_json;
+use options::{Command, ConfigCommand, DebugCommand, Format, MrCommand, Options, StateCommand};
+use priority::{issue_priority, priority, Score};
+use projects::ProjectCache;
+use prompt::Summary;
+use review_request::ReviewRequestCache;
+use roles::Roles;
+use state::State;
+use stats::{Phase, RefreshStats};
+use status::StatusCache;
 use std::{
-  collections::HashMap,
+  cmp::Reverse,
+  collections::{HashMap, HashSet},
   env,
   error::Error,
-  fmt::Display,
-  io::{stdout, Write},
-  ops::Sub,
-  thread::sleep,
   time::Duration,
 };
+use sync::{MrLists, Source};
+use tape::Tape;
+use tickets::find_tickets;
 
 type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Copy)]
-struct Id(usize);
+struct Row {
+  mr: MergeRequest,
+  roles: Roles,
+  /// The project's default branch, where it could be looked up.
+  default_branch: Option<String>,
+  /// Path of the fork a merge request comes from, where it can be seen.
+  fork_path: Option<String>,
+  /// Whether everything below was fetched or is still blank.
+  detailed: bool,
+  /// Whether the details can't be fetched because we lost access to the project.
+  inaccessible: bool,
+  approval_info: ApprovalInfo,
+  /// False on instances without merge request approvals, where
+  /// `approval_info` stays empty and says nothing.
+  approvals: bool,
+  approval_rules: Vec<ApprovalRule>,
+  /// What the reviewers of my merge requests set as their status, where
+  /// they set one.
+  reviewer_statuses: HashMap<UserId, UserStatus>,
+  /// Empty where the instance doesn't track reviewer states.
+  reviewers: Vec<Reviewer>,
+  pipeline: Option<Pipeline>,
+  /// Files the merge request changes, once detailed.
+  changed_files: Option<usize>,
+  /// The description, once detailed.
+  description: Option<String>,
+  failed_jobs: Vec<Job>,
+  /// Where the merge request waits in a merge train, if it joined one.
+  merge_train: Option<MergeTrain>,
+  discussions: Vec<Discussion>,
+  dependencies: Dependencies,
+  activity: Option<Activity>,
+  /// When I was asked to review, for merge requests I review.
+  review_requested_at: Option<DateTime<Utc>>,
+  /// When it was first listed, for merge requests I review.
+  first_seen: Option<DateTime<Utc>>,
+  tickets: Vec<String>,
+  /// From a group's triage queue rather than any role of mine.
+  triage: bool,
+}
+
+#[derive(Default)]
+struct Caches {
+  lists: MrLists,
+  activity: ActivityCache,
+  approval_rules: MrCache<String, Vec<ApprovalRule>>,
+  failed_jobs: MrCache<Id, Vec<Job>>,
+  discussions: MrCache<DateTime<Utc>, Vec<Discussion>>,
+  diffs: MrCache<String, Vec<FileDiff>>,
+  projects: ProjectCache,
+  review_requests: ReviewRequestCache,
+  /// Merge requests whose details we were denied, which are not asked for again.
+  inaccessible: HashSet<MrId>,
+  /// Set once the instance turned out not to have the reviewers endpoint.
+  no_reviewer_states: bool,
+  /// Set once the instance turned out not to have merge request approvals,
+  /// which are not asked for again.
+  no_approvals: bool,
+  /// Projects without merge trains, which are not asked again.
+  no_merge_trains: HashSet<ProjectId>,
+  /// Groups to triage by the path they were given as.
+  groups: HashMap<String, Group>,
+  reviewer_statuses: StatusCache,
+  /// Label names by project, fetched when first picked from.
+  labels: HashMap<ProjectId, Vec<String>>,
+}
+
+impl Caches {
+  fn labels(&mut self, client: &Client, project: ProjectId) -> Result<Vec<String>> {
+    if let Some(labels) = self.labels.get(&project) {
+      return Ok(labels.clone());
+    }
+    let labels: Vec<String> = Label::get_all(client, project)?
+      .into_iter()
+      .map(|label| label.name)
+      .collect();
+    self.labels.insert(project, labels.clone());
+    Ok(labels)
+  }
 
-impl Display for Id {
-  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    self.0.fmt(f)
+  // Forgets what is cached about a merge request that an action just changed.
+  fn invalidate(&mut self, id: MrId) {
+    self.activity.invalidate(id);
+    self.approval_rules.invalidate(id);
+    self.failed_jobs.invalidate(id);
+    self.discussions.invalidate(id);
+    self.diffs.invalidate(id);
   }
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
-struct User {
-  id: Id,
-  name: String,
-  username: String,
+struct Snapshot {
+  rows: Vec<(Row, Score)>,
+  merged: Vec<MergeRequest>,
+  /// Kept apart from the rows, whose ids are merge request ids that issue
+  /// ids may collide with.
+  issues: Vec<(Issue, Score)>,
+  fetched_at: DateTime<Utc>,
+  stats: RefreshStats,
+  focus: Option<Focus>,
+  /// Sources of merge requests that failed, so their merge requests may be missing.
+  unavailable: Vec<Source>,
+  /// Merge requests left out by filters such as `--author`.
+  filtered: usize,
+  /// Merge requests that were listed but aren't rows: snoozed, muted,
+  /// filtered, archived or hidden. They are still there, just not shown.
+  set_aside: Vec<MergeRequest>,
+  /// Merge requests left out because they couldn't be read.
+  unparsed: Vec<Unparsed>,
+  /// Whether the instance turned out not to have merge request approvals.
+  no_approvals: bool,
 }
 
-impl User {
-  fn get<UserName: AsRef<str>>(client: &Client, user: UserName) -> Result<Self> {
-    let response: Vec<User> = client
-      .get("https://gitlab.com/api/v4/users")
-      .query(&[("username", user.as_ref())])
-      .send()?
-      .json()?;
+impl Snapshot {
+  /// Fetches the details of a row listed without them, as when it scrolls
+  /// into view. It keeps its place until the next refresh re-sorts
+  /// everything, unless it turns out to be hidden.
+  fn fetch_details(
+    &mut self,
+    id: MrId,
+    client: &Client,
+    user: &User,
+    options: &Options,
+    config: &Config,
+    caches: &mut Caches,
+  ) -> Result<()> {
+    let Some((row, score)) = self.rows.iter_mut().find(|(row, _)| row.mr.id == id) else {
+      return Ok(());
+    };
+    if let Err(err) = row.fetch_details(client, user, options, caches, &mut self.stats) {
+      return Err(format!("Could not fetch {}: {}", row.mr.references.full, err).into());
+    }
+    *score = priority(row, user, options, config);
+    set_aside_inaccessible(options, &mut self.rows, &mut self.set_aside);
+    Ok(())
+  }
+}
 
-    response
-      .into_iter()
-      .next()
-      .ok_or("No user found with that name".into())
+/// With `--hide-inaccessible`, sets aside the rows that turned out to be
+/// inaccessible.
+fn set_aside_inaccessible(
+  options: &Options,
+  rows: &mut Vec<(Row, Score)>,
+  set_aside: &mut Vec<MergeRequest>,
+) {
+  if !options.hide_inaccessible {
+    return;
   }
+  let (inaccessible, accessible): (Vec<_>, Vec<_>) = std::mem::take(rows)
+    .into_iter()
+    .partition(|(row, _)| row.inaccessible);
+  *rows = accessible;
+  set_aside.extend(inaccessible.into_iter().map(|(row, _)| row.mr));
+}
 
-  fn get_recent_pushes(&self, client: &Client) -> Result<Vec<RecentPush>> {
-    Ok(
-      client
-        .get(format!(
-          "https://gitlab.com/api/v4/users/{}/events",
-          self.id
-        ))
-        .query(&[("action", "pushed")])
-        .send()?
-        .json()?,
-    )
+fn make_link(options: &Options, url: &str, title: &str) -> String {
+  if !options.links {
+    return title.to_string();
   }
+  // URLs come from GitLab, too, and could end the link and start anything.
+  format!(
+    "\x1B]8;;{}\x1B\\{}\x1B]8;;\x1B\\",
+    terminal::printable(url),
+    title
+  )
+}
 
-  fn get_mrs_to_review(&self, client: &Client) -> Result<HashMap<Id, MergeRequest>> {
-    let mrs: Vec<MergeRequest> = client
-      .get("https://gitlab.com/api/v4/merge_requests")
-      .query(&[
-        ("state", "opened"),
-        ("scope", "all"),
-        ("reviewer_username", self.username.as_str()),
-      ])
-      .send()?
-      .json()?;
-    let now = Utc::now();
-    let mrs = mrs
-      .into_iter()
-      .filter(|mr| now.sub(mr.updated_at).num_days() <= 14)
-      .map(|mr| (mr.id, mr))
-      .collect();
-    Ok(mrs)
-  }
-
-  fn get_assigned_mrs(&self, client: &Client) -> Result<HashMap<Id, MergeRequest>> {
-    let mrs: Vec<MergeRequest> = client
-      .get("https://gitlab.com/api/v4/merge_requests")
-      .query(&[
-        ("state", "opened"),
-        ("scope", "all"),
-        ("assignee_username", self.username.as_str()),
-      ])
-      .send()?
-      .json()?;
-    let mrs = mrs.into_iter().map(|mr| (mr.id, mr)).collect();
-    Ok(mrs)
-  }
-
-  fn get_authored_mrs(&self, client: &Client) -> Result<HashMap<Id, MergeRequest>> {
-    let mrs: Vec<MergeRequest> = client
-      .get("https://gitlab.com/api/v4/merge_requests")
-      .query(&[
-        ("state", "opened"),
-        ("scope", "all"),
-        ("author_username", self.username.as_str()),
-      ])
-      .send()?
-      .json()?;
-    let mrs = mrs.into_iter().map(|mr| (mr.id, mr)).collect();
-    Ok(mrs)
-  }
-
-  fn get_related_mrs(&self, client: &Client) -> Result<HashMap<Id, MergeRequest>> {
-    let recent_mrs: HashMap<Id, MergeRequest> = self
-      .get_recent_pushes(client)?
+impl Row {
+  /// A row from list-level data only, until `fetch_details` fills in the rest.
+  fn new(user: &User, options: &Options, mr: MergeRequest) -> Self {
+    let tickets = options
+      .ticket_pattern
+      .as_ref()
+      .map(|pattern| find_tickets(pattern, &mr))
+      .unwrap_or_default();
+
+    Row {
+      roles: Roles::of(user, &mr),
+      default_branch: None,
+      fork_path: None,
+      mr,
+      detailed: false,
+      inaccessible: false,
+      approval_info: ApprovalInfo::default(),
+      approvals: true,
+      approval_rules: Vec::new(),
+      reviewer_statuses: HashMap::new(),
+      reviewers: Vec::new(),
+      pipeline: None,
+      changed_files: None,
+      description: None,
+      failed_jobs: Vec::new(),
+      merge_train: None,
+      discussions: Vec::new(),
+      dependencies: Dependencies::default(),
+      activity: None,
+      review_requested_at: None,
+      first_seen: None,
+      tickets,
+      triage: false,
+    }
+  }
+
+  /// How far `user` got reviewing this merge request, where that is known.
+  fn review_state(&self, user: &User) -> ReviewState {
+    self
+      .reviewers
       .iter()
-      .filter_map(|recent_push| {
-        let branch = recent_push.push_data.ref_.as_ref()?;
-        Some(MergeRequest::get_by_branch(
-          client,
-          recent_push.project_id,
-          branch,
-        ))
-      })
-      .collect::<Result<Vec<_>>>()?
-      .into_iter()
-      .flat_map(|mrs| mrs.into_iter())
-      .collect();
-    let to_review = self.get_mrs_to_review(client)?;
-    let assigned = self.get_assigned_mrs(client)?;
-    let authored = self.get_authored_mrs(client)?;
+      .find(|reviewer| reviewer.user.id == user.id)
+      .map_or(ReviewState::Unknown, |reviewer| reviewer.state)
+  }
 
-    let all_mrs: HashMap<Id, MergeRequest> = recent_mrs
-      .into_iter()
-      .chain(to_review)
-      .chain(assigned)
-      .chain(authored)
-      .collect();
+  /// Whether what `approval_info` says can be relied on.
+  fn approvals_known(&self) -> bool {
+    self.detailed && self.approvals
+  }
+
+  fn changes_requested(&self) -> bool {
+    self
+      .reviewers
+      .iter()
+      .any(|reviewer| reviewer.state == ReviewState::RequestedChanges)
+  }
+
+  /// Reviewer user names, each followed by the glyph of its state.
+  fn reviewer_names(&self) -> String {
+    self
+      .mr
+      .reviewers
+      .iter()
+      .map(|user| format!("{}{}", user.username, self.review_state(user).glyph()))
+      .collect::<Vec<_>>()
+      .join(" ")
+  }
+
+  fn fetch_details(
+    &mut self,
+    client: &Client,
+    user: &User,
+    options: &Options,
+    caches: &mut Caches,
+    stats: &mut RefreshStats,
+  ) -> Result<()> {
+    if caches.inaccessible.contains(&self.mr.id) {
+      self.inaccessible = true;
+      return Ok(());
+    }
+    match self.fetch_accessible_details(client, user, options, caches, stats) {
+      Err(err) if ApiError::is_inaccessible(&*err) => {
+        caches.inaccessible.insert(self.mr.id);
+        self.inaccessible = true;
+        Ok(())
+      }
+      result => result,
+    }
+  }
 
-    Ok(all_mrs)
+  fn fetch_accessible_details(
+    &mut self,
+    client: &Client,
+    user: &User,
+    options: &Options,
+    caches: &mut Caches,
+    stats: &mut RefreshStats,
+  ) -> Result<()> {
+    let mr = &self.mr;
+    let (approval_info, approval_rules, reviewers) =
+      stats.time(Phase::Approvals, || -> Result<_> {
+        let info = if caches.no_approvals {
+          None
+        } else {
+          ApprovalInfo::get(client, mr)?
+        };
+        caches.no_approvals |= info.is_none();
+        let rules = match info {
+          Some(_) => caches
+            .approval_rules
+            .get_or_fetch(mr.id, mr.sha.clone(), || ApprovalRule::get_all(client, mr))?,
+          None => Vec::new(),
+        };
+        let reviewers = if caches.no_reviewer_states {
+          None
+        } else {
+          Reviewer::get_all(client, mr)?
+        };
+        caches.no_reviewer_states |= reviewers.is_none();
+        Ok((info, rules, reviewers.unwrap_or_default()))
+      })?;
+    // Only my own merge requests wait on their reviewers.
+    let reviewer_statuses = stats.time(Phase::Approvals, || {
+      if !self.roles.author {
+        return HashMap::new();
+      }
+      mr.reviewers
+        .iter()
+        .filter_map(|reviewer| {
+          let status = caches.reviewer_statuses.get(client, reviewer.id)?;
+          Some((reviewer.id, status))
+        })
+        .collect()
+    });
+    let (pipeline, changed_files, description, failed_jobs, merge_train) =
+      stats.time(Phase::Pipelines, || -> Result<_> {
+        let details = MergeRequestDetails::get(client, mr)?;
+        let changed_files = details.changed_files();
+        let description = details.description;
+        let pipeline = details.head_pipeline;
+        // A pipeline's set of failed jobs never changes, so cache it by pipeline.
+        let failed_jobs = match &pipeline {
+          Some(pipeline) if pipeline.status == PipelineStatus::Failed => caches
+            .failed_jobs
+            .get_or_fetch(mr.id, pipeline.id, || Job::get_failed(client, mr, pipeline))?,
+          _ => Vec::new(),
+        };
+        // Only merge requests set to auto-merge can be in a train at all.
+        let merge_train =
+          if mr.merge_when_pipeline_succeeds && !caches.no_merge_trains.contains(&mr.project_id) {
+            match MergeTrain::get(client, mr)? {
+              Some(train) => train,
+              None => {
+                caches.no_merge_trains.insert(mr.project_id);
+                None
+              }
+            }
+          } else {
+            None
+          };
+        Ok((
+          pipeline,
+          changed_files,
+          description,
+          failed_jobs,
+          merge_train,
+        ))
+      })?;
+    let (discussions, dependencies, activity, review_requested_at) =
+      stats.time(Phase::Threads, || -> Result<_> {
+        // Every new note bumps `updated_at`, so threads are only refetched then.
+        let discussions = caches
+          .discussions
+          .get_or_fetch(mr.id, mr.updated_at, || Discussion::get_all(client, mr))?;
+        let dependencies = Dependencies::get(client, mr)?;
+        let activity = if options.last_activity {
+          caches.activity.get(client, mr)?
+        } else {
+          None
+        };
+        let review_requested_at = if self.roles.reviewing() {
+          caches.review_requests.get(client, user, mr)?
+        } else {
+          None
+        };
+        Ok((discussions, dependencies, activity, review_requested_at))
+      })?;
+
+    self.approvals = approval_info.is_some();
+    self.approval_info = approval_info.unwrap_or_default();
+    self.approval_rules = approval_rules;
+    self.reviewer_statuses = reviewer_statuses;
+    self.reviewers = reviewers;
+    self.pipeline = pipeline;
+    self.changed_files = changed_files;
+    self.description = description;
+    self.failed_jobs = failed_jobs;
+    self.merge_train = merge_train;
+    self.discussions = discussions;
+    self.dependencies = dependencies;
+    self.activity = activity;
+    self.review_requested_at = review_requested_at;
+    self.detailed = true;
+    Ok(())
   }
 }
 
-#[derive(Deserialize, Debug, Clone)]
-struct PushData {
-  #[serde(rename = "ref")]
-  ref_: Option<String>,
+// Rows beyond the first screenful plus this many get no details up front.
+const DETAIL_MARGIN: usize = 10;
+
+fn detail_limit(options: &Options) -> Option<usize> {
+  if options.format != Format::Table || options.sla_report {
+    return None;
+  }
+  options.detail_limit.or_else(|| {
+    crossterm::terminal::size()
+      .ok()
+      .map(|(_, height)| height as usize + DETAIL_MARGIN)
+  })
 }
 
-#[derive(Deserialize, Debug, Clone)]
-struct RecentPush {
-  project_id: Id,
-  push_data: PushData,
+fn sort_rows(rows: &mut [(Row, Score)]) {
+  rows.sort_by_key(|(row, score)| Reverse((score.total(), row.mr.updated_at)));
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
-struct References {
-  full: String,
+fn fetch_issues(client: &Client) -> Result<Vec<(Issue, Score)>> {
+  let today = Local::now().date_naive();
+  let mut issues: Vec<(Issue, Score)> = Issue::get_assigned(client)?
+    .into_iter()
+    .map(|issue| {
+      let score = issue_priority(&issue, today);
+      (issue, score)
+    })
+    .collect();
+  issues.sort_by_key(|(issue, score)| {
+    (
+      Reverse(score.total()),
+      issue.due_date.is_none(),
+      issue.due_date,
+    )
+  });
+  Ok(issues)
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
-struct Milestone {
-  title: String,
+/// Merge requests in the groups to triage that nobody reviews or was assigned
+/// to yet, other than by their author, and that aren't mine to begin with.
+fn fetch_triage(
+  client: &Client,
+  options: &Options,
+  caches: &mut Caches,
+  mine: &HashMap<MrId, MergeRequest>,
+) -> Result<Vec<MergeRequest>> {
+  let mut mrs: HashMap<MrId, MergeRequest> = HashMap::new();
+  for path in &options.unassigned_in_group {
+    if !caches.groups.contains_key(path) {
+      caches
+        .groups
+        .insert(path.clone(), Group::get(client, path)?);
+    }
+    for mr in caches.groups[path].get_unreviewed_mrs(client)? {
+      let unassigned = mr
+        .assignees
+        .iter()
+        .all(|assignee| assignee.id == mr.author.id);
+      if mr.reviewers.is_empty() && unassigned && !mine.contains_key(&mr.id) {
+        mrs.insert(mr.id, mr);
+      }
+    }
+  }
+  Ok(mrs.into_values().collect())
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
-struct MergeRequest {
-  id: Id,
-  iid: Id,
-  project_id: Id,
-  title: String,
-  milestone: Option<Milestone>,
-  draft: bool,
-  has_conflicts: bool,
-  references: References,
-  target_branch: String,
-  web_url: String,
-  updated_at: DateTime<Utc>,
-  author: User,
-  assignees: Vec<User>,
-  reviewers: Vec<User>,
+/// The open merge requests of the dashboard's group, looked up in full on
+/// every refresh.
+fn fetch_dashboard(
+  client: &Client,
+  options: &Options,
+  caches: &mut Caches,
+) -> Result<HashMap<MrId, MergeRequest>> {
+  let path = options
+    .group
+    .as_ref()
+    .ok_or("The dashboard needs a --group")?;
+  if !caches.groups.contains_key(path) {
+    caches
+      .groups
+      .insert(path.clone(), Group::get(client, path)?);
+  }
+  Ok(
+    caches.groups[path]
+      .get_open_mrs(client)?
+      .into_iter()
+      .map(|mr| (mr.id, mr))
+      .collect(),
+  )
 }
 
-impl MergeRequest {
-  fn get_by_branch<BranchName: AsRef<str>>(
-    client: &Client,
-    project_id: Id,
-    branch: BranchName,
-  ) -> Result<HashMap<Id, MergeRequest>> {
-    let response = client
-      .get(format!(
-        "https://gitlab.com/api/v4/projects/{}/merge_requests",
-        project_id
-      ))
-      .query(&[
-        ("state", "opened"),
-        ("scope", "all"),
-        ("source_branch", branch.as_ref()),
-      ])
-      .send()?;
-
-    if !response.status().is_success() {
-      return Ok(HashMap::new());
+fn refresh(
+  client: &Client,
+  user: &User,
+  options: &Options,
+  config: &Config,
+  caches: &mut Caches,
+) -> Result<Snapshot> {
+  let _coalescing = client.coalesce();
+  let mut stats = RefreshStats::default();
+  // Only what this refresh receives, not what actions since the last one did.
+  client.take_received();
+  let all_mrs: HashMap<MrId, MergeRequest> = if options.dashboard {
+    stats.time(Phase::Lists, || fetch_dashboard(client, options, caches))?
+  } else {
+    caches.lists.refresh(
+      client,
+      user,
+      options,
+      config.widest_review_window(options),
+      |mr| {
+        config
+          .project_options(options, mr.project_path())
+          .review_window
+      },
+      &mut stats,
+    )?
+  };
+  let triage_mrs = stats.time(Phase::Lists, || {
+    fetch_triage(client, options, caches, &all_mrs)
+  })?;
+  let today = Local::now().date_naive();
+  let state = State::load();
+  let now = Utc::now();
+  let mut rows = Vec::new();
+  let mut filtered = 0;
+  let mut set_aside = Vec::new();
+  let mrs = all_mrs
+    .into_values()
+    .map(|mr| (mr, false))
+    .chain(triage_mrs.into_iter().map(|mr| (mr, true)));
+  for (mr, triage) in mrs {
+    if is_hidden(&state, mr.id, now) {
+      set_aside.push(mr);
+      continue;
+    }
+    let project_options = config.project_options(options, mr.project_path());
+    let passes = !(mr.draft && project_options.hide_drafts)
+      && project_options
+        .target_branch
+        .as_ref()
+        .is_none_or(|pattern| glob_match(pattern, &mr.target_branch))
+      && config.is_author(&project_options, &mr.author)
+      && project_options.milestone.as_ref().is_none_or(|title| {
+        mr.milestone
+          .as_ref()
+          .is_some_and(|milestone| &milestone.title == title)
+      })
+      && (!project_options.current_iteration
+        || mr
+          .milestone
+          .as_ref()
+          .is_some_and(|milestone| milestone.is_current(today)));
+    if !passes {
+      filtered += 1;
+      set_aside.push(mr);
+      continue;
     }
+    // Merge requests in archived projects can't be merged anymore.
+    if !project_options.include_archived
+      && stats.time(Phase::Lists, || {
+        caches.projects.is_archived(client, mr.project_id)
+      })?
+    {
+      set_aside.push(mr);
+      continue;
+    }
+    let default_branch = stats.time(Phase::Lists, || {
+      caches.projects.default_branch(client, mr.project_id)
+    })?;
+    let fork_path = match mr.source_project_id.filter(|_| mr.is_from_fork()) {
+      Some(source) => stats.time(Phase::Lists, || caches.projects.path(client, source))?,
+      None => None,
+    };
+    let mut row = Row::new(user, options, mr);
+    row.default_branch = default_branch;
+    row.fork_path = fork_path;
+    row.triage = triage;
+    rows.push(row);
+  }
+
+  // Merged and assigned are mine, which the dashboard has nothing of.
+  let mut unavailable = caches.lists.unavailable().to_vec();
+  let merged_mrs = match options.show_merged.filter(|_| !options.dashboard) {
+    Some(hours) => {
+      let since = clock::server_now() - chrono::Duration::hours(hours.into());
+      let (merged_mrs, failed) = stats.time(Phase::Lists, || user.get_merged_mrs(client, since))?;
+      unavailable.extend(failed.into_iter().map(Source::Merged));
+      let mut merged_mrs: Vec<MergeRequest> = merged_mrs.into_values().collect();
+      merged_mrs.sort_by_key(|mr| Reverse(mr.merged_at.unwrap_or(mr.updated_at)));
+      merged_mrs
+    }
+    None => Vec::new(),
+  };
+
+  let issues = if options.include_issues && !options.dashboard {
+    stats.time(Phase::Lists, || fetch_issues(client))?
+  } else {
+    Vec::new()
+  };
+
+  // Rank by list-level data first so that details are only fetched for the
+  // rows that are likely to be seen.
+  let mut rows: Vec<(Row, Score)> = rows
+    .into_iter()
+    .map(|row| {
+      let score = priority(&row, user, options, config);
+      (row, score)
+    })
+    .collect();
+  sort_rows(&mut rows);
 
-    let mrs: Vec<MergeRequest> = response.json()?;
-    let mrs: HashMap<Id, MergeRequest> = mrs.into_iter().map(|mr| (mr.id, mr)).collect();
-    Ok(mrs)
+  let limit = detail_limit(options).unwrap_or(rows.len());
+  for (row, score) in rows.iter_mut().take(limit) {
+    row.fetch_details(client, user, options, caches, &mut stats)?;
+    *score = priority(row, user, options, config);
   }
+  set_aside_inaccessible(options, &mut rows, &mut set_aside);
+  sort_rows(&mut rows);
+  // Whatever came in after a cancellation is incomplete, and must not be
+  // written anywhere.
+  if client.is_cancelled() {
+    return Err("Refresh cancelled".into());
+  }
+  // Only a fallback for how long reviews wait, so it may fail quietly.
+  let _ = sla::record_first_seen(&mut rows, caches.lists.unavailable().is_empty());
+  // Only spares lookups on the next start, so it may fail quietly.
+  let _ = caches.projects.save();
+  let _ = caches.review_requests.save();
+  stats.received = client.take_received();
+  client.save_tape()?;
+
+  Ok(Snapshot {
+    rows,
+    merged: merged_mrs,
+    issues,
+    fetched_at: Utc::now(),
+    stats,
+    focus: Focus::load(),
+    unavailable,
+    filtered,
+    set_aside,
+    unparsed: client.take_unparsed(),
+    no_approvals: caches.no_approvals,
+  })
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
-struct Approver {
-  user: User,
+/// Registers this instance. With another one refreshing, this one refreshes
+/// half as often, or with --single-instance doesn't start at all.
+fn claim_instance(options: &mut Options) -> Result<Instance> {
+  let instance = Instance::acquire();
+  if options.single_instance {
+    instance.require_only()?;
+  }
+  if let Instance::Another(other) = &instance {
+    options.interval *= 2;
+    eprintln!(
+      "warning: another instance is running (pid {}); refreshing every {}s to spare the API",
+      other.pid, options.interval
+    );
+  }
+  Ok(instance)
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
-struct ApprovalInfo {
-  approvals_left: usize,
-  approved_by: Vec<Approver>,
+/// Without a footer to note them in, merge requests that couldn't be read
+/// are reported on stderr.
+fn warn_unparsed(unparsed: &[Unparsed], verbose: bool) {
+  if verbose {
+    for unparsed in unparsed {
+      eprintln!("warning: could not parse {}", unparsed);
+    }
+  } else if let Some(note) = unparsed_note(unparsed, false) {
+    eprintln!("warning: {}", note);
+  }
 }
 
-impl ApprovalInfo {
-  fn get(client: &Client, mr: &MergeRequest) -> Result<Self> {
-    let info = client
-      .get(format!(
-        "https://gitlab.com/api/v4/projects/{}/merge_requests/{}/approvals",
-        mr.project_id, mr.iid
-      ))
-      .send()?
-      .json()?;
-    Ok(info)
+fn access_token(options: &Options, config: &Config) -> Result<String> {
+  if let Ok(token) = env::var("GITLAB_TOKEN") {
+    return Ok(token);
+  }
+  match config.tokens.get(&options.host) {
+    Some(token) => secrets::resolve_token(&options.host, token),
+    None => Err(
+      format!(
+        "No access token: set GITLAB_TOKEN or add one for {} under [tokens] in the config file",
+        options.host
+      )
+      .into(),
+    ),
   }
 }
 
-fn make_link(url: &str, title: &str) -> String {
-  format!("\x1B]8;;{}\x1B\\{}\x1B]8;;\x1B\\", url, title)
+fn connect(options: &Options, config: &Config) -> Result<(Client, User)> {
+  // A replay never reaches the instance, so it needs no token.
+  let gitlab_token = match &options.replay {
+    Some(_) => access_token(options, config).unwrap_or_default(),
+    None => access_token(options, config)?,
+  };
+  let mut client = Client::new(&options.host, &gitlab_token, options.auth_style.style())?
+    .with_timeout(config.request_timeout(options))?;
+  if let Some(path) = &options.replay {
+    client = client.with_tape(Tape::replay(path)?);
+  } else if let Some(path) = &options.record {
+    client = client.with_tape(Tape::record(path, &gitlab_token));
+  }
+  // Group and project access tokens act as a bot, which is nobody's to-do list.
+  let user = if options.dashboard {
+    User::nobody()
+  } else {
+    User::get(&client, options.user()?)?
+  };
+  clock::warn_if_skewed();
+  Ok((client, user))
 }
 
-fn targets_main_branch(mr: &MergeRequest) -> bool {
-  ["master", "main"].contains(&mr.target_branch.as_str())
+fn print_prompt(options: &Options, config: &Config) -> Result<()> {
+  let summary = match Summary::load_fresh(
+    options.user()?,
+    chrono::Duration::seconds(options.prompt_max_age),
+  ) {
+    Some(summary) => summary,
+    None => {
+      let (client, user) = connect(options, config)?;
+      let snapshot = refresh(&client, &user, options, config, &mut Caches::default())?;
+      Summary::of(&snapshot.rows, &user, options)
+    }
+  };
+  print!("{}", summary.render(options.prompt_style));
+  Ok(())
 }
 
-fn priority(mr: &MergeRequest, approval_info: &ApprovalInfo, user: &User) -> isize {
-  let approved = approval_info
-    .approved_by
-    .iter()
-    .any(|a| a.user.id == user.id);
+fn run_config_command(options: &Options, config: &Config, command: &ConfigCommand) -> Result<()> {
+  match command {
+    ConfigCommand::Init { force } => {
+      let path = Config::init(options.config.as_deref(), *force)?;
+      println!("Wrote {}", path.display());
+    }
+    ConfigCommand::Show { project: None } => {
+      print!("{}", toml::to_string_pretty(&config.effective(options))?)
+    }
+    ConfigCommand::Show {
+      project: Some(project),
+    } => {
+      let mut effective = config.effective(&config.project_options(options, project));
+      // The matching sections are already folded into the settings shown.
+      effective.project.clear();
+      print!("{}", toml::to_string_pretty(&effective)?)
+    }
+  }
+  Ok(())
+}
 
-  let mut prio = 0;
+fn run_state_command(command: &StateCommand) -> Result<()> {
+  match command {
+    StateCommand::Show => {
+      let path = State::path().ok_or("Could not determine the state directory")?;
+      println!("# {}", path.display());
+      println!("{}", serde_json::to_string_pretty(&State::load())?);
+    }
+    StateCommand::Clear { section } => {
+      State::update(|state| state.clear(*section))?;
+      let name = section
+        .to_possible_value()
+        .map(|value| value.get_name().to_string());
+      println!("Cleared {}", name.unwrap_or_default());
+    }
+  }
+  Ok(())
+}
 
-  if mr.assignees.iter().any(|assignee| assignee.id == user.id) && !mr.draft {
-    prio += 5;
+fn run_command(
+  client: &Client,
+  user: &User,
+  options: &Options,
+  config: &Config,
+  command: &Command,
+) -> Result<()> {
+  match command {
+    Command::Config { .. }
+    | Command::Doctor
+    | Command::Export
+    | Command::Import { .. }
+    | Command::Schema
+    | Command::State { .. }
+    | Command::Version { .. } => {
+      unreachable!("these commands run without connecting")
+    }
+    Command::Debug { .. }
+    | Command::Digest { .. }
+    | Command::Drafts
+    | Command::Issues
+    | Command::Lgtm { .. }
+    | Command::Ready { .. }
+      if options.dashboard =>
+    {
+      return Err(
+        "This command acts for a user, and the dashboard has none; run it without --dashboard"
+          .into(),
+      );
+    }
+    Command::Debug {
+      command: DebugCommand::Term,
+    } => unreachable!("this command runs without connecting"),
+    Command::Debug {
+      command:
+        DebugCommand::Dump {
+          endpoint,
+          redact,
+          redact_pattern,
+        },
+    } => debug::dump(client, user, *endpoint, *redact, redact_pattern)?,
+    Command::Digest { since } => digest::run(client, user, options, *since)?,
+    Command::Drafts => drafts::run(client, user, options, &mut Caches::default())?,
+    Command::Issues => table::print_issues(&fetch_issues(client)?, options)?,
+    Command::Mr {
+      command: MrCommand::Checkout { reference, dry_run },
+    } => {
+      let mr = MergeRequest::get_by_reference(client, reference)?;
+      checkout::run(&checkout::SystemGit, &mr, *dry_run)?;
+    }
+    Command::Lgtm {
+      reference,
+      message,
+      no_award,
+    } => {
+      let mr = MergeRequest::get_by_reference(client, reference)?;
+      let message = message.as_deref().or(config.lgtm.message.as_deref());
+      let award = config.lgtm.award && !no_award;
+      let (_, steps) = actions::lgtm(client, user, &mr, message, award)?;
+      println!("{}", steps.describe(&mr));
+    }
+    Command::Ready {
+      reference,
+      draft,
+      force,
+    } => {
+      let mr = MergeRequest::get_by_reference(client, reference)?;
+      actions::ensure_author(user, &mr, *force)?;
+      let mr = actions::set_draft(client, &mr, *draft)?;
+      println!(
+        "{} is {}",
+        mr.references.full,
+        if mr.draft { "a draft" } else { "ready" }
+      );
+    }
+    Command::Rebase { reference } => {
+      let mr = MergeRequest::get_by_reference(client, reference)?;
+      let mr = actions::rebase(client, &mr)?;
+      println!("{}", actions::describe_rebase(&mr));
+    }
+    Command::RetryPipeline { reference } => {
+      let mr = MergeRequest::get_by_reference(client, reference)?;
+      let pipeline = Pipeline::get_head(client, &mr)?;
+      let pipeline = actions::retry_pipeline(client, &mr, pipeline.as_ref())?;
+      println!(
+        "retried pipeline {} of {}: {}",
+        pipeline.id, mr.references.full, pipeline.web_url
+      );
+    }
   }
+  Ok(())
+}
+
+fn main() -> Result<()> {
+  let (mut options, mut config) = Options::load()?;
 
-  if targets_main_branch(mr) {
-    prio += 2;
+  if let Some(Command::Config { command }) = &options.command {
+    return run_config_command(&options, &config, command);
   }
 
-  if mr.author.id == user.id {
-    prio += 1;
+  if let Some(Command::State { command }) = &options.command {
+    return run_state_command(command);
   }
 
-  if mr.reviewers.iter().any(|reviewer| reviewer.id == user.id) {
-    prio += 1;
+  if let Some(Command::Schema) = &options.command {
+    return json::print_schema();
   }
 
-  if mr.has_conflicts {
-    prio -= 1;
+  if let Some(Command::Doctor) = &options.command {
+    return doctor::run(&options, &config);
   }
 
-  if approved {
-    prio -= 1;
+  if let Some(Command::Debug {
+    command: DebugCommand::Term,
+  }) = &options.command
+  {
+    debug::print_terminal(&options);
+    return Ok(());
   }
 
-  if approval_info.approvals_left < 1 {
-    prio -= 2;
+  if let Some(Command::Export) = &options.command {
+    return bundle::export(&options, &config);
   }
 
-  if mr
-    .assignees
-    .iter()
-    .all(|assignee| assignee.username == "nomadic-margebot")
+  if let Some(Command::Import {
+    path,
+    replace,
+    dry_run,
+    yes,
+    ..
+  }) = &options.command
   {
-    prio -= 5;
+    return bundle::import(&options, path, *replace, *dry_run, *yes);
   }
 
-  prio
-}
+  if let Some(Command::Version { check_update }) = &options.command {
+    return version::run(*check_update);
+  }
 
-fn cell(width: usize, body: &str) -> String {
-  let len = body.len();
+  if options.format == Format::Prompt {
+    // A broken prompt is worse than a missing one, so errors stay silent.
+    let _ = print_prompt(&options, &config);
+    return Ok(());
+  }
 
-  if len > width {
-    let mut body: String = body.chars().take(width - 3).collect();
-    body.push_str("...");
-    body
-  } else {
-    let suffix = " ".repeat(width - len);
-    let mut body = body.to_string();
-    body.push_str(&suffix);
-    body
+  if wizard::wanted(&options) {
+    wizard::run(&options)?;
+    (options, config) = Options::load()?;
   }
-}
 
-fn print_all(client: &Client, user: &User) -> Result<()> {
-  let all_mrs: HashMap<Id, MergeRequest> = user.get_related_mrs(client)?;
-  let mut all_mrs: Vec<(MergeRequest, ApprovalInfo)> = all_mrs
-    .into_values()
-    .map(|mr| ApprovalInfo::get(client, &mr).map(|approval_info| (mr, approval_info)))
-    .collect::<Result<_>>()?;
+  let (client, user) = connect(&options, &config)?;
+  options.user_ids = users::resolve(&client, &options, &config);
+  if let Some(command) = &options.command {
+    return run_command(&client, &user, &options, &config, command);
+  }
 
-  all_mrs.sort_by(|lhs, rhs| {
-    let lhs_prio = priority(&lhs.0, &lhs.1, user);
-    let rhs_prio = priority(&rhs.0, &rhs.1, user);
-    lhs_prio.cmp(&rhs_prio).reverse()
-  });
+  let mut caches = Caches::default();
 
-  let mut target = stdout();
-
-  let term_width = crossterm::terminal::size()
-    .map(|(w, __)| w as usize)
-    .unwrap_or(80);
-  let ref_width = all_mrs
-    .iter()
-    .map(|(mr, _)| mr.references.full.len())
-    .max()
-    .unwrap_or(25);
-  let assignee_width = 15;
-  let dynamic_width = term_width.saturating_sub(ref_width + assignee_width * 2 + 3);
-  let title_width = if dynamic_width > 0 {
-    dynamic_width
+  // Only the modes that keep refreshing can stampede the API.
+  let watching = options.interactive
+    || (options.format == Format::Table
+      && !options.sla_report
+      && !options.once
+      && options.fail_on.is_empty());
+  let _instance = if watching {
+    Some(claim_instance(&mut options)?)
   } else {
-    all_mrs
-      .iter()
-      .map(|(mr, _)| mr.title.len())
-      .max()
-      .unwrap_or(40)
+    None
   };
 
-  crossterm::execute!(target, Clear(ClearType::All))?;
-  for (mr, approval_info) in all_mrs {
-    let reference = make_link(&mr.web_url, &cell(ref_width, &mr.references.full)).blue();
-    let approved = approval_info
-      .approved_by
-      .iter()
-      .any(|a| a.user.id == user.id);
-    let title = cell(title_width, &mr.title).with(
-      if mr.assignees.iter().any(|assignee| assignee.id == user.id) && !mr.draft {
-        if targets_main_branch(&mr) {
-          Color::Red
-        } else {
-          Color::DarkYellow
+  if options.interactive {
+    return tui::run(&client, &user, &options, &config, &mut caches);
+  }
+
+  if !options.fail_on.is_empty() {
+    let snapshot = refresh(&client, &user, &options, &config, &mut caches)?;
+    warn_unparsed(&snapshot.unparsed, options.verbose);
+    return policy::check(&snapshot, &options.fail_on, &user, &options);
+  }
+
+  if options.sla_report {
+    let snapshot = refresh(&client, &user, &options, &config, &mut caches)?;
+    warn_unparsed(&snapshot.unparsed, options.verbose);
+    print!("{}", sla::report(&snapshot, &user, &options));
+    return Ok(());
+  }
+
+  if options.format == Format::Json {
+    let snapshot = refresh(&client, &user, &options, &config, &mut caches)?;
+    warn_unparsed(&snapshot.unparsed, options.verbose);
+    return print_json(&snapshot.rows, &user, &options);
+  }
+
+  if options.format == Format::List {
+    let snapshot = refresh(&client, &user, &options, &config, &mut caches)?;
+    warn_unparsed(&snapshot.unparsed, options.verbose);
+    let view = config.view(&options.view)?.sorted_by(options.sort);
+    if let Some(recap) = recap::since_last_run(&snapshot, &options) {
+      println!("{}", recap);
+    }
+    let _ = recap::record(&snapshot);
+    for line in list::list_lines(&snapshot, &view, &user, &options, &config) {
+      println!("{}", terminal::restyle(&line, options.depth));
+    }
+    return Ok(());
+  }
+
+  let view = config.view(&options.view)?.sorted_by(options.sort);
+  let mut screen = table::Screen::default();
+  if let Some(last_view) = stale::load(&options).filter(|_| !options.diff_refresh) {
+    table::print_stale(&last_view, &options, &mut screen)?;
+  }
+  let mut events = EventTracker::default();
+  let mut first = true;
+  loop {
+    let mut snapshot = refresh(&client, &user, &options, &config, &mut caches)?;
+    let recap = if first {
+      recap::since_last_run(&snapshot, &options)
+    } else {
+      None
+    };
+    // Only sums up the next start, so it may fail quietly.
+    let _ = recap::record(&snapshot);
+    let changes = events.update(&snapshot);
+    config.alerts.deliver(&changes);
+    let _ = Summary::of(&snapshot.rows, &user, &options).save();
+
+    let mut stats = std::mem::take(&mut snapshot.stats);
+    stats.time(Phase::Render, || {
+      if options.diff_refresh {
+        if let Some(recap) = &recap {
+          println!("{}", recap);
         }
-      } else if approval_info.approvals_left < 1 || approved {
-        Color::Green
-      } else if mr.draft {
-        Color::Grey
+        warn_unparsed(&snapshot.unparsed, options.verbose);
+        events::print_log(&snapshot, &changes, first, &user, &options);
+        Ok(())
       } else {
-        Color::White
-      },
+        table::print_all(
+          &snapshot,
+          &view,
+          &user,
+          &options,
+          &config,
+          recap.as_deref(),
+          &mut screen,
+        )
+      }
+    })?;
+    first = false;
+    if let Some(path) = &options.metrics_file {
+      stats.write_metrics(path, snapshot.rows.len())?;
+    }
+    if options.once {
+      return Ok(());
+    }
+    clock::wait(Duration::from_secs(options.interval));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fixtures::{isolate, me, mr_json, options, user_json},
+    mock::{get, reply, MockServer},
+    tape::Exchange,
+  };
+  use serde_json::json;
+
+  /// GitLab with one merge request of mine, `group/project!1`, and
+  /// `exchanges` over that.
+  fn server(exchanges: Vec<Exchange>) -> MockServer {
+    isolate();
+    let mut exchanges = exchanges;
+    exchanges.extend([
+      get(
+        "/merge_requests",
+        Some("author_username=me"),
+        json!([mr_json(1, json!({ "author": user_json(1, "me") }))]),
+      ),
+      get("/merge_requests", None, json!([])),
+      get("/users/1/events", None, json!([])),
+      get(
+        "/projects/5/merge_requests/1",
+        None,
+        json!({ "head_pipeline": null }),
+      ),
+      get("/projects/5/merge_requests/1/discussions", None, json!([])),
+    ]);
+    MockServer::start(exchanges)
+  }
+
+  fn refreshed(server: &MockServer, args: &[&str], caches: &mut Caches) -> Snapshot {
+    let options = options(args);
+    refresh(
+      &server.client(),
+      &me(),
+      &options,
+      &Config::default(),
+      caches,
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn refreshes_report_their_timings() {
+    let server = server(Vec::new());
+    let snapshot = refreshed(&server, &[], &mut Caches::default());
+    assert_eq!(snapshot.rows.len(), 1);
+    assert!(snapshot.stats.received > 0);
+    for phase in [Phase::Lists, Phase::Events, Phase::Approvals] {
+      assert!(snapshot.stats.get(phase) > Duration::ZERO, "{:?}", phase);
+    }
+    assert!(snapshot.stats.total() >= snapshot.stats.get(Phase::Lists));
+  }
+
+  fn lost_project() -> MockServer {
+    server(vec![reply(
+      "GET",
+      "/projects/5/merge_requests/1/approvals",
+      None,
+      404,
+      json!({ "message": "404 Project Not Found" }),
+    )])
+  }
+
+  #[test]
+  fn merge_requests_of_lost_projects_stay_listed_as_inaccessible() {
+    let server = lost_project();
+    let mut caches = Caches::default();
+    let snapshot = refreshed(&server, &[], &mut caches);
+    let [(row, _)] = &snapshot.rows[..] else {
+      panic!("expected one row, got {}", snapshot.rows.len());
+    };
+    assert!(row.inaccessible);
+    assert!(!row.detailed);
+    assert!(!caches.no_approvals);
+
+    // Its details aren't asked for again.
+    let asked = server.requests().len();
+    let snapshot = refreshed(&server, &[], &mut caches);
+    assert!(snapshot.rows[0].0.inaccessible);
+    assert!(server.requests()[asked..]
+      .iter()
+      .all(|request| !request.contains("/merge_requests/1/")));
+  }
+
+  #[test]
+  fn lost_projects_are_inaccessible_without_approvals_too() {
+    // The approvals endpoint is a plain 404, as without approvals, so the
+    // discussions are what find the project gone.
+    let server = server(vec![reply(
+      "GET",
+      "/projects/5/merge_requests/1/discussions",
+      None,
+      404,
+      json!({ "message": "404 Project Not Found" }),
+    )]);
+    let mut caches = Caches::default();
+    let snapshot = refreshed(&server, &[], &mut caches);
+    assert!(caches.no_approvals);
+    assert_eq!(snapshot.rows.len(), 1);
+    assert!(snapshot.rows[0].0.inaccessible);
+    assert!(caches.inaccessible.contains(&snapshot.rows[0].0.mr.id));
+  }
+
+  #[test]
+  fn lost_projects_keep_reviewer_states_on() {
+    let lost = server(vec![reply(
+      "GET",
+      "/projects/5/merge_requests/1/reviewers",
+      None,
+      404,
+      json!({ "message": "404 Project Not Found" }),
+    )]);
+    let mut caches = Caches::default();
+    let snapshot = refreshed(&lost, &[], &mut caches);
+    assert!(snapshot.rows[0].0.inaccessible);
+    assert!(!caches.no_reviewer_states);
+
+    // Where the endpoint itself is missing, it isn't asked again.
+    let older = server(Vec::new());
+    let mut caches = Caches::default();
+    let snapshot = refreshed(&older, &[], &mut caches);
+    assert!(!snapshot.rows[0].0.inaccessible);
+    assert!(caches.no_reviewer_states);
+  }
+
+  #[test]
+  fn inaccessible_merge_requests_can_be_hidden() {
+    let server = lost_project();
+    let snapshot = refreshed(&server, &["--hide-inaccessible"], &mut Caches::default());
+    assert!(snapshot.rows.is_empty());
+
+    // Nor once their details are fetched as they scroll into view.
+    let server = lost_project();
+    let args = ["--hide-inaccessible", "--detail-limit", "0"];
+    let mut caches = Caches::default();
+    let mut snapshot = refreshed(&server, &args, &mut caches);
+    assert_eq!(snapshot.rows.len(), 1);
+    let id = snapshot.rows[0].0.mr.id;
+    let options = options(&args);
+    snapshot
+      .fetch_details(
+        id,
+        &server.client(),
+        &me(),
+        &options,
+        &Config::default(),
+        &mut caches,
+      )
+      .unwrap();
+    assert!(snapshot.rows.is_empty());
+    assert_eq!(snapshot.set_aside.len(), 1);
+    assert_eq!(snapshot.set_aside[0].id, id);
+  }
+
+  #[test]
+  fn refreshes_ask_for_each_url_once() {
+    let mine = mr_json(
+      1,
+      json!({
+        "author": user_json(1, "me"),
+        "assignees": [user_json(1, "me")],
+        "reviewers": [user_json(1, "me")],
+      }),
     );
-    let author =
-      cell(assignee_width, mr.author.username.as_str()).with(if mr.author.id == user.id {
-        Color::Green
-      } else {
-        Color::White
-      });
-    let assignees = cell(
-      assignee_width,
-      mr.assignees
-        .iter()
-        .map(|a| format!("{} ", a.username))
-        .collect::<String>()
-        .as_str(),
+    let push = json!({
+      "project_id": 5,
+      "push_data": { "ref": "feature-1", "ref_type": "branch", "action": "pushed to" },
+    });
+    let server = server(vec![
+      get(
+        "/merge_requests",
+        Some("assignee_username=me"),
+        json!([mine.clone()]),
+      ),
+      get(
+        "/merge_requests",
+        Some("reviewer_username=me"),
+        json!([mine.clone()]),
+      ),
+      get(
+        "/users/1/events",
+        Some("action=pushed"),
+        json!([push.clone(), push]),
+      ),
+      get(
+        "/projects/5/merge_requests",
+        Some("source_branch=feature-1"),
+        json!([mine]),
+      ),
+    ]);
+    let snapshot = refreshed(&server, &[], &mut Caches::default());
+    assert_eq!(snapshot.rows.len(), 1);
+
+    let requests = server.requests();
+    let mut unique = requests.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(requests.len(), unique.len(), "{:#?}", requests);
+    assert!(requests
+      .iter()
+      .any(|request| request.contains("source_branch=feature-1")));
+  }
+
+  /// My merge request with two of its three tasks left, listed first.
+  fn with_tasks() -> Exchange {
+    get(
+      "/merge_requests",
+      Some("author_username=me"),
+      json!([mr_json(
+        1,
+        json!({
+          "author": user_json(1, "me"),
+          "task_completion_status": { "count": 3, "completed_count": 1 },
+        }),
+      )]),
     )
-    .red();
+  }
 
-    crossterm::execute!(
-      target,
-      Print(reference),
-      Print(" "),
-      Print(title),
-      Print(" "),
-      Print(author),
-      Print(" "),
-      Print(assignees),
-    )?;
-    writeln!(target)?;
+  fn reasons(snapshot: &Snapshot) -> Vec<&str> {
+    snapshot.rows[0]
+      .1
+      .terms
+      .iter()
+      .map(|(reason, _)| reason.as_str())
+      .collect()
   }
 
-  Ok(())
-}
+  #[test]
+  fn instances_without_approvals_are_asked_once() {
+    // The approvals endpoint is a plain 404, as without approvals.
+    let server = server(vec![with_tasks()]);
+    let mut caches = Caches::default();
+    let snapshot = refreshed(&server, &[], &mut caches);
+    assert!(caches.no_approvals);
+    assert!(snapshot.no_approvals);
+    let (row, _) = &snapshot.rows[0];
+    assert!(row.detailed);
+    assert!(!row.approvals_known());
+    assert!(row.approval_rules.is_empty());
+    assert!(!reasons(&snapshot).contains(&"2 open tasks"));
 
-fn main() -> Result<()> {
-  let gitlab_token = env::var("GITLAB_TOKEN")?;
+    let snapshot = refreshed(&server, &[], &mut caches);
+    assert!(snapshot.no_approvals);
+    let approvals = |request: &&String| request.contains("/approval");
+    assert_eq!(server.requests().iter().filter(approvals).count(), 1);
+  }
 
-  let client = Client::builder()
-    .default_headers(HeaderMap::from_iter([(
-      "Authorization".parse().unwrap(),
-      format!("Bearer {}", gitlab_token).parse()?,
-    )]))
-    .build()?;
+  #[test]
+  fn instances_with_approvals_score_them() {
+    let server = server(vec![
+      with_tasks(),
+      get(
+        "/projects/5/merge_requests/1/approvals",
+        None,
+        json!({ "approvals_left": 0, "approved_by": [] }),
+      ),
+      get(
+        "/projects/5/merge_requests/1/approval_state",
+        None,
+        json!({ "rules": [] }),
+      ),
+    ]);
+    let mut caches = Caches::default();
+    let snapshot = refreshed(&server, &[], &mut caches);
+    assert!(!caches.no_approvals);
+    assert!(!snapshot.no_approvals);
+    assert!(snapshot.rows[0].0.approvals_known());
+    assert!(reasons(&snapshot).contains(&"2 open tasks"));
+  }
 
-  let user_name = env::args()
-    .nth(1)
-    .ok_or::<Box<dyn Error>>("First argument should be the GitLab user name".into())?;
+  /// A refresh recorded from `server` with `with_tasks` and a merge request
+  /// that needs another approval.
+  const SESSION: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/session.json");
 
-  let user = User::get(&client, user_name.as_str())?;
+  fn assert_recorded(snapshot: &Snapshot) {
+    assert!(snapshot.unavailable.is_empty());
+    assert!(snapshot.unparsed.is_empty());
+    assert_eq!(snapshot.rows.len(), 1);
+    assert_eq!(snapshot.rows[0].0.mr.references.full, "group/project!1");
+    assert!(snapshot.rows[0].0.approvals_known());
+    assert!(reasons(snapshot).contains(&"waiting on others"));
+  }
 
-  loop {
-    print_all(&client, &user)?;
-    sleep(Duration::from_secs(30));
+  #[test]
+  fn recorded_sessions_replay() {
+    isolate();
+    let tape = Tape::replay(std::path::Path::new(SESSION)).unwrap();
+    // Nothing is sent, so the host needn't exist.
+    let client = Client::new(
+      "https://gitlab.example.com",
+      "",
+      Some(gitlab::AuthStyle::PrivateToken),
+    )
+    .unwrap()
+    .with_tape(tape);
+    let options = options(&[]);
+    let snapshot = refresh(
+      &client,
+      &me(),
+      &options,
+      &Config::default(),
+      &mut Caches::default(),
+    )
+    .unwrap();
+    assert_recorded(&snapshot);
+  }
+
+  #[test]
+  fn recorded_sessions_serve_from_a_mock_server() {
+    isolate();
+    let exchanges = tape::exchanges(std::path::Path::new(SESSION)).unwrap();
+    let server = MockServer::start(exchanges);
+    let snapshot = refreshed(&server, &[], &mut Caches::default());
+    assert_recorded(&snapshot);
+  }
+
+  /// My merge requests on two pages: `group/project!1` and then `!2`.
+  fn two_pages() -> Vec<Exchange> {
+    let mut first = get(
+      "/merge_requests",
+      Some("author_username=me&state=opened"),
+      json!([mr_json(1, json!({ "author": user_json(1, "me") }))]),
+    );
+    first.headers.insert(
+      "link".into(),
+      r#"<https://gitlab.example.com/api/v4/merge_requests?author_username=me&state=opened&cursor=two>; rel="next""#.into(),
+    );
+    vec![
+      // More specific than the first page, so it goes first.
+      get(
+        "/merge_requests",
+        Some("author_username=me&cursor=two"),
+        json!([mr_json(2, json!({ "author": user_json(1, "me") }))]),
+      ),
+      first,
+      // Nothing changed since.
+      get(
+        "/merge_requests",
+        Some("author_username=me&state=all"),
+        json!([]),
+      ),
+      get("/merge_requests", Some("reviewer_username=me"), json!([])),
+      get("/merge_requests", Some("assignee_username=me"), json!([])),
+      get("/users/1/events", None, json!([])),
+      get(
+        "/projects/5/merge_requests/1",
+        None,
+        json!({ "head_pipeline": null }),
+      ),
+      get(
+        "/projects/5/merge_requests/2",
+        None,
+        json!({ "head_pipeline": null }),
+      ),
+      get("/projects/5/merge_requests/1/discussions", None, json!([])),
+      get("/projects/5/merge_requests/2/discussions", None, json!([])),
+    ]
+  }
+
+  #[test]
+  fn cancelling_mid_list_keeps_nothing_of_it() {
+    isolate();
+    let server = MockServer::start(two_pages());
+    let client = server.client();
+    // Quit while the first page is on its way.
+    let cancelling = client.clone();
+    let once = std::sync::Once::new();
+    server.on_request(move |request| {
+      if request.contains("author_username=me&state=opened") && !request.contains("cursor") {
+        once.call_once(|| cancelling.cancel());
+      }
+    });
+    let options = options(&[]);
+    let mut caches = Caches::default();
+    let refreshed =
+      |caches: &mut Caches| refresh(&client, &me(), &options, &Config::default(), caches);
+
+    assert!(refreshed(&mut caches).is_err());
+    assert!(client.is_cancelled());
+    assert!(!server
+      .requests()
+      .iter()
+      .any(|request| request.contains("cursor=two")));
+
+    // Had it kept the first page, this would only ask for what changed since.
+    client.resume();
+    let snapshot = refreshed(&mut caches).unwrap();
+    let mut listed: Vec<_> = snapshot
+      .rows
+      .iter()
+      .map(|(row, _)| row.mr.references.full.as_str())
+      .collect();
+    listed.sort();
+    assert_eq!(listed, ["group/project!1", "group/project!2"]);
+  }
+
+  /// Options with `--hyperlinks` resolved as `Options::load` does.
+  fn linking(hyperlinks: &str) -> Options {
+    let mut options = options(&["--hyperlinks", hyperlinks]);
+    options.links = options.hyperlinks.enabled();
+    options
+  }
+
+  #[test]
+  fn links_are_plain_titles_without_hyperlinks() {
+    let url = "https://gitlab.example.com/group/project/-/merge_requests/1";
+    assert_eq!(
+      make_link(&linking("never"), url, "group/project!1"),
+      "group/project!1"
+    );
+    assert_eq!(
+      make_link(&linking("always"), url, "group/project!1"),
+      format!("\x1B]8;;{}\x1B\\group/project!1\x1B]8;;\x1B\\", url)
+    );
   }
 }
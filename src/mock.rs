@@ -0,0 +1,177 @@
+use crate::{
+  gitlab::{AuthStyle, Client},
+  tape::{pattern, Exchange},
+};
+use serde_json::{json, Value};
+use std::{
+  collections::HashMap,
+  io::{BufRead, BufReader, Read, Write},
+  net::{TcpListener, TcpStream},
+  sync::{Arc, Mutex},
+  thread,
+};
+
+/// A GitLab that answers with recorded exchanges, kept as a session would
+/// have them. A request matches an exchange with its method and path and,
+/// where the exchange has a query, every pair of it; matches are served in
+/// order and the last one again once they run out. Anything else is a 404.
+/// As in a replay, dates and times in the query match any other.
+pub struct MockServer {
+  pub url: String,
+  /// Requests as they came in, like `GET /api/v4/user?per_page=100`, with
+  /// their headers by lowercased name.
+  requests: Arc<Mutex<Vec<Received>>>,
+  hook: Arc<Mutex<Hook>>,
+}
+
+type Hook = Box<dyn Fn(&str) + Send>;
+
+/// A request line and the request's headers.
+type Received = (String, HashMap<String, String>);
+
+impl MockServer {
+  pub fn start(exchanges: Vec<Exchange>) -> Self {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&requests);
+    let hook: Arc<Mutex<Hook>> = Arc::new(Mutex::new(Box::new(|_| {})));
+    let called = Arc::clone(&hook);
+    thread::spawn(move || {
+      let mut served = HashMap::new();
+      for stream in listener.incoming().flatten() {
+        serve(stream, &exchanges, &mut served, &seen, &called);
+      }
+    });
+    MockServer {
+      url,
+      requests,
+      hook,
+    }
+  }
+
+  /// Calls `hook` with each request as it comes in, before it is answered.
+  pub fn on_request(&self, hook: impl Fn(&str) + Send + 'static) {
+    *self.hook.lock().unwrap() = Box::new(hook);
+  }
+
+  pub fn client(&self) -> Client {
+    Client::new(&self.url, "token", Some(AuthStyle::PrivateToken)).unwrap()
+  }
+
+  pub fn requests(&self) -> Vec<String> {
+    let requests = self.requests.lock().unwrap();
+    requests
+      .iter()
+      .map(|(request, _)| request.clone())
+      .collect()
+  }
+
+  /// The value of the header `name` in each request, in order.
+  pub fn headers(&self, name: &str) -> Vec<Option<String>> {
+    let requests = self.requests.lock().unwrap();
+    requests
+      .iter()
+      .map(|(_, headers)| headers.get(&name.to_lowercase()).cloned())
+      .collect()
+  }
+}
+
+/// A JSON reply to `GET {path}` from the API.
+pub fn get(path: &str, query: Option<&str>, body: Value) -> Exchange {
+  reply("GET", path, query, 200, body)
+}
+
+pub fn reply(method: &str, path: &str, query: Option<&str>, status: u16, body: Value) -> Exchange {
+  Exchange {
+    method: method.to_string(),
+    path: format!("/api/v4{}", path),
+    query: query.map(str::to_string),
+    status,
+    headers: Default::default(),
+    body,
+  }
+}
+
+fn matches(exchange: &Exchange, method: &str, path: &str, query: &str) -> bool {
+  let pairs: Vec<String> = query.split('&').map(pattern).collect();
+  exchange.method == method
+    && exchange.path == path
+    && exchange
+      .query
+      .iter()
+      .flat_map(|query| query.split('&'))
+      .all(|pair| pairs.contains(&pattern(pair)))
+}
+
+fn serve(
+  stream: TcpStream,
+  exchanges: &[Exchange],
+  served: &mut HashMap<String, usize>,
+  seen: &Mutex<Vec<Received>>,
+  hook: &Mutex<Hook>,
+) {
+  let mut reader = BufReader::new(&stream);
+  let mut request_line = String::new();
+  if reader.read_line(&mut request_line).is_err() {
+    return;
+  }
+  let mut headers = HashMap::new();
+  loop {
+    let mut header = String::new();
+    if reader.read_line(&mut header).is_err() || header.trim().is_empty() {
+      break;
+    }
+    if let Some((name, value)) = header.split_once(':') {
+      headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+    }
+  }
+  let length = headers
+    .get("content-length")
+    .and_then(|length| length.parse().ok())
+    .unwrap_or(0);
+  let mut body = vec![0; length];
+  let _ = reader.read_exact(&mut body);
+
+  let mut parts = request_line.split_whitespace();
+  let method = parts.next().unwrap_or_default();
+  let target = parts.next().unwrap_or_default();
+  let (path, query) = target.split_once('?').unwrap_or((target, ""));
+  let request = format!("{} {}", method, target);
+  seen.lock().unwrap().push((request.clone(), headers));
+  (hook.lock().unwrap())(&request);
+
+  let candidates: Vec<&Exchange> = exchanges
+    .iter()
+    .filter(|exchange| matches(exchange, method, path, query))
+    .collect();
+  let count = served.entry(request).or_insert(0);
+  let (status, headers, body) = match candidates.get((*count).min(candidates.len().max(1) - 1)) {
+    Some(exchange) => (
+      exchange.status,
+      exchange.headers.clone(),
+      exchange.body.clone(),
+    ),
+    None => (
+      404,
+      Default::default(),
+      json!({ "message": "404 Not Found" }),
+    ),
+  };
+  *count += 1;
+
+  let body = match body {
+    Value::String(text) => text,
+    body => body.to_string(),
+  };
+  let mut reply = format!("HTTP/1.1 {} Mock\r\n", status);
+  for (name, value) in headers {
+    reply.push_str(&format!("{}: {}\r\n", name, value));
+  }
+  reply.push_str(&format!(
+    "Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+    body.len(),
+    body
+  ));
+  let _ = (&stream).write_all(reply.as_bytes());
+}
@@ -0,0 +1,934 @@
+use crate::{
+  config::Config,
+  debug::Endpoint,
+  digest::Since,
+  gitlab::AuthStyle,
+  layout::WidthBounds,
+  policy::Policy,
+  prompt::PromptStyle,
+  reference::MrRef,
+  state::StateSection,
+  terminal::{ColorDepth, Depth, Hyperlinks},
+  timezone::TimeZone,
+  users::UserIds,
+  views::ViewSort,
+  Result,
+};
+use chrono::Weekday;
+use clap::{
+  parser::ValueSource, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+};
+use crossterm::style::Color;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+  borrow::Cow,
+  collections::{BTreeMap, HashSet},
+  path::PathBuf,
+};
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+  Table,
+  Json,
+  Prompt,
+  /// Plain numbered sentences without colors or alignment, for screen readers
+  List,
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthMode {
+  /// Pick the header from the token's format and fall back to the other on 401
+  Auto,
+  Bearer,
+  PrivateToken,
+}
+
+impl AuthMode {
+  pub fn style(&self) -> Option<AuthStyle> {
+    match self {
+      AuthMode::Auto => None,
+      AuthMode::Bearer => Some(AuthStyle::Bearer),
+      AuthMode::PrivateToken => Some(AuthStyle::PrivateToken),
+    }
+  }
+}
+
+/// What to group the table by, in place of tiers.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+  Milestone,
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+  Dark,
+  Light,
+}
+
+impl Theme {
+  pub fn text(&self) -> Color {
+    match self {
+      Theme::Dark => Color::White,
+      Theme::Light => Color::Black,
+    }
+  }
+
+  pub fn muted(&self) -> Color {
+    match self {
+      Theme::Dark => Color::Grey,
+      Theme::Light => Color::DarkGrey,
+    }
+  }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigCommand {
+  /// Write a commented configuration template to the default location
+  Init {
+    /// Overwrite an existing configuration file
+    #[arg(long)]
+    force: bool,
+  },
+
+  /// Print the effective configuration after merging flags, environment and file
+  Show {
+    /// Show the settings that apply to merge requests of this project
+    #[arg(long, value_name = "PATH")]
+    project: Option<String>,
+  },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum StateCommand {
+  /// Print the local state file
+  Show,
+
+  /// Forget one section of the local state, or all of it
+  Clear {
+    #[arg(value_enum)]
+    section: StateSection,
+  },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum DebugCommand {
+  /// Print what GitLab sends for one of the requests made, and which of the fields read it lacks
+  Dump {
+    /// Which request to repeat
+    #[arg(long, value_enum)]
+    endpoint: Endpoint,
+
+    /// Blank out tokens, email addresses and names
+    #[arg(long)]
+    redact: bool,
+
+    /// Also blank out whatever matches this regular expression; repeatable
+    #[arg(long, value_name = "REGEX", requires = "redact")]
+    redact_pattern: Vec<Regex>,
+  },
+  /// Print what was detected of the terminal: colors, hyperlinks and size
+  Term,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum MrCommand {
+  /// Fetch a merge request and check it out as `mr-IID`, in a clone of its project
+  Checkout {
+    /// Merge request as `group/project!IID` or its URL
+    reference: MrRef,
+
+    /// Only print the git commands
+    #[arg(long)]
+    dry_run: bool,
+  },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+  /// Manage the configuration file
+  Config {
+    #[command(subcommand)]
+    command: ConfigCommand,
+  },
+
+  /// Summarize what I merged, approved and still have to review, as markdown
+  Digest {
+    /// Period to cover, like `24h`, `7d` or `2w`
+    #[arg(long, default_value = "7d", value_name = "PERIOD")]
+    since: Since,
+  },
+
+  /// Look at raw API responses, to tell what an instance sends differently
+  Debug {
+    #[command(subcommand)]
+    command: DebugCommand,
+  },
+
+  /// Check the setup for common problems: token, host, user and terminal
+  Doctor,
+
+  /// List my draft merge requests, marking those that look finished, and offer to mark them ready
+  Drafts,
+
+  /// Print the settings and muted and snoozed merge requests as JSON, to
+  /// import on another machine; access tokens are left out
+  Export,
+
+  /// Take in what `export` printed, showing the changes before making them
+  Import {
+    /// File written by `export`
+    path: PathBuf,
+
+    /// Add to the present settings and mutes (the default)
+    #[arg(long)]
+    merge: bool,
+
+    /// Replace the present settings and mutes, keeping only access tokens
+    #[arg(long, conflicts_with = "merge")]
+    replace: bool,
+
+    /// Only show the changes
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Make the changes without asking
+    #[arg(long)]
+    yes: bool,
+  },
+
+  /// List the open issues assigned to me, most pressing first
+  Issues,
+
+  /// Work with a merge request locally
+  Mr {
+    #[command(subcommand)]
+    command: MrCommand,
+  },
+
+  /// Approve a merge request, with a comment and a 👍 as configured in `[lgtm]`
+  Lgtm {
+    /// Merge request as `group/project!IID` or its URL
+    reference: MrRef,
+
+    /// Comment to post first, instead of the configured one; empty for none
+    #[arg(short, long)]
+    message: Option<String>,
+
+    /// Don't give a 👍
+    #[arg(long)]
+    no_award: bool,
+  },
+
+  /// Print a JSON Schema of the `--format json` output
+  Schema,
+
+  /// Inspect or reset what is kept locally between runs
+  State {
+    #[command(subcommand)]
+    command: StateCommand,
+  },
+
+  /// Mark one of my merge requests as ready, or as draft again with --draft
+  Ready {
+    /// Merge request as `group/project!IID` or its URL
+    reference: MrRef,
+
+    /// Mark the merge request as draft instead
+    #[arg(long)]
+    draft: bool,
+
+    /// Also change merge requests I don't author
+    #[arg(long)]
+    force: bool,
+  },
+
+  /// Rebase a merge request onto its target branch
+  Rebase {
+    /// Merge request as `group/project!IID` or its URL
+    reference: MrRef,
+  },
+
+  /// Retry the failed jobs of a merge request's head pipeline
+  RetryPipeline {
+    /// Merge request as `group/project!IID` or its URL
+    reference: MrRef,
+  },
+
+  /// Print the version, commit, target and features this was built with
+  Version {
+    /// Also ask GitHub whether a newer release is out
+    #[arg(long)]
+    check_update: bool,
+  },
+}
+
+#[derive(Parser, Debug, Clone)]
+#[command(about = "GitLab To-Do Helper")]
+pub struct Options {
+  /// GitLab user name [default: `user` from the config file]
+  #[arg(env = "GITLAB_TODO_USER")]
+  pub user: Option<String>,
+
+  /// GitLab instance, as a host name or base URL
+  #[arg(long, env = "GITLAB_TODO_HOST", default_value = "gitlab.com")]
+  pub host: String,
+
+  /// How to send the access token
+  #[arg(long, env = "GITLAB_TODO_AUTH_STYLE", value_enum, default_value_t = AuthMode::Auto)]
+  pub auth_style: AuthMode,
+
+  /// Fetch approvals, pipelines and threads only for the N highest-ranked merge requests
+  /// [default: as many as fit the terminal, plus a margin]
+  #[arg(long, env = "GITLAB_TODO_DETAIL_LIMIT", value_name = "N")]
+  pub detail_limit: Option<usize>,
+
+  /// Seconds between refreshes
+  #[arg(long, env = "GITLAB_TODO_INTERVAL", default_value_t = 30, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+  pub interval: u64,
+
+  /// Seconds to wait for GitLab to answer a request before giving up on it; 0 waits as long as it takes
+  #[arg(
+    long,
+    env = "GITLAB_TODO_REQUEST_TIMEOUT",
+    default_value_t = 30,
+    value_name = "SECONDS"
+  )]
+  pub request_timeout: u64,
+
+  #[command(subcommand)]
+  pub command: Option<Command>,
+
+  /// Configuration file [default: $XDG_CONFIG_HOME/gitlab-todo/config.toml]
+  #[arg(long, env = "GITLAB_TODO_CONFIG", value_name = "PATH")]
+  pub config: Option<PathBuf>,
+
+  /// Width of the author column, either fixed (`N`) or bounded (`MIN..MAX`)
+  #[arg(long, env = "GITLAB_TODO_AUTHOR_WIDTH", default_value = "4..20")]
+  pub author_width: WidthBounds,
+
+  /// Width of the target branch column, either fixed (`N`) or bounded (`MIN..MAX`)
+  #[arg(long, env = "GITLAB_TODO_BRANCH_WIDTH", default_value = "6..20")]
+  pub branch_width: WidthBounds,
+
+  /// Width of the assignee column, either fixed (`N`) or bounded (`MIN..MAX`)
+  #[arg(long, env = "GITLAB_TODO_ASSIGNEE_WIDTH", default_value = "4..30")]
+  pub assignee_width: WidthBounds,
+
+  /// Width of the reviewer column, either fixed (`N`) or bounded (`MIN..MAX`)
+  #[arg(long, env = "GITLAB_TODO_REVIEWER_WIDTH", default_value = "4..30")]
+  pub reviewer_width: WidthBounds,
+
+  /// Width of the milestone column, either fixed (`N`) or bounded (`MIN..MAX`)
+  #[arg(long, env = "GITLAB_TODO_MILESTONE_WIDTH", default_value = "4..16")]
+  pub milestone_width: WidthBounds,
+
+  /// Width of the last activity column, either fixed (`N`) or bounded (`MIN..MAX`)
+  #[arg(long, env = "GITLAB_TODO_ACTIVITY_WIDTH", default_value = "8..30")]
+  pub activity_width: WidthBounds,
+
+  /// Narrowest the title column gets before optional columns are left out
+  #[arg(
+    long,
+    env = "GITLAB_TODO_MIN_TITLE_WIDTH",
+    default_value_t = 20,
+    value_name = "WIDTH"
+  )]
+  pub min_title_width: usize,
+
+  /// Spaces between columns
+  #[arg(
+    long,
+    env = "GITLAB_TODO_COLUMN_GAP",
+    default_value_t = 1,
+    value_name = "WIDTH"
+  )]
+  pub column_gap: usize,
+
+  /// Marks where a cell was cut short
+  #[arg(long, env = "GITLAB_TODO_ELLIPSIS", default_value = "...")]
+  pub ellipsis: String,
+
+  /// Add a second line per merge request with labels, milestone, pipeline, approvals and age,
+  /// after the first line of its description where it has one
+  #[arg(long, env = "GITLAB_TODO_TWO_LINE")]
+  pub two_line: bool,
+
+  /// In the two-line layout, start the second line with the author's initials on a color of
+  /// their own
+  #[arg(long, env = "GITLAB_TODO_BADGES")]
+  pub badges: bool,
+
+  /// In the two-line layout, description lines to pass over for the subtitle, like the headings
+  /// of a merge request template
+  #[arg(long = "description-skip", env = "GITLAB_TODO_DESCRIPTION_SKIP", value_name = "PREFIX", value_delimiter = ',', default_values_t = ["## What does this MR do".to_string(), "## Description".to_string()])]
+  pub description_skip: Vec<String>,
+
+  /// Show who last touched each merge request and how
+  #[arg(long, env = "GITLAB_TODO_LAST_ACTIVITY")]
+  pub last_activity: bool,
+
+  /// Regular expression matching issue-tracker ticket IDs in titles and branch names
+  #[arg(long, env = "GITLAB_TODO_TICKET_PATTERN", value_name = "REGEX")]
+  pub ticket_pattern: Option<Regex>,
+
+  /// URL template for ticket links, with `{}` standing in for the ticket ID
+  #[arg(
+    long,
+    env = "GITLAB_TODO_TICKET_URL",
+    value_name = "TEMPLATE",
+    requires = "ticket_pattern"
+  )]
+  pub ticket_url: Option<String>,
+
+  /// Also list merge requests merged within the given number of hours
+  #[arg(long, env = "GITLAB_TODO_SHOW_MERGED", value_name = "HOURS")]
+  pub show_merged: Option<u32>,
+
+  /// Also list open merge requests in this group that nobody reviews or was assigned yet, under
+  /// "Needs triage"; repeatable
+  #[arg(
+    long = "unassigned-in-group",
+    env = "GITLAB_TODO_UNASSIGNED_IN_GROUP",
+    value_name = "GROUP",
+    value_delimiter = ','
+  )]
+  pub unassigned_in_group: Vec<String>,
+
+  /// Most merge requests to list under "Needs triage"; the rest are only counted
+  #[arg(
+    long,
+    env = "GITLAB_TODO_TRIAGE_LIMIT",
+    default_value_t = 10,
+    value_name = "COUNT"
+  )]
+  pub triage_limit: usize,
+
+  /// Priority bonus for my merge requests whose head pipeline failed
+  #[arg(
+    long,
+    env = "GITLAB_TODO_FAILED_PIPELINE_BONUS",
+    default_value_t = 4,
+    value_name = "POINTS",
+    allow_negative_numbers = true
+  )]
+  pub failed_pipeline_bonus: isize,
+
+  /// Priority penalty for my merge requests that are only waiting on others
+  #[arg(
+    long,
+    env = "GITLAB_TODO_WAITING_PENALTY",
+    default_value_t = 2,
+    value_name = "POINTS",
+    allow_negative_numbers = true
+  )]
+  pub waiting_penalty: isize,
+
+  /// Priority bonus when I can fulfil an outstanding approval rule without being a reviewer
+  #[arg(
+    long,
+    env = "GITLAB_TODO_ELIGIBLE_APPROVER_BONUS",
+    default_value_t = 1,
+    value_name = "POINTS",
+    allow_negative_numbers = true
+  )]
+  pub eligible_approver_bonus: isize,
+
+  /// Priority penalty for merge requests blocked by other merge requests
+  #[arg(
+    long,
+    env = "GITLAB_TODO_BLOCKED_PENALTY",
+    default_value_t = 3,
+    value_name = "POINTS",
+    allow_negative_numbers = true
+  )]
+  pub blocked_penalty: isize,
+
+  /// Priority bonus for merge requests that block other merge requests
+  #[arg(
+    long,
+    env = "GITLAB_TODO_BLOCKING_BONUS",
+    default_value_t = 1,
+    value_name = "POINTS",
+    allow_negative_numbers = true
+  )]
+  pub blocking_bonus: isize,
+
+  /// Priority bonus for merge requests from a fork, which need a closer look
+  #[arg(
+    long,
+    env = "GITLAB_TODO_FORK_BONUS",
+    default_value_t = 0,
+    value_name = "POINTS",
+    allow_negative_numbers = true
+  )]
+  pub fork_bonus: isize,
+
+  /// Priority bonus per 👍 award on a merge request
+  #[arg(
+    long,
+    env = "GITLAB_TODO_UPVOTE_BONUS",
+    default_value_t = 0,
+    value_name = "POINTS",
+    allow_negative_numbers = true
+  )]
+  pub upvote_bonus: isize,
+
+  /// Maximum priority bonus from 👍 awards
+  #[arg(
+    long,
+    env = "GITLAB_TODO_UPVOTE_BONUS_CAP",
+    default_value_t = 3,
+    value_name = "POINTS"
+  )]
+  pub upvote_bonus_cap: isize,
+
+  /// Priority bonus when the author replied to a thread of mine on their merge request
+  #[arg(
+    long,
+    env = "GITLAB_TODO_THREAD_REPLY_BONUS",
+    default_value_t = 2,
+    value_name = "POINTS",
+    allow_negative_numbers = true
+  )]
+  pub thread_reply_bonus: isize,
+
+  /// Priority bonus for my merge requests where a reviewer requested changes
+  #[arg(
+    long,
+    env = "GITLAB_TODO_CHANGES_REQUESTED_BONUS",
+    default_value_t = 3,
+    value_name = "POINTS",
+    allow_negative_numbers = true
+  )]
+  pub changes_requested_bonus: isize,
+
+  /// Priority bonus for my fully approved merge requests whose description still has open tasks
+  #[arg(
+    long,
+    env = "GITLAB_TODO_OPEN_TASKS_BONUS",
+    default_value_t = 2,
+    value_name = "POINTS",
+    allow_negative_numbers = true
+  )]
+  pub open_tasks_bonus: isize,
+
+  /// User names of bots whose assignment lowers a merge request's priority
+  #[arg(long = "bot", env = "GITLAB_TODO_BOTS", value_name = "USER", value_delimiter = ',', default_values_t = ["nomadic-margebot".to_string()])]
+  pub bots: Vec<String>,
+
+  /// Target branches that count as main branches where a project's default branch is unknown
+  #[arg(long = "main-branch", env = "GITLAB_TODO_MAIN_BRANCHES", value_name = "BRANCH", value_delimiter = ',', default_values_t = ["master".to_string(), "main".to_string()])]
+  pub main_branches: Vec<String>,
+
+  /// Only list merge requests I review that were updated within this many days
+  #[arg(
+    long,
+    env = "GITLAB_TODO_REVIEW_WINDOW",
+    default_value_t = 14,
+    value_name = "DAYS"
+  )]
+  pub review_window: i64,
+
+  /// Also list merge requests from branches I pushed to within this many days
+  #[arg(
+    long,
+    env = "GITLAB_TODO_PUSH_LOOKBACK_DAYS",
+    default_value_t = 14,
+    value_name = "DAYS"
+  )]
+  pub push_lookback_days: i64,
+
+  /// Hide draft merge requests
+  #[arg(long, env = "GITLAB_TODO_HIDE_DRAFTS")]
+  pub hide_drafts: bool,
+
+  /// Only show merge requests into target branches matching this glob, e.g. `release/*`
+  #[arg(long, env = "GITLAB_TODO_TARGET_BRANCH", value_name = "GLOB")]
+  pub target_branch: Option<String>,
+
+  /// Only show merge requests by this author, or by the members of a team from the config file
+  /// given as `@TEAM`; repeatable
+  #[arg(
+    long = "author",
+    env = "GITLAB_TODO_AUTHORS",
+    value_name = "USERNAME",
+    value_delimiter = ','
+  )]
+  pub authors: Vec<String>,
+
+  /// Only show merge requests in the milestone with this title
+  #[arg(long, env = "GITLAB_TODO_MILESTONE", value_name = "TITLE")]
+  pub milestone: Option<String>,
+
+  /// Only show merge requests whose milestone spans today, such as the current sprint
+  #[arg(long, env = "GITLAB_TODO_CURRENT_ITERATION")]
+  pub current_iteration: bool,
+
+  /// Also list the open issues assigned to me, in a section of their own
+  #[arg(long, env = "GITLAB_TODO_INCLUDE_ISSUES")]
+  pub include_issues: bool,
+
+  /// Show merge requests from archived projects
+  #[arg(long, env = "GITLAB_TODO_INCLUDE_ARCHIVED")]
+  pub include_archived: bool,
+
+  /// Hide merge requests from projects we no longer have access to
+  #[arg(long, env = "GITLAB_TODO_HIDE_INACCESSIBLE")]
+  pub hide_inaccessible: bool,
+
+  /// Time zone for displayed times: `local`, `utc` or an offset like `+02:00`
+  #[arg(
+    long,
+    env = "GITLAB_TODO_TIMEZONE",
+    default_value = "local",
+    value_name = "ZONE"
+  )]
+  pub timezone: TimeZone,
+
+  /// Color theme matching the terminal background
+  #[arg(long, env = "GITLAB_TODO_THEME", value_enum, default_value_t = Theme::Dark)]
+  pub theme: Theme,
+
+  /// Whether to make references clickable: `auto` detects terminal support
+  #[arg(long, env = "GITLAB_TODO_HYPERLINKS", value_enum, default_value_t = Hyperlinks::Auto)]
+  pub hyperlinks: Hyperlinks,
+
+  /// Colors the terminal shows, to force one for debugging: `auto` tells from the environment
+  #[arg(
+    long,
+    env = "GITLAB_TODO_COLOR_DEPTH",
+    value_enum,
+    default_value_t = ColorDepth::Auto
+  )]
+  pub color_depth: ColorDepth,
+
+  /// Lower the priority of merge requests idle for longer than this many days
+  #[arg(long, env = "GITLAB_TODO_DECAY_AFTER", value_name = "DAYS")]
+  pub decay_after: Option<u32>,
+
+  /// Priority points lost per full week of idling beyond --decay-after
+  #[arg(
+    long,
+    env = "GITLAB_TODO_DECAY_PER_WEEK",
+    default_value_t = 1,
+    value_name = "POINTS"
+  )]
+  pub decay_per_week: isize,
+
+  /// Maximum priority points lost to idling
+  #[arg(
+    long,
+    env = "GITLAB_TODO_DECAY_CAP",
+    default_value_t = 5,
+    value_name = "POINTS"
+  )]
+  pub decay_cap: isize,
+
+  /// List merge requests that lost priority to idling in a separate section
+  #[arg(long, env = "GITLAB_TODO_STALE_REPORT", requires = "decay_after")]
+  pub stale_report: bool,
+
+  /// Merge requests waiting this long for my review turn amber, like `24h` or `1d`
+  #[arg(
+    long,
+    env = "GITLAB_TODO_SLA_WARN",
+    default_value = "1d",
+    value_name = "PERIOD"
+  )]
+  pub sla_warn: Since,
+
+  /// Merge requests waiting this long for my review turn red and count as breaching the SLA
+  #[arg(
+    long,
+    env = "GITLAB_TODO_SLA_BREACH",
+    default_value = "2d",
+    value_name = "PERIOD"
+  )]
+  pub sla_breach: Since,
+
+  /// Only count weekdays towards how long a merge request waits for my review
+  #[arg(long, env = "GITLAB_TODO_BUSINESS_DAYS")]
+  pub business_days: bool,
+
+  /// Days that don't count with --business-days
+  #[arg(
+    long,
+    env = "GITLAB_TODO_WEEKEND",
+    default_value = "sat,sun",
+    value_name = "DAYS",
+    value_delimiter = ','
+  )]
+  pub weekend: Vec<Weekday>,
+
+  /// List the merge requests waiting on my review beyond --sla-breach, most overdue first, as
+  /// markdown
+  #[arg(long)]
+  pub sla_report: bool,
+
+  /// Refresh once and fail, listing what is off, when a policy like `reviewer_wait>3d` or
+  /// `unassigned>5` is violated; repeatable. Metrics are reviewer_wait, unassigned, conflicts and
+  /// open, which only count merge requests older than an age where given, as in
+  /// `conflicts(7d)>0`
+  #[arg(
+    long = "fail-on",
+    env = "GITLAB_TODO_FAIL_ON",
+    value_name = "POLICY",
+    value_delimiter = ','
+  )]
+  pub fail_on: Vec<Policy>,
+
+  /// Print the table once and exit instead of refreshing it
+  #[arg(long, env = "GITLAB_TODO_ONCE")]
+  pub once: bool,
+
+  /// Group the table into "needs action", "worth a look" and "low priority" tiers
+  #[arg(long, env = "GITLAB_TODO_TIERS")]
+  pub tiers: bool,
+
+  /// Group the table under a heading per milestone, soonest due first, with how far each is
+  /// along; overrides --tiers
+  #[arg(
+    long,
+    env = "GITLAB_TODO_GROUP_BY",
+    value_enum,
+    value_name = "GROUPING"
+  )]
+  pub group_by: Option<GroupBy>,
+
+  /// Minimum priority for the "needs your action" tier
+  #[arg(
+    long,
+    env = "GITLAB_TODO_TIER_HIGH",
+    default_value_t = 5,
+    value_name = "PRIORITY",
+    allow_negative_numbers = true
+  )]
+  pub tier_high: isize,
+
+  /// Minimum priority for the "worth a look" tier
+  #[arg(
+    long,
+    env = "GITLAB_TODO_TIER_LOW",
+    default_value_t = 1,
+    value_name = "PRIORITY",
+    allow_negative_numbers = true
+  )]
+  pub tier_low: isize,
+
+  /// Re-download the complete merge request lists every N refreshes instead of only changes
+  #[arg(long, env = "GITLAB_TODO_FULL_RESYNC_EVERY", default_value_t = 10, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+  pub full_resync_every: u64,
+
+  /// When I stop reviewing a merge request, record the time with a `/spend` comment
+  #[arg(long, env = "GITLAB_TODO_FOCUS_SPEND")]
+  pub focus_spend: bool,
+
+  /// Refuse to start while another instance refreshes, instead of refreshing half as often
+  #[arg(long, env = "GITLAB_TODO_SINGLE_INSTANCE")]
+  pub single_instance: bool,
+
+  /// Comment posted when handing a review off, with `{user}` for whom it goes to
+  #[arg(long, env = "GITLAB_TODO_HANDOFF_NOTE", value_name = "TEXT")]
+  pub handoff_note: Option<String>,
+
+  /// Instead of redrawing the table, append a timestamped line per change on every refresh
+  #[arg(long, env = "GITLAB_TODO_DIFF_REFRESH")]
+  pub diff_refresh: bool,
+
+  /// Start with a blank screen instead of the last list, marked stale, until the first refresh
+  #[arg(long, env = "GITLAB_TODO_NO_STALE_START")]
+  pub no_stale_start: bool,
+
+  /// Don't offer to set things up when there is neither a token nor a configuration file
+  #[arg(long, env = "GITLAB_TODO_NO_WIZARD")]
+  pub no_wizard: bool,
+
+  /// Clear the whole screen on every refresh, scrollback included, instead of redrawing the last
+  /// list in place
+  #[arg(long, env = "GITLAB_TODO_CLEAR_SCREEN")]
+  pub clear_screen: bool,
+
+  /// View preset to show: `all`, `actionable`, `authored`, `reviewing` or one from the config file
+  #[arg(
+    long,
+    env = "GITLAB_TODO_VIEW",
+    default_value = "all",
+    value_name = "NAME"
+  )]
+  pub view: String,
+
+  /// Order by deadline, soonest first; short for `--view agenda`
+  #[arg(long, conflicts_with = "view")]
+  pub agenda: bool,
+
+  /// Run an interactive interface with a selectable list and actions
+  #[arg(short, long, conflicts_with = "format")]
+  pub interactive: bool,
+
+  /// Show a team dashboard for the --group instead of anyone's to-do list, for group and project
+  /// access tokens, which stand for no user; ranks without regard to anyone
+  #[arg(long, env = "GITLAB_TODO_DASHBOARD", requires = "group")]
+  pub dashboard: bool,
+
+  /// The group whose open merge requests the dashboard lists, subgroups included
+  #[arg(
+    long,
+    env = "GITLAB_TODO_GROUP",
+    value_name = "PATH",
+    requires = "dashboard"
+  )]
+  pub group: Option<String>,
+
+  /// Output format; machine-readable formats print once and exit
+  #[arg(long, value_enum, default_value_t = Format::Table)]
+  pub format: Format,
+
+  /// Escape style for colors in `--format prompt`
+  #[arg(long, value_enum, default_value_t = PromptStyle::Plain)]
+  pub prompt_style: PromptStyle,
+
+  /// Maximum age in seconds of a running instance's summary that `--format prompt` reuses
+  #[arg(long, default_value_t = 120, value_name = "SECONDS")]
+  pub prompt_max_age: i64,
+
+  /// List the failed CI jobs beneath merge requests with a failed pipeline
+  #[arg(long, env = "GITLAB_TODO_FAILED_JOBS")]
+  pub failed_jobs: bool,
+
+  /// Show how much of each description's task list is checked off
+  #[arg(long, env = "GITLAB_TODO_TASKS")]
+  pub tasks: bool,
+
+  /// Show how many notes people left on each merge request
+  #[arg(long, env = "GITLAB_TODO_NOTES_COUNT")]
+  pub notes_count: bool,
+
+  /// Show the review effort left on merge requests I review, as S, M or L
+  #[arg(long, env = "GITLAB_TODO_EFFORT")]
+  pub effort: bool,
+
+  /// Most files left to review for an effort of S
+  #[arg(
+    long,
+    env = "GITLAB_TODO_EFFORT_SMALL",
+    default_value_t = 5,
+    value_name = "FILES"
+  )]
+  pub effort_small: usize,
+
+  /// Most files left to review for an effort of M; more are L
+  #[arg(
+    long,
+    env = "GITLAB_TODO_EFFORT_MEDIUM",
+    default_value_t = 20,
+    value_name = "FILES"
+  )]
+  pub effort_medium: usize,
+
+  /// Order rows by this instead of the view's sort
+  #[arg(long, env = "GITLAB_TODO_SORT", value_enum)]
+  pub sort: Option<ViewSort>,
+
+  /// Show how long each phase of the last refresh took
+  #[arg(short, long, env = "GITLAB_TODO_VERBOSE")]
+  pub verbose: bool,
+
+  /// Write refresh timings to this file in the Prometheus text format after every refresh
+  #[arg(long, env = "GITLAB_TODO_METRICS_FILE", value_name = "PATH")]
+  pub metrics_file: Option<PathBuf>,
+
+  /// Save every request and reply to this file, without the token and email addresses
+  #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+  pub record: Option<PathBuf>,
+
+  /// Answer every request from a file written with --record, without any network
+  #[arg(long, value_name = "PATH")]
+  pub replay: Option<PathBuf>,
+
+  /// Show how each merge request's priority was computed
+  #[arg(long, env = "GITLAB_TODO_EXPLAIN")]
+  pub explain: bool,
+
+  /// Options given on the command line or through the environment, which the config file
+  /// must not override
+  #[arg(skip)]
+  pub explicit: HashSet<String>,
+
+  /// Whether hyperlinks are emitted, as resolved from `hyperlinks` for this terminal
+  #[arg(skip)]
+  pub links: bool,
+
+  /// Colors styled output is brought down to, as resolved from `color_depth`
+  #[arg(skip)]
+  pub depth: Depth,
+
+  /// Short names by project path, from `[aliases]` in the config file
+  #[arg(skip)]
+  pub aliases: BTreeMap<String, String>,
+
+  /// The users behind the configured usernames, as resolved once connected
+  #[arg(skip)]
+  pub user_ids: UserIds,
+}
+
+impl Options {
+  /// The options as parsed, noting which came from the command line or the
+  /// environment.
+  pub fn from_matches(matches: &ArgMatches) -> std::result::Result<Self, clap::Error> {
+    let mut options = Options::from_arg_matches(matches)?;
+    options.explicit = matches
+      .ids()
+      .filter(|id| {
+        matches!(
+          matches.value_source(id.as_str()),
+          Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        )
+      })
+      .map(|id| id.to_string())
+      .collect();
+    Ok(options)
+  }
+
+  /// Parses the command line and fills in whatever it and the environment leave open from the
+  /// configuration file.
+  pub fn load() -> Result<(Self, Config)> {
+    let matches = Options::command().get_matches();
+    let mut options = Options::from_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    let config = Config::load(options.config.as_deref())?;
+    for key in config.unknown_keys() {
+      eprintln!("warning: unknown configuration key `{}`", key);
+    }
+    config.apply(&mut options)?;
+    options.links = options.hyperlinks.enabled();
+    options.depth = options.color_depth.resolved();
+    if options.agenda {
+      options.view = "agenda".to_string();
+    }
+    config.view(&options.view)?;
+    config.check_teams(&options)?;
+    Ok((options, config))
+  }
+
+  /// A reference like `group/project!12` or `group/project#3` as shown,
+  /// with the project's alias in place of its path where it has one.
+  pub fn short_reference<'a>(&self, reference: &'a str) -> Cow<'a, str> {
+    let alias = reference
+      .rfind(['!', '#'])
+      .and_then(|at| Some((self.aliases.get(&reference[..at])?, &reference[at..])));
+    match alias {
+      Some((alias, rest)) => Cow::Owned(format!("{}{}", alias, rest)),
+      None => Cow::Borrowed(reference),
+    }
+  }
+
+  pub fn user(&self) -> Result<&str> {
+    self.user.as_deref().ok_or_else(|| {
+      "No GitLab user given; pass it as an argument or set `user` in the config file".into()
+    })
+  }
+}
@@ -0,0 +1,454 @@
+use crate::{
+  clock,
+  gitlab::User,
+  options::Options,
+  priority::Score,
+  progress::{Progress, Tally},
+  sla, Row, Snapshot,
+};
+use chrono::{DateTime, Duration, Utc};
+use std::{fmt::Display, str::FromStr};
+
+// Enough offenders to act on without flooding a CI log.
+const MAX_LISTED: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Metric {
+  /// The longest a merge request has been waiting on its reviewers.
+  ReviewerWait,
+  /// Ready merge requests that nobody was asked to review.
+  Unassigned,
+  Conflicts,
+  Open,
+}
+
+impl Metric {
+  const ALL: [(&'static str, Metric); 4] = [
+    ("reviewer_wait", Metric::ReviewerWait),
+    ("unassigned", Metric::Unassigned),
+    ("conflicts", Metric::Conflicts),
+    ("open", Metric::Open),
+  ];
+
+  fn is_duration(&self) -> bool {
+    *self == Metric::ReviewerWait
+  }
+
+  fn name(&self) -> &'static str {
+    Metric::ALL
+      .iter()
+      .find(|(_, metric)| metric == self)
+      .map_or("", |(name, _)| name)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+  Gt,
+  Ge,
+  Lt,
+  Le,
+  Eq,
+  Ne,
+}
+
+impl Op {
+  // Two-character ones first, so that `>=` isn't read as `>`.
+  const ALL: [(&'static str, Op); 6] = [
+    (">=", Op::Ge),
+    ("<=", Op::Le),
+    ("==", Op::Eq),
+    ("!=", Op::Ne),
+    (">", Op::Gt),
+    ("<", Op::Lt),
+  ];
+
+  fn holds<T: Ord>(&self, left: T, right: T) -> bool {
+    match self {
+      Op::Gt => left > right,
+      Op::Ge => left >= right,
+      Op::Lt => left < right,
+      Op::Le => left <= right,
+      Op::Eq => left == right,
+      Op::Ne => left != right,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Threshold {
+  Count(usize),
+  Duration(Duration),
+}
+
+/// A threshold that fails a CI run once crossed, like `reviewer_wait>3d`,
+/// `unassigned>5` or `conflicts(7d)>0`, where `(7d)` only counts merge
+/// requests older than that.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Policy {
+  text: String,
+  metric: Metric,
+  older_than: Option<Duration>,
+  op: Op,
+  threshold: Threshold,
+}
+
+/// Like `30m`, `12h`, `3d` or `2w`.
+fn parse_duration(input: &str) -> Result<Duration, String> {
+  let split = input
+    .find(|c: char| !c.is_ascii_digit())
+    .ok_or_else(|| format!("`{}` needs a unit: m, h, d or w", input))?;
+  let (amount, unit) = input.split_at(split);
+  let amount: i64 = amount
+    .parse()
+    .map_err(|_| format!("`{}` is not a duration like 3d", input))?;
+  match unit {
+    "m" => Ok(Duration::minutes(amount)),
+    "h" => Ok(Duration::hours(amount)),
+    "d" => Ok(Duration::days(amount)),
+    "w" => Ok(Duration::weeks(amount)),
+    _ => Err(format!(
+      "unknown unit `{}` in `{}`; use m, h, d or w",
+      unit, input
+    )),
+  }
+}
+
+impl FromStr for Policy {
+  type Err = String;
+
+  fn from_str(input: &str) -> Result<Self, Self::Err> {
+    let text: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    let (at, op, symbol) = Op::ALL
+      .iter()
+      .filter_map(|(symbol, op)| text.find(symbol).map(|at| (at, *op, *symbol)))
+      .min_by_key(|(at, _, symbol)| (*at, std::cmp::Reverse(symbol.len())))
+      .ok_or_else(|| {
+        format!(
+          "`{}` has no comparison; write one like `unassigned>5`",
+          input
+        )
+      })?;
+    let (left, right) = (&text[..at], &text[at + symbol.len()..]);
+
+    let (name, older_than) = match left.split_once('(') {
+      Some((name, age)) => {
+        let age = age
+          .strip_suffix(')')
+          .ok_or_else(|| format!("missing `)` after the age in `{}`", input))?;
+        (name, Some(parse_duration(age)?))
+      }
+      None => (left, None),
+    };
+    let metric = Metric::ALL
+      .iter()
+      .find(|(known, _)| *known == name)
+      .map(|(_, metric)| *metric)
+      .ok_or_else(|| {
+        let known: Vec<_> = Metric::ALL.iter().map(|(name, _)| *name).collect();
+        if name.is_empty() {
+          format!(
+            "`{}` has nothing to compare; start with one of {}",
+            input,
+            known.join(", ")
+          )
+        } else {
+          format!("unknown metric `{}`; known are {}", name, known.join(", "))
+        }
+      })?;
+
+    if right.is_empty() {
+      return Err(format!("`{}` has nothing to compare with", input));
+    }
+    let threshold = if metric.is_duration() {
+      Threshold::Duration(parse_duration(right)?)
+    } else {
+      Threshold::Count(right.parse().map_err(|_| {
+        format!(
+          "`{}` counts merge requests, so compare it with a plain number, not `{}`",
+          name, right
+        )
+      })?)
+    };
+
+    Ok(Policy {
+      text,
+      metric,
+      older_than,
+      op,
+      threshold,
+    })
+  }
+}
+
+impl Display for Policy {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(&self.text)
+  }
+}
+
+/// How long a merge request has been waiting on its reviewers: since I was
+/// asked where it waits on me, and otherwise since it last changed, which
+/// is as close as the lists come for other reviewers.
+fn reviewer_wait(
+  row: &Row,
+  user: &User,
+  options: &Options,
+  now: DateTime<Utc>,
+) -> Option<Duration> {
+  if row.mr.reviewers.is_empty() || Progress::of(row) != Progress::AwaitingReview {
+    return None;
+  }
+  sla::waited(row, user, options, now).or(Some(now - row.mr.updated_at))
+}
+
+impl Policy {
+  /// What is off, in a sentence, or None while the policy holds.
+  fn violation(
+    &self,
+    rows: &[(Row, Score)],
+    user: &User,
+    options: &Options,
+    now: DateTime<Utc>,
+  ) -> Option<String> {
+    let rows: Vec<&Row> = rows
+      .iter()
+      .map(|(row, _)| row)
+      .filter(|row| {
+        self
+          .older_than
+          .is_none_or(|age| now - row.mr.created_at > age)
+      })
+      .collect();
+    let (violated, measured, offenders): (bool, String, Vec<&Row>) = match self.threshold {
+      Threshold::Duration(threshold) => {
+        let mut waits: Vec<(Duration, &Row)> = rows
+          .iter()
+          .filter_map(|row| Some((reviewer_wait(row, user, options, now)?, *row)))
+          .collect();
+        waits.sort_by_key(|(wait, _)| std::cmp::Reverse(*wait));
+        let longest = waits.first().map_or(Duration::zero(), |(wait, _)| *wait);
+        let violated = self.op.holds(longest, threshold);
+        let offenders = waits
+          .into_iter()
+          .filter(|(wait, _)| self.op.holds(*wait, threshold))
+          .map(|(_, row)| row)
+          .collect();
+        (violated, sla::label(longest), offenders)
+      }
+      Threshold::Count(threshold) => {
+        let matching: Vec<&Row> = rows
+          .into_iter()
+          .filter(|row| match self.metric {
+            Metric::Unassigned => !row.mr.draft && row.mr.reviewers.is_empty(),
+            Metric::Conflicts => row.mr.has_conflicts,
+            Metric::Open | Metric::ReviewerWait => true,
+          })
+          .collect();
+        let violated = self.op.holds(matching.len(), threshold);
+        (violated, matching.len().to_string(), matching)
+      }
+    };
+    if !violated {
+      return None;
+    }
+    let mut line = format!("{}: {} is {}", self, self.metric.name(), measured);
+    if !offenders.is_empty() {
+      let mut references: Vec<&str> = offenders
+        .iter()
+        .take(MAX_LISTED)
+        .map(|row| row.mr.references.full.as_str())
+        .collect();
+      let more = offenders.len().saturating_sub(MAX_LISTED);
+      let more = format!("{} more", more);
+      if offenders.len() > MAX_LISTED {
+        references.push(&more);
+      }
+      line.push_str(&format!(" ({})", references.join(", ")));
+    }
+    Some(line)
+  }
+}
+
+/// Prints what breaks the policies, and fails if anything does.
+pub fn check(
+  snapshot: &Snapshot,
+  policies: &[Policy],
+  user: &User,
+  options: &Options,
+) -> crate::Result<()> {
+  let now = clock::server_now();
+  let violations: Vec<String> = policies
+    .iter()
+    .filter_map(|policy| policy.violation(&snapshot.rows, user, options, now))
+    .collect();
+  if violations.is_empty() {
+    let rows: Vec<_> = snapshot.rows.iter().collect();
+    println!(
+      "All {} policies hold over {} merge requests: {}",
+      policies.len(),
+      snapshot.rows.len(),
+      Tally::of(&rows)
+    );
+    return Ok(());
+  }
+  for violation in &violations {
+    println!("{}", violation);
+  }
+  Err(
+    format!(
+      "{} of {} policies violated",
+      violations.len(),
+      policies.len()
+    )
+    .into(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::{me, mr, options, row, snapshot};
+  use serde_json::json;
+
+  fn parsed(input: &str) -> Policy {
+    input.parse().unwrap()
+  }
+
+  fn error(input: &str) -> String {
+    input.parse::<Policy>().unwrap_err()
+  }
+
+  #[test]
+  fn policies_read_like_comparisons() {
+    assert_eq!(
+      parsed(" reviewer_wait > 3d "),
+      Policy {
+        text: "reviewer_wait>3d".into(),
+        metric: Metric::ReviewerWait,
+        older_than: None,
+        op: Op::Gt,
+        threshold: Threshold::Duration(Duration::days(3)),
+      }
+    );
+    assert_eq!(
+      parsed("conflicts(2w)>=1"),
+      Policy {
+        text: "conflicts(2w)>=1".into(),
+        metric: Metric::Conflicts,
+        older_than: Some(Duration::weeks(2)),
+        op: Op::Ge,
+        threshold: Threshold::Count(1),
+      }
+    );
+    assert_eq!(parsed("open<=10").op, Op::Le);
+    assert_eq!(parsed("open==0").op, Op::Eq);
+    assert_eq!(parsed("open!=0").op, Op::Ne);
+    assert_eq!(parsed("open<10").op, Op::Lt);
+    assert_eq!(
+      parsed("reviewer_wait>90m").threshold,
+      Threshold::Duration(Duration::minutes(90))
+    );
+    assert_eq!(
+      parsed("reviewer_wait<12h").threshold,
+      Threshold::Duration(Duration::hours(12))
+    );
+    assert_eq!(parsed("unassigned > 5").to_string(), "unassigned>5");
+  }
+
+  #[test]
+  fn mistakes_say_what_to_write() {
+    assert_eq!(
+      error("unassigned=5"),
+      "`unassigned=5` has no comparison; write one like `unassigned>5`"
+    );
+    assert_eq!(
+      error("wait>3d"),
+      "unknown metric `wait`; known are reviewer_wait, unassigned, conflicts, open"
+    );
+    assert_eq!(
+      error(">3"),
+      "`>3` has nothing to compare; start with one of reviewer_wait, unassigned, conflicts, open"
+    );
+    assert_eq!(error("open>"), "`open>` has nothing to compare with");
+    assert_eq!(
+      error("unassigned>3d"),
+      "`unassigned` counts merge requests, so compare it with a plain number, not `3d`"
+    );
+    assert_eq!(
+      error("open<>3"),
+      "`open` counts merge requests, so compare it with a plain number, not `>3`"
+    );
+    assert_eq!(
+      error("conflicts(7d>0"),
+      "missing `)` after the age in `conflicts(7d>0`"
+    );
+    assert_eq!(error("reviewer_wait>3"), "`3` needs a unit: m, h, d or w");
+    assert_eq!(error("reviewer_wait>d"), "`d` is not a duration like 3d");
+    assert_eq!(
+      error("reviewer_wait>3y"),
+      "unknown unit `y` in `3y`; use m, h, d or w"
+    );
+    assert_eq!(
+      error("conflicts(3x)>0"),
+      "unknown unit `x` in `3x`; use m, h, d or w"
+    );
+  }
+
+  fn violation(policy: &str, rows: Vec<Row>) -> Option<String> {
+    let now = "2026-10-14T12:00:00Z".parse().unwrap();
+    parsed(policy).violation(&snapshot(rows).rows, &me(), &options(&[]), now)
+  }
+
+  #[test]
+  fn violations_list_their_offenders() {
+    let reviewer = json!({ "reviewers": [{ "id": 3, "name": "R", "username": "r" }] });
+    let rows = || {
+      vec![
+        row(mr(1, json!({}))),
+        row(mr(2, json!({ "draft": true }))),
+        row(mr(3, reviewer.clone())),
+      ]
+    };
+    assert_eq!(
+      violation("unassigned>0", rows()).as_deref(),
+      Some("unassigned>0: unassigned is 1 (group/project!1)")
+    );
+    assert_eq!(violation("unassigned>1", rows()), None);
+    assert_eq!(
+      violation("open!=3", rows().into_iter().take(2).collect()).as_deref(),
+      Some("open!=3: open is 2 (group/project!1, group/project!2)")
+    );
+    assert_eq!(
+      violation("conflicts==0", rows()).as_deref(),
+      Some("conflicts==0: conflicts is 0")
+    );
+  }
+
+  #[test]
+  fn ages_leave_out_newer_merge_requests() {
+    let conflicting = |iid, created_at| {
+      row(mr(
+        iid,
+        json!({ "has_conflicts": true, "created_at": created_at }),
+      ))
+    };
+    let rows = vec![
+      conflicting(1, "2026-10-01T09:00:00Z"),
+      conflicting(2, "2026-10-13T09:00:00Z"),
+    ];
+    assert_eq!(
+      violation("conflicts(7d)>0", rows).as_deref(),
+      Some("conflicts(7d)>0: conflicts is 1 (group/project!1)")
+    );
+  }
+
+  #[test]
+  fn long_lists_of_offenders_are_cut_short() {
+    let rows = (1..=7).map(|iid| row(mr(iid, json!({})))).collect();
+    let line = violation("open>0", rows).unwrap();
+    assert!(line.starts_with("open>0: open is 7 ("), "{}", line);
+    assert!(line.ends_with(", 2 more)"), "{}", line);
+    assert_eq!(line.matches("group/project!").count(), MAX_LISTED);
+  }
+}
@@ -0,0 +1,408 @@
+use crate::{
+  attention::{attention, Attention},
+  clock,
+  config::Config,
+  gitlab::{Issue, MergeRequest, PipelineStatus, ReviewState, User},
+  options::Options,
+  threads::ThreadCounts,
+  Row,
+};
+use chrono::NaiveDate;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Score {
+  pub terms: Vec<(String, isize)>,
+}
+
+impl Score {
+  fn add(&mut self, reason: impl Into<String>, delta: isize) {
+    if delta != 0 {
+      self.terms.push((reason.into(), delta));
+    }
+  }
+
+  pub fn total(&self) -> isize {
+    self.terms.iter().map(|(_, delta)| delta).sum()
+  }
+}
+
+impl Display for Score {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "= {}", self.total())?;
+    for (reason, delta) in &self.terms {
+      write!(f, ", {:+} {}", delta, reason)?;
+    }
+    Ok(())
+  }
+}
+
+/// The project's default branch is its main branch; the configured names
+/// only stand in where the project couldn't be looked up.
+pub fn targets_main_branch(row: &Row, options: &Options) -> bool {
+  let target = &row.mr.target_branch;
+  match &row.default_branch {
+    Some(default_branch) => default_branch == target,
+    None => options.main_branches.contains(target),
+  }
+}
+
+pub fn pipeline_failed(row: &Row) -> bool {
+  row
+    .pipeline
+    .as_ref()
+    .is_some_and(|pipeline| pipeline.status == PipelineStatus::Failed)
+}
+
+pub fn decay(idle_days: i64, grace_days: i64, points_per_week: isize, cap: isize) -> isize {
+  let overdue_weeks = (idle_days - grace_days).max(0) / 7;
+  (overdue_weeks as isize * points_per_week).min(cap)
+}
+
+pub fn idle_days(mr: &MergeRequest) -> i64 {
+  (clock::server_now() - mr.updated_at).num_days()
+}
+
+pub fn stale_penalty(mr: &MergeRequest, options: &Options) -> isize {
+  options.decay_after.map_or(0, |grace_days| {
+    decay(
+      idle_days(mr),
+      grace_days.into(),
+      options.decay_per_week,
+      options.decay_cap,
+    )
+  })
+}
+
+/// Ranks without anyone's point of view, for the dashboard: what lands on
+/// the main branch and is closest to merging comes first, older before
+/// newer, and conflicts, drafts and blocked ones last.
+pub fn neutral_priority(row: &Row, options: &Options, config: &Config) -> Score {
+  let mr = &row.mr;
+  let project_options = config.project_options(options, mr.project_path());
+  let options = &*project_options;
+  let mut score = Score::default();
+
+  if targets_main_branch(row, options) {
+    score.add("targets main branch", 2);
+  }
+  if row.approvals_known() {
+    match row.approval_info.approvals_left {
+      0 => score.add("fully approved", 2),
+      left => score.add(format!("{} approvals left", left), -(left as isize)),
+    }
+  }
+  let weeks = (clock::server_now() - mr.created_at).num_weeks();
+  if weeks > 0 {
+    score.add(format!("open for {} weeks", weeks), weeks.min(3) as isize);
+  }
+  if mr.has_conflicts {
+    score.add("has conflicts", -1);
+  }
+  if mr.draft {
+    score.add("draft", -2);
+  }
+  if !row.dependencies.blocked_by.is_empty() {
+    score.add("blocked by other MRs", -options.blocked_penalty);
+  }
+  if let Some((pattern, weight)) = config.project_priority(mr.project_path()) {
+    score.add(format!("project matches {}", pattern), weight);
+  }
+  for rule in config.rules.iter().filter(|rule| rule.matches(row)) {
+    score.add(format!("rule {}", rule.name), rule.delta);
+  }
+  score
+}
+
+pub fn priority(row: &Row, user: &User, options: &Options, config: &Config) -> Score {
+  if options.dashboard {
+    return neutral_priority(row, options, config);
+  }
+  let Row {
+    mr,
+    roles,
+    approval_info,
+    ..
+  } = row;
+  let project_options = config.project_options(options, mr.project_path());
+  let options = &*project_options;
+  let approved = approval_info
+    .approved_by
+    .iter()
+    .any(|a| a.user.id == user.id);
+
+  let mut score = Score::default();
+
+  if roles.assigned_by_others() && !mr.draft {
+    score.add("assigned to me", 5);
+  }
+
+  if targets_main_branch(row, options) {
+    score.add("targets main branch", 2);
+  }
+
+  // Anyone in the group could pick it up, so it shouldn't outrank my own work.
+  if row.triage {
+    score.add("needs a reviewer", 1);
+  }
+
+  if roles.author {
+    score.add("authored by me", 1);
+
+    if pipeline_failed(row) {
+      score.add("my pipeline failed", options.failed_pipeline_bonus);
+    }
+
+    if row.changes_requested() {
+      score.add("changes requested", options.changes_requested_bonus);
+    }
+
+    // Nobody else is left to act once it's approved.
+    if let Some(tasks) = mr.tasks().filter(|tasks| !tasks.is_complete()) {
+      if row.approvals_known() && approval_info.approvals_left == 0 {
+        score.add(
+          format!("{} open tasks", tasks.count - tasks.completed_count),
+          options.open_tasks_bonus,
+        );
+      }
+    }
+
+    if attention(row) == Some(Attention::WaitingOnOthers) {
+      score.add("waiting on others", -options.waiting_penalty);
+    }
+  }
+
+  if roles.reviewing() {
+    score.add("review requested", 1);
+    // Unknown on instances that don't track it, which changes nothing.
+    match row.review_state(user) {
+      ReviewState::Unreviewed => score.add("not reviewed yet", 1),
+      ReviewState::Reviewed => score.add("reviewed by me", -1),
+      _ => {}
+    }
+  } else if !approved && !roles.author {
+    if let Some(rule) = row
+      .approval_rules
+      .iter()
+      .find(|rule| !rule.approved && rule.is_eligible(user))
+    {
+      score.add(
+        format!("eligible approver ({})", rule.name),
+        options.eligible_approver_bonus,
+      );
+    }
+  }
+
+  let threads = ThreadCounts::of(row, user);
+  if threads.awaiting_me > 0 {
+    score.add(
+      format!("{} threads awaiting my reply", threads.awaiting_me),
+      options.thread_reply_bonus,
+    );
+  }
+
+  if !row.dependencies.blocked_by.is_empty() {
+    score.add("blocked by other MRs", -options.blocked_penalty);
+  }
+
+  if !row.dependencies.blocking.is_empty() {
+    score.add("blocks other MRs", options.blocking_bonus);
+  }
+
+  if mr.is_from_fork() {
+    score.add("from a fork", options.fork_bonus);
+  }
+
+  if mr.upvotes > 0 {
+    score.add(
+      format!("{} upvotes", mr.upvotes),
+      (mr.upvotes as isize * options.upvote_bonus).min(options.upvote_bonus_cap),
+    );
+  }
+
+  if mr.has_conflicts {
+    score.add("has conflicts", -1);
+  }
+
+  if approved {
+    score.add("approved by me", -1);
+  }
+
+  if row.approvals_known() && approval_info.approvals_left < 1 {
+    score.add("fully approved", -2);
+  }
+
+  if row.merge_train.is_some() {
+    score.add("in merge train", -2);
+  } else if mr.merge_when_pipeline_succeeds {
+    score.add("set to auto-merge", -2);
+  }
+
+  if let Some((pattern, weight)) = config.project_priority(mr.project_path()) {
+    score.add(format!("project matches {}", pattern), weight);
+  }
+
+  if mr.assignees.iter().all(|assignee| {
+    options
+      .bots
+      .iter()
+      .any(|bot| options.user_ids.is(bot, assignee))
+  }) {
+    score.add("assigned to bot", -5);
+  }
+
+  score.add(
+    format!("idle for {} days", idle_days(mr)),
+    -stale_penalty(mr, options),
+  );
+
+  for rule in config.rules.iter().filter(|rule| rule.matches(row)) {
+    score.add(format!("rule {}", rule.name), rule.delta);
+  }
+
+  score
+}
+
+// Labels that make an issue pressing, also as the last part of a scoped
+// label like `priority::high`.
+const PRESSING_LABELS: [(&str, isize); 2] = [("urgent", 3), ("high", 2)];
+
+pub fn issue_priority(issue: &Issue, today: NaiveDate) -> Score {
+  let mut score = Score::default();
+
+  if let Some(due_date) = issue.due_date {
+    let days = (due_date - today).num_days();
+    if days < 0 {
+      score.add(format!("overdue by {} days", -days), 5);
+    } else if days <= 2 {
+      score.add(format!("due in {} days", days), 3);
+    } else if days <= 7 {
+      score.add(format!("due in {} days", days), 1);
+    }
+  }
+
+  for (pressing, bonus) in PRESSING_LABELS {
+    let labeled = issue.labels.iter().any(|label| {
+      label
+        .rsplit("::")
+        .next()
+        .is_some_and(|name| name.eq_ignore_ascii_case(pressing))
+    });
+    if labeled {
+      score.add(format!("labeled {}", pressing), bonus);
+    }
+  }
+
+  score
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::{me, mr, options, row, user_json};
+  use serde_json::json;
+
+  fn reasons(fields: serde_json::Value) -> Vec<String> {
+    let score = priority(
+      &row(mr(1, fields)),
+      &me(),
+      &options(&[]),
+      &Config::default(),
+    );
+    score.terms.into_iter().map(|(reason, _)| reason).collect()
+  }
+
+  #[test]
+  fn decay_starts_after_the_grace_period_and_stops_at_the_cap() {
+    // Two weeks' grace, one point for every full week after, five at most.
+    let curve = |idle_days| decay(idle_days, 14, 1, 5);
+    assert_eq!(curve(0), 0);
+    assert_eq!(curve(14), 0);
+    assert_eq!(curve(20), 0);
+    assert_eq!(curve(21), 1);
+    assert_eq!(curve(35), 3);
+    assert_eq!(curve(120), 5);
+  }
+
+  #[test]
+  fn decay_weighs_weeks_by_the_points_per_week() {
+    assert_eq!(decay(28, 0, 2, 100), 8);
+    assert_eq!(decay(28, 0, 2, 5), 5);
+    assert_eq!(decay(-3, 0, 2, 5), 0);
+  }
+
+  #[test]
+  fn decay_is_off_unless_configured() {
+    let ancient = mr(1, json!({ "updated_at": "2020-01-01T00:00:00Z" }));
+    assert_eq!(stale_penalty(&ancient, &options(&[])), 0);
+    assert!(stale_penalty(&ancient, &options(&["--decay-after", "14"])) > 0);
+  }
+
+  #[test]
+  fn my_own_merge_request_assigned_to_me_scores_as_authored() {
+    let reasons = reasons(json!({
+      "author": user_json(1, "me"),
+      "assignees": [user_json(1, "me")],
+      "reviewers": [user_json(1, "me")],
+    }));
+    assert!(reasons.iter().any(|reason| reason == "authored by me"));
+    assert!(!reasons.iter().any(|reason| reason == "assigned to me"));
+    assert!(!reasons.iter().any(|reason| reason == "review requested"));
+  }
+
+  #[test]
+  fn assigned_to_review_someone_elses_scores_both_roles() {
+    let reasons = reasons(json!({
+      "assignees": [user_json(1, "me")],
+      "reviewers": [user_json(1, "me")],
+    }));
+    assert!(reasons.iter().any(|reason| reason == "assigned to me"));
+    assert!(reasons.iter().any(|reason| reason == "review requested"));
+    assert!(!reasons.iter().any(|reason| reason == "authored by me"));
+  }
+
+  fn targeting(branch: &str, default_branch: Option<&str>) -> Row {
+    let mut row = row(mr(1, json!({ "target_branch": branch })));
+    row.default_branch = default_branch.map(str::to_string);
+    row
+  }
+
+  #[test]
+  fn the_default_branch_is_the_main_branch() {
+    let options = options(&[]);
+    assert!(targets_main_branch(
+      &targeting("develop", Some("develop")),
+      &options
+    ));
+    assert!(targets_main_branch(
+      &targeting("main", Some("main")),
+      &options
+    ));
+  }
+
+  #[test]
+  fn main_branch_names_dont_count_beside_another_default_branch() {
+    let options = options(&[]);
+    assert!(!targets_main_branch(
+      &targeting("main", Some("master")),
+      &options
+    ));
+    assert!(!targets_main_branch(
+      &targeting("master", Some("develop")),
+      &options
+    ));
+  }
+
+  #[test]
+  fn main_branch_names_stand_in_for_an_unknown_default_branch() {
+    let defaults = options(&[]);
+    assert!(targets_main_branch(&targeting("main", None), &defaults));
+    assert!(targets_main_branch(&targeting("master", None), &defaults));
+    assert!(!targets_main_branch(&targeting("develop", None), &defaults));
+    let configured = options(&["--main-branch", "develop"]);
+    assert!(targets_main_branch(
+      &targeting("develop", None),
+      &configured
+    ));
+  }
+}
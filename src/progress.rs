@@ -0,0 +1,57 @@
+use crate::{attention::needs_author, priority::Score, Row};
+use std::fmt::Display;
+
+/// How far a merge request is from being merged, to sum up a milestone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+  Ready,
+  AwaitingReview,
+  Blocked,
+}
+
+impl Progress {
+  /// Blocked while a draft, behind another merge request or on its author,
+  /// as the attention set has it; ready once approved; and otherwise
+  /// awaiting review. Without details, approvals and threads are unknown.
+  pub fn of(row: &Row) -> Self {
+    if row.mr.draft || !row.dependencies.blocked_by.is_empty() || needs_author(row) {
+      Progress::Blocked
+    } else if row.approvals_known() && row.approval_info.approvals_left == 0 {
+      Progress::Ready
+    } else {
+      Progress::AwaitingReview
+    }
+  }
+}
+
+/// How many merge requests of a group are at each step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tally {
+  pub ready: usize,
+  pub awaiting_review: usize,
+  pub blocked: usize,
+}
+
+impl Tally {
+  pub fn of(rows: &[&(Row, Score)]) -> Self {
+    let mut tally = Tally::default();
+    for (row, _) in rows {
+      match Progress::of(row) {
+        Progress::Ready => tally.ready += 1,
+        Progress::AwaitingReview => tally.awaiting_review += 1,
+        Progress::Blocked => tally.blocked += 1,
+      }
+    }
+    tally
+  }
+}
+
+impl Display for Tally {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "{} ready to merge, {} awaiting review, {} blocked",
+      self.ready, self.awaiting_review, self.blocked
+    )
+  }
+}
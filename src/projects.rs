@@ -0,0 +1,104 @@
+use crate::{
+  gitlab::{ApiError, Client, Project, ProjectId},
+  state::{CachedProject, State},
+  Result,
+};
+use chrono::{Duration, Utc};
+use std::collections::{hash_map::Entry, BTreeMap, HashMap};
+
+// Names and default branches rarely change; archiving shows within a day.
+const TTL: Duration = Duration::hours(24);
+const MAX_PERSISTED: usize = 500;
+
+/// Project metadata, fetched once per project and kept in the state file
+/// for a day so that restarts don't ask for it again.
+#[derive(Debug, Default)]
+pub struct ProjectCache {
+  // None for projects we may no longer look at.
+  projects: HashMap<ProjectId, Option<Project>>,
+  persisted: Option<BTreeMap<ProjectId, CachedProject>>,
+  fetched: BTreeMap<ProjectId, CachedProject>,
+}
+
+impl ProjectCache {
+  fn persisted(&mut self, id: ProjectId) -> Option<Project> {
+    let persisted = self.persisted.get_or_insert_with(|| State::load().projects);
+    persisted
+      .get(&id)
+      .filter(|cached| Utc::now() - cached.fetched_at < TTL)
+      .map(|cached| cached.project.clone())
+  }
+
+  pub fn get(&mut self, client: &Client, id: ProjectId) -> Result<Option<&Project>> {
+    if let Entry::Vacant(_) = self.projects.entry(id) {
+      let project = match self.persisted(id) {
+        Some(project) => Some(project),
+        None => match Project::get(client, id) {
+          Ok(project) => {
+            self.fetched.insert(
+              id,
+              CachedProject {
+                project: project.clone(),
+                fetched_at: Utc::now(),
+              },
+            );
+            Some(project)
+          }
+          Err(err) if ApiError::is_inaccessible(&*err) => None,
+          Err(err) => return Err(err),
+        },
+      };
+      self.projects.insert(id, project);
+    }
+    Ok(self.projects[&id].as_ref())
+  }
+
+  pub fn is_archived(&mut self, client: &Client, id: ProjectId) -> Result<bool> {
+    Ok(
+      self
+        .get(client, id)?
+        .is_some_and(|project| project.archived),
+    )
+  }
+
+  pub fn default_branch(&mut self, client: &Client, id: ProjectId) -> Result<Option<String>> {
+    Ok(
+      self
+        .get(client, id)?
+        .and_then(|project| project.default_branch.clone()),
+    )
+  }
+
+  pub fn path(&mut self, client: &Client, id: ProjectId) -> Result<Option<String>> {
+    Ok(
+      self
+        .get(client, id)?
+        .map(|project| project.path_with_namespace.clone()),
+    )
+  }
+
+  /// Writes what was fetched since the last save to the state file, keeping
+  /// only the most recently fetched projects.
+  pub fn save(&mut self) -> Result<()> {
+    if self.fetched.is_empty() {
+      return Ok(());
+    }
+    let fetched = std::mem::take(&mut self.fetched);
+    let projects = State::update(|state| {
+      state.projects.extend(fetched);
+      while state.projects.len() > MAX_PERSISTED {
+        let oldest = state
+          .projects
+          .iter()
+          .min_by_key(|(_, cached)| cached.fetched_at)
+          .map(|(id, _)| *id);
+        if let Some(oldest) = oldest {
+          state.projects.remove(&oldest);
+        }
+      }
+      state.projects.clone()
+    })?;
+    self.persisted = Some(projects);
+    Ok(())
+  }
+}
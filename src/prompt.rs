@@ -0,0 +1,77 @@
+use crate::{
+  dirs::state_dir, gitlab::User, options::Options, priority::Score, tier::Tier, Result, Row,
+};
+use chrono::{DateTime, Duration, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptStyle {
+  Zsh,
+  Bash,
+  Plain,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+  pub generated_at: DateTime<Utc>,
+  pub user: String,
+  pub actionable: usize,
+  pub urgent: usize,
+}
+
+impl Summary {
+  pub fn of(rows: &[(Row, Score)], user: &User, options: &Options) -> Self {
+    // Nothing can be done about merge requests we can no longer see, and the
+    // triage queue is anyone's to pick up.
+    let tiers: Vec<Tier> = rows
+      .iter()
+      .filter(|(row, _)| !row.inaccessible && !row.triage)
+      .map(|(_, score)| Tier::of(score, options))
+      .collect();
+    Summary {
+      generated_at: Utc::now(),
+      user: user.username.clone(),
+      actionable: tiers.iter().filter(|tier| **tier != Tier::Low).count(),
+      urgent: tiers.iter().filter(|tier| **tier == Tier::Action).count(),
+    }
+  }
+
+  fn path() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("summary.json"))
+  }
+
+  pub fn save(&self) -> Result<()> {
+    let path = Self::path().ok_or("Could not determine the state directory")?;
+    if let Some(dir) = path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_vec(self)?)?;
+    Ok(())
+  }
+
+  pub fn load_fresh(user: &str, max_age: Duration) -> Option<Self> {
+    let contents = fs::read(Self::path()?).ok()?;
+    let summary: Summary = serde_json::from_slice(&contents).ok()?;
+    (summary.user == user && Utc::now() - summary.generated_at <= max_age).then_some(summary)
+  }
+
+  pub fn render(&self, style: PromptStyle) -> String {
+    if self.actionable == 0 {
+      return String::new();
+    }
+
+    let (red, yellow, reset) = match style {
+      PromptStyle::Zsh => ("%F{red}", "%F{yellow}", "%f"),
+      PromptStyle::Bash => ("\\[\\e[31m\\]", "\\[\\e[33m\\]", "\\[\\e[0m\\]"),
+      PromptStyle::Plain => ("", "", ""),
+    };
+
+    let mut prompt = format!("{}!{}{}", yellow, self.actionable, reset);
+    if self.urgent > 0 {
+      prompt.push_str(&format!("{}⚑{}{}", red, self.urgent, reset));
+    }
+    prompt
+  }
+}
@@ -0,0 +1,119 @@
+use crate::{
+  events::{transitions, EventKind, Tracked},
+  gitlab::MrId,
+  options::Options,
+  state::{LastRun, State},
+  Result, Row, Snapshot,
+};
+use chrono::Utc;
+use crossterm::style::Stylize;
+use std::collections::{BTreeMap, HashMap};
+
+/// What a merge request looks like now, as far as this snapshot knows.
+/// Rows without details only tell the roles; the rest is kept from before.
+fn tracked(row: &Row, previous: Option<&Tracked>) -> Tracked {
+  if row.detailed {
+    return Tracked::of(row);
+  }
+  Tracked {
+    review_requested: row.roles.reviewing(),
+    assigned: row.roles.assigned_by_others(),
+    ..previous.copied().unwrap_or_default()
+  }
+}
+
+/// What changed since the last run, like `Since yesterday 17:40: 2 new review
+/// requests, 1 of your MRs was approved`. None on the very first run.
+pub fn since_last_run(snapshot: &Snapshot, options: &Options) -> Option<String> {
+  let last_run = State::load().last_run?;
+  let mut counts: HashMap<EventKind, usize> = HashMap::new();
+  let mut assigned = 0;
+  for (row, _) in &snapshot.rows {
+    let previous = last_run.merge_requests.get(&row.mr.id);
+    let current = tracked(row, previous);
+    let kinds = match previous {
+      Some(previous) => transitions(previous, &current),
+      None if current.review_requested => vec![EventKind::NewReviewRequest],
+      None => Vec::new(),
+    };
+    if current.assigned && !previous.is_some_and(|previous| previous.assigned) {
+      assigned += 1;
+    }
+    for kind in kinds {
+      *counts.entry(kind).or_default() += 1;
+    }
+  }
+
+  let count = |kind| counts.get(&kind).copied().unwrap_or(0);
+  let plural = |count: usize, one: &str, many: &str| match count {
+    0 => None,
+    1 => Some(format!("1 {}", one)),
+    count => Some(format!("{} {}", count, many)),
+  };
+  let parts: Vec<String> = [
+    plural(
+      count(EventKind::NewReviewRequest),
+      "new review request",
+      "new review requests",
+    ),
+    plural(assigned, "new assignment", "new assignments"),
+    plural(
+      count(EventKind::MyMrApproved),
+      "of your MRs was approved",
+      "of your MRs were approved",
+    ),
+    plural(
+      count(EventKind::MyMrConflicts),
+      "gained conflicts",
+      "gained conflicts",
+    ),
+    plural(
+      count(EventKind::PipelineFailed),
+      "pipeline failed",
+      "pipelines failed",
+    ),
+  ]
+  .into_iter()
+  .flatten()
+  .collect();
+
+  Some(format!(
+    "Since {}: {}",
+    options.timezone.relative(last_run.at, Utc::now()),
+    if parts.is_empty() {
+      "nothing new".to_string()
+    } else {
+      parts.join(", ")
+    }
+  ))
+}
+
+pub fn banner(recap: &str) -> String {
+  format!(" {} ", recap).bold().reverse().to_string()
+}
+
+/// Keeps this snapshot for the next start to compare against. Merge requests
+/// that may only be missing because a source failed are kept as they were.
+pub fn record(snapshot: &Snapshot) -> Result<()> {
+  State::update(|state| {
+    let previous = state
+      .last_run
+      .take()
+      .map(|last_run| last_run.merge_requests)
+      .unwrap_or_default();
+    let mut merge_requests: BTreeMap<MrId, Tracked> = snapshot
+      .rows
+      .iter()
+      .map(|(row, _)| (row.mr.id, tracked(row, previous.get(&row.mr.id))))
+      .collect();
+    if !snapshot.unavailable.is_empty() {
+      for (id, tracked) in previous {
+        merge_requests.entry(id).or_insert(tracked);
+      }
+    }
+    state.last_run = Some(LastRun {
+      at: Utc::now(),
+      merge_requests,
+    });
+  })
+}
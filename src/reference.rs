@@ -0,0 +1,54 @@
+use crate::gitlab::MrIid;
+use std::{fmt, str::FromStr};
+
+/// A merge request named on the command line, either as `group/project!123` or by its web URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MrRef {
+  pub project: String,
+  pub iid: MrIid,
+}
+
+impl MrRef {
+  /// Project path in the form the API accepts in place of a numeric project ID.
+  pub fn encoded_project(&self) -> String {
+    self.project.replace('/', "%2F")
+  }
+}
+
+impl FromStr for MrRef {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid = || {
+      format!(
+        "Expected `group/project!IID` or a merge request URL, got `{}`",
+        s
+      )
+    };
+
+    let (project, iid) = match s.split_once("://") {
+      Some((_, rest)) => {
+        let (_, path) = rest.split_once('/').ok_or_else(invalid)?;
+        let (project, iid) = path.split_once("/-/merge_requests/").ok_or_else(invalid)?;
+        (project, iid.split(['/', '#', '?']).next().unwrap_or(iid))
+      }
+      None => s.rsplit_once('!').ok_or_else(invalid)?,
+    };
+
+    let iid = iid.parse().map_err(|_| invalid())?;
+    if project.is_empty() {
+      return Err(invalid());
+    }
+
+    Ok(MrRef {
+      project: project.to_string(),
+      iid: MrIid(iid),
+    })
+  }
+}
+
+impl fmt::Display for MrRef {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}!{}", self.project, self.iid)
+  }
+}
@@ -0,0 +1,180 @@
+use crate::{
+  gitlab::{Client, MergeRequest, MrId, Note, User},
+  state::{ReviewRequest, State},
+  Result,
+};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+const MAX_PERSISTED: usize = 500;
+
+/// Who a system note like `requested review from @alice and @bob` asks for
+/// a review, ignoring any `removed review request for …` in the same note.
+fn requested_reviewers(body: &str) -> Vec<&str> {
+  let lower = body.to_lowercase();
+  let Some(start) = lower.find("requested review from") else {
+    return Vec::new();
+  };
+  let rest = &body[start + "requested review from".len()..];
+  let end = rest
+    .to_lowercase()
+    .find("removed review request")
+    .unwrap_or(rest.len());
+  rest[..end]
+    .split_whitespace()
+    .filter_map(|word| word.strip_prefix('@'))
+    .map(|name| name.trim_end_matches([',', '.', ';']))
+    .collect()
+}
+
+/// When `username` was last asked to review, according to the system notes.
+pub fn requested_at(notes: &[Note], username: &str) -> Option<DateTime<Utc>> {
+  notes
+    .iter()
+    .filter(|note| note.system)
+    .filter(|note| requested_reviewers(&note.body).contains(&username))
+    .map(|note| note.created_at)
+    .max()
+}
+
+/// When I was asked to review each merge request, kept in the state file
+/// and only looked up again once the merge request changed.
+#[derive(Debug, Default)]
+pub struct ReviewRequestCache {
+  persisted: Option<BTreeMap<MrId, ReviewRequest>>,
+  fetched: BTreeMap<MrId, ReviewRequest>,
+}
+
+impl ReviewRequestCache {
+  pub fn get(
+    &mut self,
+    client: &Client,
+    user: &User,
+    mr: &MergeRequest,
+  ) -> Result<Option<DateTime<Utc>>> {
+    let persisted = self
+      .persisted
+      .get_or_insert_with(|| State::load().review_requests);
+    let known = self.fetched.get(&mr.id).or_else(|| persisted.get(&mr.id));
+    if let Some(known) = known.filter(|known| known.mr_updated_at == mr.updated_at) {
+      return Ok(known.requested_at);
+    }
+
+    let requested_at = requested_at(&Note::get_system(client, mr)?, &user.username);
+    self.fetched.insert(
+      mr.id,
+      ReviewRequest {
+        requested_at,
+        mr_updated_at: mr.updated_at,
+      },
+    );
+    Ok(requested_at)
+  }
+
+  /// Writes what was looked up since the last save to the state file, keeping
+  /// only the most recently updated merge requests.
+  pub fn save(&mut self) -> Result<()> {
+    if self.fetched.is_empty() {
+      return Ok(());
+    }
+    let fetched = std::mem::take(&mut self.fetched);
+    let review_requests = State::update(|state| {
+      state.review_requests.extend(fetched);
+      while state.review_requests.len() > MAX_PERSISTED {
+        let oldest = state
+          .review_requests
+          .iter()
+          .min_by_key(|(_, known)| known.mr_updated_at)
+          .map(|(id, _)| *id);
+        if let Some(oldest) = oldest {
+          state.review_requests.remove(&oldest);
+        }
+      }
+      state.review_requests.clone()
+    })?;
+    self.persisted = Some(review_requests);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fixtures::mr,
+    mock::{get, MockServer},
+  };
+  use serde_json::{json, Value};
+
+  const NOTES: &str = include_str!("../tests/fixtures/system-notes.json");
+
+  fn notes() -> Vec<Note> {
+    serde_json::from_str(NOTES).unwrap()
+  }
+
+  fn at(timestamp: &str) -> Option<DateTime<Utc>> {
+    timestamp.parse().ok()
+  }
+
+  #[test]
+  fn review_requests_name_everyone_asked() {
+    assert_eq!(
+      requested_reviewers("requested review from @alice and @bob"),
+      ["alice", "bob"]
+    );
+    assert_eq!(
+      requested_reviewers(
+        "requested review from @carol, @erin and removed review request for @bob"
+      ),
+      ["carol", "erin"]
+    );
+    assert_eq!(
+      requested_reviewers("Requested review from @alice."),
+      ["alice"]
+    );
+    assert!(requested_reviewers("removed review request for @bob").is_empty());
+    assert!(requested_reviewers("assigned to @alice").is_empty());
+  }
+
+  #[test]
+  fn the_latest_request_counts() {
+    assert_eq!(requested_at(&notes(), "alice"), at("2026-10-06T10:15:00Z"));
+    assert_eq!(requested_at(&notes(), "erin"), at("2026-10-03T08:00:00Z"));
+  }
+
+  #[test]
+  fn removing_a_request_is_no_request() {
+    assert_eq!(requested_at(&notes(), "bob"), at("2026-10-01T09:05:00Z"));
+  }
+
+  #[test]
+  fn only_system_notes_count() {
+    assert_eq!(requested_at(&notes(), "carol"), at("2026-10-03T08:00:00Z"));
+    assert_eq!(requested_at(&notes(), "dana"), None);
+  }
+
+  #[test]
+  fn system_notes_are_read_from_every_page() {
+    let notes: Vec<Value> = serde_json::from_str(NOTES).unwrap();
+    let (first, second) = notes.split_at(3);
+    let mut first_page = get(
+      "/projects/5/merge_requests/1/notes",
+      Some("page=1"),
+      json!(first),
+    );
+    first_page.headers.insert("x-next-page".into(), "2".into());
+    let server = MockServer::start(vec![
+      first_page,
+      get(
+        "/projects/5/merge_requests/1/notes",
+        Some("page=2"),
+        json!(second),
+      ),
+    ]);
+
+    let system = Note::get_system(&server.client(), &mr(1, json!({}))).unwrap();
+    assert_eq!(system.len(), 5);
+    assert!(system.iter().all(|note| note.system));
+    assert_eq!(requested_at(&system, "alice"), at("2026-10-06T10:15:00Z"));
+  }
+}
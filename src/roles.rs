@@ -0,0 +1,81 @@
+use crate::gitlab::{MergeRequest, User};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Roles {
+  pub author: bool,
+  pub assignee: bool,
+  pub reviewer: bool,
+}
+
+impl Roles {
+  pub fn of(user: &User, mr: &MergeRequest) -> Self {
+    Roles {
+      author: mr.author.id == user.id,
+      assignee: mr.assignees.iter().any(|assignee| assignee.id == user.id),
+      reviewer: mr.reviewers.iter().any(|reviewer| reviewer.id == user.id),
+    }
+  }
+
+  // Being assignee or reviewer of my own merge request says nothing about
+  // someone waiting on me, so those roles only count on other people's work.
+  pub fn assigned_by_others(&self) -> bool {
+    self.assignee && !self.author
+  }
+
+  pub fn reviewing(&self) -> bool {
+    self.reviewer && !self.author
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::{me, mr, user_json};
+  use serde_json::json;
+
+  #[test]
+  fn author_who_is_assignee_and_reviewer_is_only_the_author() {
+    let mine = mr(
+      1,
+      json!({
+        "author": user_json(1, "me"),
+        "assignees": [user_json(1, "me")],
+        "reviewers": [user_json(1, "me"), user_json(3, "bot")],
+      }),
+    );
+    let roles = Roles::of(&me(), &mine);
+    assert_eq!(
+      roles,
+      Roles {
+        author: true,
+        assignee: true,
+        reviewer: true,
+      }
+    );
+    assert!(!roles.assigned_by_others());
+    assert!(!roles.reviewing());
+  }
+
+  #[test]
+  fn assignee_and_reviewer_of_someone_elses_counts_as_both() {
+    let theirs = mr(
+      1,
+      json!({ "assignees": [user_json(1, "me")], "reviewers": [user_json(1, "me")] }),
+    );
+    let roles = Roles::of(&me(), &theirs);
+    assert!(!roles.author);
+    assert!(roles.assigned_by_others());
+    assert!(roles.reviewing());
+  }
+
+  #[test]
+  fn roles_go_by_id_rather_than_name() {
+    let renamed = mr(
+      1,
+      json!({ "reviewers": [user_json(1, "me-renamed"), user_json(4, "me")] }),
+    );
+    let roles = Roles::of(&me(), &renamed);
+    assert!(roles.reviewer);
+    assert!(!roles.assignee);
+  }
+}
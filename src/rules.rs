@@ -0,0 +1,575 @@
+use crate::{clock, gitlab::User, Row};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt::Display};
+
+// Rules are restricted to comparisons over a fixed set of merge request
+// fields, so evaluation can neither loop nor touch anything outside the row.
+const MAX_NODES: usize = 64;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RuleConfig {
+  pub name: String,
+  pub when: String,
+  pub delta: isize,
+  #[serde(flatten, skip_serializing)]
+  pub unknown: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "RuleConfig", into = "RuleConfig")]
+pub struct Rule {
+  pub name: String,
+  pub delta: isize,
+  pub when: String,
+  pub unknown_keys: Vec<String>,
+  expr: Expr,
+}
+
+impl TryFrom<RuleConfig> for Rule {
+  type Error = String;
+
+  fn try_from(config: RuleConfig) -> Result<Self, Self::Error> {
+    let expr = compile(&config.when)
+      .map_err(|err| format!("Rule '{}': {} in `{}`", config.name, err, config.when))?;
+    Ok(Rule {
+      name: config.name,
+      delta: config.delta,
+      when: config.when,
+      unknown_keys: config.unknown.into_keys().collect(),
+      expr,
+    })
+  }
+}
+
+impl From<Rule> for RuleConfig {
+  fn from(rule: Rule) -> Self {
+    RuleConfig {
+      name: rule.name,
+      when: rule.when,
+      delta: rule.delta,
+      unknown: BTreeMap::new(),
+    }
+  }
+}
+
+impl Rule {
+  pub fn matches(&self, row: &Row) -> bool {
+    matches!(self.expr.eval(&Fields { row }), Value::Bool(true))
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+  Bool,
+  Int,
+  Str,
+  List,
+}
+
+impl Display for Type {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      Type::Bool => "boolean",
+      Type::Int => "integer",
+      Type::Str => "string",
+      Type::List => "list",
+    })
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+  Bool(bool),
+  Int(i64),
+  Str(String),
+  List(Vec<String>),
+  /// A field the row doesn't know yet, or the instance doesn't track.
+  Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+  Title,
+  Labels,
+  Project,
+  SourceBranch,
+  TargetBranch,
+  Draft,
+  HasConflicts,
+  Author,
+  Assignees,
+  Reviewers,
+  ApprovalsLeft,
+  AgeDays,
+  IsMeAuthor,
+  IsMeAssignee,
+  IsMeReviewer,
+}
+
+impl Field {
+  fn parse(name: &str) -> Option<Self> {
+    Some(match name {
+      "title" => Field::Title,
+      "labels" => Field::Labels,
+      "project" => Field::Project,
+      "source_branch" => Field::SourceBranch,
+      "target_branch" => Field::TargetBranch,
+      "draft" => Field::Draft,
+      "has_conflicts" => Field::HasConflicts,
+      "author" => Field::Author,
+      "assignees" => Field::Assignees,
+      "reviewers" => Field::Reviewers,
+      "approvals_left" => Field::ApprovalsLeft,
+      "age_days" => Field::AgeDays,
+      "is_me_author" => Field::IsMeAuthor,
+      "is_me_assignee" => Field::IsMeAssignee,
+      "is_me_reviewer" => Field::IsMeReviewer,
+      _ => return None,
+    })
+  }
+
+  fn typ(&self) -> Type {
+    match self {
+      Field::Title | Field::Project | Field::SourceBranch | Field::TargetBranch | Field::Author => {
+        Type::Str
+      }
+      Field::Labels | Field::Assignees | Field::Reviewers => Type::List,
+      Field::ApprovalsLeft | Field::AgeDays => Type::Int,
+      Field::Draft
+      | Field::HasConflicts
+      | Field::IsMeAuthor
+      | Field::IsMeAssignee
+      | Field::IsMeReviewer => Type::Bool,
+    }
+  }
+}
+
+struct Fields<'a> {
+  row: &'a Row,
+}
+
+impl Fields<'_> {
+  fn get(&self, field: Field) -> Value {
+    let mr = &self.row.mr;
+    let names = |users: &[User]| users.iter().map(|u| u.username.clone()).collect();
+    match field {
+      Field::Title => Value::Str(mr.title.clone()),
+      Field::Labels => Value::List(mr.labels.clone()),
+      Field::Project => Value::Str(mr.project_path().to_string()),
+      Field::SourceBranch => Value::Str(mr.source_branch.clone()),
+      Field::TargetBranch => Value::Str(mr.target_branch.clone()),
+      Field::Draft => Value::Bool(mr.draft),
+      Field::HasConflicts => Value::Bool(mr.has_conflicts),
+      Field::Author => Value::Str(mr.author.username.clone()),
+      Field::Assignees => Value::List(names(&mr.assignees)),
+      Field::Reviewers => Value::List(names(&mr.reviewers)),
+      Field::ApprovalsLeft if self.row.approvals_known() => {
+        Value::Int(self.row.approval_info.approvals_left as i64)
+      }
+      Field::ApprovalsLeft => Value::Unknown,
+      Field::AgeDays => Value::Int((clock::server_now() - mr.created_at).num_days()),
+      Field::IsMeAuthor => Value::Bool(self.row.roles.author),
+      Field::IsMeAssignee => Value::Bool(self.row.roles.assignee),
+      Field::IsMeReviewer => Value::Bool(self.row.roles.reviewer),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+  Eq,
+  Ne,
+  Lt,
+  Le,
+  Gt,
+  Ge,
+  Contains,
+  StartsWith,
+  EndsWith,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+  Literal(Value),
+  Field(Field),
+  Not(Box<Expr>),
+  And(Box<Expr>, Box<Expr>),
+  Or(Box<Expr>, Box<Expr>),
+  Compare(Op, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+  fn eval(&self, fields: &Fields) -> Value {
+    let truthy = |expr: &Expr| matches!(expr.eval(fields), Value::Bool(true));
+    match self {
+      Expr::Literal(value) => value.clone(),
+      Expr::Field(field) => fields.get(*field),
+      Expr::Not(expr) => Value::Bool(!truthy(expr)),
+      Expr::And(lhs, rhs) => Value::Bool(truthy(lhs) && truthy(rhs)),
+      Expr::Or(lhs, rhs) => Value::Bool(truthy(lhs) || truthy(rhs)),
+      Expr::Compare(op, lhs, rhs) => Value::Bool(compare(*op, lhs.eval(fields), rhs.eval(fields))),
+    }
+  }
+}
+
+fn compare(op: Op, lhs: Value, rhs: Value) -> bool {
+  match (op, lhs, rhs) {
+    // Not knowing a value is no reason to score a rule either way.
+    (_, Value::Unknown, _) | (_, _, Value::Unknown) => false,
+    (Op::Eq, lhs, rhs) => lhs == rhs,
+    (Op::Ne, lhs, rhs) => lhs != rhs,
+    (Op::Lt, Value::Int(lhs), Value::Int(rhs)) => lhs < rhs,
+    (Op::Le, Value::Int(lhs), Value::Int(rhs)) => lhs <= rhs,
+    (Op::Gt, Value::Int(lhs), Value::Int(rhs)) => lhs > rhs,
+    (Op::Ge, Value::Int(lhs), Value::Int(rhs)) => lhs >= rhs,
+    (Op::Contains, Value::List(items), Value::Str(item)) => items.contains(&item),
+    (Op::Contains, Value::Str(text), Value::Str(part)) => text.contains(&part),
+    (Op::StartsWith, Value::Str(text), Value::Str(prefix)) => text.starts_with(&prefix),
+    (Op::EndsWith, Value::Str(text), Value::Str(suffix)) => text.ends_with(&suffix),
+    _ => false,
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+  Ident(String),
+  Str(String),
+  Int(i64),
+  Op(Op),
+  And,
+  Or,
+  Not,
+  Open,
+  Close,
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, String> {
+  let chars: Vec<char> = input.chars().collect();
+  let mut tokens = Vec::new();
+  let mut pos = 0;
+
+  while pos < chars.len() {
+    let start = pos;
+    let c = chars[pos];
+    let next = chars.get(pos + 1).copied();
+    let token = match (c, next) {
+      (c, _) if c.is_whitespace() => {
+        pos += 1;
+        continue;
+      }
+      ('(', _) => Token::Open,
+      (')', _) => Token::Close,
+      ('=', Some('=')) => Token::Op(Op::Eq),
+      ('!', Some('=')) => Token::Op(Op::Ne),
+      ('<', Some('=')) => Token::Op(Op::Le),
+      ('>', Some('=')) => Token::Op(Op::Ge),
+      ('&', Some('&')) => Token::And,
+      ('|', Some('|')) => Token::Or,
+      ('<', _) => Token::Op(Op::Lt),
+      ('>', _) => Token::Op(Op::Gt),
+      ('!', _) => Token::Not,
+      ('"' | '\'', _) => {
+        let end = chars[pos + 1..]
+          .iter()
+          .position(|other| *other == c)
+          .ok_or_else(|| format!("unterminated string at column {}", start + 1))?;
+        let text: String = chars[pos + 1..pos + 1 + end].iter().collect();
+        pos += end + 2;
+        tokens.push((start, Token::Str(text)));
+        continue;
+      }
+      (c, _) if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+        pos += 1;
+        while chars.get(pos).is_some_and(|c| c.is_ascii_digit()) {
+          pos += 1;
+        }
+        let text: String = chars[start..pos].iter().collect();
+        let value = text
+          .parse()
+          .map_err(|_| format!("invalid number at column {}", start + 1))?;
+        tokens.push((start, Token::Int(value)));
+        continue;
+      }
+      (c, _) if c.is_alphabetic() || c == '_' => {
+        while chars
+          .get(pos)
+          .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+        {
+          pos += 1;
+        }
+        let word: String = chars[start..pos].iter().collect();
+        let token = match word.as_str() {
+          "and" => Token::And,
+          "or" => Token::Or,
+          "not" => Token::Not,
+          "contains" => Token::Op(Op::Contains),
+          "starts_with" => Token::Op(Op::StartsWith),
+          "ends_with" => Token::Op(Op::EndsWith),
+          _ => Token::Ident(word),
+        };
+        tokens.push((start, token));
+        continue;
+      }
+      (c, _) => {
+        return Err(format!(
+          "unexpected character '{}' at column {}",
+          c,
+          start + 1
+        ))
+      }
+    };
+
+    pos += match token {
+      Token::Op(Op::Eq | Op::Ne | Op::Le | Op::Ge) | Token::And | Token::Or => 2,
+      _ => 1,
+    };
+    tokens.push((start, token));
+  }
+
+  Ok(tokens)
+}
+
+struct Parser {
+  tokens: Vec<(usize, Token)>,
+  pos: usize,
+  end: usize,
+  nodes: usize,
+}
+
+impl Parser {
+  fn column(&self) -> usize {
+    self
+      .tokens
+      .get(self.pos)
+      .map_or(self.end, |(column, _)| *column)
+      + 1
+  }
+
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.pos).map(|(_, token)| token)
+  }
+
+  fn node(&mut self, expr: Expr, typ: Type) -> Result<(Expr, Type), String> {
+    self.count(self.column())?;
+    Ok((expr, typ))
+  }
+
+  /// Counts a term towards the limit. Nesting counts as it is entered, so
+  /// that the limit bounds how deep the parser recurses, too.
+  fn count(&mut self, column: usize) -> Result<(), String> {
+    self.nodes += 1;
+    if self.nodes > MAX_NODES {
+      return Err(format!(
+        "expression exceeds {} terms at column {}",
+        MAX_NODES, column
+      ));
+    }
+    Ok(())
+  }
+
+  fn expect_bool(&self, typ: Type, column: usize) -> Result<(), String> {
+    if typ == Type::Bool {
+      Ok(())
+    } else {
+      Err(format!(
+        "expected boolean but found {} at column {}",
+        typ, column
+      ))
+    }
+  }
+
+  fn or(&mut self) -> Result<(Expr, Type), String> {
+    let column = self.column();
+    let (mut lhs, mut typ) = self.and()?;
+    while self.peek() == Some(&Token::Or) {
+      self.expect_bool(typ, column)?;
+      self.pos += 1;
+      let column = self.column();
+      let (rhs, rhs_typ) = self.and()?;
+      self.expect_bool(rhs_typ, column)?;
+      (lhs, typ) = self.node(Expr::Or(Box::new(lhs), Box::new(rhs)), Type::Bool)?;
+    }
+    Ok((lhs, typ))
+  }
+
+  fn and(&mut self) -> Result<(Expr, Type), String> {
+    let column = self.column();
+    let (mut lhs, mut typ) = self.not()?;
+    while self.peek() == Some(&Token::And) {
+      self.expect_bool(typ, column)?;
+      self.pos += 1;
+      let column = self.column();
+      let (rhs, rhs_typ) = self.not()?;
+      self.expect_bool(rhs_typ, column)?;
+      (lhs, typ) = self.node(Expr::And(Box::new(lhs), Box::new(rhs)), Type::Bool)?;
+    }
+    Ok((lhs, typ))
+  }
+
+  fn not(&mut self) -> Result<(Expr, Type), String> {
+    if self.peek() == Some(&Token::Not) {
+      self.count(self.column())?;
+      self.pos += 1;
+      let column = self.column();
+      let (expr, typ) = self.not()?;
+      self.expect_bool(typ, column)?;
+      return Ok((Expr::Not(Box::new(expr)), Type::Bool));
+    }
+    self.compare()
+  }
+
+  fn compare(&mut self) -> Result<(Expr, Type), String> {
+    let (lhs, lhs_typ) = self.primary()?;
+    let op = match self.peek() {
+      Some(Token::Op(op)) => *op,
+      _ => return Ok((lhs, lhs_typ)),
+    };
+    let column = self.column();
+    self.pos += 1;
+    let (rhs, rhs_typ) = self.primary()?;
+
+    let valid = match op {
+      Op::Eq | Op::Ne => lhs_typ == rhs_typ,
+      Op::Lt | Op::Le | Op::Gt | Op::Ge => lhs_typ == Type::Int && rhs_typ == Type::Int,
+      Op::Contains => matches!(lhs_typ, Type::List | Type::Str) && rhs_typ == Type::Str,
+      Op::StartsWith | Op::EndsWith => lhs_typ == Type::Str && rhs_typ == Type::Str,
+    };
+    if !valid {
+      return Err(format!(
+        "cannot compare {} with {} at column {}",
+        lhs_typ, rhs_typ, column
+      ));
+    }
+
+    self.node(Expr::Compare(op, Box::new(lhs), Box::new(rhs)), Type::Bool)
+  }
+
+  fn primary(&mut self) -> Result<(Expr, Type), String> {
+    let column = self.column();
+    let token = self
+      .peek()
+      .cloned()
+      .ok_or_else(|| format!("unexpected end of expression at column {}", column))?;
+    self.pos += 1;
+
+    match token {
+      Token::Str(text) => self.node(Expr::Literal(Value::Str(text)), Type::Str),
+      Token::Int(value) => self.node(Expr::Literal(Value::Int(value)), Type::Int),
+      Token::Ident(name) if name == "true" || name == "false" => {
+        self.node(Expr::Literal(Value::Bool(name == "true")), Type::Bool)
+      }
+      Token::Ident(name) => {
+        let field = Field::parse(&name)
+          .ok_or_else(|| format!("unknown field '{}' at column {}", name, column))?;
+        self.node(Expr::Field(field), field.typ())
+      }
+      Token::Open => {
+        self.count(column)?;
+        let expr = self.or()?;
+        if self.peek() != Some(&Token::Close) {
+          return Err(format!("expected ')' at column {}", self.column()));
+        }
+        self.pos += 1;
+        Ok(expr)
+      }
+      _ => Err(format!("unexpected token at column {}", column)),
+    }
+  }
+}
+
+fn compile(input: &str) -> Result<Expr, String> {
+  let mut parser = Parser {
+    tokens: tokenize(input)?,
+    pos: 0,
+    end: input.chars().count(),
+    nodes: 0,
+  };
+  let (expr, typ) = parser.or()?;
+
+  if parser.peek().is_some() {
+    return Err(format!("unexpected token at column {}", parser.column()));
+  }
+  parser.expect_bool(typ, 1)?;
+
+  Ok(expr)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::{mr, row};
+  use serde_json::json;
+
+  fn matches(when: &str, row: &Row) -> bool {
+    matches!(
+      compile(when).unwrap().eval(&Fields { row }),
+      Value::Bool(true)
+    )
+  }
+
+  fn detailed(approvals_left: usize) -> Row {
+    let mut row = row(mr(1, json!({})));
+    row.detailed = true;
+    row.approvals = true;
+    row.approval_info.approvals_left = approvals_left;
+    row
+  }
+
+  #[test]
+  fn approvals_left_compares_once_known() {
+    let row = detailed(0);
+    assert!(matches("approvals_left == 0", &row));
+    assert!(matches("approvals_left < 1", &row));
+    assert!(!matches("approvals_left > 0", &row));
+  }
+
+  #[test]
+  fn unknown_approvals_left_compares_false_either_way() {
+    let undetailed = row(mr(1, json!({})));
+    let mut without_approvals = detailed(0);
+    without_approvals.approvals = false;
+    for row in [undetailed, without_approvals] {
+      for when in [
+        "approvals_left == 0",
+        "approvals_left != 0",
+        "approvals_left < 1",
+        "approvals_left >= 0",
+      ] {
+        assert!(!matches(when, &row), "{}", when);
+      }
+    }
+  }
+
+  #[test]
+  fn rules_compare_fields_with_literals() {
+    let row = row(mr(
+      1,
+      json!({ "labels": ["release-blocker"], "target_branch": "release/1.0" }),
+    ));
+    assert!(matches(
+      "labels contains 'release-blocker' and target_branch starts_with 'release/'",
+      &row
+    ));
+    assert!(!matches("draft or has_conflicts", &row));
+    assert!(matches("not is_me_author", &row));
+  }
+
+  #[test]
+  fn invalid_rules_say_where() {
+    let error = |when| compile(when).unwrap_err();
+    assert_eq!(
+      error("draft == 1"),
+      "cannot compare boolean with integer at column 7"
+    );
+    assert_eq!(error("nope"), "unknown field 'nope' at column 1");
+    assert_eq!(
+      error("title"),
+      "expected boolean but found string at column 1"
+    );
+    assert_eq!(error("(draft"), "expected ')' at column 7");
+    let nested = format!("{}draft{}", "(".repeat(10_000), ")".repeat(10_000));
+    assert_eq!(error(&nested), "expression exceeds 64 terms at column 65");
+    let negated = format!("{}draft", "!".repeat(10_000));
+    assert_eq!(error(&negated), "expression exceeds 64 terms at column 65");
+  }
+}
@@ -0,0 +1,61 @@
+use crate::Result;
+use keyring::Entry;
+
+/// What `[tokens]` holds for a host whose access token is in the system
+/// keyring rather than in the file.
+pub const IN_KEYRING: &str = "keyring";
+
+const SERVICE: &str = "gitlab-todo";
+
+/// Tokens in the keyring go by host, like they do in the file.
+fn entry(host: &str) -> keyring::Result<Entry> {
+  Entry::new(SERVICE, host)
+}
+
+pub fn store_token(host: &str, token: &str) -> Result<()> {
+  entry(host)?.set_password(token)?;
+  Ok(())
+}
+
+/// The access token for `host` as configured: from the keyring where the
+/// configuration refers to it, and otherwise as written.
+pub fn resolve_token(host: &str, configured: &str) -> Result<String> {
+  if configured != IN_KEYRING {
+    return Ok(configured.to_string());
+  }
+  entry(host)
+    .and_then(|entry| entry.get_password())
+    .map_err(|err| {
+      format!(
+        "Could not read the access token for {} from the system keyring: {}",
+        host, err
+      )
+      .into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn tokens_written_out_are_used_as_they_are() {
+    assert_eq!(
+      resolve_token("gitlab.example.com", "glpat-abc").unwrap(),
+      "glpat-abc"
+    );
+  }
+
+  #[test]
+  fn tokens_missing_from_the_keyring_say_where_they_were_looked_for() {
+    keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+    let err = resolve_token("gitlab.example.com", IN_KEYRING).unwrap_err();
+    assert!(
+      err.to_string().starts_with(
+        "Could not read the access token for gitlab.example.com from the system keyring"
+      ),
+      "{}",
+      err
+    );
+  }
+}
@@ -0,0 +1,291 @@
+use crate::{
+  clock,
+  gitlab::{MrId, ReviewState, User},
+  options::Options,
+  priority::Score,
+  state::State,
+  terminal::printable,
+  Result, Row, Snapshot,
+};
+use chrono::{DateTime, Datelike, Days, Duration, NaiveDateTime, NaiveTime, Utc, Weekday};
+use crossterm::style::Color;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// How a merge request waiting on my review stands against the thresholds.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+  Ok,
+  Warn,
+  Breach,
+}
+
+impl Level {
+  pub fn of(waited: Duration, options: &Options) -> Self {
+    if waited >= options.sla_breach.duration() {
+      Level::Breach
+    } else if waited >= options.sla_warn.duration() {
+      Level::Warn
+    } else {
+      Level::Ok
+    }
+  }
+
+  pub fn color(&self) -> Option<Color> {
+    match self {
+      Level::Ok => None,
+      Level::Warn => Some(Color::DarkYellow),
+      Level::Breach => Some(Color::Red),
+    }
+  }
+}
+
+/// Time between `from` and `until` that falls on none of the `weekend`
+/// days, both given as wall-clock time where the weekdays are told apart.
+pub fn business_time(from: NaiveDateTime, until: NaiveDateTime, weekend: &[Weekday]) -> Duration {
+  let mut total = Duration::zero();
+  let mut start = from;
+  while start < until {
+    let midnight = (start.date() + Days::new(1)).and_time(NaiveTime::MIN);
+    let end = midnight.min(until);
+    if !weekend.contains(&start.weekday()) {
+      total += end - start;
+    }
+    start = end;
+  }
+  total
+}
+
+/// Since when a merge request has been waiting on my review: since I was
+/// asked where a system note says so, or else since it was first listed.
+/// None once I reviewed or approved it, and for drafts.
+pub fn waiting_since(row: &Row, user: &User) -> Option<DateTime<Utc>> {
+  let reviewed = matches!(
+    row.review_state(user),
+    ReviewState::Reviewed | ReviewState::RequestedChanges | ReviewState::Approved
+  );
+  let approved = row
+    .approval_info
+    .approved_by
+    .iter()
+    .any(|approver| approver.user.id == user.id);
+  if !row.roles.reviewing() || row.mr.draft || reviewed || approved {
+    return None;
+  }
+  row.review_requested_at.or(row.first_seen)
+}
+
+/// How long a merge request has been waiting on my review, counting only
+/// business days where configured.
+pub fn waited(row: &Row, user: &User, options: &Options, now: DateTime<Utc>) -> Option<Duration> {
+  let since = waiting_since(row, user)?;
+  Some(if options.business_days {
+    business_time(
+      options.timezone.naive(since),
+      options.timezone.naive(now),
+      &options.weekend,
+    )
+  } else {
+    now - since
+  })
+}
+
+/// Like `5h` or `2.5d`.
+pub fn label(waited: Duration) -> String {
+  let hours = waited.num_minutes() as f64 / 60.0;
+  if hours < 24.0 {
+    format!("{:.0}h", hours)
+  } else {
+    format!("{:.1}d", hours / 24.0)
+  }
+}
+
+/// Remembers when merge requests waiting on my review were first listed,
+/// and forgets those no longer listed unless a source failed. Fills in
+/// `first_seen` on the rows.
+pub fn record_first_seen(rows: &mut [(Row, Score)], complete: bool) -> Result<()> {
+  let now = clock::server_now();
+  let reviewing: Vec<MrId> = rows
+    .iter()
+    .filter(|(row, _)| row.roles.reviewing())
+    .map(|(row, _)| row.mr.id)
+    .collect();
+  let known = State::load().first_seen;
+  let unchanged = reviewing.iter().all(|id| known.contains_key(id))
+    && (!complete || known.keys().all(|id| reviewing.contains(id)));
+  let first_seen: BTreeMap<MrId, DateTime<Utc>> = if unchanged {
+    known
+  } else {
+    State::update(|state| {
+      if complete {
+        state.first_seen.retain(|id, _| reviewing.contains(id));
+      }
+      for id in &reviewing {
+        state.first_seen.entry(*id).or_insert(now);
+      }
+      state.first_seen.clone()
+    })?
+  };
+  for (row, _) in rows {
+    row.first_seen = first_seen.get(&row.mr.id).copied();
+  }
+  Ok(())
+}
+
+/// The merge requests breaching the SLA, most overdue first, and the average
+/// wait of all those waiting on me, as markdown for notes.
+pub fn report(snapshot: &Snapshot, user: &User, options: &Options) -> String {
+  let now = clock::server_now();
+  let waiting: Vec<(&Row, Duration)> = snapshot
+    .rows
+    .iter()
+    .filter_map(|(row, _)| Some((row, waited(row, user, options, now)?)))
+    .collect();
+  let mut breaches: Vec<&(&Row, Duration)> = waiting
+    .iter()
+    .filter(|(_, waited)| Level::of(*waited, options) == Level::Breach)
+    .collect();
+  breaches.sort_by_key(|(row, waited)| (std::cmp::Reverse(*waited), &row.mr.references.full));
+
+  let unit = if options.business_days {
+    "business time"
+  } else {
+    "time"
+  };
+  let mut text = format!(
+    "# Review SLA for @{}\n\nOver {} ({}):\n\n",
+    user.username, options.sla_breach, unit
+  );
+  if breaches.is_empty() {
+    text.push_str("None.\n");
+  }
+  for (row, waited) in breaches {
+    text.push_str(&format!(
+      "- [{}]({}) {}: waiting {}, {} over\n",
+      row.mr.references.full,
+      row.mr.web_url,
+      printable(&row.mr.title),
+      label(*waited),
+      label(*waited - options.sla_breach.duration())
+    ));
+  }
+  if !waiting.is_empty() {
+    let total: Duration = waiting.iter().map(|(_, waited)| *waited).sum();
+    text.push_str(&format!(
+      "\nAverage wait: {} over {} merge request{}\n",
+      label(total / waiting.len() as i32),
+      waiting.len(),
+      if waiting.len() == 1 { "" } else { "s" }
+    ));
+  }
+  text
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::{me, mr, options, row, user_json};
+  use serde_json::json;
+
+  const WEEKEND: [Weekday; 2] = [Weekday::Sat, Weekday::Sun];
+
+  /// Wall-clock time in the week of Friday, 2026-10-16.
+  fn at(time: &str) -> NaiveDateTime {
+    time.parse().unwrap()
+  }
+
+  #[test]
+  fn weekends_dont_count_from_friday_to_monday() {
+    assert_eq!(
+      business_time(
+        at("2026-10-16T16:00:00"),
+        at("2026-10-19T10:00:00"),
+        &WEEKEND
+      ),
+      Duration::hours(8 + 10)
+    );
+    // A week round is five days.
+    assert_eq!(
+      business_time(
+        at("2026-10-16T16:00:00"),
+        at("2026-10-23T16:00:00"),
+        &WEEKEND
+      ),
+      Duration::days(5)
+    );
+  }
+
+  #[test]
+  fn waits_starting_on_a_weekend_start_on_monday() {
+    assert_eq!(
+      business_time(
+        at("2026-10-17T12:00:00"),
+        at("2026-10-19T09:00:00"),
+        &WEEKEND
+      ),
+      Duration::hours(9)
+    );
+    assert_eq!(
+      business_time(
+        at("2026-10-17T12:00:00"),
+        at("2026-10-18T23:00:00"),
+        &WEEKEND
+      ),
+      Duration::zero()
+    );
+  }
+
+  #[test]
+  fn the_weekend_can_be_other_days() {
+    let weekend = [Weekday::Fri, Weekday::Sat];
+    // Thursday evening to Sunday morning.
+    assert_eq!(
+      business_time(
+        at("2026-10-15T20:00:00"),
+        at("2026-10-18T08:00:00"),
+        &weekend
+      ),
+      Duration::hours(4 + 8)
+    );
+    assert_eq!(
+      business_time(at("2026-10-16T16:00:00"), at("2026-10-19T10:00:00"), &[]),
+      Duration::hours(66)
+    );
+  }
+
+  #[test]
+  fn nothing_passes_backwards() {
+    assert_eq!(
+      business_time(
+        at("2026-10-19T10:00:00"),
+        at("2026-10-16T16:00:00"),
+        &WEEKEND
+      ),
+      Duration::zero()
+    );
+  }
+
+  #[test]
+  fn business_days_count_only_where_configured() {
+    let mut row = row(mr(1, json!({ "reviewers": [user_json(1, "me")] })));
+    row.review_requested_at = "2026-10-16T16:00:00Z".parse().ok();
+    let now = "2026-10-19T10:00:00Z".parse().unwrap();
+    let waited = |args: &[&str]| waited(&row, &me(), &options(args), now).unwrap();
+    assert_eq!(waited(&["--timezone", "utc"]), Duration::hours(66));
+    assert_eq!(
+      waited(&["--timezone", "utc", "--business-days"]),
+      Duration::hours(18)
+    );
+    assert_eq!(
+      waited(&[
+        "--timezone",
+        "utc",
+        "--business-days",
+        "--weekend",
+        "sun,mon"
+      ]),
+      Duration::hours(8 + 24)
+    );
+  }
+}
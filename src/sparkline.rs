@@ -0,0 +1,119 @@
+use crate::gitlab::{Discussion, MergeRequest};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeSet;
+
+/// The days of activity shown in the detail pane.
+pub const DAYS: usize = 7;
+
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// One bar per day up to `now`, the oldest first and each as high as its
+/// share of the busiest day. Days without anything stay at the bottom, and
+/// times outside the window or in the future are left out.
+pub fn sparkline(times: &[DateTime<Utc>], now: DateTime<Utc>, days: usize) -> String {
+  let mut counts = vec![0usize; days];
+  for time in times.iter().filter(|time| **time <= now) {
+    let ago = (now - *time).num_days() as usize;
+    if ago < days {
+      counts[days - 1 - ago] += 1;
+    }
+  }
+  let busiest = counts.iter().copied().max().unwrap_or(0).max(1);
+  counts
+    .into_iter()
+    .map(|count| BARS[count * (BARS.len() - 1) / busiest])
+    .collect()
+}
+
+/// The notes column, like `💬12`; empty without any notes.
+pub fn notes_label(mr: &MergeRequest) -> String {
+  match mr.user_notes_count {
+    0 => String::new(),
+    count => format!("💬{}", count),
+  }
+}
+
+/// When the notes by people, rather than by GitLab itself, were written.
+pub fn note_times(discussions: &[Discussion]) -> Vec<DateTime<Utc>> {
+  discussions
+    .iter()
+    .flat_map(|discussion| &discussion.notes)
+    .filter(|note| !note.system)
+    .filter_map(|note| note.created_at)
+    .collect()
+}
+
+/// How many people wrote notes.
+pub fn participants(discussions: &[Discussion]) -> usize {
+  discussions
+    .iter()
+    .flat_map(|discussion| &discussion.notes)
+    .filter(|note| !note.system)
+    .map(|note| note.author.id)
+    .collect::<BTreeSet<_>>()
+    .len()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::mr;
+  use chrono::Duration;
+  use serde_json::json;
+
+  const DISCUSSIONS: &str = include_str!("../tests/fixtures/discussions.json");
+
+  fn now() -> DateTime<Utc> {
+    "2026-10-14T12:00:00Z".parse().unwrap()
+  }
+
+  /// Times `days` days and an hour before now, one for each entry.
+  fn ago(days: &[i64]) -> Vec<DateTime<Utc>> {
+    days
+      .iter()
+      .map(|days| now() - Duration::days(*days) - Duration::hours(1))
+      .collect()
+  }
+
+  #[test]
+  fn quiet_weeks_stay_flat() {
+    assert_eq!(sparkline(&[], now(), DAYS), "▁▁▁▁▁▁▁");
+    assert_eq!(sparkline(&ago(&[7, 30]), now(), DAYS), "▁▁▁▁▁▁▁");
+    assert_eq!(sparkline(&[], now(), 0), "");
+  }
+
+  #[test]
+  fn a_single_day_is_the_busiest() {
+    assert_eq!(sparkline(&ago(&[0]), now(), DAYS), "▁▁▁▁▁▁█");
+    assert_eq!(sparkline(&ago(&[3, 3, 3]), now(), DAYS), "▁▁▁█▁▁▁");
+  }
+
+  #[test]
+  fn days_are_shares_of_the_busiest() {
+    let mut times = ago(&[0, 0, 0, 0, 2, 2, 6, 7]);
+    times.push(now() + Duration::hours(1));
+    assert_eq!(sparkline(&times, now(), DAYS), "▂▁▁▁▄▁█");
+    // Days count back from now, not by the calendar.
+    let times = [now() - Duration::hours(23), now() - Duration::hours(24)];
+    assert_eq!(sparkline(&times, now(), 2), "██");
+  }
+
+  #[test]
+  fn notes_by_people_are_activity() {
+    let discussions: Vec<Discussion> = serde_json::from_str(DISCUSSIONS).unwrap();
+    let times = note_times(&discussions);
+    assert_eq!(times.len(), 9);
+    assert_eq!(participants(&discussions), 3);
+    let evening = "2026-10-14T23:00:00Z".parse().unwrap();
+    assert_eq!(sparkline(&times, evening, DAYS), "▁█▁▁▁▁▁");
+  }
+
+  #[test]
+  fn notes_show_once_there_are_some() {
+    assert_eq!(notes_label(&mr(1, json!({}))), "");
+    assert_eq!(
+      notes_label(&mr(1, json!({ "user_notes_count": 12 }))),
+      "💬12"
+    );
+  }
+}
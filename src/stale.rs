@@ -0,0 +1,38 @@
+use crate::{
+  options::Options,
+  state::{LastView, State},
+  table::Line,
+  Result,
+};
+use chrono::Utc;
+use crossterm::style::Stylize;
+
+/// Keeps the rendered list, so that the next start can show it right away.
+pub fn save(view: &str, lines: &[Line]) -> Result<()> {
+  let last_view = LastView {
+    view: view.to_string(),
+    rendered_at: Utc::now(),
+    lines: lines.iter().map(|line| line.text.clone()).collect(),
+  };
+  State::update(|state| state.last_view = Some(last_view))
+}
+
+/// The list from the last run of the same view, unless that's turned off.
+pub fn load(options: &Options) -> Option<LastView> {
+  if options.no_stale_start {
+    return None;
+  }
+  State::load()
+    .last_view
+    .filter(|last_view| last_view.view == options.view)
+}
+
+/// In inverse video, so that old data is never mistaken for current.
+pub fn banner(last_view: &LastView, options: &Options) -> String {
+  format!(
+    " stale — from {}, refreshing… ",
+    options.timezone.datetime(last_view.rendered_at)
+  )
+  .reverse()
+  .to_string()
+}
@@ -0,0 +1,268 @@
+use crate::{
+  dirs::state_dir,
+  events::Tracked,
+  focus::Focus,
+  gitlab::{MrId, Project, ProjectId, UserId},
+  Result,
+};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fs::{self, OpenOptions},
+  io::ErrorKind,
+  path::{Path, PathBuf},
+  thread::sleep,
+  time::{Duration, SystemTime},
+};
+
+const VERSION: u32 = 1;
+
+// Two instances rarely write at the same moment; waiting this long for the
+// other one is plenty, and a lock older than that was left behind.
+const LOCK_ATTEMPTS: u32 = 20;
+const LOCK_RETRY: Duration = Duration::from_millis(50);
+const STALE_LOCK: Duration = Duration::from_secs(10);
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateSection {
+  Snoozes,
+  Mutes,
+  Seen,
+  Notified,
+  Focus,
+  Projects,
+  ReviewRequests,
+  LastView,
+  LastRun,
+  FirstSeen,
+  Users,
+  All,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CachedProject {
+  pub project: Project,
+  pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReviewRequest {
+  /// None where no system note says so.
+  pub requested_at: Option<DateTime<Utc>>,
+  /// The merge request as it was when looked up, to tell when to look again.
+  pub mr_updated_at: DateTime<Utc>,
+}
+
+/// The user a configured username belonged to when looked up, or None
+/// where it belonged to nobody.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CachedUser {
+  pub id: Option<UserId>,
+  pub fetched_at: DateTime<Utc>,
+}
+
+/// The list as last rendered, to show while the first refresh runs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LastView {
+  pub view: String,
+  pub rendered_at: DateTime<Utc>,
+  pub lines: Vec<String>,
+}
+
+/// What the merge requests looked like at the end of the last run, to sum up
+/// what changed since on the next start.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LastRun {
+  pub at: DateTime<Utc>,
+  pub merge_requests: BTreeMap<MrId, Tracked>,
+}
+
+/// Everything kept locally between runs, in one file shared by all features.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct State {
+  pub version: u32,
+  /// Merge requests hidden until the given time.
+  pub snoozes: BTreeMap<MrId, DateTime<Utc>>,
+  /// Merge requests hidden for good.
+  pub mutes: BTreeSet<MrId>,
+  /// When I last looked at each merge request.
+  pub seen: BTreeMap<MrId, DateTime<Utc>>,
+  /// Merge requests already alerted on.
+  pub notified: BTreeSet<MrId>,
+  pub focus: Option<Focus>,
+  /// Project metadata, to spare a lookup per project on every start.
+  pub projects: BTreeMap<ProjectId, CachedProject>,
+  /// When I was asked to review merge requests.
+  pub review_requests: BTreeMap<MrId, ReviewRequest>,
+  pub last_view: Option<LastView>,
+  /// Dropped rather than failing the whole state when its shape changed.
+  #[serde(deserialize_with = "lenient")]
+  pub last_run: Option<LastRun>,
+  /// When each merge request waiting on my review was first listed, for
+  /// those where no system note tells when I was asked.
+  pub first_seen: BTreeMap<MrId, DateTime<Utc>>,
+  /// The users behind the usernames in the configuration, by lowercased
+  /// username, to spare looking them up on every start.
+  pub users: BTreeMap<String, CachedUser>,
+  /// Sections written by newer versions, kept as they are.
+  #[serde(flatten)]
+  unknown: BTreeMap<String, Value>,
+}
+
+impl Default for State {
+  fn default() -> Self {
+    State {
+      version: VERSION,
+      snoozes: BTreeMap::new(),
+      mutes: BTreeSet::new(),
+      seen: BTreeMap::new(),
+      notified: BTreeSet::new(),
+      focus: None,
+      projects: BTreeMap::new(),
+      review_requests: BTreeMap::new(),
+      last_view: None,
+      last_run: None,
+      first_seen: BTreeMap::new(),
+      users: BTreeMap::new(),
+      unknown: BTreeMap::new(),
+    }
+  }
+}
+
+impl State {
+  pub fn path() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("state.json"))
+  }
+
+  /// Never fails: a missing file is an empty state, and a corrupt one is
+  /// moved aside with a warning so that it can be inspected.
+  pub fn load() -> Self {
+    let Some(path) = Self::path() else {
+      return State::default();
+    };
+    let contents = match fs::read(&path) {
+      Ok(contents) => contents,
+      Err(err) if err.kind() == ErrorKind::NotFound => return State::default(),
+      Err(err) => {
+        eprintln!("warning: could not read {}: {}", path.display(), err);
+        return State::default();
+      }
+    };
+    match serde_json::from_slice::<State>(&contents) {
+      Ok(state) => {
+        if state.version > VERSION {
+          eprintln!(
+            "warning: {} was written by a newer version; unknown parts are kept as they are",
+            path.display()
+          );
+        }
+        state
+      }
+      Err(err) => {
+        let backup = path.with_extension("json.corrupt");
+        eprintln!(
+          "warning: {} is corrupt ({}); moved it to {} and starting afresh",
+          path.display(),
+          err,
+          backup.display()
+        );
+        let _ = fs::rename(&path, backup);
+        State::default()
+      }
+    }
+  }
+
+  /// Applies a change to the state on disk, re-reading it under a lock so
+  /// that changes made by another instance in the meantime aren't lost.
+  pub fn update<T>(change: impl FnOnce(&mut State) -> T) -> Result<T> {
+    let path = Self::path().ok_or("Could not determine the state directory")?;
+    if let Some(dir) = path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+    let lock = Lock::acquire(&path.with_extension("lock"));
+
+    let mut state = Self::load();
+    let result = change(&mut state);
+    state.version = state.version.max(VERSION);
+
+    // Readers must never see a partly written file.
+    let partial = path.with_extension("json.tmp");
+    fs::write(&partial, serde_json::to_vec_pretty(&state)?)?;
+    fs::rename(partial, &path)?;
+    drop(lock);
+    Ok(result)
+  }
+
+  pub fn clear(&mut self, section: StateSection) {
+    match section {
+      StateSection::Snoozes => self.snoozes.clear(),
+      StateSection::Mutes => self.mutes.clear(),
+      StateSection::Seen => self.seen.clear(),
+      StateSection::Notified => self.notified.clear(),
+      StateSection::Focus => self.focus = None,
+      StateSection::Projects => self.projects.clear(),
+      StateSection::ReviewRequests => self.review_requests.clear(),
+      StateSection::LastView => self.last_view = None,
+      StateSection::LastRun => self.last_run = None,
+      StateSection::FirstSeen => self.first_seen.clear(),
+      StateSection::Users => self.users.clear(),
+      StateSection::All => *self = State::default(),
+    }
+  }
+}
+
+fn lenient<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+  D: Deserializer<'de>,
+  T: DeserializeOwned,
+{
+  let value = Value::deserialize(deserializer)?;
+  Ok(serde_json::from_value(value).ok())
+}
+
+/// A lock file held while the state is rewritten. Should another instance
+/// hold it for too long, the last writer wins and a warning says so.
+struct Lock(Option<PathBuf>);
+
+impl Lock {
+  fn acquire(path: &Path) -> Self {
+    for _ in 0..LOCK_ATTEMPTS {
+      match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(_) => return Lock(Some(path.to_path_buf())),
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+          if is_stale(path) {
+            let _ = fs::remove_file(path);
+            continue;
+          }
+          sleep(LOCK_RETRY);
+        }
+        Err(_) => break,
+      }
+    }
+    eprintln!(
+      "warning: could not lock {}; another instance's changes may be overwritten",
+      path.display()
+    );
+    Lock(None)
+  }
+}
+
+fn is_stale(path: &Path) -> bool {
+  fs::metadata(path)
+    .and_then(|metadata| metadata.modified())
+    .ok()
+    .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+    .is_some_and(|age| age > STALE_LOCK)
+}
+
+impl Drop for Lock {
+  fn drop(&mut self) {
+    if let Some(path) = &self.0 {
+      let _ = fs::remove_file(path);
+    }
+  }
+}
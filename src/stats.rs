@@ -0,0 +1,142 @@
+use crate::Result;
+use std::{
+  fs,
+  path::Path,
+  time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+  Events,
+  Lists,
+  Approvals,
+  Pipelines,
+  Threads,
+  Render,
+}
+
+impl Phase {
+  pub const ALL: [Phase; 6] = [
+    Phase::Events,
+    Phase::Lists,
+    Phase::Approvals,
+    Phase::Pipelines,
+    Phase::Threads,
+    Phase::Render,
+  ];
+
+  fn name(&self) -> &'static str {
+    match self {
+      Phase::Events => "events",
+      Phase::Lists => "lists",
+      Phase::Approvals => "approvals",
+      Phase::Pipelines => "pipelines",
+      Phase::Threads => "threads",
+      Phase::Render => "render",
+    }
+  }
+}
+
+/// Time spent in each phase of one refresh, and how much it downloaded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RefreshStats {
+  durations: [Duration; Phase::ALL.len()],
+  /// Bytes of response bodies, not counting replies answered from coalescing.
+  pub received: u64,
+}
+
+impl RefreshStats {
+  pub fn time<T>(&mut self, phase: Phase, run: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = run();
+    self.durations[phase as usize] += start.elapsed();
+    result
+  }
+
+  pub fn get(&self, phase: Phase) -> Duration {
+    self.durations[phase as usize]
+  }
+
+  pub fn total(&self) -> Duration {
+    self.durations.iter().sum()
+  }
+
+  pub fn breakdown(&self) -> String {
+    let mut parts: Vec<String> = Phase::ALL
+      .iter()
+      .map(|phase| format!("{} {:.1}s", phase.name(), self.get(*phase).as_secs_f64()))
+      .collect();
+    parts.push(format!("{} received", bytes(self.received)));
+    parts.join(", ")
+  }
+
+  /// Writes the timings in the Prometheus text format, for node exporter's textfile collector.
+  pub fn write_metrics(&self, path: &Path, merge_requests: usize) -> Result<()> {
+    let mut metrics = String::from(
+      "# HELP gitlab_todo_refresh_seconds Time spent in each phase of the last refresh.\n\
+       # TYPE gitlab_todo_refresh_seconds gauge\n",
+    );
+    for phase in Phase::ALL {
+      metrics.push_str(&format!(
+        "gitlab_todo_refresh_seconds{{phase=\"{}\"}} {:.3}\n",
+        phase.name(),
+        self.get(phase).as_secs_f64()
+      ));
+    }
+    metrics.push_str(&format!(
+      "# HELP gitlab_todo_merge_requests Merge requests listed by the last refresh.\n\
+       # TYPE gitlab_todo_merge_requests gauge\n\
+       gitlab_todo_merge_requests {}\n",
+      merge_requests
+    ));
+    metrics.push_str(&format!(
+      "# HELP gitlab_todo_refresh_received_bytes Bytes of API responses received by the last refresh.\n\
+       # TYPE gitlab_todo_refresh_received_bytes gauge\n\
+       gitlab_todo_refresh_received_bytes {}\n",
+      self.received
+    ));
+
+    // The collector may read at any moment, so never expose a partial file.
+    let partial = path.with_extension("prom.tmp");
+    fs::write(&partial, metrics)?;
+    fs::rename(partial, path)?;
+    Ok(())
+  }
+}
+
+fn bytes(count: u64) -> String {
+  match count {
+    0..=1023 => format!("{} B", count),
+    1024..=1_048_575 => format!("{:.0} KB", count as f64 / 1024.0),
+    _ => format!("{:.1} MB", count as f64 / 1_048_576.0),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn phases_add_up_across_calls() {
+    let mut stats = RefreshStats::default();
+    let nap = Duration::from_millis(5);
+    assert_eq!(stats.time(Phase::Lists, || 42), 42);
+    stats.time(Phase::Lists, || std::thread::sleep(nap));
+    stats.time(Phase::Approvals, || std::thread::sleep(nap));
+    assert!(stats.get(Phase::Lists) >= nap);
+    assert!(stats.total() >= nap * 2);
+    assert_eq!(stats.get(Phase::Render), Duration::ZERO);
+  }
+
+  #[test]
+  fn breakdowns_name_every_phase() {
+    let stats = RefreshStats {
+      received: 2048,
+      ..Default::default()
+    };
+    assert_eq!(
+      stats.breakdown(),
+      "events 0.0s, lists 0.0s, approvals 0.0s, pipelines 0.0s, threads 0.0s, render 0.0s, 2 KB received"
+    );
+  }
+}
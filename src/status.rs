@@ -0,0 +1,81 @@
+use crate::gitlab::{Client, User, UserId, UserStatus};
+use std::{
+  collections::HashMap,
+  time::{Duration, Instant},
+};
+
+/// Statuses change over a day, not within minutes.
+const TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Phrases in a status message that say someone is away.
+const AWAY: [&str; 5] = [
+  "out of office",
+  "vacation",
+  "holiday",
+  "on leave",
+  "parental leave",
+];
+
+impl UserStatus {
+  pub fn is_busy(&self) -> bool {
+    self.availability.as_deref() == Some("busy")
+  }
+
+  /// Whether the message says they are out of office.
+  pub fn is_away(&self) -> bool {
+    let emoji_away = matches!(
+      self.emoji.as_deref(),
+      Some("palm_tree" | "airplane" | "beach_with_umbrella" | "face_with_thermometer")
+    );
+    let message = self.message.as_deref().unwrap_or_default().to_lowercase();
+    emoji_away
+      || message
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word == "ooo")
+      || AWAY.iter().any(|phrase| message.contains(phrase))
+  }
+
+  /// Whether a review from them is not coming soon.
+  pub fn is_unavailable(&self) -> bool {
+    self.is_busy() || self.is_away()
+  }
+
+  /// What goes next to their name: `⛔busy`, or else the status emoji.
+  pub fn marker(&self) -> Option<String> {
+    if self.is_busy() {
+      return Some("⛔busy".to_string());
+    }
+    self.emoji.as_deref().map(|emoji| match emoji {
+      "palm_tree" => "🌴".to_string(),
+      "airplane" => "✈️".to_string(),
+      "beach_with_umbrella" => "🏖️".to_string(),
+      "face_with_thermometer" => "🤒".to_string(),
+      "coffee" => "☕".to_string(),
+      "house" => "🏠".to_string(),
+      "calendar" | "spiral_calendar_pad" => "📆".to_string(),
+      "speech_balloon" => "💬".to_string(),
+      other => format!(":{}:", other),
+    })
+  }
+}
+
+/// The statuses of reviewers, looked up at most once an hour each.
+#[derive(Debug, Default)]
+pub struct StatusCache {
+  statuses: HashMap<UserId, (Instant, Option<UserStatus>)>,
+}
+
+impl StatusCache {
+  /// None where the status can't be had, which is not asked again within
+  /// the hour either.
+  pub fn get(&mut self, client: &Client, user: UserId) -> Option<UserStatus> {
+    match self.statuses.get(&user) {
+      Some((fetched_at, status)) if fetched_at.elapsed() < TTL => status.clone(),
+      _ => {
+        let status = User::get_status(client, user).ok();
+        self.statuses.insert(user, (Instant::now(), status.clone()));
+        status
+      }
+    }
+  }
+}
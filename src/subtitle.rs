@@ -0,0 +1,275 @@
+/// The first line of a description worth showing under the title, as plain
+/// text. Lines starting with one of `skip`, like the headings of a merge
+/// request template, are passed over, as are HTML comments, fenced code and
+/// lines without any letters or digits once stripped, like rules.
+pub fn subtitle(description: &str, skip: &[String]) -> Option<String> {
+  let skip: Vec<String> = skip.iter().map(|prefix| prefix.to_lowercase()).collect();
+  let mut in_fence = false;
+  let mut in_comment = false;
+  for line in description.lines() {
+    let line = line.trim();
+    if in_comment {
+      in_comment = !line.contains("-->");
+      continue;
+    }
+    if line.starts_with("```") || line.starts_with("~~~") {
+      in_fence = !in_fence;
+      continue;
+    }
+    if in_fence {
+      continue;
+    }
+    if line.starts_with("<!--") && !line.contains("-->") {
+      in_comment = true;
+      continue;
+    }
+    let lowercase = line.to_lowercase();
+    if skip.iter().any(|prefix| lowercase.starts_with(prefix)) {
+      continue;
+    }
+    let text = strip_markdown(line);
+    if text.chars().any(char::is_alphanumeric) {
+      return Some(text);
+    }
+  }
+  None
+}
+
+/// A line of markdown as it reads: headings, quotes and list markers go,
+/// links and images become their text, code spans their code, emphasis and
+/// HTML tags are dropped and runs of spaces shrink to one.
+pub fn strip_markdown(line: &str) -> String {
+  let mut text = String::new();
+  inline(block_content(line.trim()), &mut text);
+  text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// What follows the markers of a heading, quote, list item or task.
+fn block_content(mut line: &str) -> &str {
+  loop {
+    let rest = if let Some(rest) = line.strip_prefix('>') {
+      rest
+    } else if line.starts_with('#') {
+      let rest = line.trim_start_matches('#');
+      if !rest.is_empty() && !rest.starts_with(' ') {
+        return line;
+      }
+      // Closing hashes are optional in headings.
+      rest.trim_end().trim_end_matches('#')
+    } else if let Some(rest) = ["- ", "* ", "+ "]
+      .iter()
+      .find_map(|marker| line.strip_prefix(marker))
+    {
+      rest
+    } else if let Some(rest) = ["[ ] ", "[x] ", "[X] "]
+      .iter()
+      .find_map(|marker| line.strip_prefix(marker))
+    {
+      rest
+    } else {
+      let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+      match line[digits..].strip_prefix(". ") {
+        Some(rest) if digits > 0 => rest,
+        _ => return line,
+      }
+    };
+    line = rest.trim_start();
+  }
+}
+
+/// Where the bracket closing the one just before `text` is, minding
+/// brackets nested inside.
+fn closing(text: &str, open: char, close: char) -> Option<usize> {
+  let mut depth = 0;
+  for (at, c) in text.char_indices() {
+    if c == open {
+      depth += 1;
+    } else if c == close {
+      if depth == 0 {
+        return Some(at);
+      }
+      depth -= 1;
+    }
+  }
+  None
+}
+
+/// The text of a link or image at the start of `text`, like `[text](url)`
+/// or `[text][reference]`, and how long it is.
+fn link(text: &str) -> Option<(&str, usize)> {
+  let label_end = 1 + closing(&text[1..], '[', ']')?;
+  let label = &text[1..label_end];
+  let rest = &text[label_end + 1..];
+  let (open, close) = match rest.chars().next()? {
+    '(' => ('(', ')'),
+    '[' => ('[', ']'),
+    _ => return None,
+  };
+  let target_end = closing(&rest[1..], open, close)?;
+  Some((label, label_end + 1 + 1 + target_end + 1))
+}
+
+fn is_word(c: Option<char>) -> bool {
+  c.is_some_and(char::is_alphanumeric)
+}
+
+fn inline(text: &str, out: &mut String) {
+  let mut at = 0;
+  while let Some(c) = text[at..].chars().next() {
+    let rest = &text[at..];
+    let before = text[..at].chars().next_back();
+    match c {
+      '\\' => match rest[1..].chars().next() {
+        Some(escaped) if escaped.is_ascii_punctuation() => {
+          out.push(escaped);
+          at += 1 + escaped.len_utf8();
+          continue;
+        }
+        _ => {}
+      },
+      '`' => {
+        let ticks = rest.len() - rest.trim_start_matches('`').len();
+        let fence = &rest[..ticks];
+        if let Some(end) = rest[ticks..].find(fence) {
+          out.push_str(rest[ticks..ticks + end].trim());
+          at += ticks + end + ticks;
+          continue;
+        }
+        out.push_str(fence);
+        at += ticks;
+        continue;
+      }
+      '!' if rest[1..].starts_with('[') => {
+        if let Some((label, length)) = link(&rest[1..]) {
+          inline(label, out);
+          at += 1 + length;
+          continue;
+        }
+      }
+      '[' => {
+        if let Some((label, length)) = link(rest) {
+          inline(label, out);
+          at += length;
+          continue;
+        }
+      }
+      '<' => {
+        if let Some(end) = rest.find('>') {
+          let tag = &rest[1..end];
+          if tag.starts_with("http://") || tag.starts_with("https://") {
+            out.push_str(tag);
+            at += end + 1;
+            continue;
+          }
+          if tag.starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!') {
+            at += end + 1;
+            continue;
+          }
+        }
+      }
+      '*' | '_' | '~' => {
+        let run = rest.len() - rest.trim_start_matches(c).len();
+        let after = rest[run..].chars().next();
+        let spaced =
+          before.is_none_or(char::is_whitespace) && after.is_none_or(char::is_whitespace);
+        // Underscores inside words, like in snake_case, are no emphasis, and
+        // neither is a single tilde.
+        let literal =
+          spaced || (c == '_' && is_word(before) && is_word(after)) || (c == '~' && run == 1);
+        if literal {
+          out.push_str(&rest[..run]);
+        }
+        at += run;
+        continue;
+      }
+      _ => {}
+    }
+    out.push(c);
+    at += c.len_utf8();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn links_become_their_text() {
+    assert_eq!(
+      strip_markdown("Follow-up to [the parser fix](https://example.com/-/merge_requests/812)"),
+      "Follow-up to the parser fix"
+    );
+    assert_eq!(
+      strip_markdown("See [the docs][docs] first"),
+      "See the docs first"
+    );
+    assert_eq!(
+      strip_markdown("[**Bold** [nested] text](https://example.com/a_(b))."),
+      "Bold [nested] text."
+    );
+    assert_eq!(
+      strip_markdown("Details on <https://example.com/docs>"),
+      "Details on https://example.com/docs"
+    );
+    // Brackets that aren't links stay.
+    assert_eq!(strip_markdown("[WIP] not [done"), "[WIP] not [done");
+  }
+
+  #[test]
+  fn code_spans_become_their_code() {
+    assert_eq!(
+      strip_markdown("Rename `old_name` to `new_name`"),
+      "Rename old_name to new_name"
+    );
+    // Neither emphasis nor links count inside.
+    assert_eq!(
+      strip_markdown("Quote `` `*[not](a link)*` `` inside"),
+      "Quote `*[not](a link)*` inside"
+    );
+    assert_eq!(strip_markdown("An ` unclosed span"), "An ` unclosed span");
+  }
+
+  #[test]
+  fn images_become_their_alt_text() {
+    assert_eq!(
+      strip_markdown("![Screenshot of the table](/uploads/abc/table.png) after"),
+      "Screenshot of the table after"
+    );
+    assert_eq!(
+      strip_markdown("[![pipeline](badge.svg)](https://example.com/pipelines)"),
+      "pipeline"
+    );
+    assert_eq!(strip_markdown("Wow! [x](y)"), "Wow! x");
+  }
+
+  #[test]
+  fn markers_and_emphasis_go() {
+    assert_eq!(strip_markdown("## Why ##"), "Why");
+    assert_eq!(
+      strip_markdown("#812 is not a heading"),
+      "#812 is not a heading"
+    );
+    assert_eq!(strip_markdown("> - [x] **Done**, _mostly_"), "Done, mostly");
+    assert_eq!(strip_markdown("12. ~~Old~~ new"), "Old new");
+    assert_eq!(
+      strip_markdown("keep snake_case and 2 * 3 and ~1 day"),
+      "keep snake_case and 2 * 3 and ~1 day"
+    );
+    assert_eq!(
+      strip_markdown(r"Literal \*stars\* and <b>tags</b>"),
+      "Literal *stars* and tags"
+    );
+  }
+
+  #[test]
+  fn subtitles_skip_boilerplate() {
+    let skip = vec!["## What does this MR do".to_string()];
+    let description = "<!-- Fill in\nthe template -->\n## What does this MR do?\n\n---\n```\ncode\n```\nFixes the `--once` exit code.\nMore.";
+    assert_eq!(
+      subtitle(description, &skip).as_deref(),
+      Some("Fixes the --once exit code.")
+    );
+    assert_eq!(subtitle("## what does this mr do\n", &skip), None);
+    assert_eq!(subtitle("", &[]), None);
+  }
+}
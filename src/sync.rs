@@ -0,0 +1,245 @@
+use crate::{
+  clock,
+  gitlab::{Client, MergeRequest, MrId, Role, User},
+  options::Options,
+  stats::{Phase, RefreshStats},
+  Result,
+};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+
+// Covers requests that were in flight while the previous refresh ran, as
+// well as small clock differences between us and the server.
+const SYNC_MARGIN: Duration = Duration::minutes(2);
+
+#[derive(Debug, Default)]
+pub struct IncrementalList {
+  mrs: HashMap<MrId, MergeRequest>,
+  last_sync: Option<DateTime<Utc>>,
+}
+
+impl IncrementalList {
+  pub fn replace(&mut self, mrs: Vec<MergeRequest>, synced_at: DateTime<Utc>) {
+    self.mrs = mrs.into_iter().map(|mr| (mr.id, mr)).collect();
+    self.last_sync = Some(synced_at);
+  }
+
+  pub fn merge_delta(&mut self, mrs: Vec<MergeRequest>, synced_at: DateTime<Utc>) {
+    for mr in mrs {
+      if mr.state == "opened" {
+        self.mrs.insert(mr.id, mr);
+      } else {
+        self.mrs.remove(&mr.id);
+      }
+    }
+    self.last_sync = Some(synced_at);
+  }
+
+  pub fn delta_since(&self) -> Option<DateTime<Utc>> {
+    self.last_sync.map(|at| at - SYNC_MARGIN)
+  }
+
+  pub fn mrs(&self) -> impl Iterator<Item = &MergeRequest> {
+    self.mrs.values()
+  }
+}
+
+/// Where merge requests come from; each can fail without taking the others down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Source {
+  Role(Role),
+  Pushes,
+  /// The recently merged ones in a role, for `--show-merged`.
+  Merged(Role),
+}
+
+impl Source {
+  pub fn describe(&self) -> &'static str {
+    match self {
+      Source::Role(Role::Reviewer) => "review-requested",
+      Source::Role(Role::Assignee) => "assigned",
+      Source::Role(Role::Author) => "authored",
+      Source::Pushes => "push-derived",
+      Source::Merged(Role::Reviewer) => "merged review-requested",
+      Source::Merged(Role::Assignee) => "merged assigned",
+      Source::Merged(Role::Author) => "merged authored",
+    }
+  }
+}
+
+/// A note such as `push-derived MRs unavailable`, if any source failed.
+pub fn unavailable_note(sources: &[Source]) -> Option<String> {
+  if sources.is_empty() {
+    return None;
+  }
+  let names: Vec<_> = sources.iter().map(Source::describe).collect();
+  Some(format!("{} MRs unavailable", names.join(", ")))
+}
+
+#[derive(Debug, Default)]
+pub struct MrLists {
+  lists: HashMap<Role, IncrementalList>,
+  cycles_since_full: u64,
+  /// Sources that failed in the last refresh.
+  unavailable: Vec<Source>,
+  /// Sources whose failure was already reported, until they recover.
+  reported: HashSet<Source>,
+}
+
+impl MrLists {
+  pub fn refresh(
+    &mut self,
+    client: &Client,
+    user: &User,
+    options: &Options,
+    widest_review_window: i64,
+    review_window: impl Fn(&MergeRequest) -> i64,
+    stats: &mut RefreshStats,
+  ) -> Result<HashMap<MrId, MergeRequest>> {
+    let full = self.lists.is_empty() || self.cycles_since_full + 1 >= options.full_resync_every;
+    let now = clock::server_now();
+    // Nothing older makes it into the list anyway, so it isn't even fetched.
+    let review_cutoff = now - Duration::days(widest_review_window);
+    let mut failures = Vec::new();
+
+    for role in Role::ALL {
+      let list = self.lists.entry(role).or_default();
+      let since = list.delta_since().filter(|_| !full);
+      // A failed list keeps what it had, and its next delta covers the gap.
+      let stop_before = (role == Role::Reviewer).then_some(review_cutoff);
+      match stats.time(Phase::Lists, || {
+        user.get_role_mrs(client, role, since, stop_before)
+      }) {
+        Ok(mrs) if since.is_some() => list.merge_delta(mrs, now),
+        Ok(mrs) => list.replace(mrs, now),
+        // Not a failure of the source, so nothing to report or make up for.
+        Err(err) if client.is_cancelled() => return Err(err),
+        Err(err) => failures.push((Source::Role(role), err)),
+      }
+    }
+
+    let mut all_mrs = match stats.time(Phase::Events, || {
+      user.get_push_mrs(client, options.push_lookback_days)
+    }) {
+      Ok(mrs) => mrs,
+      Err(err) if client.is_cancelled() => return Err(err),
+      Err(err) => {
+        failures.push((Source::Pushes, err));
+        HashMap::new()
+      }
+    };
+
+    // Nothing to show at all is an error; otherwise, do without.
+    if failures.len() == Role::ALL.len() + 1 {
+      return Err(failures.remove(0).1);
+    }
+    self.cycles_since_full = if full && failures.is_empty() {
+      0
+    } else {
+      self.cycles_since_full + 1
+    };
+    self.unavailable = failures.iter().map(|(source, _)| *source).collect();
+    self
+      .reported
+      .retain(|source| self.unavailable.contains(source));
+    for (source, err) in failures {
+      if self.reported.insert(source) {
+        eprintln!(
+          "warning: could not list {} merge requests, continuing without them: {}",
+          source.describe(),
+          err
+        );
+      }
+    }
+
+    for (role, list) in &self.lists {
+      all_mrs.extend(
+        list
+          .mrs()
+          .filter(|mr| {
+            *role != Role::Reviewer || (now - mr.updated_at).num_days() <= review_window(mr)
+          })
+          .map(|mr| (mr.id, mr.clone())),
+      );
+    }
+
+    Ok(all_mrs)
+  }
+
+  pub fn unavailable(&self) -> &[Source] {
+    &self.unavailable
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::mr;
+  use serde_json::json;
+
+  fn at(time: &str) -> DateTime<Utc> {
+    time.parse().unwrap()
+  }
+
+  fn listed() -> IncrementalList {
+    let mut list = IncrementalList::default();
+    list.replace(
+      vec![mr(1, json!({})), mr(2, json!({})), mr(3, json!({}))],
+      at("2026-10-10T10:00:00Z"),
+    );
+    list
+  }
+
+  fn titles(list: &IncrementalList) -> Vec<(usize, String)> {
+    let mut titles: Vec<_> = list.mrs().map(|mr| (mr.iid.0, mr.title.clone())).collect();
+    titles.sort();
+    titles
+  }
+
+  #[test]
+  fn deltas_update_the_merge_requests_they_list() {
+    let mut list = listed();
+    list.merge_delta(
+      vec![mr(2, json!({ "title": "Renamed" })), mr(4, json!({}))],
+      at("2026-10-10T10:05:00Z"),
+    );
+    assert_eq!(
+      titles(&list),
+      [
+        (1, "MR 1".to_string()),
+        (2, "Renamed".to_string()),
+        (3, "MR 3".to_string()),
+        (4, "MR 4".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn deltas_evict_closed_and_merged_ones() {
+    let mut list = listed();
+    list.merge_delta(
+      vec![
+        mr(1, json!({ "state": "closed" })),
+        mr(3, json!({ "state": "merged" })),
+        mr(5, json!({ "state": "closed" })),
+      ],
+      at("2026-10-10T10:05:00Z"),
+    );
+    assert_eq!(titles(&list), [(2, "MR 2".to_string())]);
+  }
+
+  #[test]
+  fn empty_deltas_change_nothing_but_the_sync_time() {
+    let mut list = listed();
+    let before = titles(&list);
+    list.merge_delta(Vec::new(), at("2026-10-10T10:05:00Z"));
+    assert_eq!(titles(&list), before);
+    assert_eq!(list.delta_since(), Some(at("2026-10-10T10:03:00Z")));
+  }
+
+  #[test]
+  fn nothing_was_synced_before_the_first_full_list() {
+    assert_eq!(IncrementalList::default().delta_since(), None);
+    assert_eq!(listed().delta_since(), Some(at("2026-10-10T09:58:00Z")));
+  }
+}
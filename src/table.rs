@@ -0,0 +1,760 @@
+use crate::{
+  attention::{attention, reviewers_unavailable, Attention},
+  badges::{badge, BADGE_WIDTH},
+  clock,
+  config::Config,
+  deadline::deadline_color,
+  effort,
+  focus::{spent, Focus},
+  gitlab::{unparsed_note, Issue, MrId, User},
+  layout::{cell, column_widths, ColumnWidths, Layout, DEADLINE_WIDTH},
+  list::ago,
+  make_link,
+  options::Options,
+  priority::{pipeline_failed, targets_main_branch, Score},
+  recap, sla,
+  sparkline::notes_label,
+  stale,
+  state::LastView,
+  subtitle::subtitle,
+  sync::unavailable_note,
+  terminal::{printable, restyle, Depth},
+  tickets::{ticket_label, ticket_url},
+  view::{sections, Heading},
+  views::View,
+  Result, Row, Snapshot,
+};
+use chrono::{Local, NaiveDate, Utc};
+use crossterm::{
+  cursor::{MoveTo, MoveToPreviousLine},
+  style::{Color, Print, Stylize},
+  terminal::{Clear, ClearType},
+};
+use std::{
+  fmt::Display,
+  io::{stdout, Stdout, Write},
+};
+use unicode_width::UnicodeWidthStr;
+
+pub struct Line {
+  pub text: String,
+  /// Set on the first line of a merge request, the one that can be selected.
+  pub mr: Option<MrId>,
+  /// Set on the further lines of a merge request, to scroll it into view whole.
+  pub continues: Option<MrId>,
+}
+
+impl Line {
+  fn plain(text: impl ToString) -> Self {
+    Line {
+      text: text.to_string(),
+      mr: None,
+      continues: None,
+    }
+  }
+
+  fn continuing(id: MrId, text: impl ToString) -> Self {
+    Line {
+      text: text.to_string(),
+      mr: None,
+      continues: Some(id),
+    }
+  }
+}
+
+const MAX_FAILED_JOBS: usize = 3;
+
+/// Hyperlinked names of the first few failed jobs, with known flaky ones dimmed.
+pub fn failed_jobs_label(row: &Row, options: &Options, config: &Config) -> String {
+  let mut label = row
+    .failed_jobs
+    .iter()
+    .take(MAX_FAILED_JOBS)
+    .map(|job| {
+      let link = make_link(options, &job.web_url, &printable(&job.name));
+      if config.is_flaky(&job.name) {
+        link.dark_grey().to_string()
+      } else {
+        link.red().to_string()
+      }
+    })
+    .collect::<Vec<_>>()
+    .join(", ");
+  if row.failed_jobs.len() > MAX_FAILED_JOBS {
+    label.push_str(&format!(" +{}", row.failed_jobs.len() - MAX_FAILED_JOBS));
+  }
+  label
+}
+
+fn row_lines(
+  row: &Row,
+  score: &Score,
+  widths: &ColumnWidths,
+  user: &User,
+  options: &Options,
+  config: &Config,
+  focus: Option<&Focus>,
+) -> Vec<Line> {
+  let layout = Layout::of(options);
+  let failed_pipeline = pipeline_failed(row);
+  let waiting = attention(row) == Some(Attention::WaitingOnOthers);
+  let Row {
+    mr,
+    roles,
+    approval_info,
+    activity,
+    tickets,
+    dependencies,
+    ..
+  } = row;
+  let reference = make_link(
+    options,
+    &mr.web_url,
+    &layout.cell(
+      widths.reference,
+      &options.short_reference(&mr.references.full),
+    ),
+  )
+  .blue();
+  let ticket = widths.ticket.map(|ticket_width| {
+    let label = layout.cell(ticket_width, &ticket_label(tickets));
+    match (&options.ticket_url, tickets.first()) {
+      (Some(template), Some(first)) => make_link(options, &ticket_url(template, first), &label),
+      _ => label,
+    }
+    .magenta()
+  });
+  let approved = approval_info
+    .approved_by
+    .iter()
+    .any(|a| a.user.id == user.id);
+  let mut title = mr.title.clone();
+  if mr.downvotes > 0 {
+    title = format!("▼{} {}", mr.downvotes, title);
+  }
+  if mr.upvotes > 0 {
+    title = format!("▲{} {}", mr.upvotes, title);
+  }
+  if waiting && reviewers_unavailable(row) {
+    title = format!("⏳⛔ {}", title);
+  } else if waiting {
+    title = format!("⏳ {}", title);
+  }
+  // Nobody needs to act on these any more.
+  if let Some(train) = &row.merge_train {
+    title = format!("🚂{} {}", train.position, title);
+  } else if mr.merge_when_pipeline_succeeds {
+    title = format!("auto {}", title);
+  }
+  if let Some(blocker) = dependencies.blocked_by.first() {
+    title = format!("⛔ blocked by !{} {}", blocker.iid, title);
+  }
+  if mr.is_from_fork() {
+    title = format!("⑂ {}", title);
+  }
+  if let Some(effort) = effort::of(row, user, options).filter(|_| options.effort) {
+    title = format!("[{}] {}", effort.label(), title);
+  }
+  if mr.is_locked() {
+    title = format!("🔐 {}", title);
+  }
+  if row.inaccessible {
+    title = format!("🔒 {}", title);
+  } else if !row.detailed {
+    title = format!("… {}", title);
+  }
+  let focused = focus.filter(|focus| focus.mr == mr.id);
+  if let Some(focus) = focused {
+    title = format!("⏱ {} {}", spent(focus.elapsed(Utc::now())), title);
+  }
+  // How long it waited on me goes in front, in its own color, once that is
+  // long enough to matter.
+  let overdue = sla::waited(row, user, options, clock::server_now()).and_then(|waited| {
+    let color = sla::Level::of(waited, options).color()?;
+    Some(format!("⌛{} ", sla::label(waited)).with(color))
+  });
+  let overdue_width = overdue
+    .as_ref()
+    .map_or(0, |overdue| overdue.content().width());
+  // Everything else steps back while a review is in progress.
+  let title = layout
+    .cell(widths.title.saturating_sub(overdue_width), &title)
+    .with(if focus.is_some() && focused.is_none() {
+      options.theme.muted()
+    } else if roles.author && failed_pipeline {
+      Color::Red
+    } else if roles.assigned_by_others() && !mr.draft {
+      if targets_main_branch(row, &config.project_options(options, mr.project_path())) {
+        Color::Red
+      } else {
+        Color::DarkYellow
+      }
+    } else if row.approvals_known() && (approval_info.approvals_left < 1 || approved) {
+      Color::Green
+    } else if mr.draft {
+      options.theme.muted()
+    } else {
+      options.theme.text()
+    });
+  let author = widths.author.map(|width| {
+    layout
+      .cell(width, mr.author.username.as_str())
+      .with(if roles.author {
+        Color::Green
+      } else {
+        options.theme.text()
+      })
+  });
+  let assignees = widths
+    .assignees
+    .map(|width| layout.cell(width, &mr.assignee_names()).red());
+  let reviewers = widths.reviewers.map(|width| {
+    layout
+      .cell(width, &row.reviewer_names())
+      .with(options.theme.text())
+  });
+  // Main branches are the common case; other targets are what stands out.
+  let branch = widths.branch.map(|width| {
+    layout.cell(width, &mr.target_branch).with(
+      if targets_main_branch(row, &config.project_options(options, mr.project_path())) {
+        options.theme.muted()
+      } else {
+        options.theme.text()
+      },
+    )
+  });
+
+  let title = match overdue {
+    Some(overdue) => format!("{}{}", overdue, title),
+    None => title.to_string(),
+  };
+
+  let mut cells = vec![reference.to_string()];
+  cells.extend(ticket.map(|ticket| ticket.to_string()));
+  cells.push(title);
+  cells.extend(
+    [author, assignees, reviewers, branch]
+      .into_iter()
+      .flatten()
+      .map(|cell| cell.to_string()),
+  );
+  if let Some(tasks_width) = widths.tasks {
+    let tasks = mr.tasks();
+    let label = layout.cell(
+      tasks_width,
+      &tasks.map(|tasks| tasks.to_string()).unwrap_or_default(),
+    );
+    cells.push(match tasks {
+      Some(tasks) if tasks.is_complete() => label.green().to_string(),
+      _ => label.with(options.theme.text()).to_string(),
+    });
+  }
+  if let Some(notes_width) = widths.notes {
+    cells.push(
+      layout
+        .cell(notes_width, &notes_label(mr))
+        .with(options.theme.text())
+        .to_string(),
+    );
+  }
+  if let Some(milestone_width) = widths.milestone {
+    let title = mr
+      .milestone
+      .as_ref()
+      .map_or("", |milestone| &milestone.title);
+    cells.push(
+      layout
+        .cell(milestone_width, title)
+        .with(options.theme.muted())
+        .to_string(),
+    );
+  }
+  if let Some(deadline_width) = widths.deadline {
+    cells.push(deadline_cell(deadline_width, mr.deadline(), options));
+  }
+  if let Some(activity_width) = widths.activity {
+    let activity = activity.as_ref().map(|a| a.to_string()).unwrap_or_default();
+    cells.push(
+      layout
+        .cell(activity_width, &activity)
+        .dark_grey()
+        .to_string(),
+    );
+  }
+  let mut lines = vec![Line {
+    text: layout.join(&cells),
+    mr: Some(mr.id),
+    continues: None,
+  }];
+
+  if options.two_line {
+    // Aligned under the title, and cut to the rest of the row's width.
+    let indent =
+      widths.reference + widths.ticket.map_or(0, |width| width + layout.gap) + layout.gap;
+    let width = widths.total.saturating_sub(indent);
+    let summary = layout.cell(width, &summary_line(row));
+    // The badge takes the room left of the title where there is enough.
+    let lead = if options.badges && indent > BADGE_WIDTH {
+      format!("{}{}", badge(&mr.author), " ".repeat(indent - BADGE_WIDTH))
+    } else {
+      " ".repeat(indent)
+    };
+    let subtitle = row
+      .description
+      .as_deref()
+      .and_then(|description| subtitle(description, &options.description_skip));
+    if let Some(subtitle) = subtitle {
+      lines.push(Line::continuing(
+        mr.id,
+        format!(
+          "{}{}",
+          " ".repeat(indent),
+          layout.cell(width, &printable(&subtitle)).dim()
+        ),
+      ));
+    }
+    lines.push(Line::continuing(
+      mr.id,
+      format!("{}{}", lead, summary.dark_grey()),
+    ));
+  }
+
+  if options.failed_jobs && !row.failed_jobs.is_empty() {
+    lines.push(Line::continuing(
+      mr.id,
+      format!(
+        "  {} {}",
+        "✗".red(),
+        failed_jobs_label(row, options, config)
+      ),
+    ));
+  }
+
+  if options.explain {
+    lines.push(Line::continuing(mr.id, format!("  {}", score).dark_grey()));
+  }
+  lines
+}
+
+/// The second line of the two-line layout.
+fn summary_line(row: &Row) -> String {
+  let mr = &row.mr;
+  let mut parts = Vec::new();
+  if !mr.labels.is_empty() {
+    parts.push(printable(&mr.labels.join(", ")).into_owned());
+  }
+  if let Some(milestone) = &mr.milestone {
+    parts.push(format!("milestone {}", printable(&milestone.title)));
+  }
+  parts.push(format!("→ {}", printable(&mr.target_branch)));
+  if let Some(pipeline) = &row.pipeline {
+    parts.push(format!("pipeline {}", pipeline.status));
+  }
+  if row.approvals_known() {
+    let given = row.approval_info.approved_by.len();
+    parts.push(format!(
+      "approvals {}/{}",
+      given,
+      given + row.approval_info.approvals_left
+    ));
+  }
+  let now = clock::server_now();
+  parts.push(format!("opened {}", ago(mr.created_at, now)));
+  if let Some(requested_at) = row.review_requested_at {
+    parts.push(format!("review requested {}", ago(requested_at, now)));
+  }
+  parts.join(" · ")
+}
+
+pub fn table_lines(
+  snapshot: &Snapshot,
+  view: &View,
+  user: &User,
+  options: &Options,
+  config: &Config,
+  term_width: usize,
+) -> Vec<Line> {
+  let Snapshot {
+    rows,
+    merged: merged_mrs,
+    ..
+  } = snapshot;
+  let widths = column_widths(term_width, rows.iter().map(|(row, _)| row), options);
+  let mut lines = Vec::new();
+
+  let focus = snapshot.focus.as_ref();
+  for section in sections(
+    rows,
+    view,
+    user,
+    options,
+    config,
+    focus.map(|focus| focus.mr),
+  ) {
+    if let Some(heading) = section.heading {
+      if !lines.is_empty() || heading == Heading::Stale {
+        lines.push(Line::plain(""));
+      }
+      let title = format!(
+        "{} ({})",
+        heading.title(),
+        section.rows.len() + section.more
+      )
+      .bold();
+      lines.push(Line::plain(if heading == Heading::Stale {
+        title.dark_grey()
+      } else {
+        title
+      }));
+      if let Some(progress) = section.progress {
+        lines.push(Line::plain(progress.to_string().dark_grey()));
+      }
+    }
+
+    for (row, score) in section.rows {
+      lines.extend(row_lines(row, score, &widths, user, options, config, focus));
+    }
+    if section.more > 0 {
+      lines.push(Line::plain(format!("+{} more", section.more).dark_grey()));
+    }
+  }
+
+  if !snapshot.issues.is_empty() {
+    if !lines.is_empty() {
+      lines.push(Line::plain(""));
+    }
+    let mut issues: Vec<_> = snapshot.issues.iter().collect();
+    view.sort_issues(&mut issues);
+    issue_lines(&mut lines, &issues, widths.reference, widths.title, options);
+  }
+
+  if let Some(hours) = options.show_merged.filter(|_| !merged_mrs.is_empty()) {
+    lines.push(Line::plain(""));
+    lines.push(Line::plain(
+      format!("Merged in the last {} hours", hours)
+        .dark_grey()
+        .bold(),
+    ));
+
+    let layout = Layout::of(options);
+    for mr in merged_mrs {
+      let mut cells = vec![
+        make_link(
+          options,
+          &mr.web_url,
+          &layout.cell(
+            widths.reference,
+            &options.short_reference(&mr.references.full),
+          ),
+        )
+        .dark_grey()
+        .to_string(),
+        layout.cell(widths.title, &mr.title).dark_grey().to_string(),
+      ];
+      cells.extend(widths.author.map(|width| {
+        layout
+          .cell(width, &mr.author.username)
+          .dark_grey()
+          .to_string()
+      }));
+      lines.push(Line::plain(layout.join(&cells)));
+    }
+  }
+
+  lines
+}
+
+fn deadline_cell(width: usize, deadline: Option<NaiveDate>, options: &Options) -> String {
+  let Some(deadline) = deadline else {
+    return cell(width, "");
+  };
+  let today = Local::now().date_naive();
+  let label = cell(width, &deadline.format("%Y-%m-%d").to_string());
+  label
+    .with(deadline_color(deadline, today).unwrap_or_else(|| options.theme.muted()))
+    .to_string()
+}
+
+// Issues aren't selectable, so their lines carry no merge request id.
+fn issue_lines(
+  lines: &mut Vec<Line>,
+  issues: &[&(Issue, Score)],
+  reference_width: usize,
+  title_width: usize,
+  options: &Options,
+) {
+  lines.push(Line::plain(format!("Issues ({})", issues.len()).bold()));
+  for (issue, score) in issues {
+    let reference = make_link(
+      options,
+      &issue.web_url,
+      &cell(
+        reference_width,
+        &options.short_reference(&issue.references.full),
+      ),
+    );
+    lines.push(Line::plain(format!(
+      "{} {} {}",
+      reference.magenta(),
+      cell(title_width.saturating_sub(DEADLINE_WIDTH + 1), &issue.title).with(options.theme.text()),
+      deadline_cell(DEADLINE_WIDTH, issue.deadline(), options)
+    )));
+    if options.explain {
+      lines.push(Line::plain(format!("  {}", score).dark_grey()));
+    }
+  }
+}
+
+/// The issues on their own, for the `issues` subcommand.
+pub fn print_issues(issues: &[(Issue, Score)], options: &Options) -> Result<()> {
+  let term_width = crossterm::terminal::size()
+    .map(|(w, __)| w as usize)
+    .unwrap_or(80);
+  let reference_width = issues
+    .iter()
+    .map(|(issue, _)| options.short_reference(&issue.references.full).width())
+    .max()
+    .unwrap_or(0);
+  let title_width = term_width.saturating_sub(reference_width + 1);
+
+  let mut lines = Vec::new();
+  let issues: Vec<_> = issues.iter().collect();
+  issue_lines(&mut lines, &issues, reference_width, title_width, options);
+  let mut target = stdout();
+  for line in lines {
+    crossterm::execute!(target, Print(restyle(&line.text, options.depth)))?;
+    writeln!(target)?;
+  }
+  Ok(())
+}
+
+/// The list from the last run, until the first refresh replaces it.
+pub fn print_stale(last_view: &LastView, options: &Options, screen: &mut Screen) -> Result<()> {
+  let mut frame = screen.frame(options)?;
+  for line in &last_view.lines {
+    frame.line(line)?;
+  }
+  frame.line(stale::banner(last_view, options))?;
+  frame.finish()
+}
+
+/// The whole list, below the summary of what changed since the last run
+/// where there is one.
+pub fn print_all(
+  snapshot: &Snapshot,
+  view: &View,
+  user: &User,
+  options: &Options,
+  config: &Config,
+  recap: Option<&str>,
+  screen: &mut Screen,
+) -> Result<()> {
+  let term_width = crossterm::terminal::size()
+    .map(|(w, __)| w as usize)
+    .unwrap_or(80);
+
+  let mut frame = screen.frame(options)?;
+  if let Some(recap) = recap {
+    frame.line(recap::banner(recap))?;
+  }
+  let lines = table_lines(snapshot, view, user, options, config, term_width);
+  for line in &lines {
+    frame.line(&line.text)?;
+  }
+  // Only spares a blank screen on the next start, so it may fail quietly.
+  let _ = stale::save(&options.view, &lines);
+  let mut refreshed = format!(
+    "Refreshed at {} in {:.1}s",
+    options.timezone.time(snapshot.fetched_at),
+    snapshot.stats.total().as_secs_f64()
+  );
+  if options.verbose {
+    refreshed.push_str(&format!(" ({})", snapshot.stats.breakdown()));
+    if snapshot.no_approvals {
+      refreshed.push_str(" (approvals disabled on this instance)");
+    }
+  }
+  if let Some(note) = unavailable_note(&snapshot.unavailable) {
+    refreshed.push_str(&format!(" ({})", note));
+  }
+  if snapshot.filtered > 0 {
+    refreshed.push_str(&format!(" ({} filtered out)", snapshot.filtered));
+  }
+  if let Some(note) = unparsed_note(&snapshot.unparsed, false) {
+    refreshed.push_str(&format!(" ({})", note));
+  }
+  frame.line(refreshed.dark_grey())?;
+  if options.verbose {
+    for unparsed in &snapshot.unparsed {
+      frame.line(printable(&format!("Could not parse {}", unparsed)).dark_grey())?;
+    }
+  }
+  frame.finish()
+}
+
+/// What the last list left on the terminal, so that the next one can be
+/// drawn over it in place and whatever scrolled by above it stays.
+#[derive(Default)]
+pub struct Screen {
+  /// How many lines were drawn, at which terminal size.
+  drawn: Option<(usize, (u16, u16))>,
+}
+
+impl Screen {
+  /// Moves back up over the last list. A full clear is left for when that
+  /// can't work: the terminal was resized, or the list didn't fit on it.
+  fn frame(&mut self, options: &Options) -> Result<Frame<'_>> {
+    let mut target = stdout();
+    let size = crossterm::terminal::size().ok();
+    match (self.drawn.take(), size) {
+      (Some((lines, drawn_at)), Some(size))
+        if !options.clear_screen && drawn_at == size && lines < size.1 as usize =>
+      {
+        if lines > 0 {
+          crossterm::queue!(target, MoveToPreviousLine(lines as u16))?;
+        }
+      }
+      (None, _) if !options.clear_screen => {}
+      _ => crossterm::queue!(target, Clear(ClearType::All), MoveTo(0, 0))?,
+    }
+    Ok(Frame {
+      screen: self,
+      target,
+      size,
+      depth: options.depth,
+      lines: 0,
+    })
+  }
+}
+
+/// One list being drawn over the last, line by line. Lines are expected to
+/// fit the terminal's width, or the count of lines to go back up over is off.
+struct Frame<'a> {
+  screen: &'a mut Screen,
+  target: Stdout,
+  size: Option<(u16, u16)>,
+  depth: Depth,
+  lines: usize,
+}
+
+impl Frame<'_> {
+  fn line(&mut self, text: impl Display) -> Result<()> {
+    let text = text.to_string();
+    crossterm::queue!(
+      self.target,
+      Print(restyle(&text, self.depth)),
+      Clear(ClearType::UntilNewLine)
+    )?;
+    writeln!(self.target)?;
+    self.lines += 1;
+    Ok(())
+  }
+
+  /// Clears what is left of a longer last list.
+  fn finish(mut self) -> Result<()> {
+    crossterm::execute!(self.target, Clear(ClearType::FromCursorDown))?;
+    self.screen.drawn = self.size.map(|size| (self.lines, size));
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fixtures::{me, mr, options, row, snapshot, user_json},
+    layout::Layout,
+  };
+  use serde_json::json;
+
+  /// Everything a title pasted from a log could bring along: CSI, OSC 8
+  /// ended by ST and by BEL, their C1 forms, and a bare escape.
+  const HOSTILE: &str =
+    "a\x1b[2J\x1b]8;;https://evil.example\x1b\\b\x1b]8;;\x07c\u{9b}31m\u{9d}0;d\u{9c}\x1bc\x07\r";
+
+  /// Where `text` has escape sequences other than our own SGR and OSC 8
+  /// ones, or control characters outside of them.
+  fn foreign(text: &str) -> Option<String> {
+    let mut rest = text;
+    while let Some(at) = rest.find(|c: char| c.is_control()) {
+      let sequence = &rest[at..];
+      if let Some(parameters) = sequence.strip_prefix("\x1b[") {
+        let end = parameters.find(|c: char| !c.is_ascii_digit() && c != ';');
+        match end.filter(|end| parameters[*end..].starts_with('m')) {
+          Some(end) => rest = &parameters[end + 1..],
+          None => return Some(sequence.to_string()),
+        }
+      } else if let Some(link) = sequence.strip_prefix("\x1b]8;;") {
+        match link.split_once("\x1b\\") {
+          Some((url, after)) if !url.contains(|c: char| c.is_control()) => rest = after,
+          _ => return Some(sequence.to_string()),
+        }
+      } else {
+        return Some(sequence.to_string());
+      }
+    }
+    None
+  }
+
+  fn hostile_snapshot() -> Snapshot {
+    let hostile_user = json!({ "id": 3, "name": HOSTILE, "username": HOSTILE });
+    let mut row = row(mr(
+      1,
+      json!({
+        "title": HOSTILE,
+        "author": hostile_user,
+        "reviewers": [user_json(1, "me"), hostile_user],
+        "assignees": [hostile_user],
+        "source_branch": HOSTILE,
+        "references": { "full": format!("group/project!1{}", HOSTILE) },
+        "web_url": format!("https://gitlab.example.com/{}", HOSTILE),
+        "labels": [HOSTILE],
+        "milestone": { "title": HOSTILE },
+      }),
+    ));
+    row.description = Some(HOSTILE.to_string());
+    snapshot(vec![row])
+  }
+
+  #[test]
+  fn our_detection_of_foreign_sequences_works() {
+    assert_eq!(foreign("plain"), None);
+    assert_eq!(foreign(&"plain".blue().to_string()), None);
+    assert_eq!(foreign("\x1b]8;;https://a\x1b\\x\x1b]8;;\x1b\\"), None);
+    assert!(foreign(HOSTILE).is_some());
+    assert!(foreign("\x1b[2J").is_some());
+    assert!(foreign("\x1b]8;;https://a\x07x").is_some());
+    assert!(foreign("\u{9b}31m").is_some());
+  }
+
+  #[test]
+  fn cells_drop_foreign_sequences() {
+    let options = options(&[]);
+    let layout = Layout::of(&options);
+    for width in [0, 3, 10, 80] {
+      assert_eq!(foreign(&layout.cell(width, HOSTILE)), None, "{}", width);
+      assert_eq!(foreign(&cell(width, HOSTILE)), None, "{}", width);
+    }
+  }
+
+  #[test]
+  fn rows_show_only_our_own_sequences() {
+    let config = Config::default();
+    let view = config.view("all").unwrap();
+    for args in [&[][..], &["--two-line"][..]] {
+      let mut options = options(args);
+      options.links = true;
+      let snapshot = hostile_snapshot();
+      let lines = table_lines(&snapshot, &view, &me(), &options, &config, 200);
+      assert!(lines.len() > 1 || args.is_empty(), "{:?}", args);
+      for line in &lines {
+        assert_eq!(foreign(&line.text), None, "{:?}: {:?}", args, line.text);
+      }
+      let (row, score) = &snapshot.rows[0];
+      let widths = column_widths(200, std::iter::once(row), &options);
+      for line in row_lines(row, score, &widths, &me(), &options, &config, None) {
+        assert_eq!(foreign(&line.text), None, "{:?}: {:?}", args, line.text);
+      }
+    }
+  }
+}
@@ -0,0 +1,314 @@
+use crate::Result;
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+  collections::{BTreeMap, HashMap},
+  fs,
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+const VERSION: u32 = 1;
+const REDACTED: &str = "[redacted]";
+
+/// One request and the reply to it, as recorded. Sessions are lists of
+/// these, to replay or to serve from a mock server in tests.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Exchange {
+  pub method: String,
+  /// The path below the host, like `/api/v4/merge_requests`.
+  pub path: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub query: Option<String>,
+  pub status: u16,
+  /// The reply headers, but for cookies.
+  #[serde(default)]
+  pub headers: BTreeMap<String, String>,
+  /// JSON where the reply was JSON, or else the text.
+  pub body: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Session {
+  version: u32,
+  exchanges: Vec<Exchange>,
+}
+
+/// The exchanges of the session recorded in a file, in order.
+pub fn exchanges(path: &Path) -> Result<Vec<Exchange>> {
+  let contents = fs::read(path)
+    .map_err(|err| format!("Could not read the recording {}: {}", path.display(), err))?;
+  let session: Session = serde_json::from_slice(&contents)
+    .map_err(|err| format!("Not a recording: {}: {}", path.display(), err))?;
+  Ok(session.exchanges)
+}
+
+/// Where a session is recorded to, or replayed from.
+#[derive(Debug)]
+pub enum Tape {
+  Record {
+    path: PathBuf,
+    token: String,
+    exchanges: Mutex<Vec<Exchange>>,
+  },
+  Replay {
+    /// Replies by request, in recorded order.
+    replies: HashMap<String, Vec<Exchange>>,
+    /// How many replies to each request were served so far.
+    served: Mutex<HashMap<String, usize>>,
+  },
+}
+
+/// A request as looked up in a recording. Dates and times in the query
+/// change from run to run, so they match any other.
+fn key(method: &str, path: &str, query: Option<&str>, exact: bool) -> String {
+  let query = query.unwrap_or_default();
+  let query = if exact {
+    query.to_string()
+  } else {
+    query.split('&').map(pattern).collect::<Vec<_>>().join("&")
+  };
+  format!("{} {}?{}", method, path, query)
+}
+
+/// A pair of a query as it matches others: any date or time for one of them.
+pub fn pattern(pair: &str) -> String {
+  match pair.split_once('=') {
+    Some((name, value)) if is_time(value) => format!("{}=*", name),
+    _ => pair.to_string(),
+  }
+}
+
+fn is_time(value: &str) -> bool {
+  let value = value.replace("%3A", ":").replace("%2B", "+");
+  DateTime::parse_from_rfc3339(&value).is_ok()
+    || NaiveDate::parse_from_str(&value, "%Y-%m-%d").is_ok()
+}
+
+/// Blanks out the token wherever it appears and everything under email fields.
+fn redact(value: &mut Value, token: &str) {
+  match value {
+    Value::Object(fields) => {
+      for (name, value) in fields {
+        if name.contains("email") {
+          blank(value);
+        } else {
+          redact(value, token);
+        }
+      }
+    }
+    Value::Array(items) => items.iter_mut().for_each(|item| redact(item, token)),
+    Value::String(text) if !token.is_empty() && text.contains(token) => {
+      *text = text.replace(token, REDACTED);
+    }
+    _ => {}
+  }
+}
+
+/// Blanks out every string in `value`, however deep.
+fn blank(value: &mut Value) {
+  match value {
+    Value::String(text) => *text = REDACTED.into(),
+    Value::Array(items) => items.iter_mut().for_each(blank),
+    Value::Object(fields) => fields.values_mut().for_each(blank),
+    _ => {}
+  }
+}
+
+impl Tape {
+  pub fn record(path: &Path, token: &str) -> Self {
+    Tape::Record {
+      path: path.to_path_buf(),
+      token: token.to_string(),
+      exchanges: Mutex::new(Vec::new()),
+    }
+  }
+
+  pub fn replay(path: &Path) -> Result<Self> {
+    let mut replies: HashMap<String, Vec<Exchange>> = HashMap::new();
+    for exchange in exchanges(path)? {
+      let query = exchange.query.as_deref();
+      let exact = key(&exchange.method, &exchange.path, query, true);
+      let any_time = key(&exchange.method, &exchange.path, query, false);
+      // Without dates or times, both are the same and it's there once.
+      if any_time != exact {
+        replies.entry(any_time).or_default().push(exchange.clone());
+      }
+      replies.entry(exact).or_default().push(exchange);
+    }
+    Ok(Tape::Replay {
+      replies,
+      served: Mutex::new(HashMap::new()),
+    })
+  }
+
+  pub fn is_replaying(&self) -> bool {
+    matches!(self, Tape::Replay { .. })
+  }
+
+  /// Keeps an exchange to write out, with secrets blanked out.
+  pub fn add(&self, mut exchange: Exchange) {
+    if let Tape::Record {
+      token, exchanges, ..
+    } = self
+    {
+      redact(&mut exchange.body, token);
+      exchanges.lock().unwrap().push(exchange);
+    }
+  }
+
+  /// The recorded reply to a request: the next one each time it is made
+  /// again, and the last one once they are used up. None for requests that
+  /// weren't recorded.
+  pub fn reply(&self, method: &str, path: &str, query: Option<&str>) -> Option<Exchange> {
+    let Tape::Replay { replies, served } = self else {
+      return None;
+    };
+    let key = [true, false]
+      .into_iter()
+      .map(|exact| key(method, path, query, exact))
+      .find(|key| replies.contains_key(key))?;
+    let recorded = &replies[&key];
+    let mut served = served.lock().unwrap();
+    let count = served.entry(key).or_default();
+    let exchange = recorded[(*count).min(recorded.len() - 1)].clone();
+    *count += 1;
+    Some(exchange)
+  }
+
+  /// Writes what was recorded so far, replacing the file.
+  pub fn save(&self) -> Result<()> {
+    let Tape::Record {
+      path, exchanges, ..
+    } = self
+    else {
+      return Ok(());
+    };
+    let session = Session {
+      version: VERSION,
+      exchanges: exchanges.lock().unwrap().clone(),
+    };
+    fs::write(path, serde_json::to_vec_pretty(&session)?)?;
+    Ok(())
+  }
+}
+
+impl Drop for Tape {
+  fn drop(&mut self) {
+    if let Err(err) = self.save() {
+      eprintln!("warning: could not write the recording: {}", err);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  use std::env;
+
+  fn path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("gitlab-todo-{}-{}.json", std::process::id(), name))
+  }
+
+  fn exchange(query: Option<&str>, body: Value) -> Exchange {
+    Exchange {
+      method: "GET".into(),
+      path: "/api/v4/users/1/events".into(),
+      query: query.map(str::to_string),
+      status: 200,
+      headers: BTreeMap::new(),
+      body,
+    }
+  }
+
+  #[test]
+  fn dates_and_times_match_any_other() {
+    let recorded = key("GET", "/e", Some("after=2026-09-30&page=1"), false);
+    assert_eq!(recorded, "GET /e?after=*&page=1");
+    assert_eq!(
+      key("GET", "/e", Some("after=2026-10-14&page=1"), false),
+      recorded
+    );
+    assert_ne!(
+      key("GET", "/e", Some("after=2026-10-14&page=2"), false),
+      recorded
+    );
+    assert_eq!(
+      pattern("updated_after=2026-10-14T09%3A00%3A00%2B02%3A00"),
+      "updated_after=*"
+    );
+    assert_eq!(pattern("search=2026"), "search=2026");
+    assert_eq!(pattern("per_page"), "per_page");
+  }
+
+  #[test]
+  fn replies_come_in_recorded_order() {
+    let path = path("order");
+    let tape = Tape::record(&path, "");
+    tape.add(exchange(Some("page=1"), json!(["first"])));
+    tape.add(exchange(Some("page=1"), json!(["second"])));
+    tape.add(exchange(Some("after=2026-09-30"), json!(["recent"])));
+    drop(tape);
+
+    let tape = Tape::replay(&path).unwrap();
+    let body = |query| {
+      tape
+        .reply("GET", "/api/v4/users/1/events", Some(query))
+        .map(|exchange| exchange.body)
+    };
+    assert_eq!(body("page=1"), Some(json!(["first"])));
+    assert_eq!(body("page=1"), Some(json!(["second"])));
+    assert_eq!(body("page=1"), Some(json!(["second"])));
+    assert_eq!(body("after=2026-10-14"), Some(json!(["recent"])));
+    assert_eq!(body("page=2"), None);
+    assert_eq!(tape.reply("POST", "/api/v4/users/1/events", None), None);
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn recordings_leave_out_secrets() {
+    let path = path("secrets");
+    let tape = Tape::record(&path, "glpat-secret");
+    tape.add(exchange(
+      None,
+      json!({
+        "note": "the token is glpat-secret",
+        "author": {
+          "public_email": "me@example.com",
+          "emails": ["me@work.example.com", { "email": "me@home.example.com", "primary": true }],
+        },
+      }),
+    ));
+    tape.save().unwrap();
+
+    let contents = fs::read_to_string(&path).unwrap();
+    assert!(!contents.contains("glpat-secret"));
+    assert!(!contents.contains("example.com"));
+    let exchanges = exchanges(&path).unwrap();
+    assert_eq!(
+      exchanges[0].body,
+      json!({
+        "note": "the token is [redacted]",
+        "author": {
+          "public_email": "[redacted]",
+          "emails": ["[redacted]", { "email": "[redacted]", "primary": true }],
+        },
+      })
+    );
+    drop(tape);
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn other_files_are_no_recordings() {
+    let path = path("garbage");
+    fs::write(&path, "[]").unwrap();
+    let err = Tape::replay(&path).unwrap_err().to_string();
+    assert!(err.starts_with("Not a recording: "), "{}", err);
+    fs::remove_file(&path).unwrap();
+    let err = Tape::replay(&path).unwrap_err().to_string();
+    assert!(err.starts_with("Could not read the recording "), "{}", err);
+  }
+}
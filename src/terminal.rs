@@ -0,0 +1,578 @@
+use clap::ValueEnum;
+use crossterm::style::Color;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Hyperlinks {
+  Auto,
+  Always,
+  Never,
+}
+
+impl Hyperlinks {
+  pub fn enabled(&self) -> bool {
+    self.enabled_in(|name| std::env::var(name).ok(), cfg!(windows))
+  }
+
+  /// Whether links are used in the terminal that `var` describes.
+  pub fn enabled_in(&self, var: impl Fn(&str) -> Option<String>, windows: bool) -> bool {
+    match self {
+      Hyperlinks::Auto => supports_hyperlinks(var, windows),
+      Hyperlinks::Always => true,
+      Hyperlinks::Never => false,
+    }
+  }
+}
+
+/// The environment variables the detection of colors and hyperlinks reads.
+pub const DETECTION_VARS: [&str; 7] = [
+  "TERM",
+  "TERM_PROGRAM",
+  "COLORTERM",
+  "NO_COLOR",
+  "WT_SESSION",
+  "TERMINAL_EMULATOR",
+  "INSIDE_EMACS",
+];
+
+// Terminals that don't know OSC 8 print it as garbage, so on Windows links
+// are only used where a modern terminal announces itself; elsewhere only
+// terminals known to lack them go without. Those come first, since a
+// terminal running inside another one inherits the outer one's variables.
+pub fn supports_hyperlinks(var: impl Fn(&str) -> Option<String>, windows: bool) -> bool {
+  if var("INSIDE_EMACS").is_some()
+    || var("TERMINAL_EMULATOR").is_some_and(|emulator| emulator.starts_with("JetBrains"))
+    || var("TERM").is_some_and(|term| term.starts_with("rxvt"))
+  {
+    return false;
+  }
+  if var("WT_SESSION").is_some() {
+    return true;
+  }
+  if let Some(program) = var("TERM_PROGRAM") {
+    if ["iTerm.app", "WezTerm", "vscode", "ghostty"].contains(&program.as_str()) {
+      return true;
+    }
+  }
+  match var("TERM").as_deref() {
+    Some("dumb" | "linux") => false,
+    Some(term)
+      if ["kitty", "alacritty", "foot", "ghostty"]
+        .iter()
+        .any(|known| term.contains(known)) =>
+    {
+      true
+    }
+    _ => !windows,
+  }
+}
+
+/// Text from GitLab with control characters dropped, for titles, names and
+/// branches on their way to the terminal. A stray escape in a title pasted
+/// from a log would otherwise restyle or garble everything printed after it.
+/// Only our own styling may emit escape sequences.
+pub fn printable(text: &str) -> Cow<'_, str> {
+  if text.chars().any(char::is_control) {
+    Cow::Owned(text.chars().filter(|c| !c.is_control()).collect())
+  } else {
+    Cow::Borrowed(text)
+  }
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorDepth {
+  Auto,
+  /// No colors at all; bold, underline and inverse stand in for them.
+  #[value(name = "none")]
+  #[serde(rename = "none")]
+  Mono,
+  #[value(name = "8")]
+  #[serde(rename = "8")]
+  Ansi8,
+  #[value(name = "16")]
+  #[serde(rename = "16")]
+  Ansi16,
+  #[value(name = "256")]
+  #[serde(rename = "256")]
+  Ansi256,
+  #[value(name = "truecolor")]
+  #[serde(rename = "truecolor")]
+  TrueColor,
+}
+
+impl ColorDepth {
+  pub fn resolved(&self) -> Depth {
+    match self {
+      ColorDepth::Auto => detect_depth(|name| std::env::var(name).ok()),
+      ColorDepth::Mono => Depth::Mono,
+      ColorDepth::Ansi8 => Depth::Ansi8,
+      ColorDepth::Ansi16 => Depth::Ansi16,
+      ColorDepth::Ansi256 => Depth::Ansi256,
+      ColorDepth::TrueColor => Depth::TrueColor,
+    }
+  }
+}
+
+/// How many colors the terminal shows, as resolved from `ColorDepth`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Depth {
+  Mono,
+  Ansi8,
+  Ansi16,
+  Ansi256,
+  #[default]
+  TrueColor,
+}
+
+// Terminals rarely say how many colors they have, but the conventions of
+// COLORTERM and TERM go a long way. Unknown terminals get 256 colors, which
+// anything from the last decades shows.
+pub fn detect_depth(var: impl Fn(&str) -> Option<String>) -> Depth {
+  if var("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+    return Depth::Mono;
+  }
+  if var("COLORTERM").is_some_and(|value| value == "truecolor" || value == "24bit") {
+    return Depth::TrueColor;
+  }
+  match var("TERM").as_deref() {
+    Some("dumb") => Depth::Mono,
+    Some(term) if term.contains("256color") => Depth::Ansi256,
+    Some(term) if term.ends_with("-16color") || term == "linux" => Depth::Ansi16,
+    Some("xterm" | "vt100" | "vt220" | "ansi" | "screen" | "tmux" | "rxvt") => Depth::Ansi8,
+    Some(term) if term.contains("8color") => Depth::Ansi8,
+    _ => Depth::Ansi256,
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+  Foreground,
+  Background,
+}
+
+// The 16 basic colors as xterm shows them, to find the nearest to others.
+const BASIC: [(u8, u8, u8); 16] = [
+  (0, 0, 0),
+  (205, 0, 0),
+  (0, 205, 0),
+  (205, 205, 0),
+  (0, 0, 238),
+  (205, 0, 205),
+  (0, 205, 205),
+  (229, 229, 229),
+  (127, 127, 127),
+  (255, 0, 0),
+  (0, 255, 0),
+  (255, 255, 0),
+  (92, 92, 255),
+  (255, 0, 255),
+  (0, 255, 255),
+  (255, 255, 255),
+];
+
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The index into the 256-color palette crossterm uses for a color, if it is
+/// one of them rather than RGB.
+fn palette_index(color: Color) -> Option<u8> {
+  Some(match color {
+    Color::Black => 0,
+    Color::DarkRed => 1,
+    Color::DarkGreen => 2,
+    Color::DarkYellow => 3,
+    Color::DarkBlue => 4,
+    Color::DarkMagenta => 5,
+    Color::DarkCyan => 6,
+    Color::Grey => 7,
+    Color::DarkGrey => 8,
+    Color::Red => 9,
+    Color::Green => 10,
+    Color::Yellow => 11,
+    Color::Blue => 12,
+    Color::Magenta => 13,
+    Color::Cyan => 14,
+    Color::White => 15,
+    Color::AnsiValue(index) => index,
+    Color::Rgb { .. } | Color::Reset => return None,
+  })
+}
+
+fn rgb_of(index: u8) -> (u8, u8, u8) {
+  match index {
+    0..=15 => BASIC[index as usize],
+    16..=231 => {
+      let index = index - 16;
+      (
+        CUBE[(index / 36) as usize],
+        CUBE[(index / 6 % 6) as usize],
+        CUBE[(index % 6) as usize],
+      )
+    }
+    _ => {
+      let level = 8 + (index - 232) * 10;
+      (level, level, level)
+    }
+  }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+  let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+  d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+/// The nearest of the first `count` palette entries.
+fn nearest(rgb: (u8, u8, u8), count: u16) -> u8 {
+  (0..count)
+    .map(|index| index as u8)
+    .min_by_key(|index| distance(rgb, rgb_of(*index)))
+    .unwrap_or(0)
+}
+
+/// The SGR parameters that show `color` on a terminal of `depth` as well as
+/// it can, like `31` for red on 8 colors. Without colors, warning colors
+/// become bold, green becomes underlined and backgrounds become inverse;
+/// the rest is left plain.
+pub fn color_codes(color: Color, layer: Layer, depth: Depth) -> String {
+  let (base, bright, extended) = match layer {
+    Layer::Foreground => (30, 90, 38),
+    Layer::Background => (40, 100, 48),
+  };
+  if color == Color::Reset {
+    return match (depth, layer) {
+      (Depth::Mono, Layer::Foreground) => "22;24".to_string(),
+      (Depth::Mono, Layer::Background) => "27".to_string(),
+      _ => (base + 9).to_string(),
+    };
+  }
+  let rgb = match color {
+    Color::Rgb { r, g, b } => (r, g, b),
+    color => rgb_of(palette_index(color).unwrap_or(0)),
+  };
+  match depth {
+    Depth::TrueColor => match color {
+      Color::Rgb { r, g, b } => format!("{};2;{};{};{}", extended, r, g, b),
+      color => format!("{};5;{}", extended, palette_index(color).unwrap_or(0)),
+    },
+    Depth::Ansi256 => {
+      let index = palette_index(color).unwrap_or_else(|| nearest(rgb, 256));
+      format!("{};5;{}", extended, index)
+    }
+    Depth::Ansi16 => {
+      let index = palette_index(color)
+        .filter(|index| *index < 16)
+        .unwrap_or_else(|| nearest(rgb, 16));
+      if index < 8 {
+        (base + index).to_string()
+      } else {
+        (bright + index - 8).to_string()
+      }
+    }
+    Depth::Ansi8 => {
+      let index = palette_index(color)
+        .filter(|index| *index < 16)
+        .unwrap_or_else(|| nearest(rgb, 16));
+      // Bright black is the one that would turn invisible as black.
+      let index = match index {
+        8 => 7,
+        index => index % 8,
+      };
+      (base + index).to_string()
+    }
+    Depth::Mono => match (layer, palette_index(color)) {
+      (Layer::Background, _) => "7".to_string(),
+      // Red and yellow, dark or not.
+      (_, Some(1 | 3 | 9 | 11)) => "1".to_string(),
+      // Green.
+      (_, Some(2 | 10)) => "4".to_string(),
+      _ => String::new(),
+    },
+  }
+}
+
+/// Styled text with its colors brought down to what the terminal shows.
+/// Rewrites the SGR sequences crossterm emits for colors and leaves all
+/// other styling as it is.
+pub fn restyle(text: &str, depth: Depth) -> Cow<'_, str> {
+  if depth == Depth::TrueColor || !text.contains("\x1b[") {
+    return Cow::Borrowed(text);
+  }
+  let mut restyled = String::with_capacity(text.len());
+  let mut rest = text;
+  while let Some(start) = rest.find("\x1b[") {
+    restyled.push_str(&rest[..start]);
+    let sequence = &rest[start + 2..];
+    let Some(end) = sequence.find(|c: char| !c.is_ascii_digit() && c != ';') else {
+      restyled.push_str(&rest[start..]);
+      return Cow::Owned(restyled);
+    };
+    if !sequence[end..].starts_with('m') {
+      restyled.push_str(&rest[start..start + 2 + end + 1]);
+      rest = &sequence[end + 1..];
+      continue;
+    }
+    let codes = sgr_codes(&sequence[..end], depth);
+    if !codes.is_empty() {
+      restyled.push_str(&format!("\x1b[{}m", codes));
+    }
+    rest = &sequence[end + 1..];
+  }
+  restyled.push_str(rest);
+  Cow::Owned(restyled)
+}
+
+fn sgr_codes(parameters: &str, depth: Depth) -> String {
+  let parameters: Vec<u8> = parameters
+    .split(';')
+    .map(|parameter| parameter.parse().unwrap_or(0))
+    .collect();
+  let mut codes = Vec::new();
+  let mut i = 0;
+  while i < parameters.len() {
+    let layer = match parameters[i] {
+      38 => Some(Layer::Foreground),
+      48 => Some(Layer::Background),
+      39 => {
+        codes.push(color_codes(Color::Reset, Layer::Foreground, depth));
+        i += 1;
+        continue;
+      }
+      49 => {
+        codes.push(color_codes(Color::Reset, Layer::Background, depth));
+        i += 1;
+        continue;
+      }
+      _ => None,
+    };
+    let color = layer.and_then(|_| match parameters.get(i + 1) {
+      Some(5) => Some((Color::AnsiValue(*parameters.get(i + 2)?), 3)),
+      Some(2) => Some((
+        Color::Rgb {
+          r: *parameters.get(i + 2)?,
+          g: *parameters.get(i + 3)?,
+          b: *parameters.get(i + 4)?,
+        },
+        5,
+      )),
+      _ => None,
+    });
+    match (layer, color) {
+      (Some(layer), Some((color, length))) => {
+        codes.push(color_codes(color, layer, depth));
+        i += length;
+      }
+      _ => {
+        codes.push(parameters[i].to_string());
+        i += 1;
+      }
+    }
+  }
+  codes.retain(|code| !code.is_empty());
+  codes.join(";")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// An environment with just `vars` set.
+  fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+    move |name| {
+      vars
+        .iter()
+        .find(|(var, _)| *var == name)
+        .map(|(_, value)| value.to_string())
+    }
+  }
+
+  #[test]
+  fn windows_links_only_where_a_modern_terminal_says_so() {
+    assert!(!supports_hyperlinks(env(&[]), true));
+    assert!(!supports_hyperlinks(
+      env(&[("TERM", "xterm-256color")]),
+      true
+    ));
+    assert!(supports_hyperlinks(env(&[("WT_SESSION", "1f2e")]), true));
+    assert!(supports_hyperlinks(
+      env(&[("TERM_PROGRAM", "vscode")]),
+      true
+    ));
+    assert!(supports_hyperlinks(env(&[("TERM", "alacritty")]), true));
+  }
+
+  #[test]
+  fn elsewhere_links_unless_the_terminal_lacks_them() {
+    assert!(supports_hyperlinks(env(&[]), false));
+    assert!(supports_hyperlinks(
+      env(&[("TERM", "xterm-256color")]),
+      false
+    ));
+    assert!(!supports_hyperlinks(env(&[("TERM", "dumb")]), false));
+    assert!(!supports_hyperlinks(env(&[("TERM", "linux")]), false));
+  }
+
+  #[test]
+  fn garbling_terminals_go_without_links() {
+    let garbling: [&[(&str, &str)]; 4] = [
+      &[("TERM", "rxvt-unicode-256color")],
+      &[("TERM", "rxvt")],
+      &[("INSIDE_EMACS", "29.1,vterm")],
+      &[("TERMINAL_EMULATOR", "JetBrains-JediTerm")],
+    ];
+    for vars in garbling {
+      for windows in [false, true] {
+        assert!(!supports_hyperlinks(env(vars), windows), "{:?}", vars);
+      }
+    }
+    // They win over what they inherit from a terminal outside.
+    assert!(!supports_hyperlinks(
+      env(&[("WT_SESSION", "1f2e"), ("TERM", "rxvt-unicode")]),
+      true
+    ));
+    assert!(!supports_hyperlinks(
+      env(&[("TERM_PROGRAM", "iTerm.app"), ("INSIDE_EMACS", "29.1,eat")]),
+      false
+    ));
+    assert!(!supports_hyperlinks(
+      env(&[
+        ("TERM", "xterm-kitty"),
+        ("TERMINAL_EMULATOR", "JetBrains-JediTerm")
+      ]),
+      false
+    ));
+    // Only JetBrains' emulator is known to garble.
+    assert!(supports_hyperlinks(
+      env(&[("TERMINAL_EMULATOR", "Other")]),
+      false
+    ));
+  }
+
+  #[test]
+  fn overrides_win_over_detection() {
+    let garbling = [("TERM", "rxvt-unicode")];
+    let capable = [("TERM_PROGRAM", "WezTerm")];
+    assert!(Hyperlinks::Always.enabled_in(env(&garbling), false));
+    assert!(Hyperlinks::Always.enabled_in(env(&[]), true));
+    assert!(!Hyperlinks::Never.enabled_in(env(&capable), false));
+    assert!(!Hyperlinks::Never.enabled_in(env(&capable), true));
+    assert!(!Hyperlinks::Auto.enabled_in(env(&garbling), false));
+    assert!(Hyperlinks::Auto.enabled_in(env(&capable), true));
+  }
+
+  fn has_no_controls(text: &str) -> bool {
+    !text.chars().any(char::is_control)
+  }
+
+  #[test]
+  fn plain_text_passes_untouched() {
+    for text in ["Fix the retry loop", "Ünïcödé ✓ 修正", ""] {
+      assert!(matches!(printable(text), Cow::Borrowed(borrowed) if borrowed == text));
+    }
+  }
+
+  #[test]
+  fn c0_and_c1_controls_are_dropped() {
+    assert_eq!(printable("a\0b\x07c\x08d\te\nf\rg\x7fh"), "abcdefgh");
+    // C1 has single characters for CSI and OSC, which some terminals honor.
+    assert_eq!(
+      printable("a\u{9b}31mb\u{9d}0;titlec\u{85}d"),
+      "a31mb0;titlecd"
+    );
+  }
+
+  #[test]
+  fn escape_sequences_lose_their_escapes() {
+    let styled = printable("\x1b[31mred\x1b[0m \x1b[2J\x1b[Hcleared");
+    assert_eq!(styled, "[31mred[0m [2J[Hcleared");
+    let titled = printable("\x1b]0;owned\x07Fix");
+    assert_eq!(titled, "]0;ownedFix");
+  }
+
+  #[test]
+  fn hyperlinks_cant_be_injected() {
+    for text in [
+      "\x1b]8;;https://evil.example\x1b\\click me\x1b]8;;\x1b\\",
+      "\x1b]8;;https://evil.example\x07click me\x1b]8;;\x07",
+      "\u{9d}8;;https://evil.example\u{9c}click me\u{9d}8;;\u{9c}",
+    ] {
+      let text = printable(text);
+      assert!(has_no_controls(&text), "{:?}", text);
+      assert!(text.contains("click me"));
+    }
+    // Nor can a title close the link we wrap it in, and open another.
+    let title = "Fix\x1b]8;;\x1b\\\x1b]8;;https://evil.example\x1b\\";
+    let link = format!(
+      "\x1b]8;;https://gitlab.example.com\x1b\\{}\x1b]8;;\x1b\\",
+      printable(title)
+    );
+    assert_eq!(link.matches('\x1b').count(), 4);
+  }
+
+  const DEPTHS: [Depth; 5] = [
+    Depth::TrueColor,
+    Depth::Ansi256,
+    Depth::Ansi16,
+    Depth::Ansi8,
+    Depth::Mono,
+  ];
+
+  /// The foreground codes of `color` at each depth, deepest first.
+  fn foreground(color: Color) -> Vec<String> {
+    DEPTHS
+      .iter()
+      .map(|depth| color_codes(color, Layer::Foreground, *depth))
+      .collect()
+  }
+
+  #[test]
+  fn named_colors_step_down_through_the_depths() {
+    assert_eq!(
+      foreground(Color::Red),
+      ["38;5;9", "38;5;9", "91", "31", "1"]
+    );
+    assert_eq!(
+      foreground(Color::DarkGreen),
+      ["38;5;2", "38;5;2", "32", "32", "4"]
+    );
+    // Bright black would be invisible as plain black.
+    assert_eq!(
+      foreground(Color::DarkGrey),
+      ["38;5;8", "38;5;8", "90", "37", ""]
+    );
+  }
+
+  #[test]
+  fn other_colors_get_the_nearest_the_terminal_has() {
+    let teal = Color::Rgb {
+      r: 0,
+      g: 95,
+      b: 135,
+    };
+    assert_eq!(
+      foreground(teal),
+      ["38;2;0;95;135", "38;5;24", "36", "36", ""]
+    );
+    let orange = Color::AnsiValue(208);
+    assert_eq!(foreground(orange), ["38;5;208", "38;5;208", "33", "33", ""]);
+  }
+
+  #[test]
+  fn backgrounds_and_resets_have_codes_of_their_own() {
+    let background: Vec<String> = DEPTHS
+      .iter()
+      .map(|depth| color_codes(Color::Red, Layer::Background, *depth))
+      .collect();
+    assert_eq!(background, ["48;5;9", "48;5;9", "101", "41", "7"]);
+    assert_eq!(foreground(Color::Reset), ["39", "39", "39", "39", "22;24"]);
+    assert_eq!(
+      color_codes(Color::Reset, Layer::Background, Depth::Ansi8),
+      "49"
+    );
+    assert_eq!(
+      color_codes(Color::Reset, Layer::Background, Depth::Mono),
+      "27"
+    );
+  }
+}
@@ -0,0 +1,69 @@
+use crate::{gitlab::User, Row};
+
+/// Unresolved threads on someone else's merge request that I took part in,
+/// by whose turn it is to reply.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThreadCounts {
+  pub awaiting_me: usize,
+  pub awaiting_author: usize,
+}
+
+impl ThreadCounts {
+  pub fn of(row: &Row, user: &User) -> Self {
+    let mut counts = ThreadCounts::default();
+    if row.roles.author {
+      return counts;
+    }
+
+    let threads = row
+      .discussions
+      .iter()
+      .filter(|discussion| discussion.is_unresolved() && discussion.has_note_by(user));
+    for thread in threads {
+      match thread.last_replied_by() {
+        Some(author) if author.id == user.id => counts.awaiting_author += 1,
+        Some(_) => counts.awaiting_me += 1,
+        None => {}
+      }
+    }
+    counts
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.awaiting_me == 0 && self.awaiting_author == 0
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::{me, mr, row, user_json};
+  use serde_json::json;
+
+  const DISCUSSIONS: &str = include_str!("../tests/fixtures/discussions.json");
+
+  fn counts(fields: serde_json::Value) -> ThreadCounts {
+    let mut row = row(mr(1, fields));
+    row.discussions = serde_json::from_str(DISCUSSIONS).unwrap();
+    ThreadCounts::of(&row, &me())
+  }
+
+  #[test]
+  fn threads_await_whoever_did_not_reply_last() {
+    // Replied to by the author; only mine so far; mine last, but for a
+    // system note. Resolved threads, others' threads and plain comments
+    // don't count.
+    assert_eq!(
+      counts(json!({ "reviewers": [user_json(1, "me")] })),
+      ThreadCounts {
+        awaiting_me: 1,
+        awaiting_author: 2,
+      }
+    );
+  }
+
+  #[test]
+  fn threads_on_my_own_merge_requests_are_not_counted() {
+    assert!(counts(json!({ "author": user_json(1, "me") })).is_empty());
+  }
+}
@@ -0,0 +1,78 @@
+use crate::gitlab::MergeRequest;
+use regex::Regex;
+
+pub fn find_tickets(pattern: &Regex, mr: &MergeRequest) -> Vec<String> {
+  let mut tickets: Vec<String> = Vec::new();
+
+  for haystack in [mr.title.as_str(), mr.source_branch.as_str()] {
+    for found in pattern.find_iter(haystack) {
+      let ticket = found.as_str();
+      if !tickets.iter().any(|known| known == ticket) {
+        tickets.push(ticket.to_string());
+      }
+    }
+  }
+
+  tickets
+}
+
+pub fn ticket_label(tickets: &[String]) -> String {
+  match tickets {
+    [] => String::new(),
+    [ticket] => ticket.clone(),
+    [ticket, rest @ ..] => format!("{} +{}", ticket, rest.len()),
+  }
+}
+
+pub fn ticket_url(template: &str, ticket: &str) -> String {
+  template.replace("{}", ticket)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fixtures::mr;
+  use serde_json::json;
+
+  fn tickets(title: &str, branch: &str) -> Vec<String> {
+    let pattern = Regex::new("[A-Z]+-[0-9]+").unwrap();
+    find_tickets(
+      &pattern,
+      &mr(1, json!({ "title": title, "source_branch": branch })),
+    )
+  }
+
+  #[test]
+  fn finds_no_tickets_without_a_match() {
+    assert!(tickets("Fix the login form", "fix-login").is_empty());
+    assert_eq!(ticket_label(&[]), "");
+  }
+
+  #[test]
+  fn finds_one_ticket_in_the_title_or_the_branch() {
+    assert_eq!(
+      tickets("ABC-1234 Fix the login form", "fix-login"),
+      ["ABC-1234"]
+    );
+    assert_eq!(
+      tickets("Fix the login form", "ABC-1234-fix-login"),
+      ["ABC-1234"]
+    );
+    assert_eq!(ticket_label(&tickets("ABC-1234 Fix", "x")), "ABC-1234");
+  }
+
+  #[test]
+  fn finds_several_tickets_once_each_in_order() {
+    let found = tickets("ABC-1 and XY-22: fix both", "ABC-1-XY-22-ZZ-3");
+    assert_eq!(found, ["ABC-1", "XY-22", "ZZ-3"]);
+    assert_eq!(ticket_label(&found), "ABC-1 +2");
+  }
+
+  #[test]
+  fn fills_the_ticket_into_the_url() {
+    assert_eq!(
+      ticket_url("https://jira.example.com/browse/{}", "ABC-1"),
+      "https://jira.example.com/browse/ABC-1"
+    );
+  }
+}
@@ -0,0 +1,33 @@
+use crate::{options::Options, priority::Score};
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Tier {
+  Action,
+  Look,
+  Low,
+}
+
+impl Tier {
+  pub const ALL: [Tier; 3] = [Tier::Action, Tier::Look, Tier::Low];
+
+  pub fn of(score: &Score, options: &Options) -> Self {
+    let total = score.total();
+    if total >= options.tier_high {
+      Tier::Action
+    } else if total >= options.tier_low {
+      Tier::Look
+    } else {
+      Tier::Low
+    }
+  }
+
+  pub fn title(&self) -> &'static str {
+    match self {
+      Tier::Action => "Needs your action",
+      Tier::Look => "Worth a look",
+      Tier::Low => "Low priority / drafts / bots",
+    }
+  }
+}
@@ -0,0 +1,153 @@
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, str::FromStr};
+
+/// Time zone for displaying timestamps; everything else stays in UTC.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub enum TimeZone {
+  Local,
+  Fixed(FixedOffset),
+}
+
+impl TimeZone {
+  pub fn format(&self, time: DateTime<Utc>, format: &str) -> String {
+    match self {
+      TimeZone::Local => time.with_timezone(&Local).format(format).to_string(),
+      TimeZone::Fixed(offset) => time.with_timezone(offset).format(format).to_string(),
+    }
+  }
+
+  /// Date and time down to the minute, for fields like created/updated.
+  pub fn datetime(&self, time: DateTime<Utc>) -> String {
+    self.format(time, "%Y-%m-%d %H:%M")
+  }
+
+  /// Time of day, prefixed with the day unless that is today, like
+  /// `yesterday 17:40` or `Mon 2024-03-04 09:15`.
+  pub fn relative(&self, time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let day = |time| self.format(time, "%Y-%m-%d");
+    if day(time) == day(now) {
+      self.format(time, "%H:%M")
+    } else if day(time) == day(now - Duration::days(1)) {
+      self.format(time, "yesterday %H:%M")
+    } else {
+      self.format(time, "%a %Y-%m-%d %H:%M")
+    }
+  }
+
+  /// Wall-clock time, for telling which day of the week it is.
+  pub fn naive(&self, time: DateTime<Utc>) -> NaiveDateTime {
+    match self {
+      TimeZone::Local => time.with_timezone(&Local).naive_local(),
+      TimeZone::Fixed(offset) => time.with_timezone(offset).naive_local(),
+    }
+  }
+
+  /// Time of day, for things that happened moments ago like a refresh.
+  pub fn time(&self, time: DateTime<Utc>) -> String {
+    self.format(time, "%H:%M:%S")
+  }
+}
+
+impl FromStr for TimeZone {
+  type Err = String;
+
+  /// Accepts `local`, `utc` or a fixed offset such as `+02:00`.
+  fn from_str(input: &str) -> Result<Self, Self::Err> {
+    match input.to_ascii_lowercase().as_str() {
+      "local" => Ok(TimeZone::Local),
+      "utc" | "z" => Ok(TimeZone::Fixed(FixedOffset::east_opt(0).unwrap())),
+      _ => input
+        .parse::<FixedOffset>()
+        .map(TimeZone::Fixed)
+        .map_err(|_| {
+          format!(
+            "Invalid time zone '{}': expected `local`, `utc` or an offset like `+02:00`",
+            input
+          )
+        }),
+    }
+  }
+}
+
+impl Display for TimeZone {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      TimeZone::Local => "local".fmt(f),
+      TimeZone::Fixed(offset) if offset.local_minus_utc() == 0 => "utc".fmt(f),
+      TimeZone::Fixed(offset) => offset.fmt(f),
+    }
+  }
+}
+
+impl TryFrom<String> for TimeZone {
+  type Error = String;
+
+  fn try_from(input: String) -> Result<Self, Self::Error> {
+    input.parse()
+  }
+}
+
+impl From<TimeZone> for String {
+  fn from(timezone: TimeZone) -> Self {
+    timezone.to_string()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn at(time: &str) -> DateTime<Utc> {
+    time.parse().unwrap()
+  }
+
+  fn zone(input: &str) -> TimeZone {
+    input.parse().unwrap()
+  }
+
+  #[test]
+  fn fixed_offsets_shift_the_wall_clock() {
+    let time = at("2026-10-14T23:30:00Z");
+    assert_eq!(zone("utc").datetime(time), "2026-10-14 23:30");
+    assert_eq!(zone("+02:00").datetime(time), "2026-10-15 01:30");
+    assert_eq!(zone("-05:30").time(time), "18:00:00");
+    assert_eq!(
+      zone("+02:00").naive(time).to_string(),
+      "2026-10-15 01:30:00"
+    );
+  }
+
+  #[test]
+  fn relative_times_name_the_day_in_the_zone() {
+    let now = at("2026-10-14T12:00:00Z");
+    let utc = zone("utc");
+    assert_eq!(utc.relative(at("2026-10-14T08:05:00Z"), now), "08:05");
+    assert_eq!(
+      utc.relative(at("2026-10-13T17:40:00Z"), now),
+      "yesterday 17:40"
+    );
+    assert_eq!(
+      utc.relative(at("2026-10-12T09:15:00Z"), now),
+      "Mon 2026-10-12 09:15"
+    );
+    // Late yesterday in UTC is already today two hours east.
+    assert_eq!(
+      zone("+02:00").relative(at("2026-10-13T23:00:00Z"), now),
+      "01:00"
+    );
+  }
+
+  #[test]
+  fn zones_parse_and_print_alike() {
+    for input in ["local", "utc", "+02:00", "-05:30"] {
+      assert_eq!(zone(input).to_string(), input);
+    }
+    assert_eq!(zone("UTC"), zone("z"));
+    assert_eq!(
+      "CEST".parse::<TimeZone>().unwrap_err(),
+      "Invalid time zone 'CEST': expected `local`, `utc` or an offset like `+02:00`"
+    );
+  }
+}
@@ -0,0 +1,1350 @@
+use crate::{
+  actions::{
+    describe_rebase, ensure_author, ensure_unlocked, hand_off, hide, lgtm, my_upvote,
+    open_in_browser, rebase, retry_pipeline, set_draft, set_labels, set_membership, toggle_upvote,
+    Bulk, Membership,
+  },
+  clock,
+  config::Config,
+  detail::detail_lines,
+  diff::{check_size, diff_lines},
+  events::EventTracker,
+  focus::{spent, Focus},
+  gitlab::{unparsed_note, Client, FileDiff, MrId, Note, User},
+  labels::Picker,
+  options::Options,
+  priority::priority,
+  prompt::Summary,
+  recap, refresh,
+  roles::Roles,
+  stale,
+  state::LastView,
+  sync::unavailable_note,
+  table::table_lines,
+  terminal::{printable, restyle},
+  views::View,
+  Caches, Result, Row, Snapshot,
+};
+use chrono::Utc;
+use crossterm::{
+  cursor::{Hide, MoveTo, Show},
+  event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+  style::{Print, Stylize},
+  terminal::{
+    self, disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+    LeaveAlternateScreen,
+  },
+};
+use std::{
+  borrow::Cow,
+  collections::{BTreeSet, HashMap, VecDeque},
+  io::{stdout, Write},
+  sync::atomic::{AtomicBool, Ordering},
+  thread,
+  time::{Duration, Instant},
+};
+use unicode_width::UnicodeWidthStr;
+
+const HELP: &str =
+  "j/k move  1-9/0 projects  Space mark  a/A assign/unassign me  r add me as reviewer  R hand review off  d toggle draft  f focus  l labels  L lgtm  s snooze  m mute  o open  b rebase  p retry pipeline  + 👍  Tab view  Enter details  t two lines  D diff  F5 refresh  q quit";
+
+const SNOOZE: chrono::Duration = chrono::Duration::days(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Confirm {
+  Rebase,
+  RetryPipeline,
+  Lgtm,
+}
+
+impl Confirm {
+  fn prompt(&self) -> &'static str {
+    match self {
+      Confirm::Rebase => "Rebase",
+      Confirm::RetryPipeline => "Retry the pipeline of",
+      Confirm::Lgtm => "Approve",
+    }
+  }
+}
+
+/// The username typed to hand a review off to. Tab cycles through the team
+/// members starting with what was typed.
+struct HandOff {
+  id: MrId,
+  input: String,
+  typed: String,
+  cycle: usize,
+}
+
+impl HandOff {
+  fn prompt(&self, reference: &str) -> String {
+    format!(
+      "Hand {} off to @{}_ (Tab completes, Enter with nobody just removes me, Esc cancels)",
+      reference, self.input
+    )
+  }
+
+  fn complete(&mut self, candidates: &[&str]) {
+    let typed = self.typed.to_lowercase();
+    let matching: Vec<&&str> = candidates
+      .iter()
+      .filter(|candidate| candidate.to_lowercase().starts_with(&typed))
+      .collect();
+    if let Some(candidate) = matching.get(self.cycle % matching.len().max(1)) {
+      self.input = candidate.to_string();
+      self.cycle += 1;
+    }
+  }
+
+  fn edit(&mut self, input: String) {
+    self.typed = input.clone();
+    self.input = input;
+    self.cycle = 0;
+  }
+}
+
+/// Where the selection goes once the rows are reordered: it follows its merge
+/// request, or lands on the nearest one still there if it disappeared.
+fn reconcile_selection(selected: usize, old_order: &[MrId], new_order: &[MrId]) -> usize {
+  let last = new_order.len().saturating_sub(1);
+  if old_order.get(selected).is_none() {
+    return selected.min(last);
+  }
+
+  let position = |id: &MrId| new_order.iter().position(|new| new == id);
+  let (before, after) = old_order.split_at(selected);
+  let nearest = position(&after[0])
+    .or_else(|| {
+      after[1..]
+        .iter()
+        .zip(before.iter().rev())
+        .find_map(|(next, previous)| position(next).or_else(|| position(previous)))
+    })
+    .or_else(|| after[1..].iter().find_map(position))
+    .or_else(|| before.iter().rev().find_map(position));
+  nearest.unwrap_or(selected.min(last))
+}
+
+struct Tui<'a> {
+  client: &'a Client,
+  user: &'a User,
+  options: &'a Options,
+  config: &'a Config,
+  caches: &'a mut Caches,
+  snapshot: Snapshot,
+  order: Vec<MrId>,
+  selected: usize,
+  scroll: usize,
+  /// Screen row of the selection at the last draw, to keep it there after a refresh.
+  anchor: Option<usize>,
+  reanchor: bool,
+  /// Whether the next draw shows a fresh snapshot, to keep for the next start.
+  fresh: bool,
+  footer: Option<String>,
+  /// What changed since the last run, above the list until the first key.
+  recap: Option<String>,
+  pending: Option<(Confirm, Vec<MrId>)>,
+  /// Merge requests marked for the next action, which then applies to all of them.
+  marked: BTreeSet<MrId>,
+  /// Projects picked with the number keys, the only ones shown; all if none.
+  shown_projects: BTreeSet<String>,
+  show_detail: bool,
+  two_line: bool,
+  /// The diff shown in place of the details, and how far it is scrolled.
+  diff: Option<(MrId, Vec<FileDiff>)>,
+  diff_scroll: usize,
+  /// The label picker, open on a merge request.
+  picker: Option<(MrId, Picker)>,
+  handoff: Option<HandOff>,
+  /// Whether I upvoted, or `None` where looking it up failed.
+  my_upvotes: HashMap<MrId, Option<bool>>,
+  events: EventTracker,
+  undetailed: Vec<MrId>,
+  refreshed_at: Instant,
+  /// Input that came in during a refresh, to handle before any more.
+  queued: VecDeque<Event>,
+  views: Vec<(String, View)>,
+  view: usize,
+}
+
+impl Tui<'_> {
+  fn refresh(&mut self) {
+    // Only keys that quit once handled cancel, not those meant for a prompt.
+    let idle = self.pending.is_none()
+      && self.picker.is_none()
+      && self.handoff.is_none()
+      && self.recap.is_none();
+    let diff = self.diff.is_some();
+    let (result, events) = watched_refresh(
+      self.client,
+      self.user,
+      self.options,
+      self.config,
+      self.caches,
+      |key| idle && is_quit(key, !diff),
+    );
+    self.queued.extend(events);
+    // The key that cancelled it is queued, and quits next.
+    if self.client.is_cancelled() {
+      return;
+    }
+    match result {
+      Ok(snapshot) => {
+        self.my_upvotes.clear();
+        let events = self.events.update(&snapshot);
+        self.config.alerts.deliver(&events);
+        let _ = recap::record(&snapshot);
+        let _ = Summary::of(&snapshot.rows, self.user, self.options).save();
+        self.snapshot = snapshot;
+        self.reanchor = true;
+        self.fresh = true;
+      }
+      Err(err) => self.footer = Some(format!("Refresh failed: {}", err)),
+    }
+    self.refreshed_at = Instant::now();
+  }
+
+  fn selected_id(&self) -> Option<MrId> {
+    self.order.get(self.selected).copied()
+  }
+
+  /// What the next action applies to: the marked merge requests in the order
+  /// shown, or else the selected one.
+  fn targets(&self) -> Vec<MrId> {
+    if self.marked.is_empty() {
+      return self.selected_id().into_iter().collect();
+    }
+    self
+      .order
+      .iter()
+      .filter(|id| self.marked.contains(id))
+      .copied()
+      .collect()
+  }
+
+  fn reference(&self, id: MrId) -> Option<&str> {
+    self
+      .snapshot
+      .rows
+      .iter()
+      .find(|(row, _)| row.mr.id == id)
+      .map(|(row, _)| row.mr.references.full.as_str())
+  }
+
+  fn toggle_mark(&mut self) {
+    let Some(id) = self.selected_id() else {
+      return;
+    };
+    if !self.marked.remove(&id) {
+      self.marked.insert(id);
+    }
+    self.selected = (self.selected + 1).min(self.order.len().saturating_sub(1));
+  }
+
+  // Runs an action against a merge request and, on success, rescores its row
+  // in place so the change shows without waiting for a refresh.
+  fn apply(
+    &mut self,
+    id: MrId,
+    action: impl FnOnce(&Client, &User, &mut Row) -> Result<String>,
+  ) -> Option<Result<String>> {
+    let (row, score) = self
+      .snapshot
+      .rows
+      .iter_mut()
+      .find(|(row, _)| row.mr.id == id)?;
+
+    let result = action(self.client, self.user, row);
+    if result.is_ok() {
+      self.caches.invalidate(id);
+      row.roles = Roles::of(self.user, &row.mr);
+      *score = priority(row, self.user, self.options, self.config);
+    }
+    Some(result)
+  }
+
+  fn update_row(
+    &mut self,
+    id: MrId,
+    action: impl FnOnce(&Client, &User, &mut Row) -> Result<String>,
+  ) {
+    match self.apply(id, action) {
+      Some(Ok(message)) => self.footer = Some(message),
+      Some(Err(err)) => {
+        self.footer = Some(format!(
+          "Could not update {}: {}",
+          self.reference(id).unwrap_or_default(),
+          err
+        ))
+      }
+      None => {}
+    }
+  }
+
+  /// Snoozes for a day, or mutes for good, and takes the rows out right away.
+  fn hide_targets(&mut self, snooze: bool) {
+    let ids = self.targets();
+    if ids.is_empty() {
+      return;
+    }
+    let until = snooze.then(|| Utc::now() + SNOOZE);
+    self.footer = Some(match hide(&ids, until) {
+      Ok(()) => {
+        self
+          .snapshot
+          .rows
+          .retain(|(row, _)| !ids.contains(&row.mr.id));
+        self.marked.clear();
+        format!("{} {}", if snooze { "snoozed" } else { "muted" }, ids.len())
+      }
+      Err(err) => format!("Could not save: {}", err),
+    });
+  }
+
+  fn open(&mut self) {
+    let mut bulk = Bulk::new("opened");
+    for id in self.targets() {
+      let Some((row, _)) = self.snapshot.rows.iter().find(|(row, _)| row.mr.id == id) else {
+        continue;
+      };
+      bulk.record(&row.mr.references.full, open_in_browser(&row.mr.web_url));
+    }
+    self.marked.clear();
+    self.footer = Some(bulk.to_string());
+  }
+
+  // Approving several at once asks first, naming every one of them.
+  fn approve(&mut self) {
+    let ids = self.targets();
+    if let [id] = ids[..] {
+      self.marked.clear();
+      return self.lgtm(id);
+    }
+    if ids.is_empty() {
+      return;
+    }
+    let titles: Vec<String> = ids
+      .iter()
+      .filter_map(|id| self.snapshot.rows.iter().find(|(row, _)| row.mr.id == *id))
+      .map(|(row, _)| format!("'{}'", printable(&row.mr.title)))
+      .collect();
+    self.footer = Some(format!(
+      "{} {}: {}? [y/N]",
+      Confirm::Lgtm.prompt(),
+      ids.len(),
+      titles.join(", ")
+    ));
+    self.pending = Some((Confirm::Lgtm, ids));
+  }
+
+  fn change_membership(&mut self, membership: Membership, present: bool) {
+    let Some(id) = self.selected_id() else {
+      return;
+    };
+    self.update_row(id, |client, user, row| {
+      row.mr = set_membership(client, user, &row.mr, membership, present)?;
+      Ok(format!(
+        "{} {}",
+        membership.describe(present),
+        row.mr.references.full
+      ))
+    });
+  }
+
+  fn start_handoff(&mut self) {
+    let Some(id) = self.selected_id() else {
+      return;
+    };
+    let Some((row, _)) = self.snapshot.rows.iter().find(|(row, _)| row.mr.id == id) else {
+      return;
+    };
+    if !row.roles.reviewing() {
+      self.footer = Some(format!("Not reviewing {}", row.mr.references.full));
+      return;
+    }
+    let handoff = HandOff {
+      id,
+      input: String::new(),
+      typed: String::new(),
+      cycle: 0,
+    };
+    self.footer = Some(handoff.prompt(&row.mr.references.full));
+    self.handoff = Some(handoff);
+  }
+
+  /// Keys go to the hand-off prompt while it is open.
+  fn handle_handoff_key(&mut self, key: KeyEvent) {
+    let Some(mut handoff) = self.handoff.take() else {
+      return;
+    };
+    match key.code {
+      KeyCode::Esc => {
+        self.footer = Some("cancelled".to_string());
+        return;
+      }
+      KeyCode::Enter => return self.finish_handoff(handoff),
+      KeyCode::Tab => {
+        let mut candidates: Vec<&str> = self
+          .config
+          .teams
+          .values()
+          .flatten()
+          .map(String::as_str)
+          .filter(|username| !self.options.user_ids.is(username, self.user))
+          .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        handoff.complete(&candidates);
+      }
+      KeyCode::Backspace => {
+        let mut input = handoff.input.clone();
+        input.pop();
+        handoff.edit(input);
+      }
+      // The prompt already shows the @.
+      KeyCode::Char('@') if handoff.input.is_empty() => {}
+      KeyCode::Char(c) if !c.is_whitespace() => handoff.edit(format!("{}{}", handoff.input, c)),
+      _ => {}
+    }
+    self.footer = Some(handoff.prompt(self.reference(handoff.id).unwrap_or_default()));
+    self.handoff = Some(handoff);
+  }
+
+  fn finish_handoff(&mut self, handoff: HandOff) {
+    let to = Some(handoff.input).filter(|input| !input.is_empty());
+    let note = self.options.handoff_note.clone();
+    self.update_row(handoff.id, |client, user, row| {
+      row.mr = hand_off(client, user, &row.mr, to.as_deref(), note.as_deref())?;
+      Ok(match &to {
+        Some(to) => format!("handed {} off to @{}", row.mr.references.full, to),
+        None => format!(
+          "{} {}",
+          Membership::Reviewer.describe(false),
+          row.mr.references.full
+        ),
+      })
+    });
+  }
+
+  fn toggle_draft(&mut self) {
+    let Some(id) = self.selected_id() else {
+      return;
+    };
+    self.update_row(id, |client, user, row| {
+      ensure_author(user, &row.mr, false)?;
+      row.mr = set_draft(client, &row.mr, !row.mr.draft)?;
+      Ok(format!(
+        "marked {} as {}",
+        row.mr.references.full,
+        if row.mr.draft { "draft" } else { "ready" }
+      ))
+    });
+  }
+
+  fn toggle_upvote(&mut self) {
+    let Some(id) = self.selected_id() else {
+      return;
+    };
+    let mut upvoted = None;
+    self.update_row(id, |client, user, row| {
+      ensure_unlocked(&row.mr)?;
+      let now_upvoted = toggle_upvote(client, user, &row.mr)?;
+      upvoted = Some(now_upvoted);
+      if now_upvoted {
+        row.mr.upvotes += 1;
+      } else {
+        row.mr.upvotes = row.mr.upvotes.saturating_sub(1);
+      }
+      Ok(format!(
+        "{} my 👍 on {}",
+        if now_upvoted { "gave" } else { "withdrew" },
+        row.mr.references.full
+      ))
+    });
+    if let Some(upvoted) = upvoted {
+      self.my_upvotes.insert(id, Some(upvoted));
+    }
+  }
+
+  fn lgtm(&mut self, id: MrId) {
+    match self.lgtm_row(id) {
+      Some(Ok(message)) => self.footer = Some(message),
+      Some(Err(err)) => {
+        self.footer = Some(format!(
+          "Could not update {}: {}",
+          self.reference(id).unwrap_or_default(),
+          err
+        ))
+      }
+      None => {}
+    }
+  }
+
+  fn lgtm_row(&mut self, id: MrId) -> Option<Result<String>> {
+    let defaults = &self.config.lgtm;
+    let mut upvoted = false;
+    let result = self.apply(id, |client, user, row| {
+      let (approval_info, steps) = lgtm(
+        client,
+        user,
+        &row.mr,
+        defaults.message.as_deref(),
+        defaults.award,
+      )?;
+      row.approval_info = approval_info;
+      if steps.upvoted {
+        row.mr.upvotes += 1;
+      }
+      upvoted = defaults.award;
+      Ok(steps.describe(&row.mr))
+    });
+    if upvoted {
+      self.my_upvotes.insert(id, Some(true));
+    }
+    result
+  }
+
+  // Focusing another merge request moves the timer over to it.
+  fn toggle_focus(&mut self) {
+    let Some(id) = self.selected_id() else {
+      return;
+    };
+    let mut messages = Vec::new();
+    let previous = self.snapshot.focus.take();
+    if let Some(previous) = &previous {
+      messages.push(self.stop_focus(previous));
+    }
+    if previous.is_none_or(|previous| previous.mr != id) {
+      self.snapshot.focus = Some(Focus::start(id));
+      if let Some((row, _)) = self.snapshot.rows.iter().find(|(row, _)| row.mr.id == id) {
+        messages.push(format!("reviewing {}", row.mr.references.full));
+      }
+    }
+    if let Err(err) = Focus::save(self.snapshot.focus.as_ref()) {
+      messages.push(format!("could not save the focus: {}", err));
+    }
+    self.footer = Some(messages.join(", "));
+  }
+
+  fn stop_focus(&mut self, focus: &Focus) -> String {
+    let elapsed = focus.elapsed(Utc::now());
+    let Some((row, _)) = self
+      .snapshot
+      .rows
+      .iter()
+      .find(|(row, _)| row.mr.id == focus.mr)
+    else {
+      return format!("stopped reviewing after {}", spent(elapsed));
+    };
+    let mr = &row.mr;
+    if !self.options.focus_spend || elapsed.num_minutes() < 1 {
+      return format!(
+        "stopped reviewing {} after {}",
+        mr.references.full,
+        spent(elapsed)
+      );
+    }
+    let recorded = ensure_unlocked(mr)
+      .and_then(|_| Note::create(self.client, mr, &format!("/spend {}", spent(elapsed))));
+    match recorded {
+      Ok(_) => format!("spent {} on {}", spent(elapsed), mr.references.full),
+      Err(err) => format!(
+        "could not record {} on {}: {}",
+        spent(elapsed),
+        mr.references.full,
+        err
+      ),
+    }
+  }
+
+  fn toggle_diff(&mut self) {
+    if self.diff.take().is_some() {
+      return;
+    }
+    let Some(id) = self.selected_id() else {
+      return;
+    };
+    let Some((row, _)) = self.snapshot.rows.iter().find(|(row, _)| row.mr.id == id) else {
+      return;
+    };
+    let mr = &row.mr;
+    let files = self
+      .caches
+      .diffs
+      .get_or_fetch(mr.id, mr.sha.clone(), || FileDiff::get_all(self.client, mr))
+      .and_then(|files| check_size(&files, &mr.web_url).map(|_| files));
+    match files {
+      Ok(files) => {
+        self.diff = Some((id, files));
+        self.diff_scroll = 0;
+      }
+      Err(err) => {
+        self.footer = Some(format!(
+          "Could not show the diff of {}: {}",
+          mr.references.full, err
+        ))
+      }
+    }
+  }
+
+  fn open_picker(&mut self) {
+    let Some(id) = self.selected_id() else {
+      return;
+    };
+    let Some((row, _)) = self.snapshot.rows.iter().find(|(row, _)| row.mr.id == id) else {
+      return;
+    };
+    let (project, labels) = (row.mr.project_id, row.mr.labels.clone());
+    match self.caches.labels(self.client, project) {
+      Ok(available) => self.picker = Some((id, Picker::new(available, &labels))),
+      Err(err) => self.footer = Some(format!("Could not fetch the labels: {}", err)),
+    }
+  }
+
+  fn apply_labels(&mut self, id: MrId, picker: Picker) {
+    let (add, remove) = picker.changes();
+    if add.is_empty() && remove.is_empty() {
+      return;
+    }
+    self.update_row(id, |client, _, row| {
+      ensure_unlocked(&row.mr)?;
+      set_labels(client, &row.mr, &add, &remove)?;
+      row.mr.labels = picker.applied(&row.mr.labels);
+      Ok(format!("changed the labels of {}", row.mr.references.full))
+    });
+  }
+
+  /// Keys go to the picker while it is open.
+  fn handle_picker_key(&mut self, key: KeyEvent) {
+    let Some((id, mut picker)) = self.picker.take() else {
+      return;
+    };
+    match key.code {
+      KeyCode::Esc => return,
+      KeyCode::Enter => return self.apply_labels(id, picker),
+      KeyCode::Up => picker.move_by(false),
+      KeyCode::Down => picker.move_by(true),
+      KeyCode::Tab => picker.toggle(),
+      KeyCode::Backspace => {
+        let mut filter = picker.filter.clone();
+        filter.pop();
+        picker.set_filter(filter);
+      }
+      KeyCode::Char(c) => picker.set_filter(format!("{}{}", picker.filter, c)),
+      _ => {}
+    }
+    self.picker = Some((id, picker));
+  }
+
+  fn confirm(&mut self, action: Confirm) {
+    let Some(id) = self.selected_id() else {
+      return;
+    };
+    let Some((row, _)) = self.snapshot.rows.iter().find(|(row, _)| row.mr.id == id) else {
+      return;
+    };
+    self.footer = Some(format!(
+      "{} {}? [y/N]",
+      action.prompt(),
+      row.mr.references.full
+    ));
+    self.pending = Some((action, vec![id]));
+  }
+
+  fn perform(&mut self, action: Confirm, ids: Vec<MrId>) {
+    match action {
+      Confirm::Rebase => {
+        for id in ids {
+          self.update_row(id, |client, _, row| {
+            row.mr = rebase(client, &row.mr)?;
+            Ok(describe_rebase(&row.mr))
+          })
+        }
+      }
+      Confirm::RetryPipeline => {
+        for id in ids {
+          self.update_row(id, |client, _, row| {
+            let pipeline = retry_pipeline(client, &row.mr, row.pipeline.as_ref())?;
+            let message = format!(
+              "retried pipeline {} of {}",
+              pipeline.id, row.mr.references.full
+            );
+            row.pipeline = Some(pipeline);
+            Ok(message)
+          })
+        }
+      }
+      Confirm::Lgtm => {
+        let mut bulk = Bulk::new("approved");
+        for id in ids {
+          let reference = self.reference(id).unwrap_or_default().to_string();
+          if let Some(result) = self.lgtm_row(id) {
+            bulk.record(&reference, result);
+          }
+        }
+        self.marked.clear();
+        self.footer = Some(bulk.to_string());
+      }
+    }
+  }
+
+  /// The projects in the list, most merge requests first, as the number
+  /// keys pick them.
+  fn legend(&self) -> Vec<(&str, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (row, _) in &self.snapshot.rows {
+      *counts.entry(row.mr.project_path()).or_default() += 1;
+    }
+    let mut legend: Vec<(&str, usize)> = counts.into_iter().collect();
+    legend.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    legend.truncate(9);
+    legend
+  }
+
+  fn toggle_project(&mut self, index: usize) {
+    let Some(project) = self
+      .legend()
+      .get(index)
+      .map(|(project, _)| project.to_string())
+    else {
+      return;
+    };
+    if !self.shown_projects.remove(&project) {
+      self.shown_projects.insert(project);
+    }
+  }
+
+  fn legend_line(&self, width: usize) -> String {
+    let mut line = String::new();
+    let mut used = 0;
+    for (index, (project, count)) in self.legend().into_iter().enumerate() {
+      let entry = format!("{} {} ({})", index + 1, project, count);
+      let entry_width = entry.width() + 2;
+      if used + entry_width > width {
+        break;
+      }
+      used += entry_width;
+      let entry = if self.shown_projects.is_empty() {
+        entry.with(self.options.theme.text())
+      } else if self.shown_projects.contains(project) {
+        entry.bold().reverse()
+      } else {
+        entry.with(self.options.theme.muted())
+      };
+      line.push_str(&format!("{}  ", entry));
+    }
+    line
+  }
+
+  fn handle_key(&mut self, key: KeyEvent) -> bool {
+    if let Some((action, ids)) = self.pending.take() {
+      match key.code {
+        KeyCode::Char('y') | KeyCode::Char('Y') => self.perform(action, ids),
+        _ => self.footer = Some("cancelled".to_string()),
+      }
+      return true;
+    }
+
+    if self.picker.is_some() {
+      self.handle_picker_key(key);
+      return true;
+    }
+
+    if self.handoff.is_some() {
+      self.handle_handoff_key(key);
+      return true;
+    }
+
+    if self.diff.is_some() {
+      match key.code {
+        KeyCode::Char('J') | KeyCode::PageDown | KeyCode::Char(' ') => {
+          self.diff_scroll += 1;
+          return true;
+        }
+        KeyCode::Char('K') | KeyCode::PageUp => {
+          self.diff_scroll = self.diff_scroll.saturating_sub(1);
+          return true;
+        }
+        KeyCode::Esc => {
+          self.diff = None;
+          return true;
+        }
+        _ => {}
+      }
+    }
+
+    match key.code {
+      KeyCode::Char('q') | KeyCode::Esc => return false,
+      KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+      // The token's bot is nobody to assign, review or approve as.
+      KeyCode::Char('a' | 'A' | 'r' | 'R' | 'd' | 'L' | '+' | 'f') if self.options.dashboard => {
+        self.footer = Some("Not on the dashboard, which acts for nobody".to_string());
+      }
+      KeyCode::Char('j') | KeyCode::Down => {
+        self.selected = (self.selected + 1).min(self.order.len().saturating_sub(1))
+      }
+      KeyCode::Char('k') | KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+      KeyCode::Char('a') => self.change_membership(Membership::Assignee, true),
+      KeyCode::Char('A') => self.change_membership(Membership::Assignee, false),
+      KeyCode::Char('r') => self.change_membership(Membership::Reviewer, true),
+      KeyCode::Char('R') => self.start_handoff(),
+      KeyCode::Char('d') => self.toggle_draft(),
+      KeyCode::Char('b') => self.confirm(Confirm::Rebase),
+      KeyCode::Char('p') => self.confirm(Confirm::RetryPipeline),
+      KeyCode::Char('+') => self.toggle_upvote(),
+      KeyCode::Char('D') => self.toggle_diff(),
+      KeyCode::Char('l') => self.open_picker(),
+      KeyCode::Char('L') => self.approve(),
+      KeyCode::Char(' ') => self.toggle_mark(),
+      KeyCode::Char('s') => self.hide_targets(true),
+      KeyCode::Char('m') => self.hide_targets(false),
+      KeyCode::Char('o') => self.open(),
+      KeyCode::Char('f') => self.toggle_focus(),
+      KeyCode::Char(digit @ '1'..='9') => self.toggle_project(digit as usize - '1' as usize),
+      KeyCode::Char('0') => self.shown_projects.clear(),
+      KeyCode::Char('t') => self.two_line = !self.two_line,
+      KeyCode::Enter => self.show_detail = !self.show_detail,
+      KeyCode::Tab | KeyCode::Char('v') => self.view = (self.view + 1) % self.views.len(),
+      KeyCode::F(5) => self.refresh(),
+      _ => {}
+    }
+    true
+  }
+
+  fn draw(&mut self, target: &mut impl Write) -> Result<()> {
+    let (width, height) = terminal::size()?;
+    let (width, height) = (width as usize, height as usize);
+
+    let options = if self.two_line == self.options.two_line {
+      Cow::Borrowed(self.options)
+    } else {
+      Cow::Owned(Options {
+        two_line: self.two_line,
+        ..self.options.clone()
+      })
+    };
+    // Marks belong to merge requests, so they outlast refreshes as long as
+    // those are still listed.
+    self
+      .marked
+      .retain(|id| self.snapshot.rows.iter().any(|(row, _)| row.mr.id == *id));
+    let checkboxes = !self.marked.is_empty();
+    // Picked projects stay picked across refreshes, even while none of
+    // their merge requests are listed.
+    let view = if self.shown_projects.is_empty() {
+      Cow::Borrowed(&self.views[self.view].1)
+    } else {
+      Cow::Owned(View {
+        projects: self.shown_projects.clone(),
+        ..self.views[self.view].1.clone()
+      })
+    };
+    let lines = table_lines(
+      &self.snapshot,
+      &view,
+      self.user,
+      &options,
+      self.config,
+      width.saturating_sub(if checkboxes { 6 } else { 2 }),
+    );
+    if std::mem::take(&mut self.fresh) && self.shown_projects.is_empty() {
+      // Only spares a blank screen on the next start, so it may fail quietly.
+      let _ = stale::save(&self.views[self.view].0, &lines);
+    }
+    let order: Vec<MrId> = lines.iter().filter_map(|line| line.mr).collect();
+    self.selected = reconcile_selection(self.selected, &self.order, &order);
+    self.order = order;
+    let selected_id = self.order.get(self.selected).copied();
+
+    // The diff belongs to the merge request it was opened on.
+    if self
+      .diff
+      .as_ref()
+      .is_some_and(|(id, _)| Some(*id) != selected_id)
+    {
+      self.diff = None;
+    }
+
+    let pane_limit = height.saturating_sub(1) / 2;
+    let selected_row = self
+      .snapshot
+      .rows
+      .iter()
+      .find(|(row, _)| Some(row.mr.id) == selected_id);
+    // The picker belongs to the merge request it was opened on, too.
+    if self
+      .picker
+      .as_ref()
+      .is_some_and(|(id, _)| Some(*id) != selected_id)
+    {
+      self.picker = None;
+    }
+    let mut detail = match (selected_row, &self.diff) {
+      _ if self.picker.is_some() => self
+        .picker
+        .as_ref()
+        .map_or_else(Vec::new, |(_, picker)| picker.lines(pane_limit)),
+      (Some(_), Some((_, files))) => {
+        let lines = diff_lines(files, width);
+        self.diff_scroll = self.diff_scroll.min(lines.len().saturating_sub(pane_limit));
+        lines.into_iter().skip(self.diff_scroll).collect()
+      }
+      (Some((row, score)), None) if self.show_detail => {
+        let upvoted = self.my_upvotes.get(&row.mr.id).copied().flatten();
+        detail_lines(row, score, self.options, self.user, self.config, upvoted)
+      }
+      _ => Vec::new(),
+    };
+    detail.truncate(pane_limit);
+    let pane_height = if detail.is_empty() {
+      0
+    } else {
+      detail.len() + 1
+    };
+    let legend = self.legend().len() > 1 || !self.shown_projects.is_empty();
+    let top = usize::from(self.recap.is_some()) + usize::from(legend);
+    let body_height = height.saturating_sub(1 + pane_height + top);
+    let selected_line = lines
+      .iter()
+      .position(|line| line.mr.is_some() && line.mr == selected_id)
+      .unwrap_or(0);
+
+    if let Some(anchor) = self.anchor.filter(|_| std::mem::take(&mut self.reanchor)) {
+      self.scroll = selected_line.saturating_sub(anchor);
+    }
+    // The selection's further lines come into view along with it.
+    let selected_end = lines
+      .iter()
+      .rposition(|line| line.continues.is_some() && line.continues == selected_id)
+      .filter(|end| *end > selected_line)
+      .unwrap_or(selected_line);
+    if selected_line < self.scroll {
+      self.scroll = selected_line;
+    } else if selected_end >= self.scroll + body_height {
+      self.scroll = (selected_end + 1)
+        .saturating_sub(body_height)
+        .min(selected_line);
+    }
+    self.anchor = selected_id.map(|_| selected_line - self.scroll);
+
+    self.undetailed = lines
+      .iter()
+      .skip(self.scroll)
+      .take(body_height)
+      .filter_map(|line| line.mr)
+      .filter(|id| {
+        self
+          .snapshot
+          .rows
+          .iter()
+          .any(|(row, _)| row.mr.id == *id && !row.detailed && !row.inaccessible)
+      })
+      .collect();
+
+    let depth = self.options.depth;
+    if let Some(recap) = &self.recap {
+      let banner = recap::banner(
+        &recap
+          .chars()
+          .take(width.saturating_sub(2))
+          .collect::<String>(),
+      );
+      crossterm::queue!(
+        target,
+        MoveTo(0, 0),
+        Print(restyle(&banner, depth)),
+        Clear(ClearType::UntilNewLine),
+      )?;
+    }
+    if legend {
+      crossterm::queue!(
+        target,
+        MoveTo(0, (top - 1) as u16),
+        Print("  "),
+        Print(restyle(&self.legend_line(width.saturating_sub(2)), depth)),
+        Clear(ClearType::UntilNewLine),
+      )?;
+    }
+    for y in 0..body_height {
+      crossterm::queue!(target, MoveTo(0, (top + y) as u16))?;
+      if let Some(line) = lines.get(self.scroll + y) {
+        let marker = if self.scroll + y == selected_line && selected_id.is_some() {
+          "▶ "
+        } else {
+          "  "
+        };
+        let checkbox = match line.mr {
+          _ if !checkboxes => "",
+          Some(id) if self.marked.contains(&id) => "[x] ",
+          Some(_) => "[ ] ",
+          None => "    ",
+        };
+        crossterm::queue!(
+          target,
+          Print(marker),
+          Print(checkbox),
+          Print(restyle(&line.text, depth))
+        )?;
+      }
+      crossterm::queue!(target, Clear(ClearType::UntilNewLine))?;
+    }
+
+    if pane_height > 0 {
+      crossterm::queue!(
+        target,
+        MoveTo(0, (top + body_height) as u16),
+        Print(restyle(&"─".repeat(width).dark_grey().to_string(), depth)),
+      )?;
+      for (y, line) in detail.iter().enumerate() {
+        crossterm::queue!(
+          target,
+          MoveTo(0, (top + body_height + 1 + y) as u16),
+          Print(restyle(line, depth)),
+          Clear(ClearType::UntilNewLine),
+        )?;
+      }
+    }
+
+    let footer = self.footer.clone().unwrap_or_else(|| {
+      let stats = &self.snapshot.stats;
+      let mut footer = format!(
+        "{}  [{}]  (refreshed {} in {:.1}s",
+        HELP,
+        self.views[self.view].0,
+        self.options.timezone.time(self.snapshot.fetched_at),
+        stats.total().as_secs_f64()
+      );
+      if self.options.verbose {
+        footer.push_str(&format!(": {}", stats.breakdown()));
+        if self.snapshot.no_approvals {
+          footer.push_str("; approvals disabled on this instance");
+        }
+      }
+      if let Some(note) = unavailable_note(&self.snapshot.unavailable) {
+        footer.push_str(&format!("; {}", note));
+      }
+      if self.snapshot.filtered > 0 {
+        footer.push_str(&format!("; {} filtered out", self.snapshot.filtered));
+      }
+      if let Some(note) = unparsed_note(&self.snapshot.unparsed, self.options.verbose) {
+        footer.push_str(&format!("; {}", printable(&note)));
+      }
+      footer.push(')');
+      footer
+    });
+    crossterm::queue!(
+      target,
+      MoveTo(0, (top + body_height + pane_height) as u16),
+      Print(footer.chars().take(width).collect::<String>().reverse()),
+      Clear(ClearType::UntilNewLine),
+    )?;
+    target.flush()?;
+    Ok(())
+  }
+
+  // Rows scrolled into view get their details now, one at a time so that
+  // input is read in between.
+  fn fetch_next_details(&mut self) {
+    if self.undetailed.is_empty() {
+      return;
+    }
+    let id = self.undetailed.remove(0);
+    if let Err(err) = self.snapshot.fetch_details(
+      id,
+      self.client,
+      self.user,
+      self.options,
+      self.config,
+      self.caches,
+    ) {
+      self.footer = Some(err.to_string());
+      self.undetailed.clear();
+    }
+  }
+
+  // Award lists aren't part of the merge request payload, so mine is only
+  // looked up for the merge request being inspected. Whether it was.
+  fn fetch_my_upvote(&mut self) -> bool {
+    if !self.show_detail || self.diff.is_some() || self.picker.is_some() {
+      return false;
+    }
+    let Some(id) = self.selected_id() else {
+      return false;
+    };
+    if self.my_upvotes.contains_key(&id) {
+      return false;
+    }
+    let Some((row, _)) = self.snapshot.rows.iter().find(|(row, _)| row.mr.id == id) else {
+      return false;
+    };
+    let upvoted = my_upvote(self.client, self.user, &row.mr)
+      .ok()
+      .map(|award| award.is_some());
+    self.my_upvotes.insert(id, upvoted);
+    true
+  }
+
+  /// Waits for input until the next refresh is due, or until the system
+  /// resumes from sleep, when it is overdue. Whether there is input.
+  fn poll_until_refresh(&self) -> Result<bool> {
+    loop {
+      let timeout =
+        Duration::from_secs(self.options.interval).saturating_sub(self.refreshed_at.elapsed());
+      if timeout.is_zero() {
+        return Ok(false);
+      }
+      let slice = timeout.min(clock::WAKE_CHECK);
+      let watch = clock::Watch::start();
+      if event::poll(slice)? {
+        return Ok(true);
+      }
+      if watch.slept(slice) {
+        return Ok(false);
+      }
+    }
+  }
+
+  fn run(&mut self) -> Result<()> {
+    let mut target = stdout();
+    loop {
+      self.draw(&mut target)?;
+      // Keys go first; a page full of rows takes a while to fetch.
+      if self.queued.is_empty()
+        && !self.undetailed.is_empty()
+        && self.footer.is_none()
+        && !event::poll(Duration::ZERO)?
+      {
+        self.fetch_next_details();
+        continue;
+      }
+      if self.queued.is_empty() && !event::poll(Duration::ZERO)? && self.fetch_my_upvote() {
+        continue;
+      }
+
+      if self.queued.is_empty() && !self.poll_until_refresh()? {
+        self.refresh();
+        continue;
+      }
+
+      let event = match self.queued.pop_front() {
+        Some(event) => event,
+        None => event::read()?,
+      };
+      if let Event::Key(key) = event {
+        self.footer = None;
+        // The first key after the start only dismisses the summary.
+        if self.recap.take().is_some() {
+          continue;
+        }
+        if !self.handle_key(key) {
+          return Ok(());
+        }
+      }
+    }
+  }
+}
+
+// How often the keyboard is checked while a refresh runs.
+const WATCH_POLL: Duration = Duration::from_millis(50);
+
+/// Whether `key` quits the list; Esc only where it doesn't close something.
+fn is_quit(key: &KeyEvent, esc: bool) -> bool {
+  match key.code {
+    KeyCode::Char('q') => true,
+    KeyCode::Esc => esc,
+    KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+    _ => false,
+  }
+}
+
+/// Refreshes while watching the keyboard, so that quitting needn't wait for
+/// every request still to come: a key that `quits` cancels them, and the
+/// refresh then fails rather than leave anything half done. Every event
+/// meanwhile is handed back, to handle once it's over.
+fn watched_refresh(
+  client: &Client,
+  user: &User,
+  options: &Options,
+  config: &Config,
+  caches: &mut Caches,
+  quits: impl Fn(&KeyEvent) -> bool + Sync,
+) -> (Result<Snapshot>, Vec<Event>) {
+  client.resume();
+  let done = AtomicBool::new(false);
+  thread::scope(|scope| {
+    let watcher = scope.spawn(|| {
+      let mut events = Vec::new();
+      while !done.load(Ordering::Relaxed) {
+        match event::poll(WATCH_POLL) {
+          Ok(true) => {}
+          Ok(false) => continue,
+          Err(_) => break,
+        }
+        let Ok(event) = event::read() else {
+          break;
+        };
+        if matches!(&event, Event::Key(key) if quits(key)) {
+          client.cancel();
+        }
+        events.push(event);
+      }
+      events
+    });
+    let result = refresh(client, user, options, config, caches);
+    done.store(true, Ordering::Relaxed);
+    (result, watcher.join().unwrap_or_default())
+  })
+}
+
+/// The list from the last run, with a banner at the bottom, until the first
+/// refresh replaces it.
+fn paint_stale(target: &mut impl Write, last_view: &LastView, options: &Options) -> Result<()> {
+  let (_, height) = terminal::size()?;
+  let height = height as usize;
+  crossterm::queue!(target, Clear(ClearType::All))?;
+  for (y, line) in last_view
+    .lines
+    .iter()
+    .take(height.saturating_sub(1))
+    .enumerate()
+  {
+    crossterm::queue!(
+      target,
+      MoveTo(0, y as u16),
+      Print("  "),
+      Print(restyle(line, options.depth))
+    )?;
+  }
+  crossterm::queue!(
+    target,
+    MoveTo(0, height.saturating_sub(1) as u16),
+    Print(restyle(&stale::banner(last_view, options), options.depth)),
+  )?;
+  target.flush()?;
+  Ok(())
+}
+
+fn start(
+  client: &Client,
+  user: &User,
+  options: &Options,
+  config: &Config,
+  caches: &mut Caches,
+) -> Result<()> {
+  let views: Vec<(String, View)> = config
+    .views()
+    .into_iter()
+    .map(|(name, view)| (name, view.sorted_by(options.sort)))
+    .collect();
+  let view = views
+    .iter()
+    .position(|(name, _)| *name == options.view)
+    .unwrap_or(0);
+  if let Some(last_view) = stale::load(options) {
+    paint_stale(&mut stdout(), &last_view, options)?;
+  }
+  let (result, queued) = watched_refresh(client, user, options, config, caches, |key| {
+    is_quit(key, true)
+  });
+  if client.is_cancelled() {
+    return Ok(());
+  }
+  let snapshot = result?;
+  let recap = recap::since_last_run(&snapshot, options);
+  let _ = recap::record(&snapshot);
+  let mut tui = Tui {
+    client,
+    user,
+    options,
+    config,
+    caches,
+    snapshot,
+    order: Vec::new(),
+    selected: 0,
+    scroll: 0,
+    anchor: None,
+    reanchor: false,
+    fresh: true,
+    footer: None,
+    recap,
+    pending: None,
+    marked: BTreeSet::new(),
+    shown_projects: BTreeSet::new(),
+    show_detail: true,
+    two_line: options.two_line,
+    diff: None,
+    diff_scroll: 0,
+    picker: None,
+    handoff: None,
+    my_upvotes: HashMap::new(),
+    events: EventTracker::default(),
+    undetailed: Vec::new(),
+    refreshed_at: Instant::now(),
+    queued: queued.into(),
+    views,
+    view,
+  };
+  tui.events.update(&tui.snapshot);
+  tui.run()
+}
+
+pub fn run(
+  client: &Client,
+  user: &User,
+  options: &Options,
+  config: &Config,
+  caches: &mut Caches,
+) -> Result<()> {
+  enable_raw_mode()?;
+  crossterm::execute!(stdout(), EnterAlternateScreen, Hide)?;
+  let result = start(client, user, options, config, caches);
+  crossterm::execute!(stdout(), Show, LeaveAlternateScreen)?;
+  disable_raw_mode()?;
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn ids(ids: &[usize]) -> Vec<MrId> {
+    ids.iter().copied().map(MrId).collect()
+  }
+
+  #[test]
+  fn selection_stays_on_unchanged_rows() {
+    let order = ids(&[1, 2, 3]);
+    for selected in 0..3 {
+      assert_eq!(reconcile_selection(selected, &order, &order), selected);
+    }
+  }
+
+  #[test]
+  fn selection_follows_its_merge_request_when_it_moves() {
+    let old = ids(&[1, 2, 3, 4]);
+    assert_eq!(reconcile_selection(1, &old, &ids(&[4, 3, 1, 2])), 3);
+    assert_eq!(reconcile_selection(3, &old, &ids(&[4, 1, 2, 3])), 0);
+    assert_eq!(reconcile_selection(0, &old, &ids(&[5, 6, 2, 1])), 3);
+  }
+
+  #[test]
+  fn selection_lands_on_the_nearest_remaining_neighbour() {
+    let old = ids(&[1, 2, 3, 4, 5]);
+    // The next one down is closest, then the one above it.
+    assert_eq!(reconcile_selection(2, &old, &ids(&[1, 2, 4, 5])), 2);
+    assert_eq!(reconcile_selection(2, &old, &ids(&[4, 2])), 0);
+    assert_eq!(reconcile_selection(2, &old, &ids(&[1, 2, 5])), 1);
+    // Past the end, the last one left above.
+    assert_eq!(reconcile_selection(4, &old, &ids(&[1, 2, 3])), 2);
+    assert_eq!(reconcile_selection(2, &old, &ids(&[5, 1])), 0);
+  }
+
+  #[test]
+  fn selection_survives_empty_snapshots() {
+    let old = ids(&[1, 2, 3]);
+    assert_eq!(reconcile_selection(2, &old, &[]), 0);
+    assert_eq!(reconcile_selection(0, &[], &old), 0);
+    // Out of range to begin with, it is clamped to what is there.
+    assert_eq!(reconcile_selection(7, &old, &ids(&[1, 2])), 1);
+  }
+}
@@ -0,0 +1,211 @@
+use crate::{
+  config::Config,
+  gitlab::{Client, User, UserId},
+  options::Options,
+  state::{CachedUser, State},
+};
+use chrono::{Duration, Utc};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+// IDs never change, but a username can be given up and taken by someone
+// else; that shows within a day.
+const TTL: Duration = Duration::hours(24);
+
+/// The users behind the usernames in the configuration, so that filters and
+/// priorities compare users rather than names, which can differ in case.
+#[derive(Debug, Clone, Default)]
+pub struct UserIds {
+  /// By lowercased username.
+  ids: HashMap<String, UserId>,
+}
+
+impl UserIds {
+  /// Whether `username` is `user`: by ID where the name was resolved, and
+  /// otherwise by name, regardless of case as GitLab has it.
+  pub fn is(&self, username: &str, user: &User) -> bool {
+    match self.ids.get(&username.to_lowercase()) {
+      Some(id) => *id == user.id,
+      None => username.eq_ignore_ascii_case(&user.username),
+    }
+  }
+}
+
+/// Usernames in author filters and teams, less the team references.
+fn people(options: &Options, config: &Config) -> BTreeSet<String> {
+  let project_authors = config
+    .project
+    .iter()
+    .filter_map(|project| project.filters.authors.as_ref())
+    .flatten();
+  options
+    .authors
+    .iter()
+    .chain(project_authors)
+    .filter(|author| !author.starts_with('@'))
+    .chain(config.teams.values().flatten())
+    .map(|username| username.to_lowercase())
+    .collect()
+}
+
+fn bots(options: &Options, config: &Config) -> BTreeSet<String> {
+  options
+    .bots
+    .iter()
+    .chain(
+      config
+        .project
+        .iter()
+        .flat_map(|project| &project.extra_bots),
+    )
+    .map(|username| username.to_lowercase())
+    .collect()
+}
+
+/// Resolves the usernames in the configuration, looking up only those the
+/// state file doesn't know from the last day. People who match nobody get a
+/// warning, since filters on them fall back to comparing names. Bots don't,
+/// as the default one exists on few instances.
+pub fn resolve(client: &Client, options: &Options, config: &Config) -> UserIds {
+  let people = people(options, config);
+  let bots = bots(options, config);
+  let usernames: BTreeSet<&String> = people.iter().chain(&bots).collect();
+  let cached = State::load().users;
+  let now = Utc::now();
+
+  let mut ids = HashMap::new();
+  let mut fetched = BTreeMap::new();
+  let mut failed = false;
+  for username in usernames {
+    let id = match cached.get(username) {
+      Some(cached) if now - cached.fetched_at < TTL => cached.id,
+      // One failure is likely all of them failing, so the rest aren't tried.
+      _ if failed => None,
+      _ => match User::find(client, username) {
+        Ok(user) => {
+          let id = user.map(|user| user.id);
+          fetched.insert(
+            username.clone(),
+            CachedUser {
+              id,
+              fetched_at: now,
+            },
+          );
+          id
+        }
+        Err(err) => {
+          eprintln!(
+            "warning: could not look up users ({}); comparing them by name",
+            err
+          );
+          failed = true;
+          None
+        }
+      },
+    };
+    match id {
+      Some(id) => {
+        ids.insert(username.clone(), id);
+      }
+      None if !failed && people.contains(username) => {
+        eprintln!(
+          "warning: no user is named @{}; comparing by name instead",
+          username
+        );
+      }
+      None => {}
+    }
+  }
+
+  if !fetched.is_empty() {
+    let _ = State::update(|state| state.users.extend(fetched));
+  }
+  UserIds { ids }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    fixtures::{isolate, options, user, user_json},
+    mock::{get, reply, MockServer},
+  };
+  use serde_json::json;
+
+  fn looked_up(server: &MockServer, username: &str) -> usize {
+    let query = format!("username={}", username);
+    server
+      .requests()
+      .iter()
+      .filter(|request| request.starts_with("GET /api/v4/users?") && request.contains(&query))
+      .count()
+  }
+
+  #[test]
+  fn unresolvable_names_compare_by_name() {
+    isolate();
+    let server = MockServer::start(vec![
+      get(
+        "/users",
+        Some("username=alice"),
+        json!([user_json(7, "alice")]),
+      ),
+      get(
+        "/users",
+        Some("username=carol"),
+        json!([user_json(8, "carol")]),
+      ),
+      // Nobody is named ghost, nor anything else.
+      get("/users", None, json!([])),
+    ]);
+    let options = options(&[
+      "--author", "Ghost", "--author", "ALICE", "--author", "@core",
+    ]);
+    let mut config = Config::default();
+    config
+      .teams
+      .insert("core".into(), vec!["Carol".into(), "ghost".into()]);
+
+    let ids = resolve(&server.client(), &options, &config);
+    // Resolved names follow the user, whatever they are called now.
+    assert!(ids.is("alice", &user(7, "alice-renamed")));
+    assert!(!ids.is("alice", &user(9, "alice")));
+    assert!(ids.is("carol", &user(8, "carol")));
+    // The others are their name, in any case.
+    assert!(ids.is("ghost", &user(10, "GHOST")));
+    assert!(!ids.is("ghost", &user(10, "ghostly")));
+    assert!(ids.is("nobody-else", &user(11, "Nobody-Else")));
+    assert_eq!(looked_up(&server, "ghost"), 1);
+    assert_eq!(looked_up(&server, "@core"), 0);
+
+    // That nobody is named ghost is kept, too.
+    assert_eq!(State::load().users["ghost"].id, None);
+    let ids = resolve(&server.client(), &options, &config);
+    assert!(ids.is("ghost", &user(10, "ghost")));
+    assert_eq!(looked_up(&server, "ghost"), 1);
+    assert_eq!(looked_up(&server, "alice"), 1);
+  }
+
+  #[test]
+  fn failed_lookups_compare_by_name_and_are_tried_again() {
+    isolate();
+    let server = MockServer::start(vec![reply(
+      "GET",
+      "/users",
+      None,
+      500,
+      json!({ "message": "500 Internal Server Error" }),
+    )]);
+    let options = options(&["--author", "dave", "--author", "erin", "--bot", "ci-bot"]);
+    let ids = resolve(&server.client(), &options, &Config::default());
+    assert!(ids.is("dave", &user(12, "Dave")));
+    assert!(!ids.is("dave", &user(12, "erin")));
+    // After the first failure, the rest aren't tried.
+    let lookups = ["ci-bot", "dave", "erin"].map(|username| looked_up(&server, username));
+    assert_eq!(lookups, [1, 0, 0]);
+    // Nothing was learnt, so they are looked up next time.
+    let users = State::load().users;
+    assert!(["ci-bot", "dave", "erin"]
+      .iter()
+      .all(|username| !users.contains_key(*username)));
+  }
+}
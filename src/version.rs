@@ -0,0 +1,83 @@
+use crate::Result;
+use serde::Deserialize;
+use std::time::Duration;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const COMMIT: &str = env!("GITLAB_TODO_COMMIT");
+const TARGET: &str = env!("GITLAB_TODO_TARGET");
+const FEATURES: &str = env!("GITLAB_TODO_FEATURES");
+
+const LATEST_RELEASE: &str = "https://api.github.com/repos/vapourismo/gitlab-todo/releases/latest";
+
+/// How long the release check may take before it is given up on.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Deserialize)]
+struct Release {
+  tag_name: String,
+}
+
+/// What to tell from a bug report: version, commit, target and features.
+pub fn build_info() -> String {
+  let features = if FEATURES.is_empty() {
+    "none"
+  } else {
+    FEATURES
+  };
+  format!(
+    "gitlab-todo {}\ncommit:   {}\ntarget:   {}\nfeatures: {}",
+    VERSION, COMMIT, TARGET, features
+  )
+}
+
+/// A version like `v1.2.3` or `1.2`, as numbers to compare, ignoring any
+/// pre-release or build suffix.
+pub fn parse(version: &str) -> Option<(u64, u64, u64)> {
+  let version = version.trim().trim_start_matches('v');
+  let core = version.split(['-', '+']).next()?;
+  let mut parts = core.split('.').map(|part| part.parse::<u64>());
+  let major = parts.next()?.ok()?;
+  let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+  let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+  Some((major, minor, patch))
+}
+
+/// The tag of the latest release, where it can be had quickly.
+fn latest_release() -> Option<String> {
+  let release: Release = reqwest::blocking::Client::builder()
+    .timeout(CHECK_TIMEOUT)
+    .user_agent(concat!("gitlab-todo/", env!("CARGO_PKG_VERSION")))
+    .build()
+    .ok()?
+    .get(LATEST_RELEASE)
+    .send()
+    .ok()?
+    .error_for_status()
+    .ok()?
+    .json()
+    .ok()?;
+  Some(release.tag_name)
+}
+
+/// A hint to update when `latest` is newer than `current`.
+pub fn update_hint(current: &str, latest: &str) -> Option<String> {
+  (parse(latest)? > parse(current)?).then(|| {
+    format!(
+      "gitlab-todo {} is out, this is {}",
+      latest.trim_start_matches('v'),
+      current
+    )
+  })
+}
+
+/// Prints the build info and, when asked to, whether a newer release is
+/// out. Being offline or rate-limited only leaves the hint out.
+pub fn run(check_update: bool) -> Result<()> {
+  println!("{}", build_info());
+  if check_update {
+    if let Some(hint) = latest_release().and_then(|latest| update_hint(VERSION, &latest)) {
+      println!("{}", hint);
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,171 @@
+use crate::{
+  config::Config,
+  gitlab::{Milestone, MrId, User},
+  options::{GroupBy, Options},
+  priority::stale_penalty,
+  priority::Score,
+  progress::Tally,
+  tier::Tier,
+  views::View,
+  Row,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heading<'a> {
+  Focus,
+  Tier(Tier),
+  /// None for the merge requests without a milestone.
+  Milestone(Option<&'a Milestone>),
+  Stale,
+  Triage,
+}
+
+impl Heading<'_> {
+  pub fn title(&self) -> String {
+    match self {
+      Heading::Focus => "In review".to_string(),
+      Heading::Tier(tier) => tier.title().to_string(),
+      Heading::Milestone(Some(milestone)) => milestone.describe(),
+      Heading::Milestone(None) => "No milestone".to_string(),
+      Heading::Stale => "Stale".to_string(),
+      Heading::Triage => "Needs triage".to_string(),
+    }
+  }
+}
+
+/// A run of rows in the order every renderer shows them, under an optional heading.
+pub struct Section<'a> {
+  pub heading: Option<Heading<'a>>,
+  pub rows: Vec<&'a (Row, Score)>,
+  /// Rows left out for the section's limit, counted instead.
+  pub more: usize,
+  /// How far the rows are along, for milestones.
+  pub progress: Option<Tally>,
+}
+
+/// The rows by milestone title, so that a release spanning projects stays
+/// together, the soonest due first and those without a milestone last.
+fn milestone_sections<'a>(rows: Vec<&'a (Row, Score)>) -> Vec<Section<'a>> {
+  let milestone = |section: &Section<'a>| match section.heading {
+    Some(Heading::Milestone(milestone)) => milestone,
+    _ => None,
+  };
+  let mut sections: Vec<Section> = Vec::new();
+  for entry in rows {
+    let title = entry
+      .0
+      .mr
+      .milestone
+      .as_ref()
+      .map(|milestone| &milestone.title);
+    match sections
+      .iter_mut()
+      .find(|section| milestone(section).map(|milestone| &milestone.title) == title)
+    {
+      Some(section) => section.rows.push(entry),
+      None => sections.push(Section {
+        heading: Some(Heading::Milestone(entry.0.mr.milestone.as_ref())),
+        rows: vec![entry],
+        more: 0,
+        progress: None,
+      }),
+    }
+  }
+  sections.sort_by_key(|section| match milestone(section) {
+    Some(milestone) => (
+      false,
+      milestone.due_date.is_none(),
+      milestone.due_date,
+      milestone.title.as_str(),
+    ),
+    None => (true, true, None, ""),
+  });
+  for section in &mut sections {
+    section.progress = Some(Tally::of(&section.rows));
+  }
+  sections
+}
+
+pub fn sections<'a>(
+  rows: &'a [(Row, Score)],
+  view: &View,
+  user: &User,
+  options: &Options,
+  config: &Config,
+  focus: Option<MrId>,
+) -> Vec<Section<'a>> {
+  let mut sections = Vec::new();
+  // The merge request in review stays on top whatever the view.
+  if let Some(focused) = rows.iter().find(|(row, _)| Some(row.mr.id) == focus) {
+    sections.push(Section {
+      heading: Some(Heading::Focus),
+      rows: vec![focused],
+      more: 0,
+      progress: None,
+    });
+  }
+
+  let (mut triage_rows, rows): (Vec<_>, Vec<_>) = rows
+    .iter()
+    .filter(|(row, _)| Some(row.mr.id) != focus)
+    .filter(|(row, score)| view.matches(row, score, options))
+    .partition(|(row, _)| row.triage);
+  let (rows, stale_rows): (Vec<_>, Vec<_>) = rows.into_iter().partition(|(row, _)| {
+    let options = config.project_options(options, row.mr.project_path());
+    !options.stale_report || stale_penalty(&row.mr, &options) == 0
+  });
+
+  if options.group_by == Some(GroupBy::Milestone) {
+    sections.extend(milestone_sections(rows));
+  } else if options.tiers {
+    for tier in Tier::ALL {
+      let tier_rows: Vec<_> = rows
+        .iter()
+        .copied()
+        .filter(|(_, score)| Tier::of(score, options) == tier)
+        .collect();
+      if !tier_rows.is_empty() {
+        sections.push(Section {
+          heading: Some(Heading::Tier(tier)),
+          rows: tier_rows,
+          more: 0,
+          progress: None,
+        });
+      }
+    }
+  } else {
+    sections.push(Section {
+      heading: None,
+      rows,
+      more: 0,
+      progress: None,
+    });
+  }
+
+  for section in &mut sections {
+    view.sort(&mut section.rows, user, options);
+  }
+
+  if !stale_rows.is_empty() {
+    sections.push(Section {
+      heading: Some(Heading::Stale),
+      rows: stale_rows,
+      more: 0,
+      progress: None,
+    });
+  }
+
+  // Whole groups can have a long queue; the rest only shows as a count.
+  if !triage_rows.is_empty() {
+    view.sort(&mut triage_rows, user, options);
+    let more = triage_rows.len().saturating_sub(options.triage_limit);
+    triage_rows.truncate(options.triage_limit);
+    sections.push(Section {
+      heading: Some(Heading::Triage),
+      rows: triage_rows,
+      more,
+      progress: None,
+    });
+  }
+  sections
+}
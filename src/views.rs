@@ -0,0 +1,143 @@
+use crate::{
+  effort,
+  gitlab::{Issue, User},
+  options::Options,
+  priority::Score,
+  tier::Tier,
+  Row,
+};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+  cmp::Reverse,
+  collections::{BTreeMap, BTreeSet},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ViewFilter {
+  /// Anything above the lowest tier, as counted in the prompt.
+  Actionable,
+  Authored,
+  Reviewing,
+  Assigned,
+}
+
+impl ViewFilter {
+  fn matches(&self, row: &Row, score: &Score, options: &Options) -> bool {
+    match self {
+      ViewFilter::Actionable => Tier::of(score, options) != Tier::Low,
+      ViewFilter::Authored => row.roles.author,
+      ViewFilter::Reviewing => row.roles.reviewing(),
+      ViewFilter::Assigned => row.roles.assigned_by_others(),
+    }
+  }
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ViewSort {
+  #[default]
+  Priority,
+  Updated,
+  Created,
+  /// Soonest deadline first; items without one keep their priority order after the rest.
+  Agenda,
+  /// Least review effort left first; items without an estimate keep their priority order after
+  /// the rest.
+  Effort,
+}
+
+/// A named combination of filters, all of which a row must pass, and a sort order.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct View {
+  pub filters: Vec<ViewFilter>,
+  pub sort: ViewSort,
+  /// Only these projects, where any are picked in the TUI; never configured.
+  #[serde(skip)]
+  pub projects: BTreeSet<String>,
+  #[serde(flatten, skip_serializing)]
+  pub unknown: BTreeMap<String, toml::Value>,
+}
+
+impl View {
+  fn of(filters: &[ViewFilter]) -> Self {
+    View {
+      filters: filters.to_vec(),
+      ..View::default()
+    }
+  }
+
+  pub fn matches(&self, row: &Row, score: &Score, options: &Options) -> bool {
+    (self.projects.is_empty() || self.projects.contains(row.mr.project_path()))
+      && self
+        .filters
+        .iter()
+        .all(|filter| filter.matches(row, score, options))
+  }
+
+  /// The view with the sort given on the command line, if any.
+  pub fn sorted_by(self, sort: Option<ViewSort>) -> Self {
+    View {
+      sort: sort.unwrap_or(self.sort),
+      ..self
+    }
+  }
+
+  /// Orders rows that arrive sorted by priority.
+  pub fn sort(&self, rows: &mut [&(Row, Score)], user: &User, options: &Options) {
+    match self.sort {
+      ViewSort::Priority => {}
+      ViewSort::Updated => rows.sort_by_key(|(row, _)| Reverse(row.mr.updated_at)),
+      ViewSort::Created => rows.sort_by_key(|(row, _)| Reverse(row.mr.created_at)),
+      ViewSort::Agenda => rows.sort_by_key(|(row, _)| agenda_key(row.mr.deadline())),
+      ViewSort::Effort => rows.sort_by_key(|(row, _)| {
+        let effort = effort::of(row, user, options);
+        (effort.is_none(), effort)
+      }),
+    }
+  }
+
+  /// Orders issues that arrive sorted by priority.
+  pub fn sort_issues(&self, issues: &mut [&(Issue, Score)]) {
+    if self.sort == ViewSort::Agenda {
+      issues.sort_by_key(|(issue, _)| agenda_key(issue.deadline()));
+    }
+  }
+}
+
+fn agenda_key(deadline: Option<NaiveDate>) -> (bool, Option<NaiveDate>) {
+  (deadline.is_none(), deadline)
+}
+
+/// The built-in presets, in cycling order, followed by the configured ones;
+/// a configured preset of the same name replaces a built-in one.
+pub fn presets(configured: &BTreeMap<String, View>) -> Vec<(String, View)> {
+  let mut presets: Vec<(String, View)> = [
+    ("all", View::of(&[])),
+    ("actionable", View::of(&[ViewFilter::Actionable])),
+    ("authored", View::of(&[ViewFilter::Authored])),
+    ("reviewing", View::of(&[ViewFilter::Reviewing])),
+    (
+      "agenda",
+      View {
+        sort: ViewSort::Agenda,
+        ..View::default()
+      },
+    ),
+  ]
+  .into_iter()
+  .map(|(name, view)| {
+    let view = configured.get(name).cloned().unwrap_or(view);
+    (name.to_string(), view)
+  })
+  .collect();
+  for (name, view) in configured {
+    if !presets.iter().any(|(preset, _)| preset == name) {
+      presets.push((name.clone(), view.clone()));
+    }
+  }
+  presets
+}
@@ -0,0 +1,158 @@
+use crate::{
+  actions::open_in_browser,
+  config::Config,
+  gitlab::{Client, User},
+  options::Options,
+  secrets, Result,
+};
+use crossterm::{
+  event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+  terminal::{disable_raw_mode, enable_raw_mode},
+};
+use std::{
+  env,
+  io::{stdin, stdout, IsTerminal, Write},
+};
+
+/// Whether to walk through the setup: only on a terminal, and only for
+/// someone who has neither a token nor a configuration yet.
+pub fn wanted(options: &Options) -> bool {
+  !options.no_wizard
+    && options.replay.is_none()
+    && options.config.is_none()
+    && env::var_os("GITLAB_TOKEN").is_none()
+    && Config::default_path().is_some_and(|path| !path.exists())
+    && stdin().is_terminal()
+    && stdout().is_terminal()
+}
+
+/// Where to create a token that can read the API.
+pub fn token_url(host: &str) -> String {
+  let base = if host.contains("://") {
+    host.trim_end_matches('/').to_string()
+  } else {
+    format!("https://{}", host.trim_end_matches('/'))
+  };
+  format!(
+    "{}/-/user_settings/personal_access_tokens?scopes=read_api",
+    base
+  )
+}
+
+/// Asks a question, offering `default` for an empty answer.
+fn ask(question: &str, default: Option<&str>) -> Result<Option<String>> {
+  match default {
+    Some(default) => print!("{} [{}]: ", question, default),
+    None => print!("{}: ", question),
+  }
+  stdout().flush()?;
+  let mut answer = String::new();
+  stdin().read_line(&mut answer)?;
+  let answer = answer.trim();
+  Ok(if answer.is_empty() {
+    default.map(str::to_string)
+  } else {
+    Some(answer.to_string())
+  })
+}
+
+fn confirm(question: &str) -> Result<bool> {
+  let answer = ask(&format!("{} [Y/n]", question), None)?;
+  Ok(!matches!(answer.as_deref(), Some("n" | "N" | "no")))
+}
+
+/// Reads a line without showing it. Esc or Ctrl-C count as an empty one.
+fn read_hidden(prompt: &str) -> Result<String> {
+  print!("{}: ", prompt);
+  stdout().flush()?;
+  enable_raw_mode()?;
+  let mut secret = String::new();
+  let read = loop {
+    match event::read() {
+      Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => match key.code {
+        KeyCode::Enter => break Ok(()),
+        KeyCode::Esc => {
+          secret.clear();
+          break Ok(());
+        }
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+          secret.clear();
+          break Ok(());
+        }
+        KeyCode::Backspace => {
+          secret.pop();
+        }
+        KeyCode::Char(c) => secret.push(c),
+        _ => {}
+      },
+      Ok(_) => {}
+      Err(err) => break Err(err),
+    }
+  };
+  disable_raw_mode()?;
+  println!();
+  read?;
+  Ok(secret.trim().to_string())
+}
+
+/// Asks for the host, helps create a token, checks it and confirms whose it
+/// is, then writes the configuration. Every question can be skipped with
+/// Enter; whatever was left out is set in the file later.
+pub fn run(options: &Options) -> Result<()> {
+  println!("No configuration yet; let's set one up. Press Enter to skip a step.\n");
+  let host = ask("GitLab host", Some(&options.host))?.unwrap_or_else(|| options.host.clone());
+
+  let url = token_url(&host);
+  println!(
+    "\nAn access token with the read_api scope can be created at\n  {}",
+    url
+  );
+  if confirm("Open it in the browser?")? && open_in_browser(&url).is_err() {
+    println!("Could not open a browser; open the link above instead.");
+  }
+
+  let token = Some(read_hidden("\nAccess token (not shown)")?).filter(|token| !token.is_empty());
+  let detected = match &token {
+    Some(token) => {
+      match Client::new(&host, token, options.auth_style.style())
+        .and_then(|client| User::current(&client))
+      {
+        Ok(user) => Some(user.username),
+        Err(err) => {
+          println!("The token didn't work: {}", err);
+          if !confirm("Save it anyway?")? {
+            return Err("Set up cancelled; run again or pass --no-wizard".into());
+          }
+          None
+        }
+      }
+    }
+    None => None,
+  };
+  let user = ask(
+    "GitLab user",
+    options.user.as_deref().or(detected.as_deref()),
+  )?;
+
+  // The file only refers to the keyring, so that the token isn't kept in
+  // plain text.
+  let token_in_keyring = match &token {
+    Some(token) => match secrets::store_token(&host, token) {
+      Ok(()) => {
+        println!("Saved the access token in the system keyring.");
+        true
+      }
+      Err(err) => {
+        println!(
+          "Could not save the access token in the system keyring ({}); set GITLAB_TOKEN instead.",
+          err
+        );
+        false
+      }
+    },
+    None => false,
+  };
+  let path = Config::init_with(&host, user.as_deref(), token_in_keyring)?;
+  println!("\nWrote {}\n", path.display());
+  Ok(())
+}